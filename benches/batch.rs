@@ -0,0 +1,88 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rayon::ThreadPoolBuilder;
+use shader_pack::{
+    compile::{compile_pack_batch, CompileOptions, VariantKey},
+    parse::ast::{AstBinaryExprOpKind, AstExprKind, AstShaderPack},
+    span::SourceMap,
+    test_utils::*,
+};
+use std::sync::Arc;
+
+const FLAG_COUNT: usize = 8;
+
+/// Creates a pack with a uniform and a pass per flag, so that every flag combination is a
+/// distinct variant; the AST is built directly, since only compiling it is measured.
+///
+/// ```spk
+/// comptime if "flag_<index>" {
+///     in color_<index>: f3;
+///
+///     pass pass_<index> {
+///         vertex {
+///             let scaled = color_<index> * color_<index>;
+///         }
+///     }
+/// }
+/// ```
+fn pack() -> AstShaderPack {
+    shader_pack(Vec::from_iter((0..FLAG_COUNT).map(|index| {
+        let color = format!("color_{}", index);
+        let scaled = expr_binary(
+            AstBinaryExprOpKind::Mul,
+            expr(AstExprKind::Identifier(ident(&color))),
+            expr(AstExprKind::Identifier(ident(&color))),
+        );
+
+        comptime_if(
+            flag(&format!("flag_{}", index)),
+            vec![
+                input(ident(&color), "f3"),
+                pass_with(
+                    ident(&format!("pass_{}", index)),
+                    vec![stage(
+                        ident("vertex"),
+                        vec![statement_let(ident("scaled"), scaled)],
+                    )],
+                ),
+            ],
+            None,
+        )
+    })))
+}
+
+fn variants() -> Vec<VariantKey> {
+    Vec::from_iter((0..1u32 << FLAG_COUNT).map(|mask| {
+        VariantKey::new(
+            (0..FLAG_COUNT)
+                .filter(|index| mask & (1 << index) != 0)
+                .map(|index| format!("flag_{}", index)),
+        )
+    }))
+}
+
+fn bench_compile_batch(c: &mut Criterion) {
+    let pack = pack();
+    let variants = variants();
+    let options = CompileOptions::default();
+    let mut group = c.benchmark_group("compile_batch");
+
+    for threads in [1, 2, 4, 8] {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, _| {
+            b.iter(|| {
+                let file = SourceMap::new().add_file("", "bench", None);
+                let source_map = Arc::new(SourceMap::from_file(file.clone()));
+                pool.install(|| compile_pack_batch(file, source_map, &pack, &variants, &options))
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_compile_batch);
+criterion_main!(benches);
@@ -0,0 +1,97 @@
+mod error;
+mod reader;
+mod writer;
+
+use std::ops::Range;
+
+pub use error::*;
+pub use reader::*;
+pub use writer::*;
+
+/// Binary container of a compiled shader pack, shipped to the runtime instead of the `.spk`
+/// source.
+///
+/// Every integer is little-endian. The file starts with a fixed-size header, followed by the
+/// section table and the sections themselves:
+///
+/// ```text
+/// offset  size  field
+///      0     4  magic, `SPKA`
+///      4     2  format version
+///      6     2  reserved, 0
+///      8     8  hash of the source, see `source_hash`
+///     16     8  checksum of every other byte of the file, see `checksum`
+///     24     4  section count
+///     28     4  output count
+///     32  12*n  section table; kind, offset from the file start and length of every section
+/// ```
+///
+/// Every section starts with its entry count, followed by the entries. Strings and byte arrays
+/// are stored as their length (`u32`) followed by their bytes.
+///
+/// - variants: output index, flag count, then the flags sorted
+/// - blobs: output index, backend name, data
+/// - reflections: output index, reflection JSON
+/// - debug sources: file name, file content
+///
+/// Sections of an unknown kind are skipped, so that sections can be added without bumping the
+/// version.
+pub const ARCHIVE_MAGIC: [u8; 4] = *b"SPKA";
+/// Version written by [`ArchiveWriter`]; readers reject any other version.
+pub const ARCHIVE_VERSION: u16 = 2;
+
+const HEADER_SIZE: usize = 32;
+const CHECKSUM_RANGE: Range<usize> = 16..24;
+const SECTION_ENTRY_SIZE: usize = 12;
+
+/// Kind of a section of an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ArchiveSectionKind {
+    Variants,
+    Blobs,
+    Reflections,
+    DebugSources,
+}
+
+impl ArchiveSectionKind {
+    pub const ALL: [Self; 4] = [
+        Self::Variants,
+        Self::Blobs,
+        Self::Reflections,
+        Self::DebugSources,
+    ];
+
+    pub fn id(self) -> u32 {
+        match self {
+            Self::Variants => 1,
+            Self::Blobs => 2,
+            Self::Reflections => 3,
+            Self::DebugSources => 4,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.id() == id)
+    }
+}
+
+/// Hashes the source of a pack, so that the runtime can tell whether an archive is stale.
+pub fn source_hash(source: &str) -> u64 {
+    fnv1a(source.as_bytes())
+}
+
+/// Checksum of the whole archive but the checksum itself, so that the header is covered too; FNV-1a
+/// is used because it is stable across platforms and crate versions.
+fn checksum(archive: &[u8]) -> u64 {
+    fnv1a(
+        archive[..CHECKSUM_RANGE.start]
+            .iter()
+            .chain(&archive[CHECKSUM_RANGE.end..]),
+    )
+}
+
+fn fnv1a<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
@@ -0,0 +1,68 @@
+use std::{error::Error, fmt::Display};
+
+/// Reasons an archive cannot be read.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArchiveError {
+    /// The file does not start with [`ARCHIVE_MAGIC`](super::ARCHIVE_MAGIC).
+    InvalidMagic,
+    /// The file has been written by an incompatible version of the writer.
+    UnsupportedVersion { version: u16 },
+    /// The checksum in the header does not match the content.
+    ChecksumMismatch { expected: u64, actual: u64 },
+    /// A value at `offset` extends past the end of the file or of its section.
+    UnexpectedEnd { offset: usize },
+    /// The section table does not fit in the file.
+    InvalidSectionCount { count: u32 },
+    /// A section lies outside of the file.
+    SectionOutOfBounds {
+        kind: u32,
+        offset: usize,
+        len: usize,
+    },
+    /// A section of the same kind appears more than once.
+    DuplicateSection { kind: u32 },
+    /// A string at `offset` is not valid UTF-8.
+    InvalidUtf8 { offset: usize },
+    /// An entry refers to an output that does not exist.
+    InvalidOutput { index: u32 },
+}
+
+impl Display for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidMagic => write!(f, "not a shader-pack archive"),
+            Self::UnsupportedVersion { version } => {
+                write!(f, "unsupported archive version {}", version)
+            }
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "archive is corrupted: expected checksum {:#018x}, but it is {:#018x}",
+                expected, actual
+            ),
+            Self::UnexpectedEnd { offset } => {
+                write!(f, "archive is corrupted: unexpected end at {}", offset)
+            }
+            Self::InvalidSectionCount { count } => write!(
+                f,
+                "archive is corrupted: the table of {} sections does not fit in the file",
+                count
+            ),
+            Self::SectionOutOfBounds { kind, offset, len } => write!(
+                f,
+                "archive is corrupted: section {} ({} bytes at {}) is out of bounds",
+                kind, len, offset
+            ),
+            Self::DuplicateSection { kind } => {
+                write!(f, "archive is corrupted: section {} appears twice", kind)
+            }
+            Self::InvalidUtf8 { offset } => {
+                write!(f, "archive is corrupted: invalid string at {}", offset)
+            }
+            Self::InvalidOutput { index } => {
+                write!(f, "archive is corrupted: output {} does not exist", index)
+            }
+        }
+    }
+}
+
+impl Error for ArchiveError {}
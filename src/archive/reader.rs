@@ -0,0 +1,437 @@
+use super::{
+    checksum, ArchiveError, ArchiveSectionKind, ARCHIVE_MAGIC, ARCHIVE_VERSION, HEADER_SIZE,
+    SECTION_ENTRY_SIZE,
+};
+use crate::compile::VariantKey;
+use std::str::from_utf8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArchiveVariant<'a> {
+    /// Enabled flags, sorted.
+    pub flags: Vec<&'a str>,
+    pub output: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArchiveBlob<'a> {
+    pub output: u32,
+    pub backend: &'a str,
+    pub data: &'a [u8],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArchiveReflection<'a> {
+    pub output: u32,
+    pub json: &'a str,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ArchiveDebugSource<'a> {
+    pub name: &'a str,
+    pub content: &'a str,
+}
+
+/// Reads an archive without copying its strings and blobs.
+///
+/// The whole archive is validated up front, so that accessors never fail.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArchiveReader<'a> {
+    source_hash: u64,
+    output_count: u32,
+    variants: Vec<ArchiveVariant<'a>>,
+    blobs: Vec<ArchiveBlob<'a>>,
+    reflections: Vec<ArchiveReflection<'a>>,
+    debug_sources: Vec<ArchiveDebugSource<'a>>,
+}
+
+impl<'a> ArchiveReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Self, ArchiveError> {
+        let mut header = Cursor::new(bytes, 0, bytes.len());
+
+        if header.read(ARCHIVE_MAGIC.len())? != ARCHIVE_MAGIC {
+            return Err(ArchiveError::InvalidMagic);
+        }
+
+        let version = header.read_u16()?;
+
+        if version != ARCHIVE_VERSION {
+            return Err(ArchiveError::UnsupportedVersion { version });
+        }
+
+        header.read_u16()?;
+        let source_hash = header.read_u64()?;
+        let expected = header.read_u64()?;
+        let section_count = header.read_u32()?;
+        let output_count = header.read_u32()?;
+
+        let actual = checksum(bytes);
+
+        if expected != actual {
+            return Err(ArchiveError::ChecksumMismatch { expected, actual });
+        }
+
+        // the count is checked before anything is allocated for it, since the checksum does not
+        // protect against crafted archives
+        let table_end = SECTION_ENTRY_SIZE
+            .checked_mul(section_count as usize)
+            .and_then(|len| len.checked_add(HEADER_SIZE))
+            .filter(|end| *end <= bytes.len())
+            .ok_or(ArchiveError::InvalidSectionCount {
+                count: section_count,
+            })?;
+
+        let mut reader = Self {
+            source_hash,
+            output_count,
+            variants: vec![],
+            blobs: vec![],
+            reflections: vec![],
+            debug_sources: vec![],
+        };
+        let mut table = Cursor::new(bytes, HEADER_SIZE, table_end);
+        let mut seen = Vec::with_capacity(section_count as usize);
+
+        for _ in 0..section_count {
+            let kind = table.read_u32()?;
+            let offset = table.read_u32()? as usize;
+            let len = table.read_u32()? as usize;
+
+            if seen.contains(&kind) {
+                return Err(ArchiveError::DuplicateSection { kind });
+            }
+
+            seen.push(kind);
+
+            let is_in_bounds = table_end <= offset
+                && offset
+                    .checked_add(len)
+                    .is_some_and(|end| end <= bytes.len());
+
+            if !is_in_bounds {
+                return Err(ArchiveError::SectionOutOfBounds { kind, offset, len });
+            }
+
+            if let Some(kind) = ArchiveSectionKind::from_id(kind) {
+                reader.read_section(kind, Cursor::new(bytes, offset, offset + len))?;
+            }
+        }
+
+        Ok(reader)
+    }
+
+    fn read_section(
+        &mut self,
+        kind: ArchiveSectionKind,
+        mut cursor: Cursor<'a>,
+    ) -> Result<(), ArchiveError> {
+        let count = cursor.read_u32()?;
+
+        for _ in 0..count {
+            match kind {
+                ArchiveSectionKind::Variants => {
+                    let output = self.read_output(&mut cursor)?;
+                    let flag_count = cursor.read_u32()?;
+                    let mut flags = Vec::new();
+
+                    for _ in 0..flag_count {
+                        flags.push(cursor.read_str()?);
+                    }
+
+                    self.variants.push(ArchiveVariant { flags, output });
+                }
+                ArchiveSectionKind::Blobs => {
+                    let output = self.read_output(&mut cursor)?;
+                    let backend = cursor.read_str()?;
+                    let data = cursor.read_bytes()?;
+                    self.blobs.push(ArchiveBlob {
+                        output,
+                        backend,
+                        data,
+                    });
+                }
+                ArchiveSectionKind::Reflections => {
+                    let output = self.read_output(&mut cursor)?;
+                    let json = cursor.read_str()?;
+                    self.reflections.push(ArchiveReflection { output, json });
+                }
+                ArchiveSectionKind::DebugSources => {
+                    let name = cursor.read_str()?;
+                    let content = cursor.read_str()?;
+                    self.debug_sources
+                        .push(ArchiveDebugSource { name, content });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_output(&self, cursor: &mut Cursor<'a>) -> Result<u32, ArchiveError> {
+        let index = cursor.read_u32()?;
+
+        if self.output_count <= index {
+            return Err(ArchiveError::InvalidOutput { index });
+        }
+
+        Ok(index)
+    }
+
+    pub fn source_hash(&self) -> u64 {
+        self.source_hash
+    }
+
+    pub fn output_count(&self) -> u32 {
+        self.output_count
+    }
+
+    pub fn variants(&self) -> &[ArchiveVariant<'a>] {
+        &self.variants
+    }
+
+    pub fn blobs(&self) -> &[ArchiveBlob<'a>] {
+        &self.blobs
+    }
+
+    pub fn reflections(&self) -> &[ArchiveReflection<'a>] {
+        &self.reflections
+    }
+
+    pub fn debug_sources(&self) -> &[ArchiveDebugSource<'a>] {
+        &self.debug_sources
+    }
+
+    /// Returns the output index of the given variant; `None` if the archive does not contain it.
+    pub fn output(&self, key: &VariantKey) -> Option<u32> {
+        self.variants
+            .iter()
+            .find(|variant| {
+                variant
+                    .flags
+                    .iter()
+                    .copied()
+                    .eq(key.flags().iter().map(String::as_str))
+            })
+            .map(|variant| variant.output)
+    }
+
+    /// Returns the compiled code of an output for a single backend.
+    pub fn blob(&self, output: u32, backend: &str) -> Option<&'a [u8]> {
+        self.blobs
+            .iter()
+            .find(|blob| blob.output == output && blob.backend == backend)
+            .map(|blob| blob.data)
+    }
+
+    pub fn reflection(&self, output: u32) -> Option<&'a str> {
+        self.reflections
+            .iter()
+            .find(|reflection| reflection.output == output)
+            .map(|reflection| reflection.json)
+    }
+}
+
+/// Reads little-endian values from a bounded region of the archive.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    end: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(bytes: &'a [u8], offset: usize, end: usize) -> Self {
+        Self { bytes, offset, end }
+    }
+
+    fn read(&mut self, len: usize) -> Result<&'a [u8], ArchiveError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.end)
+            .ok_or(ArchiveError::UnexpectedEnd {
+                offset: self.offset,
+            })?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_u16(&mut self) -> Result<u16, ArchiveError> {
+        Ok(u16::from_le_bytes(self.read(2)?.try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, ArchiveError> {
+        Ok(u32::from_le_bytes(self.read(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, ArchiveError> {
+        Ok(u64::from_le_bytes(self.read(8)?.try_into().unwrap()))
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], ArchiveError> {
+        let len = self.read_u32()? as usize;
+        self.read(len)
+    }
+
+    fn read_str(&mut self) -> Result<&'a str, ArchiveError> {
+        let offset = self.offset;
+        from_utf8(self.read_bytes()?).map_err(|_| ArchiveError::InvalidUtf8 { offset })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::{source_hash, ArchiveWriter, CHECKSUM_RANGE};
+
+    fn archive() -> Vec<u8> {
+        let mut writer = ArchiveWriter::new(source_hash("in color: f3;"), 2);
+        writer.add_variant(&VariantKey::new(Vec::<String>::new()), 0);
+        writer.add_variant(&VariantKey::new(["shadow", "fog"]), 1);
+        writer.add_blob(0, "ir", b"resource#0 color: f3 = uniform\n");
+        writer.add_blob(1, "ir", &[0, 159, 146, 150]);
+        writer.add_reflection(1, "{\"passes\":[]}");
+        writer.add_debug_source("input", "in color: f3;");
+        writer.finish()
+    }
+
+    fn write_u32(bytes: &mut [u8], offset: usize, value: u32) {
+        bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Fixes the checksum after the archive has been modified.
+    fn fix_checksum(bytes: &mut [u8]) {
+        let checksum = checksum(bytes);
+        bytes[CHECKSUM_RANGE].copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    #[test]
+    fn test_archive_round_trip() {
+        let bytes = archive();
+        let reader = ArchiveReader::new(&bytes).unwrap();
+
+        assert_eq!(reader.source_hash(), source_hash("in color: f3;"));
+        assert_eq!(reader.output_count(), 2);
+        assert_eq!(
+            reader.variants(),
+            [
+                ArchiveVariant {
+                    flags: vec![],
+                    output: 0
+                },
+                ArchiveVariant {
+                    flags: vec!["fog", "shadow"],
+                    output: 1
+                }
+            ]
+        );
+        assert_eq!(reader.output(&VariantKey::new(["fog", "shadow"])), Some(1));
+        assert_eq!(reader.output(&VariantKey::new(["fog"])), None);
+        assert_eq!(
+            reader.blob(0, "ir"),
+            Some(&b"resource#0 color: f3 = uniform\n"[..])
+        );
+        assert_eq!(reader.blob(1, "ir"), Some(&[0, 159, 146, 150][..]));
+        assert_eq!(reader.blob(1, "glsl"), None);
+        assert_eq!(reader.reflection(0), None);
+        assert_eq!(reader.reflection(1), Some("{\"passes\":[]}"));
+        assert_eq!(
+            reader.debug_sources(),
+            [ArchiveDebugSource {
+                name: "input",
+                content: "in color: f3;"
+            }]
+        );
+
+        // the reader borrows the strings and blobs instead of copying them
+        let range = bytes.as_ptr_range();
+        assert!(range.contains(&reader.blob(0, "ir").unwrap().as_ptr()));
+        assert!(range.contains(&reader.variants()[1].flags[0].as_ptr()));
+    }
+
+    #[test]
+    fn test_archive_invalid_header() {
+        let mut bytes = archive();
+        bytes[0] = b'X';
+        assert_eq!(ArchiveReader::new(&bytes), Err(ArchiveError::InvalidMagic));
+
+        let mut bytes = archive();
+        bytes[4..6].copy_from_slice(&3u16.to_le_bytes());
+        assert_eq!(
+            ArchiveReader::new(&bytes),
+            Err(ArchiveError::UnsupportedVersion { version: 3 })
+        );
+
+        assert_eq!(
+            ArchiveReader::new(&archive()[..20]),
+            Err(ArchiveError::UnexpectedEnd { offset: 16 })
+        );
+    }
+
+    #[test]
+    fn test_archive_corrupted() {
+        let mut bytes = archive();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(matches!(
+            ArchiveReader::new(&bytes),
+            Err(ArchiveError::ChecksumMismatch { .. })
+        ));
+
+        // the header is covered by the checksum too
+        let mut bytes = archive();
+        bytes[8] ^= 1;
+        assert!(matches!(
+            ArchiveReader::new(&bytes),
+            Err(ArchiveError::ChecksumMismatch { .. })
+        ));
+
+        // the length of the first section is too large
+        let mut bytes = archive();
+        let len = bytes.len() as u32;
+        write_u32(&mut bytes, HEADER_SIZE + 8, len);
+        fix_checksum(&mut bytes);
+        assert!(matches!(
+            ArchiveReader::new(&bytes),
+            Err(ArchiveError::SectionOutOfBounds { kind: 1, .. })
+        ));
+
+        // the first section (variants) starts right after the table of four sections; the
+        // output index of its first entry is out of range
+        let mut bytes = archive();
+        write_u32(&mut bytes, HEADER_SIZE + SECTION_ENTRY_SIZE * 4 + 4, 7);
+        fix_checksum(&mut bytes);
+        assert_eq!(
+            ArchiveReader::new(&bytes),
+            Err(ArchiveError::InvalidOutput { index: 7 })
+        );
+
+        // the entry count of the variants section is larger than its content
+        let mut bytes = archive();
+        write_u32(&mut bytes, HEADER_SIZE + SECTION_ENTRY_SIZE * 4, 100);
+        fix_checksum(&mut bytes);
+        assert!(matches!(
+            ArchiveReader::new(&bytes),
+            Err(ArchiveError::UnexpectedEnd { .. })
+        ));
+    }
+
+    #[test]
+    fn test_archive_invalid_section_count() {
+        // nothing is allocated for the sections of an oversized count
+        let mut bytes = archive();
+        write_u32(&mut bytes, 24, u32::MAX);
+        fix_checksum(&mut bytes);
+        assert_eq!(
+            ArchiveReader::new(&bytes),
+            Err(ArchiveError::InvalidSectionCount { count: u32::MAX })
+        );
+
+        // the table of four sections is cut after its second entry
+        let mut bytes = archive()[..HEADER_SIZE + SECTION_ENTRY_SIZE * 2].to_vec();
+        fix_checksum(&mut bytes);
+        assert_eq!(
+            ArchiveReader::new(&bytes),
+            Err(ArchiveError::InvalidSectionCount { count: 4 })
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use super::{
+    checksum, source_hash, ArchiveSectionKind, ARCHIVE_MAGIC, ARCHIVE_VERSION, CHECKSUM_RANGE,
+    HEADER_SIZE, SECTION_ENTRY_SIZE,
+};
+use crate::{
+    compile::{BatchOutput, Emit, VariantKey},
+    span::SourceMap,
+};
+
+/// Builds an archive in memory.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::{archive::{ArchiveReader, ArchiveWriter}, compile::VariantKey};
+/// let mut writer = ArchiveWriter::new(0, 1);
+/// writer.add_variant(&VariantKey::new(["shadow"]), 0);
+/// writer.add_blob(0, "ir", b"...");
+///
+/// let bytes = writer.finish();
+/// let reader = ArchiveReader::new(&bytes).unwrap();
+/// assert_eq!(reader.output(&VariantKey::new(["shadow"])), Some(0));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArchiveWriter {
+    source_hash: u64,
+    output_count: u32,
+    variants: Vec<(VariantKey, u32)>,
+    blobs: Vec<(u32, String, Vec<u8>)>,
+    reflections: Vec<(u32, String)>,
+    debug_sources: Vec<(String, String)>,
+}
+
+impl ArchiveWriter {
+    pub fn new(source_hash: u64, output_count: u32) -> Self {
+        Self {
+            source_hash,
+            output_count,
+            variants: vec![],
+            blobs: vec![],
+            reflections: vec![],
+            debug_sources: vec![],
+        }
+    }
+
+    /// Collects every output of a batch compilation; the emitted outputs are stored as blobs
+    /// named after their emit kind, e.g. `ir`.
+    ///
+    /// Every file of the `source_map`, the root file first, is added as a debug source; the
+    /// source hash covers all of them, separated by NUL characters.
+    pub fn from_batch(source_map: &SourceMap, batch: &BatchOutput) -> Self {
+        let contents = Vec::from_iter(source_map.files().iter().map(|file| file.content()));
+        let mut writer = Self::new(
+            source_hash(&contents.join("\0")),
+            batch.outputs.len() as u32,
+        );
+
+        for file in source_map.files() {
+            writer.add_debug_source(file.name(), file.content());
+        }
+
+        let mut variants = Vec::from_iter(&batch.variants);
+        variants.sort();
+
+        for (key, output) in variants {
+            writer.add_variant(key, *output as u32);
+        }
+
+        for (index, output) in batch.outputs.iter().enumerate() {
+            for (emit, emitted) in &output.emitted {
+                if *emit != Emit::Reflection {
+                    writer.add_blob(index as u32, emit.name(), emitted.as_bytes());
+                }
+            }
+
+            if let Some(reflection) = &output.reflection {
+                writer.add_reflection(index as u32, &reflection.to_json());
+            }
+        }
+
+        writer
+    }
+
+    pub fn add_variant(&mut self, key: &VariantKey, output: u32) {
+        self.variants.push((key.clone(), output));
+    }
+
+    /// Adds the compiled code of an output for a single backend.
+    pub fn add_blob(&mut self, output: u32, backend: &str, data: &[u8]) {
+        self.blobs.push((output, backend.to_owned(), data.to_vec()));
+    }
+
+    pub fn add_reflection(&mut self, output: u32, json: &str) {
+        self.reflections.push((output, json.to_owned()));
+    }
+
+    /// Embeds a source file, so that debuggers can map spans back to the source.
+    pub fn add_debug_source(&mut self, name: &str, content: &str) {
+        self.debug_sources
+            .push((name.to_owned(), content.to_owned()));
+    }
+
+    pub fn finish(&self) -> Vec<u8> {
+        let mut sections = Vec::with_capacity(ArchiveSectionKind::ALL.len());
+
+        for kind in ArchiveSectionKind::ALL {
+            let mut section = Vec::new();
+
+            match kind {
+                ArchiveSectionKind::Variants => {
+                    write_u32(&mut section, self.variants.len() as u32);
+
+                    for (key, output) in &self.variants {
+                        write_u32(&mut section, *output);
+                        write_u32(&mut section, key.flags().len() as u32);
+
+                        for flag in key.flags() {
+                            write_bytes(&mut section, flag.as_bytes());
+                        }
+                    }
+                }
+                ArchiveSectionKind::Blobs => {
+                    write_u32(&mut section, self.blobs.len() as u32);
+
+                    for (output, backend, data) in &self.blobs {
+                        write_u32(&mut section, *output);
+                        write_bytes(&mut section, backend.as_bytes());
+                        write_bytes(&mut section, data);
+                    }
+                }
+                ArchiveSectionKind::Reflections => {
+                    write_u32(&mut section, self.reflections.len() as u32);
+
+                    for (output, json) in &self.reflections {
+                        write_u32(&mut section, *output);
+                        write_bytes(&mut section, json.as_bytes());
+                    }
+                }
+                ArchiveSectionKind::DebugSources => {
+                    if self.debug_sources.is_empty() {
+                        continue;
+                    }
+
+                    write_u32(&mut section, self.debug_sources.len() as u32);
+
+                    for (name, content) in &self.debug_sources {
+                        write_bytes(&mut section, name.as_bytes());
+                        write_bytes(&mut section, content.as_bytes());
+                    }
+                }
+            }
+
+            sections.push((kind, section));
+        }
+
+        let mut body = Vec::new();
+        let mut offset = HEADER_SIZE + SECTION_ENTRY_SIZE * sections.len();
+
+        for (kind, section) in &sections {
+            write_u32(&mut body, kind.id());
+            write_u32(&mut body, offset as u32);
+            write_u32(&mut body, section.len() as u32);
+            offset += section.len();
+        }
+
+        for (_, section) in &sections {
+            body.extend_from_slice(section);
+        }
+
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + body.len());
+        bytes.extend_from_slice(&ARCHIVE_MAGIC);
+        bytes.extend_from_slice(&ARCHIVE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&0u16.to_le_bytes());
+        bytes.extend_from_slice(&self.source_hash.to_le_bytes());
+        // written once the rest of the archive is known
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        write_u32(&mut bytes, sections.len() as u32);
+        write_u32(&mut bytes, self.output_count);
+        bytes.extend_from_slice(&body);
+
+        let checksum = checksum(&bytes);
+        bytes[CHECKSUM_RANGE].copy_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+}
+
+fn write_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_bytes(bytes: &mut Vec<u8>, value: &[u8]) {
+    write_u32(bytes, value.len() as u32);
+    bytes.extend_from_slice(value);
+}
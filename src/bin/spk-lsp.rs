@@ -0,0 +1,14 @@
+use std::{
+    io::{stdin, stdout},
+    process::ExitCode,
+};
+
+fn main() -> ExitCode {
+    match shader_pack::lsp::run(stdin().lock(), stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use shader_pack::{
+    diagnostics::{LintLevel, LintLevels},
+    ir::{OptLevel, OptPass},
+};
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+pub const USAGE: &str = "usage: spk <command> [options] <file>
+       spk explain <code>
+
+commands:
+    check          report diagnostics
+    build          compile and write the outputs and the reflection
+    variants       list the distinct variants over the comptime flags
+    dump-tokens    print the tokens
+    dump-ast       print the AST
+    dump-ir        print the optimized IR
+    explain        print the explanation of a diagnostic code, e.g. SPK2010
+
+options:
+    --flag <name>             enable a comptime flag; repeatable
+    --backend <name>          code generator of `build`: ir (default)
+    --out-dir <dir>           output directory of `build`; defaults to the directory of the file
+    --archive                 make `build` compile every variant into a single `.spka` archive
+    --cache-dir <dir>         reuse the outputs of `build` cached in a directory, and cache new ones
+    -O0, -O1, -O2             optimization level (default -O0)
+    --enable-pass <name>      run an optimization pass in addition to the level; repeatable
+    --disable-pass <name>     skip an optimization pass of the level; repeatable
+    --color <when>            auto (default), always or never
+    --diagnostic-format <f>   format of the diagnostics on stderr: human (default), json or sarif
+    --fix                     apply the machine-applicable suggestions to the files in place
+    --allow <lint>            discard the items of a lint, e.g. unused-input or SPK2120; repeatable
+    --warn <lint>             report a lint as warnings; repeatable
+    --deny <lint>             report a lint as errors; repeatable
+    --forbid <lint>           like --deny, and ignore the `@allow` attributes of the lint
+    -h, --help                print this message
+
+lint levels override the `[lints]` table of the nearest spk.toml, in the directory of the file
+or above it.
+
+exit status:
+    0     no errors
+    1-63  number of reported errors, saturating at 63
+    64    invalid command line
+    74    a file cannot be read or written";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    Check,
+    Build,
+    Variants,
+    DumpTokens,
+    DumpAst,
+    DumpIr,
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "check" => Ok(Self::Check),
+            "build" => Ok(Self::Build),
+            "variants" => Ok(Self::Variants),
+            "dump-tokens" => Ok(Self::DumpTokens),
+            "dump-ast" => Ok(Self::DumpAst),
+            "dump-ir" => Ok(Self::DumpIr),
+            _ => Err(format!("unknown command `{}`", str)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// The textual dump of the optimized IR.
+    #[default]
+    Ir,
+}
+
+impl Backend {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ir => "ir",
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "ir" => Ok(Self::Ir),
+            _ => Err(format!("unknown backend `{}`; available: ir", str)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "auto" => Ok(Self::Auto),
+            "always" => Ok(Self::Always),
+            "never" => Ok(Self::Never),
+            _ => Err(format!(
+                "invalid color choice `{}`; expected auto, always or never",
+                str
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticFormat {
+    #[default]
+    Human,
+    /// The JSON format of `shader_pack::diagnostics::items_to_json`.
+    Json,
+    Sarif,
+}
+
+impl FromStr for DiagnosticFormat {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "human" => Ok(Self::Human),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            _ => Err(format!(
+                "invalid diagnostic format `{}`; expected human, json or sarif",
+                str
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Args {
+    Help,
+    /// Prints the explanation of a diagnostic code.
+    Explain(String),
+    Run(RunArgs),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RunArgs {
+    pub command: Command,
+    pub file: PathBuf,
+    pub flags: Vec<String>,
+    pub backend: Backend,
+    pub out_dir: Option<PathBuf>,
+    pub archive: bool,
+    pub cache_dir: Option<PathBuf>,
+    pub opt_level: OptLevel,
+    pub enabled_passes: Vec<OptPass>,
+    pub disabled_passes: Vec<OptPass>,
+    pub color: ColorChoice,
+    pub diagnostic_format: DiagnosticFormat,
+    /// Applies the machine-applicable suggestions of the reported items to the files.
+    pub fix: bool,
+    pub lints: LintLevels,
+}
+
+impl Args {
+    /// Parses the arguments, excluding the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+
+        if args.next_if(|arg| arg == "explain").is_some() {
+            return match (args.next(), args.next()) {
+                (Some(arg), _) if arg == "-h" || arg == "--help" => Ok(Self::Help),
+                (Some(code), None) => Ok(Self::Explain(code)),
+                (Some(_), Some(arg)) => Err(format!("unexpected argument `{}`", arg)),
+                (None, _) => Err("no diagnostic code given".to_owned()),
+            };
+        }
+
+        let mut command = None;
+        let mut file = None;
+        let mut flags = Vec::new();
+        let mut backend = Backend::default();
+        let mut out_dir = None;
+        let mut archive = false;
+        let mut cache_dir = None;
+        let mut opt_level = OptLevel::default();
+        let mut enabled_passes = Vec::new();
+        let mut disabled_passes = Vec::new();
+        let mut color = ColorChoice::default();
+        let mut diagnostic_format = DiagnosticFormat::default();
+        let mut fix = false;
+        let mut lints = LintLevels::new();
+
+        while let Some(arg) = args.next() {
+            // both `--name value` and `--name=value` are accepted
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) if arg.starts_with("--") => (name, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("`{}` requires a value", name))
+            };
+
+            match name {
+                "-h" | "--help" => return Ok(Self::Help),
+                "--flag" => flags.push(value()?),
+                "--backend" => backend = parse_value(&value()?)?,
+                "--out-dir" => out_dir = Some(PathBuf::from(value()?)),
+                "--archive" => archive = true,
+                "--cache-dir" => cache_dir = Some(PathBuf::from(value()?)),
+                "--enable-pass" => enabled_passes.push(parse_value(&value()?)?),
+                "--disable-pass" => disabled_passes.push(parse_value(&value()?)?),
+                "--color" => color = parse_value(&value()?)?,
+                "--diagnostic-format" => diagnostic_format = parse_value(&value()?)?,
+                "--fix" => fix = true,
+                "--allow" | "--warn" | "--deny" | "--forbid" => {
+                    let level: LintLevel = parse_value(&name[2..])?;
+                    lints.set_by_name(&value()?, level)?;
+                }
+                _ if name.starts_with("-O") => opt_level = parse_value(&name[2..])?,
+                _ if name.starts_with('-') => return Err(format!("unknown option `{}`", name)),
+                _ if command.is_none() => command = Some(parse_value(name)?),
+                _ if file.is_none() => file = Some(PathBuf::from(name)),
+                _ => return Err(format!("unexpected argument `{}`", name)),
+            }
+        }
+
+        Ok(Self::Run(RunArgs {
+            command: command.ok_or("no command given")?,
+            file: file.ok_or("no input file given")?,
+            flags,
+            backend,
+            out_dir,
+            archive,
+            cache_dir,
+            opt_level,
+            enabled_passes,
+            disabled_passes,
+            color,
+            diagnostic_format,
+            fix,
+            lints,
+        }))
+    }
+}
+
+fn parse_value<T>(str: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    str.parse().map_err(|err: T::Err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shader_pack::diagnostics::codes::TYPE_WARN_UNUSED_INPUT;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_args_parse() {
+        let args = parse(&[
+            "build",
+            "--flag",
+            "shadow",
+            "--flag=fog",
+            "-O2",
+            "--disable-pass",
+            "inline",
+            "--color=never",
+            "--diagnostic-format",
+            "sarif",
+            "--fix",
+            "--cache-dir",
+            ".spk-cache",
+            "--deny=unused-input",
+            "--allow",
+            "SPK2120",
+            "pack.spk",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args,
+            Args::Run(RunArgs {
+                command: Command::Build,
+                file: PathBuf::from("pack.spk"),
+                flags: vec!["shadow".to_owned(), "fog".to_owned()],
+                backend: Backend::Ir,
+                out_dir: None,
+                archive: false,
+                cache_dir: Some(PathBuf::from(".spk-cache")),
+                opt_level: OptLevel::Full,
+                enabled_passes: vec![],
+                disabled_passes: vec![OptPass::FunctionInlining],
+                color: ColorChoice::Never,
+                diagnostic_format: DiagnosticFormat::Sarif,
+                fix: true,
+                lints: {
+                    let mut lints = LintLevels::new();
+                    lints.set(TYPE_WARN_UNUSED_INPUT, LintLevel::Allow);
+                    lints
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_args_parse_explain() {
+        assert_eq!(
+            parse(&["explain", "SPK2010"]),
+            Ok(Args::Explain("SPK2010".to_owned()))
+        );
+        assert_eq!(parse(&["explain", "--help"]), Ok(Args::Help));
+    }
+
+    #[test]
+    fn test_args_parse_invalid() {
+        assert_eq!(parse(&["check", "--help"]), Ok(Args::Help));
+        assert!(parse(&["compile", "pack.spk"]).is_err());
+        assert!(parse(&["check"]).is_err());
+        assert!(parse(&["check", "a.spk", "b.spk"]).is_err());
+        assert!(parse(&["check", "--flag"]).is_err());
+        assert!(parse(&["check", "--const=count=4", "a.spk"]).is_err());
+        assert!(parse(&["build", "--backend=glsl", "a.spk"]).is_err());
+        assert!(parse(&["check", "--diagnostic-format=xml", "a.spk"]).is_err());
+        assert!(parse(&["check", "--deny=unused-flag", "a.spk"]).is_err());
+        assert!(parse(&["explain"]).is_err());
+        assert!(parse(&["explain", "SPK2010", "SPK2020"]).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+mod args;
+
+use args::{Args, Backend, ColorChoice, Command, DiagnosticFormat, RunArgs, USAGE};
+use shader_pack::{
+    archive::ArchiveWriter,
+    cache::{
+        compile_cached, compile_pack_batch_cached, Cache, CacheLimits, FileCacheStore,
+        RecordingFileLoader,
+    },
+    compile::{
+        compile_pack_batch, compile_with_loader, CompileOptions, Emit, ProjectConfig, VariantKey,
+    },
+    comptime::enumerate_variants,
+    diagnostics::{
+        apply_suggestions, codes::explain, items_to_json, items_to_sarif, stringify_item, Item,
+        ItemCollector, ItemLevel, ItemSender,
+    },
+    parse::{
+        ast::AstShaderPack,
+        lexer::{token_iter, TokenKind},
+        parse_shader_pack_with_imports, FileLoader, FsFileLoader,
+    },
+    span::{SourceFile, SourceMap},
+};
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{stderr, stdout, IsTerminal, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+const EXIT_MAX_ERRORS: usize = 63;
+const EXIT_USAGE: u8 = 64;
+const EXIT_IO: u8 = 74;
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Args::Help) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Args::Explain(code)) => {
+            return match explain(&code) {
+                Some(explanation) => {
+                    print!("{}", explanation);
+                    ExitCode::SUCCESS
+                }
+                None => {
+                    eprintln!("error: unknown diagnostic code `{}`", code);
+                    ExitCode::from(EXIT_USAGE)
+                }
+            };
+        }
+        Ok(Args::Run(args)) => args,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let result = run(&args).and_then(|items| {
+        if args.fix {
+            fix(&items)?;
+        }
+        Ok(items)
+    });
+
+    match result {
+        Ok(items) => report(&items, &args),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            ExitCode::from(EXIT_IO)
+        }
+    }
+}
+
+/// Applies the machine-applicable suggestions to every file they are in, and writes the files.
+///
+/// The items are still reported as they are; checking again shows what remains.
+fn fix(items: &[Item]) -> Result<(), String> {
+    let mut files = Vec::<Arc<SourceFile>>::new();
+
+    for origin in items.iter().filter_map(|item| item.origin.as_ref()) {
+        if !files.iter().any(|file| Arc::ptr_eq(file, &origin.file)) {
+            files.push(origin.file.clone());
+        }
+    }
+
+    for file in files {
+        let path = match file.path() {
+            Some(path) => path,
+            None => continue,
+        };
+        let (fixed, count) = apply_suggestions(&file, items);
+
+        if count == 0 {
+            continue;
+        }
+
+        fs::write(path, fixed.content())
+            .map_err(|err| format!("cannot write `{}`: {}", path.display(), err))?;
+        eprintln!(
+            "fixed {} problem{} in `{}`",
+            count,
+            if count == 1 { "" } else { "s" },
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+fn apply_styles(color: ColorChoice) -> bool {
+    let apply_styles = match color {
+        ColorChoice::Auto => stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    };
+    // `colored` has its own detection; keep it from overriding the choice
+    colored::control::set_override(apply_styles);
+    apply_styles
+}
+
+/// Prints every item to stderr, and turns the number of errors into the exit code.
+fn report(items: &[Item], args: &RunArgs) -> ExitCode {
+    match args.diagnostic_format {
+        DiagnosticFormat::Human => {
+            let apply_styles = apply_styles(args.color);
+
+            for item in items {
+                eprint!("{}", stringify_item(item, apply_styles));
+            }
+        }
+        DiagnosticFormat::Json => eprintln!("{}", items_to_json(items)),
+        DiagnosticFormat::Sarif => eprintln!("{}", items_to_sarif(items)),
+    }
+
+    let errors = items
+        .iter()
+        .filter(|item| item.level == ItemLevel::Error)
+        .count();
+    ExitCode::from(errors.min(EXIT_MAX_ERRORS) as u8)
+}
+
+/// Runs the command; returns the reported items, or an error if a file cannot be accessed.
+fn run(args: &RunArgs) -> Result<Vec<Item>, String> {
+    let content = fs::read_to_string(&args.file)
+        .map_err(|err| format!("cannot read `{}`: {}", args.file.display(), err))?;
+    let name = args
+        .file
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let mut source_map = SourceMap::new();
+    let file = source_map.add_file(content, name, Some(args.file.clone()));
+
+    let mut options = CompileOptions {
+        emit: vec![],
+        flags: args.flags.clone(),
+        opt_level: args.opt_level,
+        enabled_passes: args.enabled_passes.clone(),
+        disabled_passes: args.disabled_passes.clone(),
+        lints: args.lints.clone(),
+    };
+
+    if let Some(path) = ProjectConfig::find(&args.file) {
+        ProjectConfig::load(&path)?.apply(&mut options);
+    }
+
+    match args.command {
+        Command::Check => Ok(compile_with_loader(file, &options, &FsFileLoader).items),
+        Command::Build if args.archive => build_archive(file, args, &options),
+        Command::Build => build(file, args, options),
+        Command::Variants => with_parsed(file, &options, &FsFileLoader, |pack, reporter, _| {
+            if let Some(variants) = enumerate_variants(pack, reporter) {
+                print_output(&variants.to_string());
+            }
+        }),
+        Command::DumpTokens => {
+            let mut output = String::new();
+
+            for token in token_iter(&file) {
+                // the end of file token is placed right after the last character
+                if token.kind == TokenKind::EndOfFile {
+                    writeln!(output, "{:?}", token.kind).unwrap();
+                    break;
+                }
+
+                let line_col = file.find_line_col(token.span_low);
+                writeln!(
+                    output,
+                    "{}:{} {:?}",
+                    line_col.line + 1,
+                    line_col.col + 1,
+                    token.kind
+                )
+                .unwrap();
+            }
+
+            print_output(&output);
+            Ok(vec![])
+        }
+        Command::DumpAst => with_parsed(file, &options, &FsFileLoader, |pack, _, _| {
+            print_output(&format!("{:#?}\n", pack))
+        }),
+        Command::DumpIr => {
+            let options = CompileOptions {
+                emit: vec![Emit::Ir],
+                ..options
+            };
+            let output = compile_with_loader(file, &options, &FsFileLoader);
+
+            for (_, emitted) in &output.emitted {
+                print_output(emitted);
+            }
+
+            Ok(output.items)
+        }
+    }
+}
+
+/// Writes to stdout; a closed stdout, e.g. piped into `head`, is not an error.
+fn print_output(output: &str) {
+    let _ = stdout().lock().write_all(output.as_bytes());
+}
+
+/// Parses the file with its imports loaded through `loader` and the lint levels of the options,
+/// and calls `f` with the pack and the source map of the file and its imports if it parses.
+fn with_parsed(
+    file: Arc<SourceFile>,
+    options: &CompileOptions,
+    loader: &dyn FileLoader,
+    f: impl FnOnce(&AstShaderPack, &ItemSender, &Arc<SourceMap>),
+) -> Result<Vec<Item>, String> {
+    let collector = Arc::new(ItemCollector::new());
+    let reporter =
+        ItemSender::new(file.clone(), collector.clone()).with_lint_levels(options.lints.clone());
+
+    let mut source_map = SourceMap::from_file(file.clone());
+    let pack = parse_shader_pack_with_imports(&file, &mut source_map, loader, &reporter);
+    let source_map = Arc::new(source_map);
+    let reporter = reporter.with_source_map(source_map.clone());
+
+    if let Some(pack) = pack {
+        f(&pack, &reporter, &source_map);
+    }
+    drop(reporter);
+
+    Ok(collector.take())
+}
+
+fn build(
+    file: Arc<SourceFile>,
+    args: &RunArgs,
+    options: CompileOptions,
+) -> Result<Vec<Item>, String> {
+    let options = CompileOptions {
+        emit: vec![backend_emit(args.backend), Emit::Reflection],
+        ..options
+    };
+    let output = match &args.cache_dir {
+        Some(dir) => compile_cached(&open_cache(dir)?, file, &options, &FsFileLoader).0,
+        None => compile_with_loader(file, &options, &FsFileLoader),
+    };
+
+    for (emit, emitted) in &output.emitted {
+        let extension = match emit {
+            Emit::Reflection => "reflection.json",
+            _ => args.backend.name(),
+        };
+        write_output(args, extension, emitted.as_bytes())?;
+    }
+
+    Ok(output.items)
+}
+
+/// Compiles every combination of the comptime flags of the pack into a single archive.
+fn build_archive(
+    file: Arc<SourceFile>,
+    args: &RunArgs,
+    options: &CompileOptions,
+) -> Result<Vec<Item>, String> {
+    let options = CompileOptions {
+        emit: vec![backend_emit(args.backend)],
+        ..options.clone()
+    };
+    let cache = args.cache_dir.as_deref().map(open_cache).transpose()?;
+    // records the imports, which complete the cache keys
+    let recorder = RecordingFileLoader::new(&FsFileLoader);
+    let mut archive = None;
+    let mut items = with_parsed(
+        file.clone(),
+        &options,
+        &recorder,
+        |pack, reporter, source_map| {
+            let variants = match enumerate_variants(pack, reporter) {
+                Some(variants) => variants,
+                None => return,
+            };
+            let keys = Vec::from_iter((0..variants.combination_variants.len()).map(|mask| {
+                VariantKey::new(
+                    variants
+                        .flags
+                        .iter()
+                        .enumerate()
+                        .filter(|(index, _)| mask & (1 << index) != 0)
+                        .map(|(_, flag)| flag.to_str()),
+                )
+            }));
+            let batch = match &cache {
+                Some(cache) => compile_pack_batch_cached(
+                    cache,
+                    file.clone(),
+                    source_map.clone(),
+                    pack,
+                    &keys,
+                    &options,
+                    &recorder,
+                ),
+                None => compile_pack_batch(file.clone(), source_map.clone(), pack, &keys, &options),
+            };
+            archive = Some((ArchiveWriter::from_batch(source_map, &batch), batch));
+        },
+    )?;
+
+    if let Some((writer, batch)) = archive {
+        for output in batch.outputs {
+            items.extend(output.items);
+        }
+
+        write_output(args, "spka", &writer.finish())?;
+    }
+
+    Ok(items)
+}
+
+fn open_cache(dir: &Path) -> Result<Cache<FileCacheStore>, String> {
+    let store = FileCacheStore::new(dir)
+        .map_err(|err| format!("cannot open the cache `{}`: {}", dir.display(), err))?;
+    Ok(Cache::new(store, CacheLimits::default()))
+}
+
+fn backend_emit(backend: Backend) -> Emit {
+    match backend {
+        Backend::Ir => Emit::Ir,
+    }
+}
+
+/// Writes `<out-dir>/<file stem>.<extension>`.
+fn write_output(args: &RunArgs, extension: &str, data: &[u8]) -> Result<(), String> {
+    let dir = match &args.out_dir {
+        Some(dir) => dir.clone(),
+        None => args
+            .file
+            .parent()
+            .map_or_else(PathBuf::new, Path::to_path_buf),
+    };
+    let stem = args
+        .file
+        .file_stem()
+        .map_or_else(|| "out".into(), |stem| stem.to_string_lossy());
+    let path = dir.join(format!("{}.{}", stem, extension));
+
+    fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&path, data))
+        .map_err(|err| format!("cannot write `{}`: {}", path.display(), err))
+}
@@ -0,0 +1,736 @@
+mod item;
+mod key;
+mod store;
+
+pub use item::*;
+pub use key::*;
+pub use store::*;
+
+use crate::{
+    compile::{
+        compile_pack_batch, compile_with_loader, BatchOutput, CompileOptions, CompileOutput, Emit,
+        VariantKey,
+    },
+    diagnostics::ItemLevel,
+    parse::{ast::AstShaderPack, FileLoader},
+    reflect::Reflection,
+    span::{SourceFile, SourceMap},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Limits of a cache; the least recently used entries are evicted once any of them is exceeded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheLimits {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<u64>,
+}
+
+/// Outputs of a successful compilation, as stored in the cache.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CachedOutput {
+    pub emitted: Vec<(Emit, String)>,
+    pub reflection: Option<Reflection>,
+    /// The warnings and hints of the compilation, reported again on hits.
+    pub items: Vec<CachedItem>,
+    /// The files the items are in.
+    pub files: Vec<CachedFile>,
+}
+
+/// Content-addressed cache of compiled outputs.
+#[derive(Debug, Clone)]
+pub struct Cache<S> {
+    store: S,
+    limits: CacheLimits,
+}
+
+/// What is actually written to the store; the full key is kept next to the outputs, so that a
+/// digest collision is detected instead of returning the outputs of another program.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    /// Imported paths, to complete the key with the canonical paths they are resolved to now.
+    imports: Vec<String>,
+    /// Paths of the imported files, to complete the key with their current content.
+    includes: Vec<String>,
+    output: CachedOutput,
+}
+
+impl<S> Cache<S>
+where
+    S: CacheStore,
+{
+    pub fn new(store: S, limits: CacheLimits) -> Self {
+        Self { store, limits }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the cached outputs of the key; unreadable entries are treated as misses.
+    pub fn get(&self, key: &CacheKey) -> Option<CachedOutput> {
+        let entry = self.load(key)?;
+        (entry.key == key.encode()).then_some(entry.output)
+    }
+
+    /// Returns the cached outputs of a key without imports, if the imports of the cached
+    /// compilation still resolve to the same files and these files have not changed since; they
+    /// are resolved and loaded through `loader`, and the files are returned along with the
+    /// outputs.
+    pub fn get_with_loader(
+        &self,
+        key: &CacheKey,
+        loader: &dyn FileLoader,
+    ) -> Option<(CachedOutput, Vec<(PathBuf, String)>)> {
+        let entry = self.load(key)?;
+        let mut full_key = key.clone();
+        let mut files = Vec::with_capacity(entry.includes.len());
+
+        for path in entry.imports {
+            let canonical_path = loader.canonicalize(Path::new(&path));
+            full_key = full_key.with_import(&path, &canonical_path.to_string_lossy());
+        }
+
+        for path in entry.includes {
+            let path = PathBuf::from(path);
+            let content = loader.load(&path).ok()?;
+            full_key = full_key.with_include(&path.to_string_lossy(), &content);
+            files.push((path, content));
+        }
+
+        (entry.key == full_key.encode()).then_some((entry.output, files))
+    }
+
+    fn load(&self, key: &CacheKey) -> Option<CacheEntry> {
+        let data = self.store.load(&key.digest()).ok()??;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// Stores the outputs of the key, then evicts entries exceeding the limits.
+    pub fn put(&self, key: &CacheKey, output: &CachedOutput) -> io::Result<()> {
+        let entry = CacheEntry {
+            key: key.encode(),
+            imports: Vec::from_iter(key.imports.iter().map(|(path, _)| path.clone())),
+            includes: Vec::from_iter(key.includes.iter().map(|(path, _)| path.clone())),
+            output: output.clone(),
+        };
+        self.store
+            .store(&key.digest(), &serde_json::to_vec(&entry)?)?;
+        self.evict()?;
+        Ok(())
+    }
+
+    /// Removes the least recently used entries until the limits are met; returns the number of
+    /// removed entries.
+    pub fn evict(&self) -> io::Result<usize> {
+        let mut entries = self.store.entries()?;
+        entries.sort_by_key(|entry| entry.last_used);
+
+        let mut count = entries.len();
+        let mut bytes = entries.iter().map(|entry| entry.size).sum::<u64>();
+        let mut removed = 0;
+
+        for entry in entries {
+            let is_within_limits = self.limits.max_entries.is_none_or(|max| count <= max)
+                && self.limits.max_bytes.is_none_or(|max| bytes <= max);
+
+            if is_within_limits {
+                break;
+            }
+
+            self.store.remove(&entry.digest)?;
+            count -= 1;
+            bytes -= entry.size;
+            removed += 1;
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Whether [`compile_cached`] found the outputs in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+}
+
+/// Compiles the file unless its outputs are already cached; a hit skips every compilation step,
+/// including lexing and parsing, so `module` of the output is always `None` on hits. The items
+/// of the cached compilation are returned again on hits.
+///
+/// Imported files are resolved and loaded through `loader`; a hit requires the file to be at the
+/// same path, and its imports to resolve to the same unchanged files as in the cached
+/// compilation. Only successful compilations are stored.
+pub fn compile_cached<S>(
+    cache: &Cache<S>,
+    file: Arc<SourceFile>,
+    options: &CompileOptions,
+    loader: &dyn FileLoader,
+) -> (CompileOutput, CacheStatus)
+where
+    S: CacheStore,
+{
+    let key = root_key(&file, options, loader);
+
+    if let Some((cached, includes)) = cache.get_with_loader(&key, loader) {
+        let mut source_map = SourceMap::from_file(file.clone());
+        let files = Vec::from_iter(cached.files.iter().map(|cached_file| {
+            if cached_file.name == file.name() && cached_file.path.as_deref() == file.path() {
+                return Some(file.clone());
+            }
+
+            let path = cached_file.path.as_ref()?;
+            let (_, content) = includes.iter().find(|(include, _)| include == path)?;
+            Some(source_map.add_file(content.clone(), cached_file.name.clone(), path.clone()))
+        }));
+        return (restore_output(cached, &files), CacheStatus::Hit);
+    }
+
+    let recorder = RecordingFileLoader::new(loader);
+    let output = compile_with_loader(file.clone(), options, &recorder);
+
+    if output.module.is_some() {
+        // failing to fill the cache must not fail the compilation
+        let _ = cache.put(&recorder.complete_key(key, &file), &cache_output(&output));
+    }
+
+    (output, CacheStatus::Miss)
+}
+
+/// Compiles every variant of an already parsed pack like [`compile_pack_batch`], reusing the
+/// cached outputs of the variants whose imports are unchanged; only the other variants are
+/// compiled, then stored.
+///
+/// The pack must have been parsed through `recorder`, which completes the keys of the compiled
+/// variants with the imports of the pack. Cached outputs equal to another output are merged into
+/// it.
+pub fn compile_pack_batch_cached<S>(
+    cache: &Cache<S>,
+    file: Arc<SourceFile>,
+    source_map: Arc<SourceMap>,
+    pack: &AstShaderPack,
+    variants: &[VariantKey],
+    options: &CompileOptions,
+    recorder: &RecordingFileLoader,
+) -> BatchOutput
+where
+    S: CacheStore,
+{
+    let keys = Vec::from_iter(variants.iter().map(|variant| {
+        let options = CompileOptions {
+            flags: variant.flags().to_vec(),
+            ..options.clone()
+        };
+        root_key(&file, &options, recorder.loader())
+    }));
+    let mut hits = Vec::new();
+    let mut misses = Vec::new();
+
+    for (variant, key) in variants.iter().zip(&keys) {
+        match cache.get_with_loader(key, recorder.loader()) {
+            Some((cached, _)) => hits.push((variant, cached)),
+            None => misses.push(variant.clone()),
+        }
+    }
+
+    let mut batch = compile_pack_batch(file.clone(), source_map.clone(), pack, &misses, options);
+
+    for (variant, key) in variants.iter().zip(keys) {
+        if let Some(output) = batch
+            .output(variant)
+            .filter(|output| output.module.is_some())
+        {
+            let _ = cache.put(&recorder.complete_key(key, &file), &cache_output(output));
+        }
+    }
+
+    for (variant, cached) in hits {
+        let files = Vec::from_iter(cached.files.iter().map(|cached_file| {
+            source_map
+                .files()
+                .iter()
+                .find(|file| {
+                    file.name() == cached_file.name && file.path() == cached_file.path.as_deref()
+                })
+                .cloned()
+        }));
+        let output = restore_output(cached, &files);
+        let index = match batch.outputs.iter().position(|other| {
+            other.emitted == output.emitted && other.reflection == output.reflection
+        }) {
+            Some(index) => index,
+            None => {
+                batch.outputs.push(output);
+                batch.outputs.len() - 1
+            }
+        };
+
+        batch.variants.insert(variant.clone(), index);
+    }
+
+    batch
+}
+
+/// Returns the key of a compilation of the file, without its imports.
+fn root_key(file: &SourceFile, options: &CompileOptions, loader: &dyn FileLoader) -> CacheKey {
+    let key = CacheKey::from_options(file.content(), options);
+
+    match file.path() {
+        Some(path) => key.with_root_path(&loader.canonicalize(path).to_string_lossy()),
+        None => key,
+    }
+}
+
+/// Keeps the outputs and the items but the errors of a successful compilation.
+fn cache_output(output: &CompileOutput) -> CachedOutput {
+    let items = Vec::from_iter(
+        output
+            .items
+            .iter()
+            .filter(|item| item.level != ItemLevel::Error)
+            .cloned(),
+    );
+    let mut files = Vec::new();
+
+    CachedOutput {
+        emitted: output.emitted.clone(),
+        reflection: output.reflection.clone(),
+        items: cache_items(&items, &mut files),
+        files,
+    }
+}
+
+/// `files` are the source files of the cached files, see [`restore_items`].
+fn restore_output(cached: CachedOutput, files: &[Option<Arc<SourceFile>>]) -> CompileOutput {
+    CompileOutput {
+        items: restore_items(cached.items, files),
+        module: None,
+        reflection: cached.reflection,
+        emitted: cached.emitted,
+    }
+}
+
+/// Records the paths resolved and the files loaded through another loader, to complete the cache
+/// keys of the compilations using it with their imports.
+pub struct RecordingFileLoader<'a> {
+    loader: &'a dyn FileLoader,
+    resolved: RefCell<Vec<(PathBuf, PathBuf)>>,
+    loaded: RefCell<Vec<(PathBuf, String)>>,
+}
+
+impl<'a> RecordingFileLoader<'a> {
+    pub fn new(loader: &'a dyn FileLoader) -> Self {
+        Self {
+            loader,
+            resolved: RefCell::new(Vec::new()),
+            loaded: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// The loader the files are actually loaded through.
+    pub fn loader(&self) -> &'a dyn FileLoader {
+        self.loader
+    }
+
+    /// Adds the imports resolved and the files loaded so far to the key of the `root` file.
+    pub fn complete_key(&self, key: CacheKey, root: &SourceFile) -> CacheKey {
+        let mut key = key;
+
+        // the root file is resolved too, but it is part of the key already
+        for (path, canonical_path) in self.resolved.borrow().iter() {
+            if Some(path.as_path()) != root.path() {
+                key = key.with_import(&path.to_string_lossy(), &canonical_path.to_string_lossy());
+            }
+        }
+
+        for (path, content) in self.loaded.borrow().iter() {
+            key = key.with_include(&path.to_string_lossy(), content);
+        }
+
+        key
+    }
+}
+
+impl FileLoader for RecordingFileLoader<'_> {
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        let canonical_path = self.loader.canonicalize(path);
+        self.resolved
+            .borrow_mut()
+            .push((path.to_path_buf(), canonical_path.clone()));
+        canonical_path
+    }
+
+    fn load(&self, path: &Path) -> io::Result<String> {
+        let content = self.loader.load(path)?;
+        self.loaded
+            .borrow_mut()
+            .push((path.to_path_buf(), content.clone()));
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compile::VariantKey,
+        diagnostics::{Applicability, Item, ItemOrigin, LintLevel, Suggestion},
+        parse::MemoryFileLoader,
+        span::Span,
+        test_utils::*,
+    };
+
+    fn output(ir: &str) -> CachedOutput {
+        CachedOutput {
+            emitted: vec![(Emit::Ir, ir.to_owned())],
+            reflection: Some(Reflection::default()),
+            items: vec![],
+            files: vec![],
+        }
+    }
+
+    #[test]
+    fn test_cache_get_put() {
+        let cache = Cache::new(MemoryCacheStore::new(), CacheLimits::default());
+        let key = CacheKey::new("in color: f3;", &VariantKey::new(["fog"]), "ir");
+
+        assert_eq!(cache.get(&key), None);
+        cache.put(&key, &output("a")).unwrap();
+        assert_eq!(cache.get(&key), Some(output("a")));
+
+        let other = CacheKey::new("in color: f3;", &VariantKey::new(["fog"]), "ir")
+            .with_include("common.spk", "fn f() {}");
+        assert_eq!(cache.get(&other), None);
+    }
+
+    #[test]
+    fn test_cache_evict() {
+        let limits = CacheLimits {
+            max_entries: Some(2),
+            max_bytes: None,
+        };
+        let cache = Cache::new(MemoryCacheStore::new(), limits);
+        let keys = Vec::from_iter(
+            ["a", "b", "c"].map(|source| CacheKey::new(source, &VariantKey::new(["x"]), "ir")),
+        );
+
+        cache.put(&keys[0], &output("a")).unwrap();
+        cache.put(&keys[1], &output("b")).unwrap();
+        // `a` is now used more recently than `b`
+        assert!(cache.get(&keys[0]).is_some());
+        cache.put(&keys[2], &output("c")).unwrap();
+
+        assert!(cache.get(&keys[0]).is_some());
+        assert_eq!(cache.get(&keys[1]), None);
+        assert!(cache.get(&keys[2]).is_some());
+    }
+
+    #[test]
+    fn test_cache_key_lints() {
+        let key = |levels: &[(&str, LintLevel)]| {
+            let mut options = CompileOptions::default();
+
+            for (lint, level) in levels {
+                options.lints.set_by_name(lint, *level).unwrap();
+            }
+
+            CacheKey::from_options("in color: f3;", &options)
+        };
+
+        assert_eq!(
+            key(&[]),
+            CacheKey::from_options("in color: f3;", &Default::default())
+        );
+        assert_ne!(key(&[]), key(&[("unused-input", LintLevel::Deny)]));
+        assert_eq!(
+            key(&[("unused-input", LintLevel::Deny)]),
+            key(&[
+                ("unused-input", LintLevel::Allow),
+                ("SPK2120", LintLevel::Deny)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_compile_cached_hit_skips_parsing() {
+        let mut source_map = SourceMap::new();
+        // not even valid syntax; a hit must not look at it
+        let file = source_map.add_file("pass {", "test", None);
+        let options = CompileOptions {
+            emit: vec![Emit::Ir],
+            ..Default::default()
+        };
+        let cache = Cache::new(MemoryCacheStore::new(), CacheLimits::default());
+        cache
+            .put(
+                &CacheKey::from_options("pass {", &options),
+                &output("cached"),
+            )
+            .unwrap();
+
+        let (output, status) = compile_cached(&cache, file, &options, &MemoryFileLoader::new());
+        assert_eq!(status, CacheStatus::Hit);
+        assert!(output.items.is_empty());
+        assert_eq!(output.emitted, [(Emit::Ir, "cached".to_owned())]);
+    }
+
+    #[test]
+    fn test_compile_cached_hit_restores_items() {
+        let mut source_map = SourceMap::new();
+        source_map.add_file("# other", "other", None);
+        let file = source_map.add_file("in color: f3;", "test", None);
+        let file_low = file.span().low();
+        let item = |code: u32, low: u32, high: u32| Item {
+            code,
+            level: ItemLevel::Warning,
+            message: "unused".to_owned(),
+            origin: Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(low, high),
+            }),
+            sub_items: vec![],
+            suggestions: vec![Suggestion::new(
+                "remove it",
+                Span::new(low, high),
+                "",
+                Applicability::MaybeIncorrect,
+            )],
+        };
+        let options = CompileOptions::default();
+        let cache = Cache::new(MemoryCacheStore::new(), CacheLimits::default());
+        let mut files = Vec::new();
+        let items = cache_items(&[item(2120, file_low + 3, file_low + 8)], &mut files);
+        files.push(CachedFile {
+            name: "missing.spk".to_owned(),
+            path: Some("missing.spk".into()),
+        });
+        let mut cached = output("cached");
+        cached.items = items.clone();
+        let missing = CachedSpan {
+            file: 1,
+            low: 0,
+            high: 1,
+        };
+        let mut suggestion = items[0].suggestions[0].clone();
+        suggestion.span = missing.clone();
+        cached.items.push(CachedItem {
+            origin: Some(missing),
+            suggestions: vec![suggestion],
+            ..items[0].clone()
+        });
+        cached.files = files;
+        cache
+            .put(&CacheKey::from_options(file.content(), &options), &cached)
+            .unwrap();
+
+        // the file is placed elsewhere in the source map of the next compilation
+        let file = SourceMap::new().add_file("in color: f3;", "test", None);
+        let (output, status) =
+            compile_cached(&cache, file.clone(), &options, &MemoryFileLoader::new());
+        assert_eq!(status, CacheStatus::Hit);
+        assert_eq!(output.items.len(), 2);
+
+        let origin = output.items[0].origin.as_ref().unwrap();
+        assert!(Arc::ptr_eq(&origin.file, &file));
+        assert_eq!(file.slice(origin.span), "color");
+        assert_eq!(output.items[0].suggestions[0].span, origin.span);
+        // spans in files which are gone are dropped
+        assert!(output.items[1].origin.is_none());
+        assert!(output.items[1].suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_cache_get_with_loader() {
+        let cache = Cache::new(MemoryCacheStore::new(), CacheLimits::default());
+        let key = CacheKey::new("import \"common.spk\";", &VariantKey::new(["x"]), "ir");
+        cache
+            .put(
+                &key.clone().with_include("common.spk", "fn f() {}"),
+                &output("a"),
+            )
+            .unwrap();
+        let get = |content: Option<&str>| {
+            let mut loader = MemoryFileLoader::new();
+
+            if let Some(content) = content {
+                loader.add_file("common.spk", content);
+            }
+
+            cache.get_with_loader(&key, &loader)
+        };
+
+        assert_eq!(
+            get(Some("fn f() {}")),
+            Some((
+                output("a"),
+                vec![(PathBuf::from("common.spk"), "fn f() {}".to_owned())]
+            ))
+        );
+        assert_eq!(get(Some("fn g() {}")), None);
+        assert_eq!(get(None), None);
+        // the imports are not known without a loader
+        assert_eq!(cache.get(&key), None);
+    }
+
+    #[test]
+    fn test_compile_cached_hit_restores_imported_files() {
+        let file = SourceMap::new().add_file("import \"common.spk\";", "main.spk", None);
+        let options = CompileOptions::default();
+        let cache = Cache::new(MemoryCacheStore::new(), CacheLimits::default());
+        let mut cached = output("cached");
+        cached.items = vec![CachedItem {
+            code: 2120,
+            level: ItemLevel::Warning,
+            message: "unused".to_owned(),
+            origin: Some(CachedSpan {
+                file: 0,
+                low: 3,
+                high: 8,
+            }),
+            sub_items: vec![],
+            suggestions: vec![],
+        }];
+        cached.files = vec![CachedFile {
+            name: "./common.spk".to_owned(),
+            path: Some("common.spk".into()),
+        }];
+        let key = CacheKey::from_options(file.content(), &options)
+            .with_include("common.spk", "in color: f3;");
+        cache.put(&key, &cached).unwrap();
+
+        let mut loader = MemoryFileLoader::new();
+        loader.add_file("common.spk", "in color: f3;");
+        let (output, status) = compile_cached(&cache, file, &options, &loader);
+        assert_eq!(status, CacheStatus::Hit);
+
+        let origin = output.items[0].origin.as_ref().unwrap();
+        assert_eq!(origin.file.name(), "./common.spk");
+        assert_eq!(origin.file.slice(origin.span), "color");
+    }
+
+    #[test]
+    fn test_compile_cached_root_paths() {
+        // not even valid syntax; a hit must not look at it
+        let source = "import \"common.spk\"; pass {";
+        let options = CompileOptions::default();
+        let cache = Cache::new(MemoryCacheStore::new(), CacheLimits::default());
+        let mut loader = MemoryFileLoader::new();
+        loader.add_file("a/common.spk", "in first: f;");
+        loader.add_file("b/common.spk", "in second: f;");
+        let key = |dir: &str| {
+            CacheKey::from_options(source, &options).with_root_path(&format!("{}/main.spk", dir))
+        };
+        cache
+            .put(
+                &key("a")
+                    .with_import("a/common.spk", "a/common.spk")
+                    .with_include("a/common.spk", "in first: f;"),
+                &output("a"),
+            )
+            .unwrap();
+
+        let file = SourceMap::new().add_file(source, "main.spk", PathBuf::from("a/main.spk"));
+        let (output, status) = compile_cached(&cache, file, &options, &loader);
+        assert_eq!(status, CacheStatus::Hit);
+        assert_eq!(output.emitted, [(Emit::Ir, "a".to_owned())]);
+
+        // the same source in another directory imports another file
+        assert_ne!(key("a").digest(), key("b").digest());
+        assert_eq!(cache.get_with_loader(&key("b"), &loader), None);
+    }
+
+    #[test]
+    fn test_cache_get_with_loader_resolves_imports() {
+        let cache = Cache::new(MemoryCacheStore::new(), CacheLimits::default());
+        let key = CacheKey::new("import \"link.spk\";", &VariantKey::new(["x"]), "ir")
+            .with_root_path("main.spk");
+        cache
+            .put(
+                &key.clone()
+                    .with_import("link.spk", "common.spk")
+                    .with_include("common.spk", "fn f() {}"),
+                &output("a"),
+            )
+            .unwrap();
+        let mut loader = LinkFileLoader(MemoryFileLoader::new());
+        loader.0.add_file("common.spk", "fn f() {}");
+        loader.0.add_file("link.spk", "fn f() {}");
+        assert!(cache.get_with_loader(&key, &loader).is_some());
+
+        // `link.spk` resolves to itself now, not to `common.spk` anymore
+        assert_eq!(cache.get_with_loader(&key, &loader.0), None);
+    }
+
+    /// Resolves `link.spk` to `common.spk`, like a symbolic link.
+    struct LinkFileLoader(MemoryFileLoader);
+
+    impl FileLoader for LinkFileLoader {
+        fn canonicalize(&self, path: &Path) -> PathBuf {
+            match path.to_str() {
+                Some("link.spk") => PathBuf::from("common.spk"),
+                _ => self.0.canonicalize(path),
+            }
+        }
+
+        fn load(&self, path: &Path) -> io::Result<String> {
+            self.0.load(path)
+        }
+    }
+
+    #[test]
+    fn test_compile_pack_batch_cached() {
+        let file = SourceMap::new().add_file("", "test", None);
+        let source_map = Arc::new(SourceMap::from_file(file.clone()));
+        let pack = shader_pack(vec![
+            input(ident("color"), "f3"),
+            comptime_if(flag("fog"), vec![input(ident("fog_color"), "f3")], None),
+        ]);
+        let variants = [
+            VariantKey::new(Vec::<String>::new()),
+            VariantKey::new(["fog"]),
+            VariantKey::new(["unused"]),
+        ];
+        let options = CompileOptions {
+            emit: vec![Emit::Ir],
+            ..Default::default()
+        };
+        let cache = Cache::new(MemoryCacheStore::new(), CacheLimits::default());
+        let loader = MemoryFileLoader::new();
+        let compile = |variants: &[VariantKey]| {
+            compile_pack_batch_cached(
+                &cache,
+                file.clone(),
+                source_map.clone(),
+                &pack,
+                variants,
+                &options,
+                &RecordingFileLoader::new(&loader),
+            )
+        };
+
+        let compiled = compile(&variants[..2]);
+        assert!(compiled
+            .outputs
+            .iter()
+            .all(|output| output.module.is_some()));
+        assert_eq!(cache.store().entries().unwrap().len(), 2);
+
+        // the first two variants are cached, the last one shares the output of the first one
+        let batch = compile(&variants);
+        assert_eq!(batch.outputs.len(), 2);
+        assert_eq!(batch.variants[&variants[0]], batch.variants[&variants[2]]);
+
+        for variant in &variants[..2] {
+            let output = batch.output(variant).unwrap();
+            assert_eq!(output.emitted, compiled.output(variant).unwrap().emitted);
+            assert!(!output.items.is_empty());
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{
+    diagnostics::{Applicability, Item, ItemLevel, ItemOrigin, SubItem, Suggestion},
+    span::{SourceFile, Span},
+};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::Arc};
+
+/// An item of a cached compilation. Spans are relative to the start of their file, since the
+/// files may be placed elsewhere in the source map of a later compilation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CachedItem {
+    pub code: u32,
+    pub level: ItemLevel,
+    pub message: String,
+    pub origin: Option<CachedSpan>,
+    pub sub_items: Vec<CachedSubItem>,
+    pub suggestions: Vec<CachedSuggestion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CachedSubItem {
+    pub level: ItemLevel,
+    pub message: String,
+    pub origin: Option<CachedSpan>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CachedSuggestion {
+    pub message: String,
+    pub span: CachedSpan,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CachedSpan {
+    /// Index of the file in [`CachedOutput::files`](super::CachedOutput::files).
+    pub file: usize,
+    pub low: u32,
+    pub high: u32,
+}
+
+/// Name and path of a file the cached items are in.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CachedFile {
+    pub name: String,
+    pub path: Option<PathBuf>,
+}
+
+/// Converts items to cached items, adding the files they are in to `files`.
+pub(super) fn cache_items(items: &[Item], files: &mut Vec<CachedFile>) -> Vec<CachedItem> {
+    let mut cacher = ItemCacher { files };
+    let mut cached_items = Vec::with_capacity(items.len());
+
+    for item in items {
+        let mut suggestions = Vec::with_capacity(item.suggestions.len());
+
+        // suggestions are in the file of the item
+        if let Some(origin) = &item.origin {
+            for suggestion in &item.suggestions {
+                suggestions.push(CachedSuggestion {
+                    message: suggestion.message.clone(),
+                    span: cacher.span(&origin.file, suggestion.span),
+                    replacement: suggestion.replacement.clone(),
+                    applicability: suggestion.applicability,
+                });
+            }
+        }
+
+        cached_items.push(CachedItem {
+            code: item.code,
+            level: item.level,
+            message: item.message.clone(),
+            origin: item
+                .origin
+                .as_ref()
+                .map(|origin| cacher.span(&origin.file, origin.span)),
+            sub_items: Vec::from_iter(item.sub_items.iter().map(|sub_item| {
+                CachedSubItem {
+                    level: sub_item.level,
+                    message: sub_item.message.clone(),
+                    origin: sub_item
+                        .origin
+                        .as_ref()
+                        .map(|origin| cacher.span(&origin.file, origin.span)),
+                }
+            })),
+            suggestions,
+        });
+    }
+
+    cached_items
+}
+
+struct ItemCacher<'a> {
+    files: &'a mut Vec<CachedFile>,
+}
+
+impl ItemCacher<'_> {
+    fn span(&mut self, source_file: &SourceFile, span: Span) -> CachedSpan {
+        let file = CachedFile {
+            name: source_file.name().to_owned(),
+            path: source_file.path().map(|path| path.to_path_buf()),
+        };
+        let index = match self.files.iter().position(|other| *other == file) {
+            Some(index) => index,
+            None => {
+                self.files.push(file);
+                self.files.len() - 1
+            }
+        };
+        let file_low = source_file.span().low();
+
+        CachedSpan {
+            file: index,
+            low: span.low() - file_low,
+            high: span.high() - file_low,
+        }
+    }
+}
+
+/// Converts cached items back to items; `files` are the source files of the cached files, `None`
+/// for the ones that are not available anymore, whose spans are dropped.
+pub(super) fn restore_items(
+    items: Vec<CachedItem>,
+    files: &[Option<Arc<SourceFile>>],
+) -> Vec<Item> {
+    let origin = |span: CachedSpan| {
+        let file = files.get(span.file)?.clone()?;
+        let file_low = file.span().low();
+        let span = Span::new(file_low + span.low, file_low + span.high);
+        file.span()
+            .contains_span(span)
+            .then_some(ItemOrigin { file, span })
+    };
+
+    Vec::from_iter(items.into_iter().map(|item| Item {
+        code: item.code,
+        level: item.level,
+        message: item.message,
+        origin: item.origin.and_then(origin),
+        sub_items: Vec::from_iter(item.sub_items.into_iter().map(|sub_item| SubItem {
+            level: sub_item.level,
+            message: sub_item.message,
+            origin: sub_item.origin.and_then(origin),
+        })),
+        suggestions: Vec::from_iter(item.suggestions.into_iter().filter_map(|suggestion| {
+            Some(Suggestion {
+                message: suggestion.message,
+                span: origin(suggestion.span)?.span,
+                replacement: suggestion.replacement,
+                applicability: suggestion.applicability,
+            })
+        })),
+    }))
+}
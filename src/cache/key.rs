@@ -0,0 +1,133 @@
+use crate::{
+    archive::source_hash,
+    compile::{CompileOptions, VariantKey},
+};
+use std::fmt::Write;
+
+/// Version of the compiler; outputs of other versions are never reused.
+pub const COMPILER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Everything the outputs of a compilation depend on.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CacheKey {
+    pub source_hash: u64,
+    /// Canonical path of the root file, which its imports are resolved from; `None` for files
+    /// which are not on disk.
+    pub root_path: Option<String>,
+    /// Every imported path, joined to the directory of the importing file, and the canonical path
+    /// it has been resolved to, sorted.
+    pub imports: Vec<(String, String)>,
+    /// Canonical path and content hash of every imported file, sorted by path.
+    pub includes: Vec<(String, u64)>,
+    pub flags: VariantKey,
+    /// Identifies the code generator and its settings, e.g. `emit=ir;passes=const-fold`.
+    pub backend: String,
+    pub compiler_version: String,
+}
+
+impl CacheKey {
+    pub fn new(source: &str, flags: &VariantKey, backend: &str) -> Self {
+        Self {
+            source_hash: source_hash(source),
+            root_path: None,
+            imports: vec![],
+            includes: vec![],
+            flags: flags.clone(),
+            backend: backend.to_owned(),
+            compiler_version: COMPILER_VERSION.to_owned(),
+        }
+    }
+
+    /// Creates the key of a single compilation; the requested outputs and the optimization
+    /// passes that will actually run make up the backend.
+    pub fn from_options(source: &str, options: &CompileOptions) -> Self {
+        let emit = Vec::from_iter(options.emit.iter().map(|emit| emit.name()));
+        let passes = Vec::from_iter(
+            options
+                .pass_manager()
+                .passes()
+                .iter()
+                .map(|pass| pass.name()),
+        );
+        let mut backend = format!("emit={};passes={}", emit.join(","), passes.join(","));
+
+        // lint levels change the reported items
+        if !options.lints.is_empty() {
+            let mut lints = Vec::from_iter(options.lints.iter());
+            lints.sort();
+            let lints = Vec::from_iter(
+                lints
+                    .into_iter()
+                    .map(|(code, level)| format!("{}:{}", code, level)),
+            );
+            write!(backend, ";lints={}", lints.join(",")).unwrap();
+        }
+
+        Self::new(source, &VariantKey::new(&options.flags), &backend)
+    }
+
+    pub fn with_root_path(mut self, path: &str) -> Self {
+        self.root_path = Some(path.to_owned());
+        self
+    }
+
+    pub fn with_import(mut self, path: &str, canonical_path: &str) -> Self {
+        let import = (path.to_owned(), canonical_path.to_owned());
+
+        if let Err(index) = self.imports.binary_search(&import) {
+            self.imports.insert(index, import);
+        }
+
+        self
+    }
+
+    pub fn with_include(mut self, path: &str, content: &str) -> Self {
+        let include = (path.to_owned(), source_hash(content));
+        let index = self.includes.partition_point(|other| *other < include);
+        self.includes.insert(index, include);
+        self
+    }
+
+    /// Returns the canonical textual form of the key; equal keys have equal encodings.
+    pub fn encode(&self) -> String {
+        self.encode_with_imports(&self.imports, &self.includes)
+    }
+
+    fn encode_with_imports(
+        &self,
+        imports: &[(String, String)],
+        includes: &[(String, u64)],
+    ) -> String {
+        let mut encoded = String::new();
+        writeln!(encoded, "version {:?}", self.compiler_version).unwrap();
+        writeln!(encoded, "source {:016x}", self.source_hash).unwrap();
+
+        if let Some(path) = &self.root_path {
+            writeln!(encoded, "root {:?}", path).unwrap();
+        }
+
+        for (path, canonical_path) in imports {
+            writeln!(encoded, "import {:?} {:?}", path, canonical_path).unwrap();
+        }
+
+        for (path, hash) in includes {
+            writeln!(encoded, "include {:?} {:016x}", path, hash).unwrap();
+        }
+
+        for flag in self.flags.flags() {
+            writeln!(encoded, "flag {:?}", flag).unwrap();
+        }
+
+        writeln!(encoded, "backend {:?}", self.backend).unwrap();
+        encoded
+    }
+
+    /// Returns the address of the entry in a [`CacheStore`](super::CacheStore), 16 hex digits.
+    ///
+    /// Imports and imported files are left out, since they are only known once the source is
+    /// parsed; keys differing only by them share an entry, which holds the latest of them. The
+    /// root path is not, so that the same source in different directories has different entries.
+    pub fn digest(&self) -> String {
+        format!("{:016x}", source_hash(&self.encode_with_imports(&[], &[])))
+    }
+}
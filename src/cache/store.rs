@@ -0,0 +1,248 @@
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Describes a stored entry, for eviction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheEntryInfo {
+    pub digest: String,
+    pub size: u64,
+    /// Time of the last load or store; larger is more recent. Only comparable within a store.
+    pub last_used: u64,
+}
+
+/// Storage of cache entries, addressed by their digest.
+///
+/// Implementations must be safe to share across threads, so that a single cache can serve a batch
+/// compilation.
+pub trait CacheStore: Send + Sync {
+    /// Returns `None` if there is no entry; marks the entry as used otherwise.
+    fn load(&self, digest: &str) -> io::Result<Option<Vec<u8>>>;
+
+    /// Replaces the entry, if any.
+    fn store(&self, digest: &str, data: &[u8]) -> io::Result<()>;
+
+    fn remove(&self, digest: &str) -> io::Result<()>;
+
+    fn entries(&self) -> io::Result<Vec<CacheEntryInfo>>;
+}
+
+/// Keeps entries in memory; mostly useful for tests and long-running processes.
+#[derive(Debug, Default)]
+pub struct MemoryCacheStore {
+    entries: Mutex<FxHashMap<String, (Vec<u8>, u64)>>,
+    clock: AtomicU64,
+}
+
+impl MemoryCacheStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+impl CacheStore for MemoryCacheStore {
+    fn load(&self, digest: &str) -> io::Result<Option<Vec<u8>>> {
+        let mut entries = self.entries.lock();
+
+        Ok(entries.get_mut(digest).map(|(data, last_used)| {
+            *last_used = self.tick();
+            data.clone()
+        }))
+    }
+
+    fn store(&self, digest: &str, data: &[u8]) -> io::Result<()> {
+        let last_used = self.tick();
+        self.entries
+            .lock()
+            .insert(digest.to_owned(), (data.to_vec(), last_used));
+        Ok(())
+    }
+
+    fn remove(&self, digest: &str) -> io::Result<()> {
+        self.entries.lock().remove(digest);
+        Ok(())
+    }
+
+    fn entries(&self) -> io::Result<Vec<CacheEntryInfo>> {
+        Ok(Vec::from_iter(self.entries.lock().iter().map(
+            |(digest, (data, last_used))| CacheEntryInfo {
+                digest: digest.clone(),
+                size: data.len() as u64,
+                last_used: *last_used,
+            },
+        )))
+    }
+}
+
+/// Keeps every entry in its own file, `<digest>.cache`, under a directory; the modification time
+/// of the file is its last use.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileCacheStore {
+    dir: PathBuf,
+}
+
+impl FileCacheStore {
+    const EXTENSION: &'static str = "cache";
+
+    /// Creates the directory if it does not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, digest: &str) -> PathBuf {
+        self.dir.join(digest).with_extension(Self::EXTENSION)
+    }
+}
+
+impl CacheStore for FileCacheStore {
+    fn load(&self, digest: &str) -> io::Result<Option<Vec<u8>>> {
+        let path = self.path(digest);
+
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(SystemTime::now())?;
+        Ok(Some(data))
+    }
+
+    fn store(&self, digest: &str, data: &[u8]) -> io::Result<()> {
+        // every write has its own temporary file, so that concurrent writers never share one,
+        // and concurrent readers never see a partial entry
+        static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let path = self.path(digest);
+        let temp = path.with_extension(format!(
+            "{}.{}.{}",
+            Self::EXTENSION,
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp, data)?;
+        fs::rename(&temp, &path)
+    }
+
+    fn remove(&self, digest: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(digest)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn entries(&self) -> io::Result<Vec<CacheEntryInfo>> {
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+
+            if path
+                .extension()
+                .is_none_or(|extension| extension != Self::EXTENSION)
+            {
+                continue;
+            }
+
+            let digest = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(digest) => digest.to_owned(),
+                None => continue,
+            };
+            let metadata = fs::metadata(&path)?;
+            let last_used = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_nanos() as u64);
+
+            entries.push(CacheEntryInfo {
+                digest,
+                size: metadata.len(),
+                last_used,
+            });
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_cache_store() {
+        let dir = std::env::temp_dir().join(format!("shader-pack-cache-{}", std::process::id()));
+        let store = FileCacheStore::new(&dir).unwrap();
+
+        assert_eq!(store.load("0123").unwrap(), None);
+        store.store("0123", b"first").unwrap();
+        store.store("0123", b"second").unwrap();
+        store.store("4567", b"other").unwrap();
+        assert_eq!(store.load("0123").unwrap(), Some(b"second".to_vec()));
+
+        let mut entries = store.entries().unwrap();
+        entries.sort_by(|a, b| a.digest.cmp(&b.digest));
+        assert_eq!(
+            Vec::from_iter(
+                entries
+                    .iter()
+                    .map(|entry| (entry.digest.as_str(), entry.size))
+            ),
+            [("0123", 6), ("4567", 5)]
+        );
+
+        store.remove("0123").unwrap();
+        store.remove("0123").unwrap();
+        assert_eq!(store.load("0123").unwrap(), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_file_cache_store_concurrent_stores() {
+        let dir = std::env::temp_dir().join(format!(
+            "shader-pack-cache-concurrent-{}",
+            std::process::id()
+        ));
+        let store = FileCacheStore::new(&dir).unwrap();
+
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let store = &store;
+                scope.spawn(move || {
+                    for _ in 0..50 {
+                        store
+                            .store("0123", format!("thread {}", thread).as_bytes())
+                            .unwrap();
+                    }
+                });
+            }
+        });
+
+        let data = String::from_utf8(store.load("0123").unwrap().unwrap()).unwrap();
+        assert!(data.starts_with("thread "));
+        // no temporary file is left behind
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+mod batch;
+mod config;
+
+pub use batch::*;
+pub use config::*;
+
+use crate::{
+    comptime::{enumerate_variants, expand, VariantSet},
+    diagnostics::{Item, ItemCollector, ItemLevel, ItemSender, LintLevels},
+    ir::{lower, IrModule, OptLevel, OptPass, PassManager},
+    parse::{ast::AstShaderPack, parse_shader_pack_with_imports, FileLoader, MemoryFileLoader},
+    reflect::Reflection,
+    span::{SourceFile, SourceMap},
+    symbol::Symbol,
+};
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use std::{str::FromStr, sync::Arc};
+
+/// Options that control a single compilation.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct CompileOptions {
+    /// Extra textual outputs to produce, e.g. `--emit=ir`.
+    pub emit: Vec<Emit>,
+    /// Enabled `comptime if` flags, e.g. `--flag=shadow`.
+    pub flags: Vec<String>,
+    /// Selects the default set of optimization passes, e.g. `-O2`.
+    pub opt_level: OptLevel,
+    /// Passes to run in addition to the ones of `opt_level`.
+    pub enabled_passes: Vec<OptPass>,
+    /// Passes of `opt_level` to skip; takes precedence over `enabled_passes`.
+    pub disabled_passes: Vec<OptPass>,
+    /// Levels of lints, e.g. `--deny=unused-input`; the attributes of the items in the source
+    /// take precedence, unless a lint is forbidden.
+    pub lints: LintLevels,
+}
+
+impl CompileOptions {
+    pub fn pass_manager(&self) -> PassManager {
+        let mut manager = PassManager::new(self.opt_level);
+
+        for pass in &self.enabled_passes {
+            manager.enable(*pass);
+        }
+
+        for pass in &self.disabled_passes {
+            manager.disable(*pass);
+        }
+
+        manager
+    }
+}
+
+/// Represents an intermediate output that can be emitted for debugging.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Emit {
+    /// The textual dump of the lowered IR.
+    Ir,
+    /// The reflection of every pass, as JSON.
+    Reflection,
+    /// The report of every distinct variant of the pack over its comptime flags.
+    Variants,
+}
+
+impl Emit {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ir => "ir",
+            Self::Reflection => "reflection",
+            Self::Variants => "variants",
+        }
+    }
+}
+
+impl FromStr for Emit {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "ir" => Ok(Self::Ir),
+            "reflection" => Ok(Self::Reflection),
+            "variants" => Ok(Self::Variants),
+            _ => Err(format!("unknown emit kind `{}`", str)),
+        }
+    }
+}
+
+/// Result of a single compilation.
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    /// Every diagnostics item reported during the compilation, in order.
+    pub items: Vec<Item>,
+    /// The lowered and optimized module; `None` if an error has been reported.
+    pub module: Option<IrModule>,
+    /// The reflection of the optimized module; `None` if an error has been reported.
+    pub reflection: Option<Reflection>,
+    /// Requested textual outputs, in the order of [`CompileOptions::emit`].
+    pub emitted: Vec<(Emit, String)>,
+}
+
+/// Compiles the given file down to the IR, and optimizes it as requested by the options.
+///
+/// No file can be imported; see [`compile_with_loader`].
+pub fn compile(file: Arc<SourceFile>, options: &CompileOptions) -> CompileOutput {
+    compile_with_loader(file, options, &MemoryFileLoader::new())
+}
+
+/// Compiles the given file like [`compile`], loading the files it imports through the `loader`.
+pub fn compile_with_loader(
+    file: Arc<SourceFile>,
+    options: &CompileOptions,
+    loader: &dyn FileLoader,
+) -> CompileOutput {
+    let collector = Arc::new(ItemCollector::new());
+    let reporter =
+        ItemSender::new(file.clone(), collector.clone()).with_lint_levels(options.lints.clone());
+    let flags = FxHashSet::from_iter(options.flags.iter().map(Symbol::from_str));
+
+    let mut source_map = SourceMap::from_file(file.clone());
+    let pack = parse_shader_pack_with_imports(&file, &mut source_map, loader, &reporter);
+    let reporter = reporter.with_source_map(Arc::new(source_map));
+    let variants = match &pack {
+        Some(pack) if options.emit.contains(&Emit::Variants) => enumerate_variants(pack, &reporter),
+        _ => None,
+    };
+    let module = pack
+        .and_then(|pack| expand(&pack, &flags, &reporter))
+        .and_then(|pack| lower_and_optimize(&pack, &reporter, options));
+
+    finish(&collector, reporter, module, variants.as_ref(), options)
+}
+
+fn lower_and_optimize(
+    pack: &AstShaderPack,
+    reporter: &ItemSender,
+    options: &CompileOptions,
+) -> Option<IrModule> {
+    let mut module = lower(pack, reporter)?;
+    options.pass_manager().run(&mut module);
+    Some(module)
+}
+
+/// Collects the reflection of the module, which may report layout errors, and the items.
+fn finish(
+    collector: &ItemCollector,
+    reporter: ItemSender,
+    module: Option<IrModule>,
+    variants: Option<&VariantSet>,
+    options: &CompileOptions,
+) -> CompileOutput {
+    let reflection = module
+        .as_ref()
+        .map(|module| Reflection::from_module(module, &reporter));
+    drop(reporter);
+    let items = collector.take();
+    // denied lints and layout errors fail the compilation like any other error
+    let failed = items.iter().any(|item| item.level == ItemLevel::Error);
+    let module = module.filter(|_| !failed);
+    let reflection = reflection.filter(|_| !failed);
+    let mut emitted = Vec::with_capacity(options.emit.len());
+
+    if let (Some(module), Some(reflection)) = (&module, &reflection) {
+        for emit in &options.emit {
+            match emit {
+                Emit::Ir => emitted.push((*emit, module.to_string())),
+                Emit::Reflection => emitted.push((*emit, reflection.to_json())),
+                Emit::Variants => {
+                    if let Some(variants) = variants {
+                        emitted.push((*emit, variants.to_string()));
+                    }
+                }
+            }
+        }
+    }
+
+    CompileOutput {
+        items,
+        module,
+        reflection,
+        emitted,
+    }
+}
@@ -0,0 +1,268 @@
+use super::{finish, lower_and_optimize, CompileOptions, CompileOutput};
+use crate::{
+    comptime::{expand_silently, hash, ExpandError},
+    diagnostics::{Item, ItemCollector, ItemSender},
+    parse::{ast::AstShaderPack, parse_shader_pack},
+    span::{SourceFile, SourceMap},
+    symbol::Symbol,
+};
+use rayon::prelude::*;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::{hash::Hash, sync::Arc};
+
+/// Identifies a variant by its set of enabled flags; the order and duplicates of the flags do not
+/// matter.
+///
+/// Example:
+///
+/// `["shadow", "fog"]` and `["fog", "shadow", "fog"]` are the same key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VariantKey {
+    flags: Vec<String>,
+}
+
+impl VariantKey {
+    pub fn new(flags: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let mut flags = Vec::from_iter(flags.into_iter().map(Into::into));
+        flags.sort();
+        flags.dedup();
+        Self { flags }
+    }
+
+    /// Returns the enabled flags, sorted.
+    pub fn flags(&self) -> &[String] {
+        &self.flags
+    }
+}
+
+/// Result of a batch compilation.
+#[derive(Debug, Clone)]
+pub struct BatchOutput {
+    /// Every diagnostics item reported while parsing, shared by every variant.
+    pub items: Vec<Item>,
+    /// Distinct outputs, in the order of the first variant that produces each of them.
+    pub outputs: Vec<CompileOutput>,
+    /// Index into `outputs` of every requested variant.
+    pub variants: FxHashMap<VariantKey, usize>,
+}
+
+impl BatchOutput {
+    /// Returns the output of the given variant; `None` if it has not been requested.
+    pub fn output(&self, key: &VariantKey) -> Option<&CompileOutput> {
+        self.variants.get(key).map(|index| &self.outputs[*index])
+    }
+}
+
+/// Parses the file once and compiles every variant of it across threads.
+///
+/// See [`compile_pack_batch`].
+pub fn compile_batch(
+    file: Arc<SourceFile>,
+    variants: &[VariantKey],
+    options: &CompileOptions,
+) -> BatchOutput {
+    let collector = Arc::new(ItemCollector::new());
+    let reporter =
+        ItemSender::new(file.clone(), collector.clone()).with_lint_levels(options.lints.clone());
+    let pack = parse_shader_pack(&file, &reporter);
+    drop(reporter);
+
+    let items = collector.take();
+
+    match pack {
+        Some(pack) => {
+            let source_map = Arc::new(SourceMap::from_file(file.clone()));
+            BatchOutput {
+                items,
+                ..compile_pack_batch(file, source_map, &pack, variants, options)
+            }
+        }
+        None => BatchOutput {
+            items,
+            outputs: vec![],
+            variants: FxHashMap::default(),
+        },
+    }
+}
+
+/// Compiles every variant of an already parsed pack across threads.
+///
+/// Variants are expanded in parallel first; variants whose expanded programs are identical share
+/// a single output, which is compiled only once. `flags` of the options is ignored, the flags of
+/// each variant are used instead.
+///
+/// `source_map` holds the root `file` and the files it imports, so that items in imported files
+/// point at them.
+pub fn compile_pack_batch(
+    file: Arc<SourceFile>,
+    source_map: Arc<SourceMap>,
+    pack: &AstShaderPack,
+    variants: &[VariantKey],
+    options: &CompileOptions,
+) -> BatchOutput {
+    let expansions = Vec::from_par_iter(variants.par_iter().map(|key| {
+        let flags = FxHashSet::from_iter(key.flags.iter().map(Symbol::from_str));
+        let expansion = expand_silently(pack, &flags);
+        (hash(&expansion), expansion)
+    }));
+
+    // index into `expansions` of the first variant of every output
+    let mut distinct = Vec::<usize>::new();
+    let mut distinct_by_hash = FxHashMap::<u64, Vec<usize>>::default();
+    let mut variant_outputs = FxHashMap::default();
+
+    for (index, (key, (hash, expansion))) in variants.iter().zip(&expansions).enumerate() {
+        let outputs = distinct_by_hash.entry(*hash).or_default();
+        let output = match outputs
+            .iter()
+            .find(|output| &expansions[distinct[**output]].1 == expansion)
+        {
+            Some(output) => *output,
+            None => {
+                outputs.push(distinct.len());
+                distinct.push(index);
+                distinct.len() - 1
+            }
+        };
+
+        variant_outputs.insert(key.clone(), output);
+    }
+
+    let outputs = Vec::from_par_iter(distinct.par_iter().map(|index| {
+        let (pack, errors) = &expansions[*index].1;
+        compile_expanded(file.clone(), source_map.clone(), pack, errors, options)
+    }));
+
+    BatchOutput {
+        items: vec![],
+        outputs,
+        variants: variant_outputs,
+    }
+}
+
+fn compile_expanded(
+    file: Arc<SourceFile>,
+    source_map: Arc<SourceMap>,
+    pack: &AstShaderPack,
+    errors: &[ExpandError],
+    options: &CompileOptions,
+) -> CompileOutput {
+    let collector = Arc::new(ItemCollector::new());
+    let reporter = ItemSender::new(file, collector.clone())
+        .with_lint_levels(options.lints.clone())
+        .with_source_map(source_map);
+
+    for error in errors {
+        reporter.error(error.code, error.span, error.message.clone());
+    }
+
+    let module = if errors.is_empty() {
+        lower_and_optimize(pack, &reporter, options)
+    } else {
+        None
+    };
+
+    finish(&collector, reporter, module, None, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        compile::Emit, diagnostics::codes::TYPE_ERR_UNKNOWN_TYPE, parse::ast::AstTopLevelKind,
+        span::Span, test_utils::*,
+    };
+
+    #[test]
+    fn test_variant_key() {
+        assert_eq!(
+            VariantKey::new(["shadow", "fog"]),
+            VariantKey::new(["fog", "shadow", "fog"])
+        );
+        assert_eq!(VariantKey::new(["b", "a"]).flags(), ["a", "b"]);
+    }
+
+    #[test]
+    fn test_compile_pack_batch() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("", "test", None);
+        let pack = shader_pack(vec![
+            input(ident("color"), "f3"),
+            comptime_if(flag("fog"), vec![input(ident("fog_color"), "f3")], None),
+            comptime_if(
+                flag("broken"),
+                vec![input(ident("broken"), "unknown")],
+                None,
+            ),
+        ]);
+        let variants = [
+            VariantKey::new(["fog"]),
+            VariantKey::new(Vec::<String>::new()),
+            VariantKey::new(["fog", "unused"]),
+            VariantKey::new(["unused"]),
+            VariantKey::new(["broken"]),
+        ];
+        let options = CompileOptions {
+            emit: vec![Emit::Ir],
+            ..Default::default()
+        };
+        let source_map = Arc::new(source_map);
+        let output = compile_pack_batch(file, source_map, &pack, &variants, &options);
+
+        assert_eq!(output.outputs.len(), 3);
+        assert_eq!(
+            Vec::from_iter(variants.iter().map(|key| output.variants[key])),
+            [0, 1, 0, 1, 2]
+        );
+        assert_eq!(
+            output.output(&variants[0]).unwrap().emitted[0].1,
+            "resource#0 color: f3 = uniform
+resource#1 fog_color: f3 = uniform
+"
+        );
+        assert_eq!(
+            output.output(&variants[1]).unwrap().emitted[0].1,
+            "resource#0 color: f3 = uniform
+"
+        );
+        assert!(output.outputs[2].module.is_none());
+        assert!(!output.outputs[2].items.is_empty());
+    }
+
+    #[test]
+    fn test_compile_pack_batch_imported_items() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("in color: f3;\n", "main.spk", None);
+        let common = source_map.add_file(
+            "# imported\nin broken: unknown;\n",
+            "common.spk",
+            Some("common.spk".into()),
+        );
+        let mut broken = input(ident("broken"), "unknown");
+
+        if let AstTopLevelKind::Input(input) = &mut broken {
+            let low = common.span().low() + 22;
+            input.type_name.span = Span::new(low, low + 7);
+        }
+
+        let pack = shader_pack(vec![input(ident("color"), "f3"), broken]);
+        let variants = [VariantKey::new(Vec::<String>::new())];
+        let output = compile_pack_batch(
+            file,
+            Arc::new(source_map),
+            &pack,
+            &variants,
+            &CompileOptions::default(),
+        );
+
+        let item = output.outputs[0]
+            .items
+            .iter()
+            .find(|item| item.code == TYPE_ERR_UNKNOWN_TYPE)
+            .unwrap();
+        let origin = item.origin.as_ref().unwrap();
+        assert!(Arc::ptr_eq(&origin.file, &common));
+        assert_eq!(common.slice(origin.span), "unknown");
+        assert_eq!(common.find_line(origin.span.low()), 1);
+    }
+}
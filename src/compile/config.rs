@@ -0,0 +1,125 @@
+use super::CompileOptions;
+use crate::diagnostics::{LintLevel, LintLevels};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The name of the project config file; see [`ProjectConfig`].
+pub const CONFIG_FILE_NAME: &str = "spk.toml";
+
+/// Settings shared by every file of a project, read from `spk.toml`.
+///
+/// Example:
+///
+/// ```toml
+/// [lints]
+/// unused-input = "allow"
+/// SPK2120 = "deny"
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct ProjectConfig {
+    pub lints: LintLevels,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawProjectConfig {
+    #[serde(default)]
+    lints: BTreeMap<String, LintLevel>,
+}
+
+impl ProjectConfig {
+    /// Parses the content of a config file.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// # use shader_pack::{compile::ProjectConfig, diagnostics::{codes::TYPE_WARN_UNUSED_INPUT, LintLevel}};
+    /// let config = ProjectConfig::parse("[lints]\nunused-input = \"deny\"\n").unwrap();
+    /// assert_eq!(config.lints.get(TYPE_WARN_UNUSED_INPUT), Some(LintLevel::Deny));
+    /// ```
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let raw: RawProjectConfig = toml::from_str(content).map_err(|err| err.to_string())?;
+        let mut lints = LintLevels::new();
+
+        for (lint, level) in raw.lints {
+            lints.set_by_name(&lint, level)?;
+        }
+
+        Ok(Self { lints })
+    }
+
+    /// Returns the path of the config file of a source file: the nearest `spk.toml` in its
+    /// directory or any of the directories above it.
+    pub fn find(source_path: &Path) -> Option<PathBuf> {
+        source_path
+            .ancestors()
+            .skip(1)
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("cannot read `{}`: {}", path.display(), err))?;
+        Self::parse(&content).map_err(|err| format!("invalid `{}`: {}", path.display(), err))
+    }
+
+    /// Sets the settings of the config in the options; those already set in the options, e.g. by
+    /// the command line, take precedence.
+    pub fn apply(&self, options: &mut CompileOptions) {
+        let mut lints = self.lints.clone();
+        lints.extend(&options.lints);
+        options.lints = lints;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::codes::TYPE_WARN_UNUSED_INPUT;
+
+    #[test]
+    fn test_project_config_parse() {
+        assert_eq!(ProjectConfig::parse(""), Ok(ProjectConfig::default()));
+        assert!(ProjectConfig::parse("[lints]\nunused-input = \"never\"\n").is_err());
+        assert!(ProjectConfig::parse("[lints]\nunused-flag = \"allow\"\n")
+            .unwrap_err()
+            .contains("unknown lint `unused-flag`"));
+        assert!(ProjectConfig::parse("[lint]\n").is_err());
+
+        let config = ProjectConfig::parse("[lints]\nunused-input = \"allow\"\n").unwrap();
+        let mut options = CompileOptions::default();
+        config.apply(&mut options);
+        assert_eq!(
+            options.lints.get(TYPE_WARN_UNUSED_INPUT),
+            Some(LintLevel::Allow)
+        );
+
+        // the options take precedence
+        options.lints.set(TYPE_WARN_UNUSED_INPUT, LintLevel::Deny);
+        config.apply(&mut options);
+        assert_eq!(
+            options.lints.get(TYPE_WARN_UNUSED_INPUT),
+            Some(LintLevel::Deny)
+        );
+    }
+
+    #[test]
+    fn test_project_config_find() {
+        let root = std::env::temp_dir().join(format!("shader-pack-config-{}", std::process::id()));
+        let nested = root.join("shaders/lighting");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(CONFIG_FILE_NAME), "").unwrap();
+
+        assert_eq!(
+            ProjectConfig::find(&nested.join("main.spk")),
+            Some(root.join(CONFIG_FILE_NAME))
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
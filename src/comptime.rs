@@ -0,0 +1,5 @@
+mod expand;
+mod variant;
+
+pub use expand::*;
+pub use variant::*;
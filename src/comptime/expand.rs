@@ -0,0 +1,622 @@
+use crate::{
+    diagnostics::{
+        codes::{COMPTIME_ERR_INVALID_EXPR, COMPTIME_ERR_INVALID_IDENT},
+        ItemSender,
+    },
+    parse::{
+        ast::{
+            AstBinaryExprOpKind, AstCompTimeIf, AstCompTimeIfPredicateExpr,
+            AstCompTimeIfPredicateExprKind, AstCompTimeIfPredicateExprSingleKind, AstCompTimeKind,
+            AstComposedIdentifier, AstExpr, AstExprKind, AstFnDef, AstIdentifier,
+            AstIdentifierKind, AstInput, AstLiteral, AstLiteralKind, AstPassLevelKind,
+            AstShaderPack, AstStatement, AstStatementKind, AstTopLevel, AstTopLevelKind,
+            AstUnaryExprOpKind, MapSpans,
+        },
+        lexer::TokenNumberLiteralKind,
+        low_lexer::{is_id_continue, is_id_start},
+    },
+    span::{Expansion, ExpansionKind, Span, SyntaxContext},
+    symbol::Symbol,
+};
+use rustc_hash::FxHashSet;
+
+/// Upper bound of the iteration count of a single `comptime loop`.
+pub const MAX_COMPTIME_LOOP_ITERATIONS: i64 = 1024;
+
+/// Expands every `comptime` item of the pack for the given set of enabled flags.
+///
+/// - `comptime if` is replaced with the items of the first branch whose predicate holds.
+/// - `comptime loop` repeats its items; inside of them, the loop variable is replaced with the
+///   iteration index.
+/// - `!ident("name_{}", ...)` is replaced with the composed identifier.
+///
+/// The spans of the expanded items record the expansions they have been produced by in their
+/// [`SyntaxContext`], so that diagnostics can point back at them.
+///
+/// Every error is reported through the `reporter`; `None` is returned if any error has been
+/// reported.
+pub fn expand(
+    pack: &AstShaderPack,
+    flags: &FxHashSet<Symbol>,
+    reporter: &ItemSender,
+) -> Option<AstShaderPack> {
+    let (pack, errors) = expand_silently(pack, flags);
+
+    if errors.is_empty() {
+        return Some(pack);
+    }
+
+    for error in errors {
+        reporter.error(error.code, error.span, error.message);
+    }
+
+    None
+}
+
+/// An error found while expanding; kept aside so that the errors of many expansions of the same
+/// pack can be de-duplicated before being reported.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct ExpandError {
+    pub code: u32,
+    pub span: Span,
+    pub message: String,
+}
+
+pub(crate) fn expand_silently(
+    pack: &AstShaderPack,
+    flags: &FxHashSet<Symbol>,
+) -> (AstShaderPack, Vec<ExpandError>) {
+    let mut expander = Expander {
+        flags,
+        loop_vars: Vec::new(),
+        ctxt: SyntaxContext::ROOT,
+        errors: Vec::new(),
+    };
+    let mut top_levels = Vec::with_capacity(pack.top_levels.len());
+    expander.expand_top_levels(&pack.top_levels, &mut top_levels);
+
+    let pack = AstShaderPack {
+        node_id: pack.node_id,
+        span: pack.span,
+        top_levels,
+    };
+    (pack, expander.errors)
+}
+
+/// Evaluates a `comptime if` predicate.
+pub fn eval_predicate(predicate: &AstCompTimeIfPredicateExpr, flags: &FxHashSet<Symbol>) -> bool {
+    match &predicate.kind {
+        AstCompTimeIfPredicateExprKind::Invalid => false,
+        AstCompTimeIfPredicateExprKind::Single(single) => match &single.kind {
+            AstCompTimeIfPredicateExprSingleKind::Invalid => false,
+            AstCompTimeIfPredicateExprSingleKind::Flag(flag) => flags.contains(&flag.flag.value()),
+            AstCompTimeIfPredicateExprSingleKind::Paren(paren) => {
+                eval_predicate(&paren.expr, flags)
+            }
+            AstCompTimeIfPredicateExprSingleKind::Not(not) => !eval_predicate(&not.expr, flags),
+        },
+        AstCompTimeIfPredicateExprKind::And(and) => {
+            eval_predicate(&and.lhs, flags) && eval_predicate(&and.rhs, flags)
+        }
+        AstCompTimeIfPredicateExprKind::Or(or) => {
+            eval_predicate(&or.lhs, flags) || eval_predicate(&or.rhs, flags)
+        }
+    }
+}
+
+struct Expander<'a> {
+    flags: &'a FxHashSet<Symbol>,
+    /// Variables of the enclosing `comptime loop`s, innermost last.
+    loop_vars: Vec<(Symbol, i64)>,
+    /// The context of the innermost `comptime loop` iteration being expanded.
+    ctxt: SyntaxContext,
+    errors: Vec<ExpandError>,
+}
+
+impl Expander<'_> {
+    fn error(&mut self, code: u32, span: Span, message: impl Into<String>) {
+        self.errors.push(ExpandError {
+            code,
+            span,
+            message: message.into(),
+        });
+    }
+
+    fn expand_top_levels(&mut self, top_levels: &[AstTopLevel], output: &mut Vec<AstTopLevel>) {
+        for top_level in top_levels {
+            let comptime = match &top_level.kind {
+                AstTopLevelKind::CompTime(comptime) => comptime,
+                _ => {
+                    let mut top_level = top_level.clone();
+
+                    if !self.ctxt.is_root() {
+                        let ctxt = self.ctxt;
+                        top_level.map_spans(&mut |span| span.with_ctxt(ctxt));
+                    }
+
+                    self.substitute_top_level(&mut top_level);
+                    output.push(top_level);
+                    continue;
+                }
+            };
+
+            match &comptime.kind {
+                // already reported by the parser
+                AstCompTimeKind::Invalid => {}
+                AstCompTimeKind::If(comptime_if) => {
+                    if let Some(items) = self.select_branch(comptime_if) {
+                        self.expand_top_levels(items, output);
+                    }
+                }
+                AstCompTimeKind::Loop(comptime_loop) => {
+                    let name = match &comptime_loop.loop_var_ident.kind {
+                        AstIdentifierKind::Symbol(symbol) => *symbol,
+                        _ => {
+                            self.error(
+                                COMPTIME_ERR_INVALID_IDENT,
+                                comptime_loop.loop_var_ident.span,
+                                "loop variables must be plain identifiers",
+                            );
+                            continue;
+                        }
+                    };
+                    let count = match self.eval_expr(&comptime_loop.expr) {
+                        Some(count) => count,
+                        None => continue,
+                    };
+
+                    if !(0..=MAX_COMPTIME_LOOP_ITERATIONS).contains(&count) {
+                        self.error(
+                            COMPTIME_ERR_INVALID_EXPR,
+                            comptime_loop.expr.span,
+                            format!(
+                                "loop count must be between 0 and {}, but it is {}",
+                                MAX_COMPTIME_LOOP_ITERATIONS, count
+                            ),
+                        );
+                        continue;
+                    }
+
+                    let parent = self.ctxt;
+
+                    for index in 0..count {
+                        self.ctxt = SyntaxContext::new(Expansion {
+                            parent,
+                            kind: ExpansionKind::Loop {
+                                var: name,
+                                value: index,
+                            },
+                            call_site: top_level.span,
+                        });
+                        self.loop_vars.push((name, index));
+                        self.expand_top_levels(&comptime_loop.block.items, output);
+                        self.loop_vars.pop();
+                    }
+
+                    self.ctxt = parent;
+                }
+            }
+        }
+    }
+
+    fn select_branch<'b, T>(&self, comptime_if: &'b AstCompTimeIf<T>) -> Option<&'b [T]> {
+        if eval_predicate(&comptime_if.if_part.predicate, self.flags) {
+            return Some(&comptime_if.if_part.block.items);
+        }
+
+        for part in &comptime_if.else_if_parts {
+            if eval_predicate(&part.predicate, self.flags) {
+                return Some(&part.block.items);
+            }
+        }
+
+        comptime_if
+            .else_part
+            .as_ref()
+            .map(|part| &part.block.items[..])
+    }
+
+    fn loop_var(&self, symbol: Symbol) -> Option<i64> {
+        self.loop_vars
+            .iter()
+            .rev()
+            .find(|(name, _)| *name == symbol)
+            .map(|(_, value)| *value)
+    }
+
+    /// Evaluates a compile-time integer expression, e.g. the count of a `comptime loop` or an
+    /// argument of `!ident`.
+    fn eval_expr(&mut self, expr: &AstExpr) -> Option<i64> {
+        let value = match &expr.kind {
+            // already reported by the parser
+            AstExprKind::Invalid => return None,
+            AstExprKind::Literal(AstLiteral {
+                kind:
+                    AstLiteralKind::Number {
+                        kind,
+                        content,
+                        suffix: None,
+                    },
+                ..
+            }) => parse_integer(*kind, content.to_str()),
+            AstExprKind::Identifier(AstIdentifier {
+                kind: AstIdentifierKind::Symbol(symbol),
+                ..
+            }) => match self.loop_var(*symbol) {
+                Some(value) => Some(value),
+                None => {
+                    self.error(
+                        COMPTIME_ERR_INVALID_EXPR,
+                        expr.span,
+                        format!("{} is not a comptime loop variable", symbol),
+                    );
+                    return None;
+                }
+            },
+            AstExprKind::Unary(unary) => {
+                let rhs = self.eval_expr(&unary.rhs)?;
+
+                match unary.op.kind {
+                    AstUnaryExprOpKind::Pos => Some(rhs),
+                    AstUnaryExprOpKind::Neg => rhs.checked_neg(),
+                    AstUnaryExprOpKind::BitNot => Some(!rhs),
+                    _ => None,
+                }
+            }
+            AstExprKind::Binary(binary) => {
+                let lhs = self.eval_expr(&binary.lhs)?;
+                let rhs = self.eval_expr(&binary.rhs)?;
+
+                match binary.op.kind {
+                    AstBinaryExprOpKind::Add => lhs.checked_add(rhs),
+                    AstBinaryExprOpKind::Sub => lhs.checked_sub(rhs),
+                    AstBinaryExprOpKind::Mul => lhs.checked_mul(rhs),
+                    AstBinaryExprOpKind::Div => lhs.checked_div(rhs),
+                    AstBinaryExprOpKind::Mod => lhs.checked_rem(rhs),
+                    AstBinaryExprOpKind::Pow => {
+                        u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_pow(rhs))
+                    }
+                    AstBinaryExprOpKind::Shl => {
+                        u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shl(rhs))
+                    }
+                    AstBinaryExprOpKind::Shr => {
+                        u32::try_from(rhs).ok().and_then(|rhs| lhs.checked_shr(rhs))
+                    }
+                    AstBinaryExprOpKind::BitOr => Some(lhs | rhs),
+                    AstBinaryExprOpKind::BitAnd => Some(lhs & rhs),
+                    AstBinaryExprOpKind::BitXor => Some(lhs ^ rhs),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        if value.is_none() {
+            self.error(
+                COMPTIME_ERR_INVALID_EXPR,
+                expr.span,
+                "expected a compile-time integer expression",
+            );
+        }
+
+        value
+    }
+
+    fn compose_identifier(
+        &mut self,
+        span: Span,
+        composed: &AstComposedIdentifier,
+    ) -> Option<Symbol> {
+        let rule = composed.rule_str.value().to_str();
+        let placeholders = rule.matches("{}").count();
+
+        if placeholders != composed.args.len() {
+            self.error(
+                COMPTIME_ERR_INVALID_IDENT,
+                span,
+                format!(
+                    "`!ident` rule has {} placeholder(s), but {} argument(s) are given",
+                    placeholders,
+                    composed.args.len()
+                ),
+            );
+            return None;
+        }
+
+        let mut name = String::with_capacity(rule.len());
+        let mut rest = rule;
+
+        for arg in &composed.args {
+            let value = self.eval_expr(&arg.expr)?;
+            let index = rest.find("{}").unwrap();
+            name.push_str(&rest[..index]);
+            name.push_str(&value.to_string());
+            rest = &rest[index + 2..];
+        }
+
+        name.push_str(rest);
+
+        let is_valid =
+            name.chars().next().is_some_and(is_id_start) && name.chars().all(is_id_continue);
+
+        if !is_valid {
+            self.error(
+                COMPTIME_ERR_INVALID_IDENT,
+                span,
+                format!("`{}` is not a valid identifier", name),
+            );
+            return None;
+        }
+
+        Some(Symbol::from_str(name))
+    }
+
+    fn substitute_ident(&mut self, ident: &mut AstIdentifier) {
+        if let AstIdentifierKind::Composed(composed) = &ident.kind {
+            ident.kind = match self.compose_identifier(ident.span, composed) {
+                Some(symbol) => {
+                    let ctxt = SyntaxContext::new(Expansion {
+                        parent: ident.span.ctxt(),
+                        kind: ExpansionKind::ComposedIdent { name: symbol },
+                        call_site: ident.span,
+                    });
+                    ident.span = ident.span.with_ctxt(ctxt);
+                    AstIdentifierKind::Symbol(symbol)
+                }
+                None => AstIdentifierKind::Invalid,
+            };
+        }
+    }
+
+    fn substitute_top_level(&mut self, top_level: &mut AstTopLevel) {
+        match &mut top_level.kind {
+            AstTopLevelKind::CompTime(_) => unreachable!(),
+            AstTopLevelKind::FnDef(fn_def) => self.substitute_fn_def(fn_def),
+            // left for the lowering to report, like unexpanded `comptime` items
+            AstTopLevelKind::Import(_) => {}
+            AstTopLevelKind::Input(input) => self.substitute_input(input),
+            AstTopLevelKind::Pass(pass) => {
+                self.substitute_ident(&mut pass.ident);
+
+                for pass_level in &mut pass.pass_levels {
+                    match &mut pass_level.kind {
+                        AstPassLevelKind::Input(input) => self.substitute_input(input),
+                        AstPassLevelKind::Stage(stage) => {
+                            self.substitute_statements(&mut stage.statements)
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn substitute_fn_def(&mut self, fn_def: &mut AstFnDef) {
+        self.substitute_ident(&mut fn_def.ident);
+
+        for param in &mut fn_def.params {
+            self.substitute_ident(&mut param.ident);
+            self.substitute_ident(&mut param.type_name.ident);
+        }
+
+        if let Some(return_type) = &mut fn_def.return_type {
+            self.substitute_ident(&mut return_type.type_name.ident);
+        }
+
+        self.substitute_statements(&mut fn_def.statements);
+    }
+
+    fn substitute_input(&mut self, input: &mut AstInput) {
+        self.substitute_ident(&mut input.ident);
+        self.substitute_ident(&mut input.type_name.ident);
+    }
+
+    fn substitute_statements(&mut self, statements: &mut [AstStatement]) {
+        for statement in statements {
+            match &mut statement.kind {
+                AstStatementKind::VarDecl(var_decl) => {
+                    self.substitute_ident(&mut var_decl.ident);
+
+                    if let Some(type_name) = &mut var_decl.type_name {
+                        self.substitute_ident(&mut type_name.type_name.ident);
+                    }
+
+                    if let Some(assignment) = &mut var_decl.assignment {
+                        self.substitute_expr(&mut assignment.rhs);
+                    }
+                }
+                AstStatementKind::Assignment(assignment) => {
+                    self.substitute_expr(&mut assignment.lhs);
+                    self.substitute_expr(&mut assignment.rhs);
+                }
+                AstStatementKind::Return(statement) => {
+                    if let Some(expr) = &mut statement.expr {
+                        self.substitute_expr(expr);
+                    }
+                }
+                AstStatementKind::Expr(expr) => self.substitute_expr(expr),
+            }
+        }
+    }
+
+    fn substitute_expr(&mut self, expr: &mut AstExpr) {
+        match &mut expr.kind {
+            AstExprKind::Invalid | AstExprKind::Literal(_) => {}
+            AstExprKind::Binary(binary) => {
+                self.substitute_expr(&mut binary.lhs);
+                self.substitute_expr(&mut binary.rhs);
+            }
+            AstExprKind::Unary(unary) => self.substitute_expr(&mut unary.rhs),
+            AstExprKind::Identifier(ident) => {
+                let value = match &ident.kind {
+                    AstIdentifierKind::Symbol(symbol) => self.loop_var(*symbol),
+                    _ => None,
+                };
+
+                match value {
+                    // the loop variable is an integer literal within the loop body
+                    Some(value) => {
+                        expr.kind = AstExprKind::Literal(AstLiteral {
+                            node_id: ident.node_id,
+                            span: ident.span,
+                            kind: AstLiteralKind::Number {
+                                kind: TokenNumberLiteralKind::IntegerDecimal,
+                                content: Symbol::from_str(value.to_string()),
+                                suffix: None,
+                            },
+                        })
+                    }
+                    None => self.substitute_ident(ident),
+                }
+            }
+            AstExprKind::Call(call) => {
+                self.substitute_ident(&mut call.callee);
+
+                for arg in &mut call.args {
+                    self.substitute_expr(&mut arg.expr);
+                }
+            }
+            AstExprKind::Member(member) => {
+                self.substitute_expr(&mut member.lhs);
+                self.substitute_ident(&mut member.member);
+            }
+            AstExprKind::Index(index) => {
+                self.substitute_expr(&mut index.lhs);
+                self.substitute_expr(&mut index.index);
+            }
+            AstExprKind::Object(object) => {
+                for field in &mut object.fields {
+                    self.substitute_ident(&mut field.ident);
+                    self.substitute_expr(&mut field.expr);
+                }
+            }
+        }
+    }
+}
+
+fn parse_integer(kind: TokenNumberLiteralKind, content: &str) -> Option<i64> {
+    let digits = content.replace('_', "");
+
+    match kind {
+        TokenNumberLiteralKind::IntegerBinary => i64::from_str_radix(&digits[2..], 2).ok(),
+        TokenNumberLiteralKind::IntegerOctal => i64::from_str_radix(&digits[2..], 8).ok(),
+        TokenNumberLiteralKind::IntegerHexadecimal => i64::from_str_radix(&digits[2..], 16).ok(),
+        TokenNumberLiteralKind::IntegerDecimal => digits.parse().ok(),
+        TokenNumberLiteralKind::Float => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::codes::COMPTIME_ERR_INVALID_EXPR, parse::ast::AstBinaryExprOpKind,
+        test_utils::*,
+    };
+
+    fn flags(names: &[&str]) -> FxHashSet<Symbol> {
+        FxHashSet::from_iter(names.iter().map(Symbol::from_str))
+    }
+
+    #[test]
+    fn test_expand_comptime_if() {
+        let pack = shader_pack(vec![comptime_if(
+            and(flag("shadow"), not(flag("fog"))),
+            vec![input(ident("shadow_map"), "t2")],
+            Some(vec![input(ident("ambient"), "f3")]),
+        )]);
+
+        let (expanded, errors) = expand_silently(&pack, &flags(&["shadow"]));
+        assert!(errors.is_empty());
+        assert_eq!(top_level_names(&expanded), ["shadow_map"]);
+
+        let (expanded, errors) = expand_silently(&pack, &flags(&["shadow", "fog"]));
+        assert!(errors.is_empty());
+        assert_eq!(top_level_names(&expanded), ["ambient"]);
+    }
+
+    #[test]
+    fn test_expand_comptime_loop() {
+        let pack = shader_pack(vec![comptime_loop(
+            "i",
+            expr_binary(AstBinaryExprOpKind::Add, expr_int("1"), expr_int("2")),
+            vec![input(
+                composed_ident(
+                    "light_{}_{}",
+                    vec![
+                        expr_ident("i"),
+                        expr_binary(AstBinaryExprOpKind::Mul, expr_ident("i"), expr_int("2")),
+                    ],
+                ),
+                "f3",
+            )],
+        )]);
+
+        let (expanded, errors) = expand_silently(&pack, &flags(&[]));
+        assert!(errors.is_empty());
+        assert_eq!(
+            top_level_names(&expanded),
+            ["light_0_0", "light_1_2", "light_2_4"]
+        );
+    }
+
+    #[test]
+    fn test_expand_comptime_loop_provenance() {
+        let pack = shader_pack(vec![
+            input(ident("plain"), "f2"),
+            comptime_loop(
+                "n",
+                expr_int("2"),
+                vec![input(composed_ident("uv_{}", vec![expr_ident("n")]), "f2")],
+            ),
+        ]);
+
+        let (expanded, errors) = expand_silently(&pack, &flags(&[]));
+        assert!(errors.is_empty());
+
+        let spans = Vec::from_iter(expanded.top_levels.iter().map(|top_level| {
+            let AstTopLevelKind::Input(input) = &top_level.kind else {
+                unreachable!()
+            };
+            (input.span, input.ident.span)
+        }));
+        assert!(spans[0].0.ctxt().is_root());
+        assert!(spans[0].1.ctxt().is_root());
+
+        let backtrace = spans[2].1.ctxt().backtrace();
+        assert_eq!(
+            Vec::from_iter(backtrace.iter().map(|expansion| expansion.kind.to_string())),
+            [
+                "in expansion of `!ident` (`uv_1`)",
+                "in expansion of `comptime loop` (n = 1)"
+            ]
+        );
+        assert_eq!(spans[2].0.ctxt(), backtrace[0].parent);
+
+        // the same expansion yields the same contexts, e.g. for another set of flags
+        let (other, _) = expand_silently(&pack, &flags(&["fog"]));
+        assert_eq!(other, expanded);
+    }
+
+    #[test]
+    fn test_expand_invalid() {
+        let pack = shader_pack(vec![
+            comptime_loop("i", expr_int("4096"), vec![]),
+            comptime_loop(
+                "i",
+                expr_int("1"),
+                vec![input(composed_ident("{}", vec![expr_ident("i")]), "f")],
+            ),
+            input(composed_ident("{}_{}", vec![expr_ident("j")]), "f"),
+        ]);
+
+        let (_, errors) = expand_silently(&pack, &flags(&[]));
+        assert_eq!(
+            Vec::from_iter(errors.iter().map(|error| error.code)),
+            [
+                COMPTIME_ERR_INVALID_EXPR,
+                COMPTIME_ERR_INVALID_IDENT,
+                COMPTIME_ERR_INVALID_IDENT
+            ]
+        );
+    }
+}
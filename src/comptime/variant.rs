@@ -0,0 +1,392 @@
+use super::{expand_silently, ExpandError};
+use crate::{
+    diagnostics::{codes::COMPTIME_ERR_TOO_MANY_FLAGS, ItemSender},
+    parse::ast::{
+        AstCompTimeIf, AstCompTimeIfPredicateExpr, AstCompTimeIfPredicateExprKind,
+        AstCompTimeIfPredicateExprSingleKind, AstCompTimeKind, AstIdentifierKind, AstShaderPack,
+        AstTopLevel, AstTopLevelKind,
+    },
+    symbol::Symbol,
+};
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use std::{
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
+
+/// Upper bound of the number of distinct flags in a single pack; every combination of them is
+/// expanded while enumerating variants.
+pub const MAX_COMPTIME_FLAGS: usize = 16;
+
+/// Every distinct expansion of a shader pack.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VariantSet {
+    /// Every flag referenced by a `comptime if`, sorted by name.
+    pub flags: Vec<Symbol>,
+    pub variants: Vec<Variant>,
+    /// Variant index of every flag combination; bit `i` of the index is set if `flags[i]` is
+    /// enabled.
+    pub combination_variants: Vec<usize>,
+    pub passes: Vec<PassVariants>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Variant {
+    /// The enabled flags of the smallest combination that produces this variant.
+    pub flags: Vec<Symbol>,
+    /// Number of flag combinations that produce this variant.
+    pub combinations: usize,
+    pub pack: AstShaderPack,
+}
+
+/// Describes how the flags affect a single pass.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PassVariants {
+    pub name: Symbol,
+    /// Flags whose value changes the pass, or whether the pass exists at all.
+    pub flags: Vec<Symbol>,
+    /// Number of distinct expansions of the pass.
+    pub variant_count: usize,
+}
+
+impl VariantSet {
+    /// Returns the index of the variant produced by the given enabled flags; flags that are not
+    /// referenced by the pack are ignored.
+    pub fn variant_index(&self, flags: &FxHashSet<Symbol>) -> usize {
+        let mask = self
+            .flags
+            .iter()
+            .enumerate()
+            .filter(|(_, flag)| flags.contains(flag))
+            .fold(0, |mask, (index, _)| mask | (1 << index));
+        self.combination_variants[mask]
+    }
+}
+
+/// Collects every flag referenced by a `comptime if` predicate, sorted by name.
+pub fn collect_flags(pack: &AstShaderPack) -> Vec<Symbol> {
+    let mut flags = FxHashSet::default();
+    collect_top_level_flags(&pack.top_levels, &mut flags);
+
+    let mut flags = Vec::from_iter(flags);
+    flags.sort_by_key(|flag| flag.to_str());
+    flags
+}
+
+/// Expands the pack for every combination of its flags and groups the combinations that produce
+/// identical packs. Passes are compared on their own, together with the top-level functions and
+/// inputs that every pass shares.
+///
+/// Errors found while expanding are reported once, however many combinations reach them; `None`
+/// is returned if any error has been reported.
+pub fn enumerate_variants(pack: &AstShaderPack, reporter: &ItemSender) -> Option<VariantSet> {
+    let flags = collect_flags(pack);
+
+    if MAX_COMPTIME_FLAGS < flags.len() {
+        reporter.error(
+            COMPTIME_ERR_TOO_MANY_FLAGS,
+            pack.span,
+            format!(
+                "the pack references {} comptime flags, but at most {} are supported",
+                flags.len(),
+                MAX_COMPTIME_FLAGS
+            ),
+        );
+        return None;
+    }
+
+    // enumerate from the smallest combinations, so that each variant is named after its
+    // smallest combination
+    let mut masks = Vec::from_iter(0..1u32 << flags.len());
+    masks.sort_by_key(|mask| (mask.count_ones(), *mask));
+
+    let mut errors = Vec::<ExpandError>::new();
+    let mut variants = Vec::<Variant>::new();
+    let mut variant_indices = FxHashMap::<u64, Vec<usize>>::default();
+    let mut combination_variants = vec![0; masks.len()];
+    let mut pass_hashes = FxHashMap::<Symbol, FxHashMap<u32, u64>>::default();
+    let mut pass_names = Vec::new();
+
+    for mask in masks {
+        let enabled = Vec::from_iter(
+            flags
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| mask & (1 << index) != 0)
+                .map(|(_, flag)| *flag),
+        );
+        let (expanded, expand_errors) =
+            expand_silently(pack, &FxHashSet::from_iter(enabled.iter().copied()));
+
+        for error in expand_errors {
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
+        }
+
+        let shared =
+            hash(Vec::from_iter(expanded.top_levels.iter().filter(
+                |top_level| !matches!(top_level.kind, AstTopLevelKind::Pass(_)),
+            )));
+
+        for top_level in &expanded.top_levels {
+            if let AstTopLevelKind::Pass(pass) = &top_level.kind {
+                if let AstIdentifierKind::Symbol(name) = pass.ident.kind {
+                    let hashes = pass_hashes.entry(name).or_insert_with(|| {
+                        pass_names.push(name);
+                        FxHashMap::default()
+                    });
+                    hashes.insert(mask, hash((shared, top_level)));
+                }
+            }
+        }
+
+        let indices = variant_indices.entry(hash(&expanded)).or_default();
+
+        match indices
+            .iter()
+            .find(|index| variants[**index].pack == expanded)
+        {
+            Some(index) => {
+                variants[*index].combinations += 1;
+                combination_variants[mask as usize] = *index;
+            }
+            None => {
+                combination_variants[mask as usize] = variants.len();
+                indices.push(variants.len());
+                variants.push(Variant {
+                    flags: enabled,
+                    combinations: 1,
+                    pack: expanded,
+                });
+            }
+        }
+    }
+
+    if !errors.is_empty() {
+        for error in errors {
+            reporter.error(error.code, error.span, error.message);
+        }
+
+        return None;
+    }
+
+    let passes = Vec::from_iter(pass_names.into_iter().map(|name| {
+        let hashes = &pass_hashes[&name];
+        let relevant = Vec::from_iter(flags.iter().enumerate().filter_map(|(index, flag)| {
+            let bit = 1 << index;
+            let is_relevant = (0..1u32 << flags.len())
+                .filter(|mask| mask & bit == 0)
+                .any(|mask| hashes.get(&mask) != hashes.get(&(mask | bit)));
+            is_relevant.then_some(*flag)
+        }));
+
+        PassVariants {
+            name,
+            flags: relevant,
+            variant_count: FxHashSet::from_iter(hashes.values()).len(),
+        }
+    }));
+
+    Some(VariantSet {
+        flags,
+        variants,
+        combination_variants,
+        passes,
+    })
+}
+
+/// Hashes a value with the hasher of the crate's hash maps.
+pub(crate) fn hash(value: impl Hash) -> u64 {
+    let mut hasher = FxHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn collect_top_level_flags(top_levels: &[AstTopLevel], flags: &mut FxHashSet<Symbol>) {
+    for top_level in top_levels {
+        if let AstTopLevelKind::CompTime(comptime) = &top_level.kind {
+            match &comptime.kind {
+                AstCompTimeKind::Invalid => {}
+                AstCompTimeKind::If(comptime_if) => {
+                    collect_if_flags(comptime_if, flags);
+                }
+                AstCompTimeKind::Loop(comptime_loop) => {
+                    collect_top_level_flags(&comptime_loop.block.items, flags);
+                }
+            }
+        }
+    }
+}
+
+fn collect_if_flags(comptime_if: &AstCompTimeIf<AstTopLevel>, flags: &mut FxHashSet<Symbol>) {
+    collect_predicate_flags(&comptime_if.if_part.predicate, flags);
+    collect_top_level_flags(&comptime_if.if_part.block.items, flags);
+
+    for part in &comptime_if.else_if_parts {
+        collect_predicate_flags(&part.predicate, flags);
+        collect_top_level_flags(&part.block.items, flags);
+    }
+
+    if let Some(part) = &comptime_if.else_part {
+        collect_top_level_flags(&part.block.items, flags);
+    }
+}
+
+fn collect_predicate_flags(predicate: &AstCompTimeIfPredicateExpr, flags: &mut FxHashSet<Symbol>) {
+    match &predicate.kind {
+        AstCompTimeIfPredicateExprKind::Invalid => {}
+        AstCompTimeIfPredicateExprKind::Single(single) => match &single.kind {
+            AstCompTimeIfPredicateExprSingleKind::Invalid => {}
+            AstCompTimeIfPredicateExprSingleKind::Flag(flag) => {
+                flags.insert(flag.flag.value());
+            }
+            AstCompTimeIfPredicateExprSingleKind::Paren(paren) => {
+                collect_predicate_flags(&paren.expr, flags)
+            }
+            AstCompTimeIfPredicateExprSingleKind::Not(not) => {
+                collect_predicate_flags(&not.expr, flags)
+            }
+        },
+        AstCompTimeIfPredicateExprKind::And(and) => {
+            collect_predicate_flags(&and.lhs, flags);
+            collect_predicate_flags(&and.rhs, flags);
+        }
+        AstCompTimeIfPredicateExprKind::Or(or) => {
+            collect_predicate_flags(&or.lhs, flags);
+            collect_predicate_flags(&or.rhs, flags);
+        }
+    }
+}
+
+/// Textual report, used by `--emit=variants`.
+///
+/// Example:
+///
+/// ```text
+/// flags: "shadow", "skinning"
+/// variant#0: (none), 1 combination(s)
+/// variant#1: "shadow", 2 combination(s)
+/// pass first: 2 variant(s), depends on "shadow"
+/// ```
+impl Display for VariantSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "flags: ")?;
+        fmt_flags(f, &self.flags)?;
+        writeln!(f)?;
+
+        for (index, variant) in self.variants.iter().enumerate() {
+            write!(f, "variant#{}: ", index)?;
+            fmt_flags(f, &variant.flags)?;
+            writeln!(f, ", {} combination(s)", variant.combinations)?;
+        }
+
+        for pass in &self.passes {
+            write!(
+                f,
+                "pass {}: {} variant(s), depends on ",
+                pass.name.to_str(),
+                pass.variant_count
+            )?;
+            fmt_flags(f, &pass.flags)?;
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn fmt_flags(f: &mut std::fmt::Formatter<'_>, flags: &[Symbol]) -> std::fmt::Result {
+    if flags.is_empty() {
+        return write!(f, "(none)");
+    }
+
+    for (index, flag) in flags.iter().enumerate() {
+        if index != 0 {
+            write!(f, ", ")?;
+        }
+
+        write!(f, "\"{}\"", flag.to_str())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{codes::COMPTIME_ERR_INVALID_EXPR, Item, ItemCollector},
+        span::SourceMap,
+        test_utils::*,
+    };
+    use std::sync::Arc;
+
+    fn enumerate(pack: &AstShaderPack) -> (Option<VariantSet>, Vec<Item>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("", "test", None);
+        let collector = Arc::new(ItemCollector::new());
+        let reporter = ItemSender::new(file, collector.clone());
+        let variants = enumerate_variants(pack, &reporter);
+        let items = collector.take();
+
+        (variants, items)
+    }
+
+    #[test]
+    fn test_enumerate_variants() {
+        let pack = shader_pack(vec![
+            // never holds, so "unused" does not matter
+            comptime_if(and(flag("unused"), not(flag("unused"))), vec![], None),
+            comptime_if(flag("fog"), vec![input(ident("fog_color"), "f3")], None),
+            pass("main"),
+            comptime_if(
+                flag("shadow"),
+                vec![comptime_if(flag("soft"), vec![pass("shadow")], None)],
+                None,
+            ),
+        ]);
+        let (variants, items) = enumerate(&pack);
+        assert!(items.is_empty());
+
+        let variants = variants.unwrap();
+        assert_eq!(variants.combination_variants.len(), 16);
+        assert_eq!(
+            variants.variant_index(&FxHashSet::from_iter([
+                Symbol::from_str("shadow"),
+                Symbol::from_str("soft"),
+                Symbol::from_str("unused"),
+            ])),
+            2
+        );
+        assert_eq!(
+            variants.to_string(),
+            "flags: \"fog\", \"shadow\", \"soft\", \"unused\"
+variant#0: (none), 6 combination(s)
+variant#1: \"fog\", 6 combination(s)
+variant#2: \"shadow\", \"soft\", 2 combination(s)
+variant#3: \"fog\", \"shadow\", \"soft\", 2 combination(s)
+pass main: 2 variant(s), depends on \"fog\"
+pass shadow: 2 variant(s), depends on \"fog\", \"shadow\", \"soft\"
+"
+        );
+    }
+
+    #[test]
+    fn test_enumerate_variants_errors_once() {
+        let pack = shader_pack(vec![
+            comptime_if(
+                flag("many"),
+                vec![comptime_loop("i", expr_int("4096"), vec![])],
+                None,
+            ),
+            comptime_if(flag("other"), vec![pass("other")], None),
+        ]);
+        let (variants, items) = enumerate(&pack);
+        assert_eq!(variants, None);
+        assert_eq!(
+            Vec::from_iter(items.iter().map(|item| item.code)),
+            [COMPTIME_ERR_INVALID_EXPR]
+        );
+    }
+}
@@ -1,8 +1,20 @@
 pub mod codes;
 mod item;
+mod json;
+mod lint;
+mod sarif;
 mod sender;
+mod sink;
+mod snippet;
 mod stringify;
+mod suggestion;
 
 pub use item::*;
+pub use json::*;
+pub use lint::*;
+pub use sarif::*;
 pub use sender::*;
+pub use sink::*;
+pub use snippet::*;
 pub use stringify::*;
+pub use suggestion::*;
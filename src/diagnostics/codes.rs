@@ -1 +1,761 @@
+use super::ItemLevel;
+
+// 0xxx: lexer errors, 1xxx: parse errors, 2xxx: type errors and warnings, 3xxx: comptime errors,
+// 4xxx: import errors, 5xxx: layout errors
+
+pub const LEX_ERR_UNKNOWN_CHAR: u32 = 10;
+pub const LEX_ERR_UNTERMINATED_STRING: u32 = 20;
+pub const LEX_ERR_INVALID_ESCAPE: u32 = 30;
+pub const LEX_ERR_INVALID_DIGIT: u32 = 40;
+pub const LEX_ERR_INVALID_SUFFIX: u32 = 50;
+pub const LEX_ERR_UNTERMINATED_BLOCK_COMMENT: u32 = 60;
+
 pub const PARSE_ERR_INVALID_COMPTIME: u32 = 1010;
+
+pub const TYPE_ERR_UNKNOWN_TYPE: u32 = 2010;
+pub const TYPE_ERR_UNDEFINED_NAME: u32 = 2020;
+pub const TYPE_ERR_DUPLICATE_NAME: u32 = 2030;
+pub const TYPE_ERR_MISMATCHED_TYPES: u32 = 2040;
+pub const TYPE_ERR_INVALID_CALL: u32 = 2050;
+pub const TYPE_ERR_INVALID_MEMBER: u32 = 2060;
+pub const TYPE_ERR_INVALID_ASSIGNMENT: u32 = 2070;
+pub const TYPE_ERR_INVALID_LITERAL: u32 = 2080;
+pub const TYPE_ERR_INVALID_ATTRIBUTE: u32 = 2090;
+pub const TYPE_ERR_INVALID_STAGE: u32 = 2100;
+pub const TYPE_ERR_TYPE_ANNOTATION_NEEDED: u32 = 2110;
+pub const TYPE_WARN_UNUSED_INPUT: u32 = 2120;
+
+pub const COMPTIME_ERR_NOT_EXPANDED: u32 = 3010;
+pub const COMPTIME_ERR_INVALID_EXPR: u32 = 3020;
+pub const COMPTIME_ERR_INVALID_IDENT: u32 = 3030;
+pub const COMPTIME_ERR_TOO_MANY_FLAGS: u32 = 3040;
+
+pub const IMPORT_ERR_NOT_RESOLVED: u32 = 4010;
+pub const IMPORT_ERR_CANNOT_LOAD: u32 = 4020;
+pub const IMPORT_ERR_CYCLE: u32 = 4030;
+pub const IMPORT_ERR_NOT_TOP_LEVEL: u32 = 4040;
+pub const IMPORT_ERR_PRIVATE: u32 = 4050;
+pub const IMPORT_ERR_AMBIGUOUS: u32 = 4060;
+
+pub const LAYOUT_ERR_NO_UNIFORM_LAYOUT: u32 = 5010;
+pub const LAYOUT_ERR_NO_VERTEX_FORMAT: u32 = 5020;
+
+/// Describes a diagnostic code; see [`CODES`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CodeInfo {
+    pub code: u32,
+    /// The stable name of the code, e.g. `SPK1010`.
+    pub name: &'static str,
+    pub level: ItemLevel,
+    /// The name of the lint, e.g. `unused-input`, if the level of the code can be configured;
+    /// see [`LintLevels`](super::LintLevels).
+    pub lint: Option<&'static str>,
+    pub title: &'static str,
+    /// A long-form explanation in Markdown, with examples.
+    pub explanation: &'static str,
+}
+
+/// Every diagnostic code, sorted by code.
+pub const CODES: &[CodeInfo] = &[
+    CodeInfo {
+        code: LEX_ERR_UNKNOWN_CHAR,
+        name: "SPK0010",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "unknown character",
+        explanation: r#"A character cannot start any token.
+
+Characters which look like ASCII punctuation, such as fullwidth forms, smart quotes and dashes,
+often come from copying code out of documents or chat messages. Invisible characters such as
+the zero width space are reported as well.
+
+```spk
+in color: f3；      # error: unknown character `；`
+!ident “lighting”; # error: unknown character `“`
+```
+
+Write the ASCII characters instead:
+
+```spk
+in color: f3;
+!ident "lighting";
+```
+"#,
+    },
+    CodeInfo {
+        code: LEX_ERR_UNTERMINATED_STRING,
+        name: "SPK0020",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "unterminated string literal",
+        explanation: r#"A string literal has no closing `"`, so it runs until the end of the file.
+
+```spk
+!ident "lighting; # error: unterminated string literal
+```
+
+Close the string on the same line:
+
+```spk
+!ident "lighting";
+```
+"#,
+    },
+    CodeInfo {
+        code: LEX_ERR_INVALID_ESCAPE,
+        name: "SPK0030",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid escape sequence",
+        explanation: r#"A `\` in a string literal does not start a valid escape sequence.
+
+The escape sequences are `\\`, `\"`, `\n`, `\r`, `\t`, `\0`, and `\u{...}` with 1 to 6
+hexadecimal digits naming a Unicode character.
+
+```spk
+@path = "C:\shaders";  # error: unknown escape sequence `\s`
+@label = "\u{d800}";  # error: `d800` is not a Unicode character
+```
+
+Escape the backslash itself:
+
+```spk
+@path = "C:\\shaders";
+```
+"#,
+    },
+    CodeInfo {
+        code: LEX_ERR_INVALID_DIGIT,
+        name: "SPK0040",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid digit",
+        explanation: r#"A digit of a binary or octal number literal is out of the range of its radix.
+
+```spk
+let mask = 0b0120; # error: invalid digit `2` in binary literal
+let mode = 0o758;  # error: invalid digit `8` in octal literal
+```
+
+Binary literals have the digits `0` and `1`, octal literals `0` to `7`; use a decimal or
+hexadecimal literal for other values.
+"#,
+    },
+    CodeInfo {
+        code: LEX_ERR_INVALID_SUFFIX,
+        name: "SPK0050",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid number suffix",
+        explanation: r#"A number literal has a suffix which is unknown or not allowed on its kind of literal.
+
+The suffixes are `i` for signed integers, `u` for unsigned integers and `f` for floats. `f` is
+only allowed on decimal literals, and float literals only allow `f`.
+
+```spk
+let a = 1px;   # error: unknown suffix `px`
+let b = 0b1f;  # error: suffix `f` is not allowed on a binary literal
+let c = 1.5u;  # error: suffix `u` is not allowed on a float literal
+```
+"#,
+    },
+    CodeInfo {
+        code: LEX_ERR_UNTERMINATED_BLOCK_COMMENT,
+        name: "SPK0060",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "unterminated block comment",
+        explanation: r#"A block comment has no closing `]#`, so it runs until the end of the file.
+
+Block comments nest, so each `#[` inside a block comment needs its own `]#`:
+
+```spk
+#[ disabled:
+fn unused() { #[ nested ]# }
+# error: unterminated block comment
+```
+
+Close every block comment:
+
+```spk
+#[ disabled:
+fn unused() { #[ nested ]# }
+]#
+```
+"#,
+    },
+    CodeInfo {
+        code: PARSE_ERR_INVALID_COMPTIME,
+        name: "SPK1010",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid `comptime` item",
+        explanation: r#"A `comptime` item is malformed.
+
+`comptime` must be followed by `if` or `loop`, and the predicate of `comptime if` is made of
+flag names as string literals, combined with `and`, `or`, `not` and parentheses.
+
+```spk
+comptime while "shadow" { }         # error: `comptime` must be followed by `if` or `loop`
+comptime if shadow { }              # error: flags are string literals
+comptime if ("shadow" and "fog" { } # error: `)` is expected
+```
+
+Write instead:
+
+```spk
+comptime if ("shadow" and "fog") or not "low" {
+    in shadow_map: t2;
+}
+```
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_UNKNOWN_TYPE,
+        name: "SPK2010",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "unknown type",
+        explanation: r#"A type name does not name a built-in type.
+
+```spk
+in color: float3; # error: unknown type `float3`
+```
+
+Built-in types are short: scalars such as `f`, vectors such as `f3`, matrices such as `m4` and
+textures such as `t2`.
+
+```spk
+in color: f3;
+```
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_UNDEFINED_NAME,
+        name: "SPK2020",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "undefined name",
+        explanation: r#"A name does not refer to any variable, input, function or module in scope.
+
+```spk
+fn brighten(color: f3) -> f3 {
+    return colour * 2.0; # error: cannot find `colour` in this scope
+}
+```
+
+Check the spelling, and that the name is declared before its use. Functions of other files
+must be `pub`, and their file must be imported.
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_DUPLICATE_NAME,
+        name: "SPK2030",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "duplicate name",
+        explanation: r#"Two items of the same kind have the same name.
+
+```spk
+in color: f3;
+in color: f4; # error: input `color` is defined multiple times
+```
+
+Rename one of them; inside of `comptime loop`, `!ident` can give each iteration its own name.
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_MISMATCHED_TYPES,
+        name: "SPK2040",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "mismatched types",
+        explanation: r#"An expression does not have the type its context requires.
+
+```spk
+fn half(value: f) -> f3 {
+    return value * 0.5; # error: mismatched return types; expected `f3`, found `f`
+}
+```
+
+Operators also require compatible operands, and variables cannot have the `void` type.
+Convert the value explicitly, e.g. `f3(value * 0.5)`.
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_INVALID_CALL,
+        name: "SPK2050",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid call",
+        explanation: r#"A function, a built-in or a constructor is called with arguments it does not
+accept, or a function calls itself, directly or through other functions.
+
+```spk
+fn scale(value: f, factor: f) -> f {
+    return value * factor;
+}
+
+fn double(value: f) -> f {
+    return scale(value); # error: `scale` expects (f, f), found (f)
+}
+```
+
+Recursion is not supported, since shaders cannot have a call stack.
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_INVALID_MEMBER,
+        name: "SPK2060",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid member access",
+        explanation: r#"A member does not exist on the value it is accessed on.
+
+```spk
+let color: f3 = f3(1.0, 0.5, 0.0);
+let alpha = color.w; # error: invalid swizzle `w` on `f3`
+```
+
+Vectors have swizzles made of the components they have, and objects have the fields they are
+created with; other types have no members.
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_INVALID_ASSIGNMENT,
+        name: "SPK2070",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid assignment",
+        explanation: r#"The left-hand side of an assignment is not a local variable.
+
+```spk
+in color: f3;
+
+fn reset() {
+    color = f3(0.0); # error: the left-hand side of an assignment must be a local variable
+}
+```
+
+Inputs are read-only; copy them into a local variable with `let` to modify them.
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_INVALID_LITERAL,
+        name: "SPK2080",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid literal",
+        explanation: r#"A literal cannot be used as a value.
+
+```spk
+let name = "albedo";     # error: string literals cannot be used as values
+let count = 3000000000;  # error: number literal is out of range for `int`
+let mask = 4294967296u;  # error: number literal is out of range for `uint`
+```
+
+String literals only appear in attributes, `comptime` predicates, `!ident` rules and imports.
+Number literals must fit in their 32-bit type; add the `u` suffix for `uint` values above
+2147483647.
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_INVALID_ATTRIBUTE,
+        name: "SPK2090",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid attribute",
+        explanation: r#"An attribute is unknown, or is not allowed on the item it is attached to.
+
+```spk
+@vertex = "position"
+in pos: m4; # error: vertex attributes must be scalars or vectors, not `m4`
+```
+
+`@vertex` maps an input to a mesh attribute, and only applies to scalar and vector inputs.
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_INVALID_STAGE,
+        name: "SPK2100",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid stage",
+        explanation: r#"A pass contains a stage other than `vertex` or `fragment`.
+
+```spk
+pass first {
+    geometry { } # error: unknown stage `geometry`; expected `vertex` or `fragment`
+}
+```
+"#,
+    },
+    CodeInfo {
+        code: TYPE_ERR_TYPE_ANNOTATION_NEEDED,
+        name: "SPK2110",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "type annotation needed",
+        explanation: r#"The type of a variable cannot be inferred, since it has no initializer.
+
+```spk
+let value; # error: type annotation needed for `value`
+```
+
+Give the variable a type or an initial value:
+
+```spk
+let value: f;
+let other = 1.0;
+```
+"#,
+    },
+    CodeInfo {
+        code: TYPE_WARN_UNUSED_INPUT,
+        name: "SPK2120",
+        level: ItemLevel::Warning,
+        lint: Some("unused-input"),
+        title: "unused input",
+        explanation: r#"An input is never read by any function or stage.
+
+```spk
+in color: f3; # warning: input `color` is never used
+
+pass main {
+    fragment {
+        return f4(1.0, 1.0, 1.0, 1.0);
+    }
+}
+```
+
+Remove the input, or allow the lint if it is read by other means, e.g. by an engine binding
+every input of a set of packs:
+
+```spk
+@allow = "unused-input"
+in color: f3;
+```
+
+The level of this lint can be set with `@allow`, `@warn`, `@deny` and `@forbid` attributes on
+items, in the `[lints]` table of `spk.toml`, or with `--allow`, `--warn`, `--deny` and `--forbid`.
+"#,
+    },
+    CodeInfo {
+        code: COMPTIME_ERR_NOT_EXPANDED,
+        name: "SPK3010",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "`comptime` item not expanded",
+        explanation: r#"A `comptime` item or an `!ident` reached the lowering without being expanded.
+
+This is an internal error of a tool driving the compiler: `comptime` items must be expanded for
+a set of flags before the pack is lowered. Compiling through `spk` never reports it.
+"#,
+    },
+    CodeInfo {
+        code: COMPTIME_ERR_INVALID_EXPR,
+        name: "SPK3020",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid compile-time expression",
+        explanation: r#"An expression evaluated at compile time is not a valid integer expression, or its
+value is out of range.
+
+The count of a `comptime loop` and the arguments of `!ident` are made of integer literals,
+loop variables and arithmetic operators. The loop count must be between 0 and 1024.
+
+```spk
+comptime loop n times 2.5 { }   # error: expected a compile-time integer expression
+comptime loop n times 4096 { }  # error: loop count must be between 0 and 1024
+comptime loop n times 4 {
+    in !ident("uv_{}", m): f2;  # error: `m` is not a comptime loop variable
+}
+```
+"#,
+    },
+    CodeInfo {
+        code: COMPTIME_ERR_INVALID_IDENT,
+        name: "SPK3030",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "invalid composed identifier",
+        explanation: r#"An `!ident` call does not compose a valid identifier, or a loop variable is not a
+plain identifier.
+
+The rule of `!ident` has one `{}` placeholder per argument, and the composed name must be a
+valid identifier.
+
+```spk
+comptime loop n times 4 {
+    in !ident("uv_{}_{}", n): f2; # error: `!ident` rule has 2 placeholder(s), but 1 argument(s) are given
+    in !ident("{}_uv", n): f2;    # error: `0_uv` is not a valid identifier
+}
+```
+"#,
+    },
+    CodeInfo {
+        code: COMPTIME_ERR_TOO_MANY_FLAGS,
+        name: "SPK3040",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "too many comptime flags",
+        explanation: r#"The pack references more comptime flags than its variants can be enumerated for.
+
+Every combination of flags is a variant of the pack, so their number grows exponentially with
+the number of flags. Merge flags that are always enabled together, or split the pack into
+several files.
+
+```spk
+comptime if "shadow" and "shadow-soft" { } # two flags, four variants
+comptime if "soft-shadow" { }              # one flag, two variants
+```
+"#,
+    },
+    CodeInfo {
+        code: IMPORT_ERR_NOT_RESOLVED,
+        name: "SPK4010",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "import not resolved",
+        explanation: r#"An `import` item or a qualified call reached the lowering without being resolved.
+
+Imports are resolved when a pack is compiled with a file loader, e.g. by `spk check` and
+`spk build`; compiling a single source string cannot resolve them.
+
+```spk
+import "common/lighting.spk" as lighting;
+
+fn shade(normal: f3) -> f3 {
+    return lighting::diffuse(normal);
+}
+```
+"#,
+    },
+    CodeInfo {
+        code: IMPORT_ERR_CANNOT_LOAD,
+        name: "SPK4020",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "cannot load an imported file",
+        explanation: r#"An imported file cannot be read.
+
+Import paths are relative to the directory of the importing file.
+
+```spk
+import "lighting.spk"; # error, if there is no `lighting.spk` next to this file
+```
+"#,
+    },
+    CodeInfo {
+        code: IMPORT_ERR_CYCLE,
+        name: "SPK4030",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "import cycle",
+        explanation: r#"Files import each other in a cycle.
+
+```spk
+# a.spk
+import "b.spk";
+
+# b.spk
+import "a.spk"; # error: `a.spk` imports itself
+```
+
+Move the functions both files need into a third file imported by both.
+"#,
+    },
+    CodeInfo {
+        code: IMPORT_ERR_NOT_TOP_LEVEL,
+        name: "SPK4040",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "import inside of a `comptime` item",
+        explanation: r#"An `import` item is nested in a `comptime` item.
+
+Imports are resolved before `comptime` items are expanded, so they must be top-level items.
+
+```spk
+comptime if "shadow" {
+    import "shadow.spk"; # error
+}
+```
+
+Import the file unconditionally instead.
+"#,
+    },
+    CodeInfo {
+        code: IMPORT_ERR_PRIVATE,
+        name: "SPK4050",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "private function",
+        explanation: r#"A function of an imported file is called, but it is not `pub`.
+
+```spk
+# lighting.spk
+fn diffuse(normal: f3) -> f3 { ... }
+
+# main.spk
+import "lighting.spk" as lighting;
+let color = lighting::diffuse(normal); # error: function `diffuse` is private to `lighting.spk`
+```
+
+Add `pub` to the definition to call it from other files:
+
+```spk
+pub fn diffuse(normal: f3) -> f3 { ... }
+```
+"#,
+    },
+    CodeInfo {
+        code: IMPORT_ERR_AMBIGUOUS,
+        name: "SPK4060",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "ambiguous name",
+        explanation: r#"A name is exported by several imports, or an alias is given to several imports.
+
+```spk
+import "lighting.spk";
+import "shadow.spk";
+
+let value = attenuate(distance); # error, if both files export `attenuate`
+```
+
+Import one of the files with an alias, and qualify the call:
+
+```spk
+import "shadow.spk" as shadow;
+
+let value = shadow::attenuate(distance);
+```
+"#,
+    },
+    CodeInfo {
+        code: LAYOUT_ERR_NO_UNIFORM_LAYOUT,
+        name: "SPK5010",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "input without a uniform layout",
+        explanation: r#"An input is passed in the uniform buffer, but its type has no std140 layout.
+
+Scalars, vectors and matrices can be uniforms, and textures are bound separately, so every type
+that can be written in the source has a layout; this error means that the compiler produced an
+invalid module, and should be reported as a bug.
+"#,
+    },
+    CodeInfo {
+        code: LAYOUT_ERR_NO_VERTEX_FORMAT,
+        name: "SPK5020",
+        level: ItemLevel::Error,
+        lint: None,
+        title: "input without a vertex format",
+        explanation: r#"An input is passed as a vertex attribute, but its type has no vertex format.
+
+Only scalars and vectors can be vertex attributes, which is checked before the reflection is
+collected (see SPK2090); this error means that the compiler produced an invalid module, and should
+be reported as a bug.
+"#,
+    },
+];
+
+/// Returns the stable name of a code, e.g. `SPK1010`.
+pub fn code_name(code: u32) -> String {
+    format!("SPK{:04}", code)
+}
+
+pub fn lookup(code: u32) -> Option<&'static CodeInfo> {
+    CODES
+        .binary_search_by_key(&code, |info| info.code)
+        .ok()
+        .map(|index| &CODES[index])
+}
+
+/// Returns the code of a lint, given either by its lint name, e.g. `unused-input`, or like the
+/// codes of [`explain`].
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::diagnostics::codes::{lookup_lint, TYPE_WARN_UNUSED_INPUT};
+/// assert_eq!(lookup_lint("unused-input").unwrap().code, TYPE_WARN_UNUSED_INPUT);
+/// assert_eq!(lookup_lint("SPK2120"), lookup_lint("unused-input"));
+/// // errors are not lints
+/// assert_eq!(lookup_lint("SPK2010"), None);
+/// ```
+pub fn lookup_lint(lint: &str) -> Option<&'static CodeInfo> {
+    let info = match CODES.iter().find(|info| info.lint == Some(lint)) {
+        Some(info) => info,
+        None => lookup(parse_code(lint)?)?,
+    };
+    info.lint.map(|_| info)
+}
+
+/// Parses a code given either by name, case-insensitively, or by number.
+fn parse_code(code: &str) -> Option<u32> {
+    let digits = match code.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("SPK") => &code[3..],
+        _ => code,
+    };
+    digits.parse().ok()
+}
+
+/// Returns the explanation of a code as a Markdown document; the code is given either by name,
+/// case-insensitively, or by number.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::diagnostics::codes::explain;
+/// assert!(explain("SPK2010").unwrap().starts_with("# SPK2010: unknown type"));
+/// assert_eq!(explain("spk2010"), explain("2010"));
+/// assert_eq!(explain("SPK9999"), None);
+/// ```
+pub fn explain(code: &str) -> Option<String> {
+    let info = lookup(parse_code(code)?)?;
+    Some(format!(
+        "# {}: {}\n\n{}",
+        info.name, info.title, info.explanation
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        assert!(CODES.windows(2).all(|pair| pair[0].code < pair[1].code));
+
+        for info in CODES {
+            assert_eq!(info.name, code_name(info.code));
+            assert!(!info.title.is_empty());
+            assert!(!info.explanation.is_empty());
+            // only warnings can be configured
+            assert_eq!(info.lint.is_some(), info.level == ItemLevel::Warning);
+        }
+
+        for code in [
+            LEX_ERR_UNKNOWN_CHAR,
+            PARSE_ERR_INVALID_COMPTIME,
+            TYPE_ERR_TYPE_ANNOTATION_NEEDED,
+            COMPTIME_ERR_TOO_MANY_FLAGS,
+            IMPORT_ERR_AMBIGUOUS,
+            LAYOUT_ERR_NO_VERTEX_FORMAT,
+        ] {
+            assert_eq!(lookup(code).unwrap().code, code);
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let explanation = explain("SPK4050").unwrap();
+        assert!(explanation.starts_with("# SPK4050: private function\n\n"));
+        assert_eq!(explain("spk4050"), Some(explanation.clone()));
+        assert_eq!(explain("4050"), Some(explanation));
+        assert_eq!(explain("SPK"), None);
+        assert_eq!(explain("SPK4051"), None);
+        assert_eq!(explain("E0308"), None);
+    }
+}
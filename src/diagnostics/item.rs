@@ -1,4 +1,6 @@
+use super::Suggestion;
 use crate::span::{SourceFile, Span};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
 /// Represents a single diagnostics item.
@@ -12,16 +14,29 @@ pub struct Item {
     pub message: String,
     pub origin: Option<ItemOrigin>,
     pub sub_items: Vec<SubItem>,
+    pub suggestions: Vec<Suggestion>,
 }
 
 /// Level of the diagnostics item.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ItemLevel {
     Hint,
     Warning,
     Error,
 }
 
+impl ItemLevel {
+    /// The lowercase name of the level, e.g. `"warning"`, as in serialized items.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Hint => "hint",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
 /// Represents the origin of the diagnostics item.
 /// Used to trace which part of the codebase caused the diagnostic.
 #[derive(Debug, Clone, Hash)]
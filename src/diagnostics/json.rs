@@ -0,0 +1,299 @@
+use super::{codes::code_name, Applicability, Item, ItemLevel, ItemOrigin};
+use crate::span::ColUnit;
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON format of [`items_to_json`]; bumped on incompatible changes.
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// The JSON document produced by [`items_to_json`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonDiagnostics {
+    pub version: u32,
+    pub items: Vec<JsonItem>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonItem {
+    /// The name of the code, e.g. `SPK2010`; `null` for helper items.
+    pub code: Option<String>,
+    pub level: ItemLevel,
+    pub message: String,
+    pub location: Option<JsonLocation>,
+    pub sub_items: Vec<JsonSubItem>,
+    pub suggestions: Vec<JsonSuggestion>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonSubItem {
+    pub level: ItemLevel,
+    pub message: String,
+    pub location: Option<JsonLocation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonSuggestion {
+    pub message: String,
+    /// The replaced range, in the file of the item; empty to insert the replacement.
+    pub location: Option<JsonLocation>,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonLocation {
+    /// The path of the file, or its name if it has no path.
+    pub file: String,
+    /// Byte offsets from the start of the file; `end` is exclusive.
+    pub byte_start: u32,
+    pub byte_end: u32,
+    /// 1-based lines and columns, counted in characters; the end is exclusive.
+    pub start: JsonLineCol,
+    pub end: JsonLineCol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JsonLineCol {
+    pub line: u32,
+    pub col: u32,
+}
+
+/// Serializes the items into a JSON document of the following shape:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "items": [
+///     {
+///       "code": "SPK2010",
+///       "level": "error",
+///       "message": "unknown type `float3`",
+///       "location": {
+///         "file": "shaders/main.spk",
+///         "byte_start": 6,
+///         "byte_end": 12,
+///         "start": { "line": 1, "col": 7 },
+///         "end": { "line": 1, "col": 13 }
+///       },
+///       "sub_items": [{ "level": "hint", "message": "...", "location": null }],
+///       "suggestions": [
+///         {
+///           "message": "did you mean `f3`?",
+///           "location": { "file": "shaders/main.spk", "byte_start": 6, ... },
+///           "replacement": "f3",
+///           "applicability": "maybe_incorrect"
+///         }
+///       ]
+///     }
+///   ]
+/// }
+/// ```
+///
+/// `level` is one of `hint`, `warning` and `error`, and `applicability` is one of
+/// `machine_applicable`, `maybe_incorrect` and `has_placeholders`.
+pub fn items_to_json(items: &[Item]) -> String {
+    let diagnostics = JsonDiagnostics {
+        version: JSON_FORMAT_VERSION,
+        items: Vec::from_iter(items.iter().map(to_json_item)),
+    };
+    serde_json::to_string_pretty(&diagnostics).unwrap()
+}
+
+pub fn to_json_item(item: &Item) -> JsonItem {
+    JsonItem {
+        code: match item.code {
+            0 => None,
+            code => Some(code_name(code)),
+        },
+        level: item.level,
+        message: item.message.clone(),
+        location: item.origin.as_ref().map(to_json_location),
+        sub_items: Vec::from_iter(item.sub_items.iter().map(|sub_item| JsonSubItem {
+            level: sub_item.level,
+            message: sub_item.message.clone(),
+            location: sub_item.origin.as_ref().map(to_json_location),
+        })),
+        suggestions: Vec::from_iter(item.suggestions.iter().map(|suggestion| {
+            JsonSuggestion {
+                message: suggestion.message.clone(),
+                location: item
+                    .origin
+                    .as_ref()
+                    .filter(|origin| origin.file.span().contains_span(suggestion.span))
+                    .map(|origin| {
+                        to_json_location(&ItemOrigin {
+                            file: origin.file.clone(),
+                            span: suggestion.span,
+                        })
+                    }),
+                replacement: suggestion.replacement.clone(),
+                applicability: suggestion.applicability,
+            }
+        })),
+    }
+}
+
+pub fn to_json_location(origin: &ItemOrigin) -> JsonLocation {
+    let file = &origin.file;
+    let line_col = |pos: u32| {
+        let line_col = file.to_line_col(pos, ColUnit::Char);
+        JsonLineCol {
+            line: line_col.line + 1,
+            col: line_col.col + 1,
+        }
+    };
+
+    JsonLocation {
+        file: match file.path() {
+            Some(path) => path.display().to_string(),
+            None => file.name().to_owned(),
+        },
+        byte_start: origin.span.low() - file.span().low(),
+        byte_end: origin.span.high() - file.span().low(),
+        start: line_col(origin.span.low()),
+        end: line_col(origin.span.high()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{codes::TYPE_ERR_DUPLICATE_NAME, SubItem, Suggestion},
+        span::{SourceMap, Span},
+    };
+
+    #[test]
+    fn test_items_to_json() {
+        let mut map = SourceMap::new();
+        map.add_file("# other file\n", "other.spk", None);
+        let file = map.add_file(
+            "in é: f3;\nin é: f4;\n",
+            "main.spk",
+            Some("shaders/main.spk".into()),
+        );
+        let low = file.span().low();
+        let origin = |low_offset: u32, high_offset: u32| {
+            Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(low + low_offset, low + high_offset),
+            })
+        };
+        let items = [
+            Item {
+                code: TYPE_ERR_DUPLICATE_NAME,
+                level: ItemLevel::Error,
+                message: "input `é` is defined multiple times".into(),
+                origin: origin(14, 16),
+                sub_items: vec![
+                    SubItem {
+                        level: ItemLevel::Hint,
+                        message: "first defined here".into(),
+                        origin: origin(3, 5),
+                    },
+                    SubItem {
+                        level: ItemLevel::Hint,
+                        message: "rename one of them".into(),
+                        origin: None,
+                    },
+                ],
+                suggestions: vec![Suggestion::new(
+                    "rename it",
+                    Span::new(low + 14, low + 16),
+                    "e",
+                    Applicability::MaybeIncorrect,
+                )],
+            },
+            Item {
+                code: 0,
+                level: ItemLevel::Hint,
+                message: "1 error".into(),
+                origin: None,
+                sub_items: vec![],
+                suggestions: vec![],
+            },
+        ];
+
+        let json = items_to_json(&items);
+        assert_eq!(
+            json,
+            r#"{
+  "version": 1,
+  "items": [
+    {
+      "code": "SPK2030",
+      "level": "error",
+      "message": "input `é` is defined multiple times",
+      "location": {
+        "file": "shaders/main.spk",
+        "byte_start": 14,
+        "byte_end": 16,
+        "start": {
+          "line": 2,
+          "col": 4
+        },
+        "end": {
+          "line": 2,
+          "col": 5
+        }
+      },
+      "sub_items": [
+        {
+          "level": "hint",
+          "message": "first defined here",
+          "location": {
+            "file": "shaders/main.spk",
+            "byte_start": 3,
+            "byte_end": 5,
+            "start": {
+              "line": 1,
+              "col": 4
+            },
+            "end": {
+              "line": 1,
+              "col": 5
+            }
+          }
+        },
+        {
+          "level": "hint",
+          "message": "rename one of them",
+          "location": null
+        }
+      ],
+      "suggestions": [
+        {
+          "message": "rename it",
+          "location": {
+            "file": "shaders/main.spk",
+            "byte_start": 14,
+            "byte_end": 16,
+            "start": {
+              "line": 2,
+              "col": 4
+            },
+            "end": {
+              "line": 2,
+              "col": 5
+            }
+          },
+          "replacement": "e",
+          "applicability": "maybe_incorrect"
+        }
+      ]
+    },
+    {
+      "code": null,
+      "level": "hint",
+      "message": "1 error",
+      "location": null,
+      "sub_items": [],
+      "suggestions": []
+    }
+  ]
+}"#
+        );
+
+        let parsed: JsonDiagnostics = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.items[0], to_json_item(&items[0]));
+    }
+}
@@ -0,0 +1,252 @@
+use super::{
+    codes::{lookup, lookup_lint},
+    Item, ItemLevel,
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt::Display, str::FromStr, sync::Arc};
+
+/// How the items of a lint are reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// The items are discarded.
+    Allow,
+    /// The items are reported as warnings.
+    Warn,
+    /// The items are reported as errors.
+    Deny,
+    /// Like `Deny`, and nested scopes cannot change the level anymore.
+    Forbid,
+}
+
+impl LintLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Allow => "allow",
+            Self::Warn => "warn",
+            Self::Deny => "deny",
+            Self::Forbid => "forbid",
+        }
+    }
+}
+
+impl Display for LintLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "allow" => Ok(Self::Allow),
+            "warn" => Ok(Self::Warn),
+            "deny" => Ok(Self::Deny),
+            "forbid" => Ok(Self::Forbid),
+            _ => Err(format!(
+                "invalid lint level `{}`; expected allow, warn, deny or forbid",
+                str
+            )),
+        }
+    }
+}
+
+/// Levels of lints by code, e.g. from the compile options or the `[lints]` table of `spk.toml`.
+///
+/// A level set later overrides the one set earlier for the same code.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::diagnostics::{codes::TYPE_WARN_UNUSED_INPUT, LintLevel, LintLevels};
+/// let mut levels = LintLevels::new();
+/// levels.set_by_name("unused-input", LintLevel::Deny).unwrap();
+/// assert_eq!(levels.get(TYPE_WARN_UNUSED_INPUT), Some(LintLevel::Deny));
+/// assert!(levels.set_by_name("unused-flag", LintLevel::Allow).is_err());
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct LintLevels {
+    levels: Vec<(u32, LintLevel)>,
+}
+
+impl LintLevels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    pub fn set(&mut self, code: u32, level: LintLevel) {
+        self.levels.retain(|(set_code, _)| *set_code != code);
+        self.levels.push((code, level));
+    }
+
+    /// Sets the level of a lint given by name, e.g. `unused-input`, or by code, e.g. `SPK2120`.
+    pub fn set_by_name(&mut self, lint: &str, level: LintLevel) -> Result<(), String> {
+        let info = lookup_lint(lint).ok_or_else(|| format!("unknown lint `{}`", lint))?;
+        self.set(info.code, level);
+        Ok(())
+    }
+
+    /// Iterates over the codes and their levels, in the order they have been set.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, LintLevel)> + '_ {
+        self.levels.iter().copied()
+    }
+
+    pub fn get(&self, code: u32) -> Option<LintLevel> {
+        self.levels
+            .iter()
+            .find(|(set_code, _)| *set_code == code)
+            .map(|(_, level)| *level)
+    }
+
+    /// Sets every level of `other` over the levels of `self`.
+    pub fn extend(&mut self, other: &LintLevels) {
+        for (code, level) in &other.levels {
+            self.set(*code, *level);
+        }
+    }
+}
+
+/// The lint levels in effect at some point of the source: those set by the innermost enclosing
+/// scope win, except that a lint forbidden by any scope stays forbidden.
+///
+/// The root scope usually holds the levels of the compile options; the items of the source, e.g.
+/// passes and inputs, nest their `@allow = "<lint>"` attributes in it.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct LintScope {
+    levels: LintLevels,
+    parent: Option<Arc<LintScope>>,
+}
+
+impl LintScope {
+    pub fn new(levels: LintLevels) -> Self {
+        Self {
+            levels,
+            parent: None,
+        }
+    }
+
+    /// Returns a scope nested in this one.
+    pub fn nest(self: &Arc<Self>, levels: LintLevels) -> Self {
+        Self {
+            levels,
+            parent: Some(self.clone()),
+        }
+    }
+
+    /// Returns the level in effect for the code, or `None` if no scope sets it.
+    pub fn level(&self, code: u32) -> Option<LintLevel> {
+        let mut level = None;
+        let mut scope = Some(self);
+
+        while let Some(current) = scope {
+            match current.levels.get(code) {
+                Some(LintLevel::Forbid) => return Some(LintLevel::Forbid),
+                Some(set) if level.is_none() => level = Some(set),
+                _ => {}
+            }
+
+            scope = current.parent.as_deref();
+        }
+
+        level
+    }
+
+    /// Applies the level in effect to an item of a lint; `None` if the lint is allowed. Items
+    /// which are not of a lint are returned as they are.
+    pub fn apply(&self, mut item: Item) -> Option<Item> {
+        if lookup(item.code).and_then(|info| info.lint).is_none() {
+            return Some(item);
+        }
+
+        match self.level(item.code) {
+            Some(LintLevel::Allow) => return None,
+            Some(LintLevel::Warn) => item.level = ItemLevel::Warning,
+            Some(LintLevel::Deny | LintLevel::Forbid) => item.level = ItemLevel::Error,
+            None => {}
+        }
+
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::codes::{TYPE_ERR_UNKNOWN_TYPE, TYPE_WARN_UNUSED_INPUT};
+
+    fn levels(level: LintLevel) -> LintLevels {
+        let mut levels = LintLevels::new();
+        levels.set(TYPE_WARN_UNUSED_INPUT, level);
+        levels
+    }
+
+    fn item(code: u32, level: ItemLevel) -> Item {
+        Item {
+            code,
+            level,
+            message: "test".into(),
+            origin: None,
+            sub_items: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_lint_scope() {
+        let root = Arc::new(LintScope::new(levels(LintLevel::Deny)));
+        assert_eq!(root.level(TYPE_WARN_UNUSED_INPUT), Some(LintLevel::Deny));
+        assert_eq!(root.level(TYPE_ERR_UNKNOWN_TYPE), None);
+
+        // the innermost scope wins
+        let allowed = Arc::new(root.nest(levels(LintLevel::Allow)));
+        assert_eq!(
+            allowed.level(TYPE_WARN_UNUSED_INPUT),
+            Some(LintLevel::Allow)
+        );
+        let inherited = allowed.nest(LintLevels::new());
+        assert_eq!(
+            inherited.level(TYPE_WARN_UNUSED_INPUT),
+            Some(LintLevel::Allow)
+        );
+
+        // except over a forbidden lint
+        let forbidden = Arc::new(root.nest(levels(LintLevel::Forbid)));
+        let allowed = forbidden.nest(levels(LintLevel::Allow));
+        assert_eq!(
+            allowed.level(TYPE_WARN_UNUSED_INPUT),
+            Some(LintLevel::Forbid)
+        );
+    }
+
+    #[test]
+    fn test_lint_scope_apply() {
+        let warning = item(TYPE_WARN_UNUSED_INPUT, ItemLevel::Warning);
+        let scope = |level| LintScope::new(levels(level));
+
+        assert!(scope(LintLevel::Allow).apply(warning.clone()).is_none());
+        assert_eq!(
+            scope(LintLevel::Warn).apply(warning.clone()).unwrap().level,
+            ItemLevel::Warning
+        );
+        assert_eq!(
+            scope(LintLevel::Deny).apply(warning.clone()).unwrap().level,
+            ItemLevel::Error
+        );
+        assert_eq!(
+            LintScope::default().apply(warning).unwrap().level,
+            ItemLevel::Warning
+        );
+
+        // errors are not lints
+        let mut levels = LintLevels::new();
+        levels.set(TYPE_ERR_UNKNOWN_TYPE, LintLevel::Allow);
+        let error = item(TYPE_ERR_UNKNOWN_TYPE, ItemLevel::Error);
+        assert!(LintScope::new(levels).apply(error).is_some());
+    }
+}
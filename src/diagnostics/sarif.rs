@@ -0,0 +1,446 @@
+use super::{
+    codes::{code_name, lookup},
+    Item, ItemLevel, ItemOrigin,
+};
+use crate::span::ColUnit;
+use serde::Serialize;
+
+pub const SARIF_VERSION: &str = "2.1.0";
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    pub schema: &'static str,
+    pub version: &'static str,
+    pub runs: Vec<SarifRun>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRun {
+    pub tool: SarifTool,
+    /// Columns are counted in characters, like in the text output.
+    pub column_kind: &'static str,
+    pub results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct SarifTool {
+    pub driver: SarifDriver,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifDriver {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// The codes of the results, sorted.
+    pub rules: Vec<SarifRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRule {
+    pub id: String,
+    pub short_description: SarifMessage,
+    pub help: SarifMarkdownMessage,
+    pub default_configuration: SarifConfiguration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct SarifConfiguration {
+    pub level: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule_index: Option<usize>,
+    pub level: &'static str,
+    pub message: SarifMessage,
+    pub locations: Vec<SarifLocation>,
+    /// The sub-items with an origin.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub related_locations: Vec<SarifLocation>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fixes: Vec<SarifFix>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifFix {
+    pub description: SarifMessage,
+    pub artifact_changes: Vec<SarifArtifactChange>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifArtifactChange {
+    pub artifact_location: SarifArtifactLocation,
+    pub replacements: Vec<SarifReplacement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifReplacement {
+    pub deleted_region: SarifRegion,
+    pub inserted_content: SarifMessage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct SarifMessage {
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct SarifMarkdownMessage {
+    pub text: String,
+    pub markdown: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifLocation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<usize>,
+    pub physical_location: SarifPhysicalLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<SarifMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifPhysicalLocation {
+    pub artifact_location: SarifArtifactLocation,
+    pub region: SarifRegion,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct SarifArtifactLocation {
+    pub uri: String,
+}
+
+/// 1-based lines and columns; the end column is exclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifRegion {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
+    pub byte_offset: u32,
+    pub byte_length: u32,
+}
+
+/// Serializes the items into a SARIF 2.1.0 log with a single run, e.g. for code scanning
+/// services. Sub-items with an origin become related locations, and the other ones are appended
+/// to the message; suggestions become fixes.
+pub fn items_to_sarif(items: &[Item]) -> String {
+    serde_json::to_string_pretty(&to_sarif_log(items)).unwrap()
+}
+
+pub fn to_sarif_log(items: &[Item]) -> SarifLog {
+    let mut codes = Vec::from_iter(items.iter().map(|item| item.code).filter(|code| *code != 0));
+    codes.sort_unstable();
+    codes.dedup();
+
+    let rules = Vec::from_iter(codes.iter().map(|code| {
+        let info = lookup(*code);
+        let title = info.map_or("", |info| info.title);
+        let explanation = info.map_or("", |info| info.explanation);
+
+        SarifRule {
+            id: code_name(*code),
+            short_description: SarifMessage {
+                text: title.to_owned(),
+            },
+            help: SarifMarkdownMessage {
+                text: explanation.to_owned(),
+                markdown: explanation.to_owned(),
+            },
+            default_configuration: SarifConfiguration {
+                level: sarif_level(info.map_or(ItemLevel::Error, |info| info.level)),
+            },
+        }
+    }));
+
+    let results = Vec::from_iter(items.iter().map(|item| {
+        let mut text = item.message.clone();
+        let mut related_locations = Vec::new();
+
+        for sub_item in &item.sub_items {
+            match &sub_item.origin {
+                Some(origin) => related_locations.push(SarifLocation {
+                    id: Some(related_locations.len()),
+                    physical_location: to_physical_location(origin),
+                    message: Some(SarifMessage {
+                        text: sub_item.message.clone(),
+                    }),
+                }),
+                None => text.push_str(&format!("\n{}", sub_item.message)),
+            }
+        }
+
+        let fixes = Vec::from_iter(item.origin.iter().flat_map(|origin| {
+            item.suggestions
+                .iter()
+                .filter(|suggestion| origin.file.span().contains_span(suggestion.span))
+                .map(|suggestion| {
+                    let location = to_physical_location(&ItemOrigin {
+                        file: origin.file.clone(),
+                        span: suggestion.span,
+                    });
+
+                    SarifFix {
+                        description: SarifMessage {
+                            text: suggestion.message.clone(),
+                        },
+                        artifact_changes: vec![SarifArtifactChange {
+                            artifact_location: location.artifact_location,
+                            replacements: vec![SarifReplacement {
+                                deleted_region: location.region,
+                                inserted_content: SarifMessage {
+                                    text: suggestion.replacement.clone(),
+                                },
+                            }],
+                        }],
+                    }
+                })
+        }));
+
+        SarifResult {
+            rule_id: (item.code != 0).then(|| code_name(item.code)),
+            rule_index: codes.binary_search(&item.code).ok(),
+            level: sarif_level(item.level),
+            message: SarifMessage { text },
+            locations: Vec::from_iter(item.origin.iter().map(|origin| SarifLocation {
+                id: None,
+                physical_location: to_physical_location(origin),
+                message: None,
+            })),
+            related_locations,
+            fixes,
+        }
+    }));
+
+    SarifLog {
+        schema: SARIF_SCHEMA,
+        version: SARIF_VERSION,
+        runs: vec![SarifRun {
+            tool: SarifTool {
+                driver: SarifDriver {
+                    name: "spk",
+                    version: env!("CARGO_PKG_VERSION"),
+                    rules,
+                },
+            },
+            column_kind: "unicodeCodePoints",
+            results,
+        }],
+    }
+}
+
+fn to_physical_location(origin: &ItemOrigin) -> SarifPhysicalLocation {
+    let file = &origin.file;
+    let start = file.to_line_col(origin.span.low(), ColUnit::Char);
+    let end = file.to_line_col(origin.span.high(), ColUnit::Char);
+    let uri = match file.path() {
+        // URIs only use forward slashes
+        Some(path) => path.to_string_lossy().replace('\\', "/"),
+        None => file.name().to_owned(),
+    };
+
+    SarifPhysicalLocation {
+        artifact_location: SarifArtifactLocation { uri },
+        region: SarifRegion {
+            start_line: start.line + 1,
+            start_column: start.col + 1,
+            end_line: end.line + 1,
+            end_column: end.col + 1,
+            byte_offset: origin.span.low() - file.span().low(),
+            byte_length: origin.span.len(),
+        },
+    }
+}
+
+/// SARIF has no hint level, so hints are notes.
+fn sarif_level(level: ItemLevel) -> &'static str {
+    match level {
+        ItemLevel::Hint => "note",
+        level => level.name(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{codes::IMPORT_ERR_PRIVATE, Applicability, SubItem, Suggestion},
+        span::{SourceMap, Span},
+    };
+
+    #[test]
+    fn test_items_to_sarif() {
+        let mut map = SourceMap::new();
+        let file = map.add_file(
+            "fn light() {}\nlet a = light();\n",
+            "lighting.spk",
+            Some("shaders\\lighting.spk".into()),
+        );
+        let items = [Item {
+            code: IMPORT_ERR_PRIVATE,
+            level: ItemLevel::Error,
+            message: "function `light` is private to `lighting.spk`".into(),
+            origin: Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(22, 27),
+            }),
+            sub_items: vec![
+                SubItem {
+                    level: ItemLevel::Hint,
+                    message: "add `pub` to call it from other files".into(),
+                    origin: Some(ItemOrigin {
+                        file: file.clone(),
+                        span: Span::new(3, 8),
+                    }),
+                },
+                SubItem {
+                    level: ItemLevel::Hint,
+                    message: "imported here".into(),
+                    origin: None,
+                },
+            ],
+            suggestions: vec![Suggestion::new(
+                "add `pub`",
+                Span::empty(0),
+                "pub ",
+                Applicability::MachineApplicable,
+            )],
+        }];
+
+        // the explanation is tested along with the codes
+        let mut log = to_sarif_log(&items);
+        let help = &mut log.runs[0].tool.driver.rules[0].help;
+        assert!(help.markdown.contains("Add `pub`"));
+        help.text = "...".to_owned();
+        help.markdown = "...".to_owned();
+
+        assert_eq!(
+            serde_json::to_string_pretty(&log).unwrap(),
+            format!(
+                r#"{{
+  "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+  "version": "2.1.0",
+  "runs": [
+    {{
+      "tool": {{
+        "driver": {{
+          "name": "spk",
+          "version": "{}",
+          "rules": [
+            {{
+              "id": "SPK4050",
+              "shortDescription": {{
+                "text": "private function"
+              }},
+              "help": {{
+                "text": "...",
+                "markdown": "..."
+              }},
+              "defaultConfiguration": {{
+                "level": "error"
+              }}
+            }}
+          ]
+        }}
+      }},
+      "columnKind": "unicodeCodePoints",
+      "results": [
+        {{
+          "ruleId": "SPK4050",
+          "ruleIndex": 0,
+          "level": "error",
+          "message": {{
+            "text": "function `light` is private to `lighting.spk`\nimported here"
+          }},
+          "locations": [
+            {{
+              "physicalLocation": {{
+                "artifactLocation": {{
+                  "uri": "shaders/lighting.spk"
+                }},
+                "region": {{
+                  "startLine": 2,
+                  "startColumn": 9,
+                  "endLine": 2,
+                  "endColumn": 14,
+                  "byteOffset": 22,
+                  "byteLength": 5
+                }}
+              }}
+            }}
+          ],
+          "relatedLocations": [
+            {{
+              "id": 0,
+              "physicalLocation": {{
+                "artifactLocation": {{
+                  "uri": "shaders/lighting.spk"
+                }},
+                "region": {{
+                  "startLine": 1,
+                  "startColumn": 4,
+                  "endLine": 1,
+                  "endColumn": 9,
+                  "byteOffset": 3,
+                  "byteLength": 5
+                }}
+              }},
+              "message": {{
+                "text": "add `pub` to call it from other files"
+              }}
+            }}
+          ],
+          "fixes": [
+            {{
+              "description": {{
+                "text": "add `pub`"
+              }},
+              "artifactChanges": [
+                {{
+                  "artifactLocation": {{
+                    "uri": "shaders/lighting.spk"
+                  }},
+                  "replacements": [
+                    {{
+                      "deletedRegion": {{
+                        "startLine": 1,
+                        "startColumn": 1,
+                        "endLine": 1,
+                        "endColumn": 1,
+                        "byteOffset": 0,
+                        "byteLength": 0
+                      }},
+                      "insertedContent": {{
+                        "text": "pub "
+                      }}
+                    }}
+                  ]
+                }}
+              ]
+            }}
+          ]
+        }}
+      ]
+    }}
+  ]
+}}"#,
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+}
@@ -1,78 +1,133 @@
-use super::{Item, ItemLevel, ItemOrigin, SubItem};
-use crate::span::{SourceFile, Span};
-use std::sync::Arc;
-use tokio::sync::mpsc::UnboundedSender;
+use super::{
+    DiagnosticSink, Item, ItemLevel, ItemOrigin, LintLevels, LintScope, SubItem, Suggestion,
+};
+use crate::span::{SourceFile, SourceMap, Span};
+use std::{fmt::Debug, sync::Arc};
 
-#[derive(Debug, Clone)]
+/// Builds items with the origins of spans, and reports them to a [`DiagnosticSink`].
+///
+/// The levels of lints are applied to the items before they are reported; see [`LintScope`].
+#[derive(Clone)]
 pub struct ItemSender {
     file: Arc<SourceFile>,
-    sender: UnboundedSender<Item>,
+    /// Looked up for spans outside of `file`, e.g. in items of imported files.
+    source_map: Option<Arc<SourceMap>>,
+    sink: Arc<dyn DiagnosticSink>,
+    lints: Arc<LintScope>,
 }
 
 impl ItemSender {
-    pub fn new(file: Arc<SourceFile>, sender: UnboundedSender<Item>) -> Self {
-        Self { file, sender }
+    pub fn new(file: Arc<SourceFile>, sink: impl DiagnosticSink + 'static) -> Self {
+        Self {
+            file,
+            source_map: None,
+            sink: Arc::new(sink),
+            lints: Arc::default(),
+        }
+    }
+
+    /// Returns a sender reporting to the same receiver, for spans in another file.
+    pub fn with_file(&self, file: Arc<SourceFile>) -> Self {
+        Self {
+            file,
+            source_map: self.source_map.clone(),
+            sink: self.sink.clone(),
+            lints: self.lints.clone(),
+        }
+    }
+
+    /// Returns a sender which attributes spans outside of its file to the file of the
+    /// `source_map` containing them.
+    pub fn with_source_map(self, source_map: Arc<SourceMap>) -> Self {
+        Self {
+            source_map: Some(source_map),
+            ..self
+        }
+    }
+
+    /// Returns a sender reporting to the same sink, with the lint levels nested in those of this
+    /// sender, e.g. the `@allow = "<lint>"` attributes of an item.
+    pub fn with_lint_levels(&self, levels: LintLevels) -> Self {
+        if levels.is_empty() {
+            return self.clone();
+        }
+
+        Self {
+            lints: Arc::new(self.lints.nest(levels)),
+            ..self.clone()
+        }
     }
 
     pub fn file(&self) -> Arc<SourceFile> {
         self.file.clone()
     }
 
+    fn origin(&self, span: Span) -> ItemOrigin {
+        let file = match &self.source_map {
+            Some(source_map) if !self.file.span().contains_span(span) => source_map
+                .lookup_file(span.low())
+                .filter(|file| file.span().contains_span(span))
+                .unwrap_or_else(|| self.file.clone()),
+            _ => self.file.clone(),
+        };
+
+        ItemOrigin { file, span }
+    }
+
+    /// Returns `true` once the sink discards every further item, e.g. after too many errors;
+    /// passes may stop early then.
+    pub fn is_full(&self) -> bool {
+        self.sink.is_full()
+    }
+
+    fn send(&self, item: Item) {
+        if let Some(item) = self.lints.apply(item) {
+            self.sink.report(item);
+        }
+    }
+
     pub fn hint(&self, span: Span, message: impl Into<String>) {
-        self.sender
-            .send(Item {
-                code: 0,
-                level: ItemLevel::Hint,
-                message: message.into(),
-                origin: Some(ItemOrigin {
-                    file: self.file.clone(),
-                    span,
-                }),
-                sub_items: vec![],
-            })
-            .unwrap();
+        self.send(Item {
+            code: 0,
+            level: ItemLevel::Hint,
+            message: message.into(),
+            origin: Some(self.origin(span)),
+            sub_items: vec![],
+            suggestions: vec![],
+        });
     }
 
     pub fn hint_sub(&self, span: Span, message: impl Into<String>, sub_items: Vec<SubItem>) {
-        self.sender
-            .send(Item {
-                code: 0,
-                level: ItemLevel::Hint,
-                message: message.into(),
-                origin: Some(ItemOrigin {
-                    file: self.file.clone(),
-                    span,
-                }),
-                sub_items,
-            })
-            .unwrap();
+        self.send(Item {
+            code: 0,
+            level: ItemLevel::Hint,
+            message: message.into(),
+            origin: Some(self.origin(span)),
+            sub_items,
+            suggestions: vec![],
+        });
     }
 
     pub fn hint_simple(&self, message: impl Into<String>) {
-        self.sender
-            .send(Item {
-                code: 0,
-                level: ItemLevel::Hint,
-                message: message.into(),
-                origin: None,
-                sub_items: vec![],
-            })
-            .unwrap()
+        self.send(Item {
+            code: 0,
+            level: ItemLevel::Hint,
+            message: message.into(),
+            origin: None,
+            sub_items: vec![],
+            suggestions: vec![],
+        })
     }
 
     pub fn warning(&self, code: u32, span: Span, message: impl Into<String>) {
-        self.sender
-            .send(Item {
-                code,
-                level: ItemLevel::Warning,
-                message: message.into(),
-                origin: Some(ItemOrigin {
-                    file: self.file.clone(),
-                    span,
-                }),
-                sub_items: vec![],
-            })
-            .unwrap()
+        self.send(Item {
+            code,
+            level: ItemLevel::Warning,
+            message: message.into(),
+            origin: Some(self.origin(span)),
+            sub_items: vec![],
+            suggestions: vec![],
+        })
     }
 
     pub fn warning_sub(
@@ -82,45 +137,53 @@ impl ItemSender {
         message: impl Into<String>,
         sub_items: Vec<SubItem>,
     ) {
-        self.sender
-            .send(Item {
-                code,
-                level: ItemLevel::Warning,
-                message: message.into(),
-                origin: Some(ItemOrigin {
-                    file: self.file.clone(),
-                    span,
-                }),
-                sub_items,
-            })
-            .unwrap()
+        self.send(Item {
+            code,
+            level: ItemLevel::Warning,
+            message: message.into(),
+            origin: Some(self.origin(span)),
+            sub_items,
+            suggestions: vec![],
+        })
     }
 
     pub fn warning_simple(&self, code: u32, message: impl Into<String>) {
-        self.sender
-            .send(Item {
-                code,
-                level: ItemLevel::Warning,
-                message: message.into(),
-                origin: None,
-                sub_items: vec![],
-            })
-            .unwrap()
+        self.send(Item {
+            code,
+            level: ItemLevel::Warning,
+            message: message.into(),
+            origin: None,
+            sub_items: vec![],
+            suggestions: vec![],
+        })
+    }
+
+    pub fn warning_fix(
+        &self,
+        code: u32,
+        span: Span,
+        message: impl Into<String>,
+        suggestions: Vec<Suggestion>,
+    ) {
+        self.send(Item {
+            code,
+            level: ItemLevel::Warning,
+            message: message.into(),
+            origin: Some(self.origin(span)),
+            sub_items: vec![],
+            suggestions,
+        })
     }
 
     pub fn error(&self, code: u32, span: Span, message: impl Into<String>) {
-        self.sender
-            .send(Item {
-                code,
-                level: ItemLevel::Error,
-                message: message.into(),
-                origin: Some(ItemOrigin {
-                    file: self.file.clone(),
-                    span,
-                }),
-                sub_items: vec![],
-            })
-            .unwrap()
+        self.send(Item {
+            code,
+            level: ItemLevel::Error,
+            message: message.into(),
+            origin: Some(self.origin(span)),
+            sub_items: vec![],
+            suggestions: vec![],
+        })
     }
 
     pub fn error_sub(
@@ -130,40 +193,49 @@ impl ItemSender {
         message: impl Into<String>,
         sub_items: Vec<SubItem>,
     ) {
-        self.sender
-            .send(Item {
-                code,
-                level: ItemLevel::Error,
-                message: message.into(),
-                origin: Some(ItemOrigin {
-                    file: self.file.clone(),
-                    span,
-                }),
-                sub_items,
-            })
-            .unwrap()
+        self.send(Item {
+            code,
+            level: ItemLevel::Error,
+            message: message.into(),
+            origin: Some(self.origin(span)),
+            sub_items,
+            suggestions: vec![],
+        })
     }
 
     pub fn error_simple(&self, code: u32, message: impl Into<String>) {
-        self.sender
-            .send(Item {
-                code,
-                level: ItemLevel::Error,
-                message: message.into(),
-                origin: None,
-                sub_items: vec![],
-            })
-            .unwrap()
+        self.send(Item {
+            code,
+            level: ItemLevel::Error,
+            message: message.into(),
+            origin: None,
+            sub_items: vec![],
+            suggestions: vec![],
+        })
+    }
+
+    pub fn error_fix(
+        &self,
+        code: u32,
+        span: Span,
+        message: impl Into<String>,
+        suggestions: Vec<Suggestion>,
+    ) {
+        self.send(Item {
+            code,
+            level: ItemLevel::Error,
+            message: message.into(),
+            origin: Some(self.origin(span)),
+            sub_items: vec![],
+            suggestions,
+        })
     }
 
     pub fn sub_hint(&self, span: Span, message: impl Into<String>) -> SubItem {
         SubItem {
             level: ItemLevel::Hint,
             message: message.into(),
-            origin: Some(ItemOrigin {
-                file: self.file.clone(),
-                span,
-            }),
+            origin: Some(self.origin(span)),
         }
     }
 
@@ -179,10 +251,7 @@ impl ItemSender {
         SubItem {
             level: ItemLevel::Warning,
             message: message.into(),
-            origin: Some(ItemOrigin {
-                file: self.file.clone(),
-                span,
-            }),
+            origin: Some(self.origin(span)),
         }
     }
 
@@ -198,10 +267,7 @@ impl ItemSender {
         SubItem {
             level: ItemLevel::Error,
             message: message.into(),
-            origin: Some(ItemOrigin {
-                file: self.file.clone(),
-                span,
-            }),
+            origin: Some(self.origin(span)),
         }
     }
 
@@ -213,3 +279,11 @@ impl ItemSender {
         }
     }
 }
+
+impl Debug for ItemSender {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ItemSender")
+            .field("file", &self.file.name())
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,308 @@
+use super::{Item, ItemLevel};
+use parking_lot::Mutex;
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// The destination of the items reported through an [`ItemSender`](super::ItemSender).
+///
+/// Reporting never fails; a sink which cannot deliver an item, e.g. a channel whose receiver has
+/// been dropped, discards it.
+pub trait DiagnosticSink: Send + Sync {
+    fn report(&self, item: Item);
+
+    /// Returns `true` once the sink discards every further item, so passes can stop early.
+    fn is_full(&self) -> bool {
+        false
+    }
+}
+
+impl<S> DiagnosticSink for Arc<S>
+where
+    S: DiagnosticSink + ?Sized,
+{
+    fn report(&self, item: Item) {
+        S::report(self, item)
+    }
+
+    fn is_full(&self) -> bool {
+        S::is_full(self)
+    }
+}
+
+impl DiagnosticSink for std::sync::mpsc::Sender<Item> {
+    fn report(&self, item: Item) {
+        let _ = self.send(item);
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl DiagnosticSink for tokio::sync::mpsc::UnboundedSender<Item> {
+    fn report(&self, item: Item) {
+        let _ = self.send(item);
+    }
+}
+
+/// Collects the items in the order they are reported.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::{diagnostics::{ItemCollector, ItemSender}, span::SourceMap};
+/// # use std::sync::Arc;
+/// let file = SourceMap::new().add_file("", "test", None);
+/// let collector = Arc::new(ItemCollector::new());
+/// let reporter = ItemSender::new(file, collector.clone());
+/// reporter.error_simple(1010, "test");
+/// assert_eq!(collector.take().len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct ItemCollector {
+    items: Mutex<Vec<Item>>,
+}
+
+impl ItemCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes and returns the items collected so far.
+    pub fn take(&self) -> Vec<Item> {
+        std::mem::take(&mut *self.items.lock())
+    }
+
+    pub fn into_items(self) -> Vec<Item> {
+        self.items.into_inner()
+    }
+}
+
+impl DiagnosticSink for ItemCollector {
+    fn report(&self, item: Item) {
+        self.items.lock().push(item);
+    }
+}
+
+/// Passes every item to a function as soon as it is reported.
+pub struct CallbackSink<F>
+where
+    F: Fn(Item) + Send + Sync,
+{
+    callback: F,
+}
+
+impl<F> CallbackSink<F>
+where
+    F: Fn(Item) + Send + Sync,
+{
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+impl<F> Debug for CallbackSink<F>
+where
+    F: Fn(Item) + Send + Sync,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallbackSink").finish_non_exhaustive()
+    }
+}
+
+impl<F> DiagnosticSink for CallbackSink<F>
+where
+    F: Fn(Item) + Send + Sync,
+{
+    fn report(&self, item: Item) {
+        (self.callback)(item)
+    }
+}
+
+/// Counts the items passed to the inner sink, and stops passing them after a number of errors.
+///
+/// The last passed error is followed by a hint saying so, and every further item is discarded;
+/// errors are still counted.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::diagnostics::{DiagnosticSink, ItemCollector, ItemSender, LimitSink};
+/// # use shader_pack::span::SourceMap;
+/// # use std::sync::Arc;
+/// let file = SourceMap::new().add_file("", "test", None);
+/// let sink = Arc::new(LimitSink::new(ItemCollector::new(), 2));
+/// let reporter = ItemSender::new(file, sink.clone());
+///
+/// for _ in 0..4 {
+///     reporter.error_simple(1010, "test");
+/// }
+///
+/// assert_eq!(sink.error_count(), 4);
+/// assert!(sink.is_full());
+/// assert_eq!(sink.inner().take().len(), 3);
+/// ```
+#[derive(Debug)]
+pub struct LimitSink<S>
+where
+    S: DiagnosticSink,
+{
+    inner: S,
+    max_errors: Option<usize>,
+    errors: AtomicUsize,
+    warnings: AtomicUsize,
+}
+
+impl<S> LimitSink<S>
+where
+    S: DiagnosticSink,
+{
+    /// Passes at most `max_errors` errors, and everything reported before them.
+    pub fn new(inner: S, max_errors: usize) -> Self {
+        Self {
+            inner,
+            max_errors: Some(max_errors),
+            errors: AtomicUsize::new(0),
+            warnings: AtomicUsize::new(0),
+        }
+    }
+
+    /// Passes every item, only counting them.
+    pub fn counting(inner: S) -> Self {
+        Self {
+            inner,
+            max_errors: None,
+            errors: AtomicUsize::new(0),
+            warnings: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// The number of reported errors, including the discarded ones.
+    pub fn error_count(&self) -> usize {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// The number of reported warnings, including the discarded ones.
+    pub fn warning_count(&self) -> usize {
+        self.warnings.load(Ordering::Relaxed)
+    }
+}
+
+impl<S> DiagnosticSink for LimitSink<S>
+where
+    S: DiagnosticSink,
+{
+    fn report(&self, item: Item) {
+        let level = item.level;
+        let errors = match level {
+            ItemLevel::Error => self.errors.fetch_add(1, Ordering::Relaxed),
+            ItemLevel::Warning => {
+                self.warnings.fetch_add(1, Ordering::Relaxed);
+                self.error_count()
+            }
+            ItemLevel::Hint => self.error_count(),
+        };
+        let max_errors = match self.max_errors {
+            Some(max_errors) if max_errors <= errors => return,
+            Some(max_errors) => max_errors,
+            None => usize::MAX,
+        };
+
+        self.inner.report(item);
+
+        if level == ItemLevel::Error && errors + 1 == max_errors {
+            self.inner.report(Item {
+                code: 0,
+                level: ItemLevel::Hint,
+                message: format!(
+                    "stopped after {} error{}; further items are not reported",
+                    max_errors,
+                    if max_errors == 1 { "" } else { "s" }
+                ),
+                origin: None,
+                sub_items: vec![],
+                suggestions: vec![],
+            });
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        match self.max_errors {
+            Some(max_errors) => max_errors <= self.error_count() || self.inner.is_full(),
+            None => self.inner.is_full(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(level: ItemLevel, message: &str) -> Item {
+        Item {
+            code: 0,
+            level,
+            message: message.into(),
+            origin: None,
+            sub_items: vec![],
+            suggestions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_limit_sink() {
+        let sink = LimitSink::new(ItemCollector::new(), 2);
+        sink.report(item(ItemLevel::Warning, "a"));
+        sink.report(item(ItemLevel::Error, "b"));
+        assert!(!sink.is_full());
+        sink.report(item(ItemLevel::Error, "c"));
+        assert!(sink.is_full());
+        sink.report(item(ItemLevel::Warning, "d"));
+        sink.report(item(ItemLevel::Error, "e"));
+        sink.report(item(ItemLevel::Error, "f"));
+
+        assert_eq!(sink.error_count(), 4);
+        assert_eq!(sink.warning_count(), 2);
+        assert_eq!(
+            Vec::from_iter(
+                sink.into_inner()
+                    .into_items()
+                    .into_iter()
+                    .map(|item| item.message)
+            ),
+            [
+                "a",
+                "b",
+                "c",
+                "stopped after 2 errors; further items are not reported"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_counting_sink() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let sink = LimitSink::counting(CallbackSink::new({
+            let messages = messages.clone();
+            move |item: Item| messages.lock().push(item.message)
+        }));
+
+        for message in ["a", "b", "c"] {
+            sink.report(item(ItemLevel::Error, message));
+        }
+
+        assert_eq!(sink.error_count(), 3);
+        assert!(!sink.is_full());
+        assert_eq!(*messages.lock(), ["a", "b", "c"]);
+    }
+}
@@ -0,0 +1,442 @@
+use super::{
+    stringify::{Markup, Style},
+    ItemLevel,
+};
+use crate::span::{ColUnit, SourceFile, Span};
+use std::collections::BTreeSet;
+use unicode_width::UnicodeWidthChar;
+
+/// The number of columns between tab stops when rendering source lines.
+pub const TAB_WIDTH: usize = 4;
+
+/// A span underlined in a snippet, with a message next to the underline.
+///
+/// Primary labels are underlined with `^` in the color of the item level, secondary ones with
+/// `-`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    pub primary: bool,
+}
+
+impl Label {
+    pub fn primary(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: true,
+        }
+    }
+
+    pub fn secondary(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+            primary: false,
+        }
+    }
+}
+
+/// Renders the lines of the file covered by the labels, each with one line of context, and
+/// underlines the labels below them.
+///
+/// The location in the first line is the one of the first primary label, or of the first label
+/// if none is primary. Lines which are not shown are elided with `...`.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::{diagnostics::{stringify_snippet, ItemLevel, Label}, span::{SourceMap, Span}};
+/// let file = SourceMap::new().add_file("in a: f;\nin a: f;\n", "foo.spk", Some("foo.spk".into()));
+/// let labels = [
+///     Label::primary(Span::new(12, 13), "defined again"),
+///     Label::secondary(Span::new(3, 4), "first defined here"),
+/// ];
+/// assert_eq!(
+///     stringify_snippet(ItemLevel::Error, &file, &labels, false),
+///     "at foo.spk:2:4
+///  1 | in a: f;
+///         - first defined here
+///  2 | in a: f;
+///         ^ defined again
+///  3 | \n",
+/// );
+/// ```
+pub fn stringify_snippet(
+    level: ItemLevel,
+    file: &SourceFile,
+    labels: &[Label],
+    apply_styles: bool,
+) -> String {
+    render_snippet(level, file, labels, Markup::new(apply_styles))
+}
+
+pub(super) fn render_snippet(
+    level: ItemLevel,
+    file: &SourceFile,
+    labels: &[Label],
+    markup: Markup,
+) -> String {
+    let mut lines = Vec::new();
+    let first = match labels.iter().find(|label| label.primary).or(labels.first()) {
+        Some(first) => first,
+        None => return "".into(),
+    };
+
+    let line_col = file.to_line_col(first.span.low(), ColUnit::Char);
+    let path = match file.path() {
+        Some(path) => path.display().to_string(),
+        None => "?".to_owned(),
+    };
+    let location = format!("at {}:{}:{}", path, line_col.line + 1, line_col.col + 1);
+    lines.push(markup.apply(Style::Location, &location));
+
+    let marks = Vec::from_iter(labels.iter().flat_map(|label| marks_of(file, label)));
+    let max_line = (file.line_lows().len() - 1) as u32;
+    let mut shown_lines = BTreeSet::new();
+
+    for label in labels {
+        let line_low = file.to_line_col(label.span.low(), ColUnit::Utf8).line;
+        let line_high = file.to_line_col(label.span.high(), ColUnit::Utf8).line;
+        shown_lines.insert(line_low.saturating_sub(1));
+        shown_lines.insert(line_low);
+        shown_lines.insert(line_high);
+        shown_lines.insert(u32::min(line_high + 1, max_line));
+    }
+
+    let last_line = shown_lines.last().copied().unwrap_or_default();
+    let max_line_number_width = ((last_line + 1) as f64).log(10f64).ceil() as usize;
+    let indent = " ".repeat(max_line_number_width + 4);
+    let mut previous_line = None;
+
+    for line in shown_lines {
+        if matches!(previous_line, Some(previous) if previous + 1 < line) {
+            lines.push(" ...".into());
+        }
+
+        previous_line = Some(line);
+        let text = format!(
+            "{:>width$} | {}",
+            line + 1,
+            expand_tabs(line_text(file, line)),
+            width = max_line_number_width + 1
+        );
+        lines.push(markup.apply(Style::Plain, &text));
+
+        let line_marks = Vec::from_iter(marks.iter().filter(|mark| mark.line == line));
+
+        for row in render_marks(&line_marks) {
+            lines.push(format!("{}{}", indent, stringify_row(level, &row, markup)));
+        }
+    }
+
+    lines.push("".into());
+    lines.join("\n")
+}
+
+/// Replaces the tabs of a line with spaces up to the next tab stop.
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut col = 0;
+
+    for char in line.chars() {
+        if char == '\t' {
+            let next_col = (col / TAB_WIDTH + 1) * TAB_WIDTH;
+            expanded.extend(std::iter::repeat_n(' ', next_col - col));
+            col = next_col;
+        } else {
+            expanded.push(char);
+            col += char.width().unwrap_or(0);
+        }
+    }
+
+    expanded
+}
+
+/// Returns the column of the byte offset in the line as displayed, i.e. with tabs expanded and
+/// wide characters taking two columns.
+fn display_col(line: &str, offset: usize) -> usize {
+    let mut col = 0;
+
+    for (char_offset, char) in line.char_indices() {
+        if offset <= char_offset {
+            break;
+        }
+
+        col = match char {
+            '\t' => (col / TAB_WIDTH + 1) * TAB_WIDTH,
+            _ => col + char.width().unwrap_or(0),
+        };
+    }
+
+    col
+}
+
+fn line_text(file: &SourceFile, line: u32) -> &str {
+    file.slice_line(line).trim_end_matches(['\n', '\r'])
+}
+
+/// An underline in a single line; a label spanning several lines is underlined from its start
+/// to the end of its first line, and from the start of its last line to its end.
+struct Mark<'a> {
+    line: u32,
+    col_low: usize,
+    col_high: usize,
+    message: Option<&'a str>,
+    primary: bool,
+}
+
+fn marks_of<'a>(file: &SourceFile, label: &'a Label) -> Vec<Mark<'a>> {
+    let low = file.to_line_col(label.span.low(), ColUnit::Utf8);
+    let high = file.to_line_col(label.span.high(), ColUnit::Utf8);
+    let message = Some(label.message.as_str()).filter(|message| !message.is_empty());
+    let low_text = line_text(file, low.line);
+    let high_text = line_text(file, high.line);
+    let col_low = display_col(low_text, low.col as usize);
+    let col_high = display_col(high_text, high.col as usize);
+
+    if low.line == high.line {
+        return vec![Mark {
+            line: low.line,
+            col_low,
+            col_high: usize::max(col_high, col_low + 1),
+            message,
+            primary: label.primary,
+        }];
+    }
+
+    vec![
+        Mark {
+            line: low.line,
+            col_low,
+            col_high: usize::max(display_col(low_text, low_text.len()), col_low + 1),
+            message: None,
+            primary: label.primary,
+        },
+        Mark {
+            line: high.line,
+            col_low: 0,
+            col_high: usize::max(col_high, 1),
+            message,
+            primary: label.primary,
+        },
+    ]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellStyle {
+    Plain,
+    Primary,
+    Secondary,
+}
+
+type Row = Vec<(char, CellStyle)>;
+
+fn style_of(mark: &Mark) -> CellStyle {
+    match mark.primary {
+        true => CellStyle::Primary,
+        false => CellStyle::Secondary,
+    }
+}
+
+fn put(row: &mut Row, col: usize, char: char, style: CellStyle) {
+    if row.len() <= col {
+        row.resize(col + 1, (' ', CellStyle::Plain));
+    }
+
+    row[col] = (char, style);
+}
+
+fn put_str(row: &mut Row, col: usize, str: &str, style: CellStyle) {
+    row.truncate(col);
+    row.resize(col, (' ', CellStyle::Plain));
+    row.extend(str.chars().map(|char| (char, style)));
+}
+
+/// Lays out the underlines of a line in a first row, followed by the messages which do not fit
+/// after them, each hanging from its underline like in rustc.
+fn render_marks(marks: &[&Mark]) -> Vec<Row> {
+    if marks.is_empty() {
+        return vec![];
+    }
+
+    let mut underlines = Row::new();
+
+    // primary underlines are drawn over secondary ones
+    for primary in [false, true] {
+        for mark in marks.iter().filter(|mark| mark.primary == primary) {
+            let char = if primary { '^' } else { '-' };
+
+            for col in mark.col_low..mark.col_high {
+                put(&mut underlines, col, char, style_of(mark));
+            }
+        }
+    }
+
+    let mut pending = Vec::from_iter(marks.iter().filter(|mark| mark.message.is_some()));
+    pending.sort_by_key(|mark| (mark.col_low, mark.col_high));
+
+    // the rightmost message goes after the underlines if nothing else is underlined past it
+    if let Some(last) = pending.last() {
+        if marks.iter().all(|mark| mark.col_high <= last.col_high) {
+            let message = format!(" {}", last.message.unwrap_or_default());
+            put_str(&mut underlines, last.col_high, &message, style_of(last));
+            pending.pop();
+        }
+    }
+
+    let mut rows = vec![underlines];
+
+    while let Some(mark) = pending.pop() {
+        let mut connectors = Row::new();
+
+        for hanging in pending.iter().chain([&mark]) {
+            put(&mut connectors, hanging.col_low, '|', style_of(hanging));
+        }
+
+        let mut message = Row::new();
+
+        for hanging in &pending {
+            put(&mut message, hanging.col_low, '|', style_of(hanging));
+        }
+
+        put_str(
+            &mut message,
+            mark.col_low,
+            mark.message.unwrap_or_default(),
+            style_of(mark),
+        );
+        rows.push(connectors);
+        rows.push(message);
+    }
+
+    rows
+}
+
+fn stringify_row(level: ItemLevel, row: &Row, markup: Markup) -> String {
+    let mut stringified = String::new();
+    let mut cells = row.as_slice();
+
+    while let Some(&(_, style)) = cells.first() {
+        let len = cells
+            .iter()
+            .position(|(_, cell_style)| *cell_style != style)
+            .unwrap_or(cells.len());
+        let text = String::from_iter(cells[..len].iter().map(|(char, _)| char));
+        let style = match style {
+            CellStyle::Plain => Style::Plain,
+            CellStyle::Primary => Style::Level(level),
+            CellStyle::Secondary => Style::Secondary,
+        };
+        stringified.push_str(&markup.apply(style, &text));
+        cells = &cells[len..];
+    }
+
+    stringified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::SourceMap;
+
+    fn file(content: &str) -> std::sync::Arc<SourceFile> {
+        SourceMap::new().add_file(content, "foo.spk", Some("foo.spk".into()))
+    }
+
+    #[test]
+    fn test_snippet_labels_on_one_line() {
+        let file = file("in a: f; in a: f; in b: f;\n");
+        let labels = [
+            Label::primary(Span::new(12, 13), "defined again"),
+            Label::secondary(Span::new(3, 4), "first defined here"),
+            Label::secondary(Span::new(18, 26), "other input"),
+        ];
+
+        let stringified = stringify_snippet(ItemLevel::Error, &file, &labels, false);
+        println!("{}", stringified);
+        assert_eq!(
+            stringified,
+            "at foo.spk:1:13
+ 1 | in a: f; in a: f; in b: f;
+        -        ^     -------- other input
+        |        |
+        |        defined again
+        |
+        first defined here
+ 2 | 
+"
+        );
+    }
+
+    #[test]
+    fn test_snippet_distant_lines() {
+        let file = file("a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\n");
+        let labels = [
+            Label::secondary(Span::new(2, 3), "here"),
+            Label::primary(Span::new(18, 19), "there"),
+        ];
+
+        let stringified = stringify_snippet(ItemLevel::Warning, &file, &labels, false);
+        println!("{}", stringified);
+        assert_eq!(
+            stringified,
+            "at foo.spk:10:1
+  1 | a
+  2 | b
+      - here
+  3 | c
+ ...
+  9 | i
+ 10 | j
+      ^ there
+ 11 | k
+"
+        );
+    }
+
+    #[test]
+    fn test_snippet_multi_line_label() {
+        let file = file("pass {\n  a;\n  b;\n}\n");
+        let labels = [Label::primary(Span::new(0, 18), "unclosed")];
+
+        let stringified = stringify_snippet(ItemLevel::Error, &file, &labels, false);
+        assert_eq!(
+            stringified,
+            "at foo.spk:1:1
+ 1 | pass {
+     ^^^^^^
+ ...
+ 4 | }
+     ^ unclosed
+ 5 | 
+"
+        );
+    }
+
+    #[test]
+    fn test_snippet_tabs_and_wide_chars() {
+        let file = file("\tin a: f;\nin 名前: f; in x: f;\n");
+        let labels = [
+            Label::primary(Span::new(4, 5), "tab"),
+            Label::primary(Span::new(13, 19), "wide"),
+            Label::secondary(Span::new(27, 28), "after wide"),
+        ];
+
+        let stringified = stringify_snippet(ItemLevel::Error, &file, &labels, false);
+        println!("{}", stringified);
+        assert_eq!(
+            stringified,
+            "at foo.spk:1:5
+ 1 |     in a: f;
+            ^ tab
+ 2 | in 名前: f; in x: f;
+        ^^^^        - after wide
+        |
+        wide
+ 3 | 
+"
+        );
+    }
+}
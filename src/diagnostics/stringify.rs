@@ -1,200 +1,252 @@
-use super::{Item, ItemLevel, ItemOrigin};
-use colored::{ColoredString, Colorize};
+use super::{
+    codes::code_name, snippet::render_snippet, Item, ItemLevel, ItemOrigin, Label, SubItem,
+    Suggestion,
+};
+use crate::span::{ColUnit, SourceFile, Span};
+use colored::Colorize;
 
-pub fn stringify_item(item: &Item, apply_styles: bool) -> String {
-    let mut lines = Vec::with_capacity(2 + 2 * item.sub_items.len() + 1);
-    lines.push(apply_level_header(item.level, &item.message, apply_styles));
+/// How the renderers mark up the styled parts of their output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Markup {
+    Plain,
+    /// ANSI escape codes, for terminals.
+    Ansi,
+    /// `<span>` elements with a class per style; the text is escaped.
+    Html,
+}
 
-    if let Some(origin) = &item.origin {
-        lines.push(stringify_origin(item.level, &item.message, origin, apply_styles).into());
-    }
+/// The style of a part of a rendered item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) enum Style {
+    Plain,
+    Header(ItemLevel),
+    Location,
+    /// A primary label, in the color of the level.
+    Level(ItemLevel),
+    /// A secondary label.
+    Secondary,
+    Removed,
+    Added,
+}
 
-    for item in &item.sub_items {
-        lines.push(apply_level_header(item.level, &item.message, apply_styles));
+impl Markup {
+    pub(super) fn new(apply_styles: bool) -> Self {
+        match apply_styles {
+            true => Self::Ansi,
+            false => Self::Plain,
+        }
+    }
 
-        if let Some(origin) = &item.origin {
-            lines.push(stringify_origin(item.level, &item.message, origin, apply_styles).into());
+    pub(super) fn apply(self, style: Style, str: &str) -> String {
+        match self {
+            Self::Plain => str.into(),
+            Self::Ansi => match style {
+                Style::Plain => str.into(),
+                Style::Header(level) => level_color(level, str).bold().to_string(),
+                Style::Location => str.bold().to_string(),
+                Style::Level(level) => level_color(level, str).to_string(),
+                Style::Secondary => str.bright_blue().to_string(),
+                Style::Removed => str.red().to_string(),
+                Style::Added => str.green().to_string(),
+            },
+            Self::Html => {
+                let class = match style {
+                    Style::Plain => return escape_html(str),
+                    Style::Header(level) => format!("spk-header spk-{}", level.name()),
+                    Style::Location => "spk-location".into(),
+                    Style::Level(level) => format!("spk-{}", level.name()),
+                    Style::Secondary => "spk-secondary".into(),
+                    Style::Removed => "spk-removed".into(),
+                    Style::Added => "spk-added".into(),
+                };
+                format!("<span class=\"{}\">{}</span>", class, escape_html(str))
+            }
         }
     }
+}
 
-    lines.push("".into());
-    Vec::from_iter(lines.into_iter().map(|line| line.to_string())).join("\n")
+pub fn stringify_item(item: &Item, apply_styles: bool) -> String {
+    render_item(item, Markup::new(apply_styles))
 }
 
-fn stringify_origin(
-    level: ItemLevel,
-    message: &str,
-    origin: &ItemOrigin,
-    apply_styles: bool,
-) -> String {
-    let mut lines = Vec::with_capacity(9);
-
-    let line_col_low = origin.file.find_line_col(origin.span.low());
-    let path = match origin.file.path() {
-        Some(path) => path.display().to_string(),
-        None => "?".to_owned(),
-    };
-    let mut location = format!(
-        "at {}:{}:{}",
-        path,
-        line_col_low.line + 1,
-        line_col_low.col + 1
+/// Renders the item like [`stringify_item`] as a `<pre>` element, for web pages.
+///
+/// The styled parts are `<span>` elements with the classes `spk-header`, `spk-location`,
+/// `spk-error`, `spk-warning`, `spk-hint` (primary labels and headers, by level),
+/// `spk-secondary` (secondary labels), `spk-removed` and `spk-added` (suggestion diffs).
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::diagnostics::{stringify_item_html, Item, ItemLevel};
+/// let item = Item {
+///     code: 0,
+///     level: ItemLevel::Error,
+///     message: "expected `<type>`".into(),
+///     origin: None,
+///     sub_items: vec![],
+///     suggestions: vec![],
+/// };
+/// assert_eq!(
+///     stringify_item_html(&item),
+///     "<pre class=\"spk-diagnostic\"><span class=\"spk-header spk-error\">error: expected `&lt;type&gt;`</span>\n</pre>",
+/// );
+/// ```
+pub fn stringify_item_html(item: &Item) -> String {
+    format!(
+        "<pre class=\"spk-diagnostic\">{}</pre>",
+        render_item(item, Markup::Html)
     )
-    .normal();
-
-    if apply_styles {
-        location = location.bold();
-    }
+}
 
-    lines.push(location);
+fn render_item(item: &Item, markup: Markup) -> String {
+    let mut lines = Vec::with_capacity(2 + 2 * item.sub_items.len() + 1);
+    lines.push(render_header(item.level, item.code, &item.message, markup));
 
-    let line_col_high = origin.file.find_line_col(origin.span.high());
-    let max_line = (origin.file.line_lows().len() - 1) as u32;
-    let line_low = if line_col_low.line == 0 {
-        0
-    } else {
-        line_col_low.line - 1
-    };
-    let line_high = if line_col_high.line == max_line {
-        max_line
-    } else {
-        line_col_high.line + 1
-    };
-    let max_line_number_width = ((line_high + 1) as f64).log(10f64).ceil() as usize;
-
-    if line_low != line_col_low.line {
-        let line = origin.file.slice_line(line_low);
-        let line = line.trim_end_matches(&['\n', '\r']);
-        lines.push(
-            format!(
-                "{:>width$} | {}",
-                line_low + 1,
-                line,
-                width = max_line_number_width + 1
-            )
-            .into(),
+    if let Some(origin) = &item.origin {
+        let labels = Vec::from_iter(
+            std::iter::once(Label::primary(origin.span, &item.message)).chain(
+                item.sub_items
+                    .iter()
+                    .filter(|sub_item| is_label_of(sub_item, origin))
+                    .map(|sub_item| {
+                        let span = sub_item.origin.as_ref().unwrap().span;
+                        Label::secondary(span, &sub_item.message)
+                    }),
+            ),
         );
+        lines.push(render_snippet(item.level, &origin.file, &labels, markup));
+        push_expansions(&mut lines, origin, markup);
     }
 
-    if line_col_low.line != line_col_high.line {
-        let line = origin.file.slice_line(line_col_low.line);
-        let line = line.trim_end_matches(&['\n', '\r']);
-        lines.push(
-            format!(
-                "{:>width$} | {}",
-                line_col_low.line + 1,
-                line,
-                width = max_line_number_width + 1
-            )
-            .into(),
-        );
+    for sub_item in &item.sub_items {
+        if matches!(&item.origin, Some(origin) if is_label_of(sub_item, origin)) {
+            continue;
+        }
 
-        let indent = " ".repeat(max_line_number_width + 4 + line_col_low.col as usize);
-        let caret = "^".repeat(line.len() - line_col_low.col as usize);
-        lines.push(
-            format!(
-                "{}{}",
-                indent,
-                apply_level_color(level, &caret, apply_styles)
-            )
-            .into(),
-        );
+        lines.push(render_header(sub_item.level, 0, &sub_item.message, markup));
+
+        if let Some(origin) = &sub_item.origin {
+            lines.push(render_origin(
+                sub_item.level,
+                &sub_item.message,
+                origin,
+                markup,
+            ));
+            push_expansions(&mut lines, origin, markup);
+        }
     }
 
-    if 2 <= line_col_high.line - line_col_low.line {
-        lines.push(" ...".into());
+    for suggestion in &item.suggestions {
+        lines.push(render_header(
+            ItemLevel::Hint,
+            0,
+            &suggestion.message,
+            markup,
+        ));
+
+        match &item.origin {
+            Some(origin) if origin.file.span().contains_span(suggestion.span) => {
+                lines.push(render_suggestion(&origin.file, suggestion, markup));
+            }
+            _ => {}
+        }
     }
 
-    if line_col_low.line == line_col_high.line {
-        let line = origin.file.slice_line(line_col_high.line);
-        let line = line.trim_end_matches(&['\n', '\r']);
-        lines.push(
-            format!(
-                "{:>width$} | {}",
-                line_col_high.line + 1,
-                line,
-                width = max_line_number_width + 1
-            )
-            .into(),
-        );
+    lines.push("".into());
+    lines.join("\n")
+}
 
-        let indent = " ".repeat(max_line_number_width + 4 + line_col_low.col as usize);
-        let caret = "^".repeat(line_col_high.col as usize - line_col_low.col as usize);
-        let message = format!(" {}", message);
-        lines.push(
-            format!(
-                "{}{}{}",
-                indent,
-                apply_level_color(level, &caret, apply_styles),
-                apply_level_color(level, &message, apply_styles)
-            )
-            .into(),
-        );
-    } else {
-        let line = origin.file.slice_line(line_col_high.line);
-        let line = line.trim_end_matches(&['\n', '\r']);
-        lines.push(
-            format!(
-                "{:>width$} | {}",
-                line_col_high.line + 1,
-                line,
-                width = max_line_number_width + 1
-            )
-            .into(),
-        );
+/// Returns `true` if the sub-item is drawn as a secondary label in the snippet of its parent,
+/// i.e. it points into the same file and not into a `comptime` expansion.
+fn is_label_of(sub_item: &SubItem, origin: &ItemOrigin) -> bool {
+    match &sub_item.origin {
+        Some(sub_origin) => {
+            sub_origin.file.span() == origin.file.span() && sub_origin.span.ctxt().is_root()
+        }
+        None => false,
+    }
+}
 
-        let indent = " ".repeat(max_line_number_width + 4);
-        let caret = "^".repeat(usize::max(line_col_high.col as usize, 1));
-        let message = format!(" {}", message);
-        lines.push(
-            format!(
-                "{}{}{}",
-                indent,
-                apply_level_color(level, &caret, apply_styles),
-                apply_level_color(level, &message, apply_styles)
-            )
-            .into(),
-        );
+/// Appends a note for every `comptime` expansion the origin has been produced by, innermost
+/// first, like a macro backtrace.
+fn push_expansions(lines: &mut Vec<String>, origin: &ItemOrigin, markup: Markup) {
+    for expansion in origin.span.ctxt().backtrace() {
+        let message = expansion.kind.to_string();
+        lines.push(render_header(ItemLevel::Hint, 0, &message, markup));
+
+        if origin.file.span().contains_span(expansion.call_site) {
+            let origin = ItemOrigin {
+                file: origin.file.clone(),
+                span: expansion.call_site,
+            };
+            lines.push(render_origin(ItemLevel::Hint, &message, &origin, markup));
+        }
     }
+}
+
+/// Renders the lines changed by the suggestion as a diff, e.g. `1 - in a: f` and `1 + in a: f;`.
+fn render_suggestion(file: &SourceFile, suggestion: &Suggestion, markup: Markup) -> String {
+    let line_low = file.to_line_col(suggestion.span.low(), ColUnit::Utf8).line;
+    let line_high = file.to_line_col(suggestion.span.high(), ColUnit::Utf8).line;
+    let lines_span = Span::new(
+        file.line_span(line_low).low(),
+        file.line_span(line_high).high(),
+    );
+    let before = file.slice(lines_span);
+    let after = format!(
+        "{}{}{}",
+        file.slice(Span::new(lines_span.low(), suggestion.span.low())),
+        suggestion.replacement,
+        file.slice(Span::new(suggestion.span.high(), lines_span.high()))
+    );
+    let max_line_number_width = ((line_high + 2) as f64).log(10f64).ceil() as usize;
+    let mut lines = Vec::new();
 
-    if line_high != line_col_high.line {
-        let line = origin.file.slice_line(line_high);
-        let line = line.trim_end_matches(&['\n', '\r']);
-        lines.push(
-            format!(
-                "{:>width$} | {}",
-                line_high + 1,
+    for (sign, text) in [('-', before), ('+', after.as_str())] {
+        let style = match sign {
+            '-' => Style::Removed,
+            _ => Style::Added,
+        };
+
+        for (index, line) in text.lines().enumerate() {
+            let line = format!(
+                "{:>width$} {} {}",
+                line_low as usize + index + 1,
+                sign,
                 line,
                 width = max_line_number_width + 1
-            )
-            .into(),
-        );
+            );
+            lines.push(markup.apply(style, &line));
+        }
     }
 
     lines.push("".into());
-    Vec::from_iter(lines.into_iter().map(|line| line.to_string())).join("\n")
+    lines.join("\n")
+}
+
+fn render_origin(level: ItemLevel, message: &str, origin: &ItemOrigin, markup: Markup) -> String {
+    let labels = [Label::primary(origin.span, message)];
+    render_snippet(level, &origin.file, &labels, markup)
 }
 
-fn apply_level_header(level: ItemLevel, str: &str, apply_styles: bool) -> ColoredString {
+/// Formats the header of an item, e.g. `error[SPK2010]: unknown type`; helper items with the
+/// code 0 have no code in their header.
+fn render_header(level: ItemLevel, code: u32, str: &str, markup: Markup) -> String {
     let tag = match level {
-        ItemLevel::Hint => " hint:",
-        ItemLevel::Warning => " warn:",
-        ItemLevel::Error => "error:",
+        ItemLevel::Hint => " hint",
+        ItemLevel::Warning => " warn",
+        ItemLevel::Error => "error",
+    };
+    let header = match code {
+        0 => format!("{}: {}", tag, str),
+        code => format!("{}[{}]: {}", tag, code_name(code), str),
     };
-    let header = format!("{} {}", tag, str);
-
-    if !apply_styles {
-        return header.into();
-    }
 
-    apply_level_color(level, &header, true).bold()
+    markup.apply(Style::Header(level), &header)
 }
 
-fn apply_level_color(level: ItemLevel, str: &str, apply_styles: bool) -> ColoredString {
-    if !apply_styles {
-        return str.into();
-    }
-
+fn level_color(level: ItemLevel, str: &str) -> colored::ColoredString {
     match level {
         ItemLevel::Hint => str.bright_green(),
         ItemLevel::Warning => str.yellow(),
@@ -202,10 +254,31 @@ fn apply_level_color(level: ItemLevel, str: &str, apply_styles: bool) -> Colored
     }
 }
 
+fn escape_html(str: &str) -> String {
+    let mut escaped = String::with_capacity(str.len());
+
+    for char in str.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::span::{SourceMap, Span};
+    use crate::{
+        diagnostics::{codes::TYPE_ERR_UNKNOWN_TYPE, Applicability},
+        span::{Expansion, ExpansionKind, SourceMap, Span, SyntaxContext},
+        symbol::Symbol,
+    };
 
     #[test]
     fn test_stringify_item() {
@@ -220,6 +293,7 @@ mod tests {
                 span: Span::new(0, 1),
             }),
             sub_items: vec![],
+            suggestions: vec![],
         };
 
         let stringified = stringify_item(&item, false);
@@ -232,6 +306,188 @@ at foo.txt:1:1
      ^ test
  2 | bar
 
+"
+        );
+    }
+
+    #[test]
+    fn test_stringify_item_code() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("in a: float3;\n", "foo.spk", Some("foo.spk".into()));
+        let item = Item {
+            code: TYPE_ERR_UNKNOWN_TYPE,
+            level: ItemLevel::Error,
+            message: "unknown type `float3`".into(),
+            origin: Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(6, 12),
+            }),
+            sub_items: vec![],
+            suggestions: vec![],
+        };
+
+        let stringified = stringify_item(&item, false);
+        assert_eq!(
+            Vec::from_iter(stringified.lines().take(2)),
+            ["error[SPK2010]: unknown type `float3`", "at foo.spk:1:7"]
+        );
+    }
+
+    #[test]
+    fn test_stringify_item_sub_item_labels() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("in a: f;\nin a: f;\n", "foo.spk", Some("foo.spk".into()));
+        let item = Item {
+            code: 0,
+            level: ItemLevel::Error,
+            message: "input `a` is defined multiple times".into(),
+            origin: Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(12, 13),
+            }),
+            sub_items: vec![
+                SubItem {
+                    level: ItemLevel::Hint,
+                    message: "input `a` is first defined here".into(),
+                    origin: Some(ItemOrigin {
+                        file: file.clone(),
+                        span: Span::new(3, 4),
+                    }),
+                },
+                SubItem {
+                    level: ItemLevel::Hint,
+                    message: "rename one of them".into(),
+                    origin: None,
+                },
+            ],
+            suggestions: vec![],
+        };
+
+        let stringified = stringify_item(&item, false);
+        println!("{}", stringified);
+        assert_eq!(
+            stringified,
+            "error: input `a` is defined multiple times
+at foo.spk:2:4
+ 1 | in a: f;
+        - input `a` is first defined here
+ 2 | in a: f;
+        ^ input `a` is defined multiple times
+ 3 | 
+
+ hint: rename one of them
+"
+        );
+    }
+
+    #[test]
+    fn test_stringify_item_html() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("in a: f<2>;\n", "foo.spk", Some("foo.spk".into()));
+        let item = Item {
+            code: TYPE_ERR_UNKNOWN_TYPE,
+            level: ItemLevel::Error,
+            message: "unknown type `f<2>`".into(),
+            origin: Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(6, 10),
+            }),
+            sub_items: vec![],
+            suggestions: vec![],
+        };
+
+        let stringified = stringify_item_html(&item);
+        println!("{}", stringified);
+        assert_eq!(
+            stringified,
+            r#"<pre class="spk-diagnostic"><span class="spk-header spk-error">error[SPK2010]: unknown type `f&lt;2&gt;`</span>
+<span class="spk-location">at foo.spk:1:7</span>
+ 1 | in a: f&lt;2&gt;;
+           <span class="spk-error">^^^^ unknown type `f&lt;2&gt;`</span>
+ 2 | 
+
+</pre>"#
+        );
+    }
+
+    #[test]
+    fn test_stringify_item_suggestions() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("comptime if (\"a\" {}\n", "foo.spk", Some("foo.spk".into()));
+        let item = Item {
+            code: 0,
+            level: ItemLevel::Error,
+            message: "`)` is expected".into(),
+            origin: Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(17, 18),
+            }),
+            sub_items: vec![],
+            suggestions: vec![Suggestion::new(
+                "insert `)`",
+                Span::empty(16),
+                ")",
+                Applicability::MachineApplicable,
+            )],
+        };
+
+        let stringified = stringify_item(&item, false);
+        println!("{}", stringified);
+        assert_eq!(
+            Vec::from_iter(stringified.lines().skip(6)),
+            [
+                " hint: insert `)`",
+                " 1 - comptime if (\"a\" {}",
+                " 1 + comptime if (\"a\") {}",
+                "",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stringify_item_expansions() {
+        let mut map = SourceMap::new();
+        let file = map.add_file(
+            "comptime loop n times 4 { in uv: f2; }\n",
+            "foo.spk",
+            Some("foo.spk".into()),
+        );
+        let ctxt = SyntaxContext::new(Expansion {
+            parent: SyntaxContext::ROOT,
+            kind: ExpansionKind::Loop {
+                var: Symbol::from_str("n"),
+                value: 3,
+            },
+            call_site: Span::new(0, 38),
+        });
+        let item = Item {
+            code: 0,
+            level: ItemLevel::Error,
+            message: "test".into(),
+            origin: Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(29, 31).with_ctxt(ctxt),
+            }),
+            sub_items: vec![],
+            suggestions: vec![],
+        };
+
+        let stringified = stringify_item(&item, false);
+        println!("{}", stringified);
+        assert_eq!(
+            stringified,
+            "error: test
+at foo.spk:1:30
+ 1 | comptime loop n times 4 { in uv: f2; }
+                                  ^^ test
+ 2 | 
+
+ hint: in expansion of `comptime loop` (n = 3)
+at foo.spk:1:1
+ 1 | comptime loop n times 4 { in uv: f2; }
+     ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ in expansion of `comptime loop` (n = 3)
+ 2 | 
+
 "
         );
     }
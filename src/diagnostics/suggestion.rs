@@ -0,0 +1,215 @@
+use super::Item;
+use crate::span::{SourceFile, Span};
+use serde::{Deserialize, Serialize};
+
+/// A change of the source code which fixes the problem reported by an item.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Suggestion {
+    pub message: String,
+    /// The replaced span; empty to insert the replacement.
+    pub span: Span,
+    pub replacement: String,
+    pub applicability: Applicability,
+}
+
+/// How confident a suggestion is; only machine-applicable suggestions are applied automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Applicability {
+    /// The suggestion is certainly what the user meant.
+    MachineApplicable,
+    /// The suggestion may not be what the user meant, e.g. the closest of several names.
+    MaybeIncorrect,
+    /// The replacement has placeholders which the user must fill in.
+    HasPlaceholders,
+}
+
+impl Suggestion {
+    pub fn new(
+        message: impl Into<String>,
+        span: Span,
+        replacement: impl Into<String>,
+        applicability: Applicability,
+    ) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            replacement: replacement.into(),
+            applicability,
+        }
+    }
+}
+
+/// Applies the machine-applicable suggestions of the items which are in the file, and returns
+/// the fixed file along with the number of applied suggestions.
+///
+/// When suggestions overlap, only the first one is applied; running the fix again after
+/// re-checking the file applies the others.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::diagnostics::{apply_suggestions, Applicability, Item, ItemLevel, Suggestion};
+/// # use shader_pack::span::{SourceFile, Span};
+/// let file = SourceFile::new(0, "comptime if (\"a\" {}", "test", None);
+/// let item = Item {
+///     code: 1010,
+///     level: ItemLevel::Error,
+///     message: "`)` is expected".into(),
+///     origin: None,
+///     sub_items: vec![],
+///     suggestions: vec![Suggestion::new(
+///         "insert `)`",
+///         Span::empty(16),
+///         ")",
+///         Applicability::MachineApplicable,
+///     )],
+/// };
+/// let (fixed, count) = apply_suggestions(&file, &[item]);
+/// assert_eq!(fixed.content(), "comptime if (\"a\") {}");
+/// assert_eq!(count, 1);
+/// ```
+pub fn apply_suggestions(file: &SourceFile, items: &[Item]) -> (SourceFile, usize) {
+    let mut suggestions = Vec::from_iter(items.iter().flat_map(|item| &item.suggestions).filter(
+        |suggestion| {
+            suggestion.applicability == Applicability::MachineApplicable
+                && file.span().contains_span(suggestion.span)
+        },
+    ));
+    suggestions.sort_by_key(|suggestion| (suggestion.span.low(), suggestion.span.high()));
+
+    let file_low = file.span().low();
+    let mut content = String::with_capacity(file.content().len());
+    let mut copied = file_low;
+    let mut count = 0;
+
+    for suggestion in suggestions {
+        // overlapping suggestions would corrupt each other, but two insertions at the same
+        // position are applied in order
+        if suggestion.span.low() < copied {
+            continue;
+        }
+
+        content.push_str(file.slice(Span::new(copied, suggestion.span.low())));
+        content.push_str(&suggestion.replacement);
+        copied = suggestion.span.high();
+        count += 1;
+    }
+
+    content.push_str(file.slice(Span::new(copied, file.span().high())));
+
+    let fixed = SourceFile::new(
+        file_low,
+        content,
+        file.name(),
+        file.path().map(|path| path.to_owned()),
+    );
+    (fixed, count)
+}
+
+/// Returns the candidate closest to `name`, if it is close enough to be a likely misspelling.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::diagnostics::find_similar_name;
+/// assert_eq!(find_similar_name("vertx", ["vertex", "fragment"]), Some("vertex"));
+/// assert_eq!(find_similar_name("color", ["vertex", "fragment"]), None);
+/// ```
+pub fn find_similar_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = usize::max(1, name.chars().count() / 3);
+
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance counted in characters.
+fn edit_distance(lhs: &str, rhs: &str) -> usize {
+    let rhs = Vec::from_iter(rhs.chars());
+    let mut row = Vec::from_iter(0..=rhs.len());
+
+    for (i, lhs_char) in lhs.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, rhs_char) in rhs.iter().enumerate() {
+            let substitution = diagonal + usize::from(lhs_char != *rhs_char);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+
+    row[rhs.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{diagnostics::ItemLevel, span::SourceMap};
+
+    fn item(suggestions: Vec<Suggestion>) -> Item {
+        Item {
+            code: 0,
+            level: ItemLevel::Error,
+            message: "test".into(),
+            origin: None,
+            sub_items: vec![],
+            suggestions,
+        }
+    }
+
+    #[test]
+    fn test_apply_suggestions() {
+        let mut map = SourceMap::new();
+        let other = map.add_file("in a: f\n", "other.spk", None);
+        let file = map.add_file("in a: f\nin b: f\n", "main.spk", None);
+        let low = file.span().low();
+        let fix = |low_offset: u32, high_offset: u32, text: &str, applicability| {
+            Suggestion::new(
+                "fix",
+                Span::new(low + low_offset, low + high_offset),
+                text,
+                applicability,
+            )
+        };
+        let items = [
+            item(vec![fix(15, 15, ";", Applicability::MachineApplicable)]),
+            item(vec![
+                fix(6, 8, "f3;\n", Applicability::MachineApplicable),
+                // overlaps the previous one
+                fix(7, 7, ";", Applicability::MachineApplicable),
+                fix(3, 4, "x", Applicability::MaybeIncorrect),
+            ]),
+            item(vec![Suggestion::new(
+                "fix",
+                Span::empty(other.span().low() + 7),
+                ";",
+                Applicability::MachineApplicable,
+            )]),
+        ];
+
+        let (fixed, count) = apply_suggestions(&file, &items);
+        assert_eq!(fixed.content(), "in a: f3;\nin b: f;\n");
+        assert_eq!(fixed.span().low(), low);
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_find_similar_name() {
+        assert_eq!(edit_distance("loop", "lop"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("é", "e"), 1);
+        assert_eq!(find_similar_name("lop", ["if", "loop"]), Some("loop"));
+        assert_eq!(find_similar_name("iff", ["if", "loop"]), Some("if"));
+        assert_eq!(find_similar_name("if", ["if", "loop"]), None);
+        assert_eq!(find_similar_name("while", ["if", "loop"]), None);
+    }
+}
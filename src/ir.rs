@@ -0,0 +1,362 @@
+mod builtin;
+mod dump;
+mod lower;
+mod opt;
+mod ty;
+mod visit;
+
+pub use builtin::*;
+pub use lower::*;
+pub use opt::*;
+pub use ty::*;
+
+use crate::{span::Span, symbol::Symbol};
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+
+/// Represents a lowered shader pack.
+///
+/// The module is produced from an expanded AST (no `comptime` items left) and is the single
+/// representation consumed by every code generator. Every expression is typed, every name is
+/// resolved, and stage entry points are explicit functions.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct IrModule {
+    pub structs: Vec<IrStruct>,
+    pub resources: Vec<IrResource>,
+    pub functions: Vec<IrFunction>,
+    pub passes: Vec<IrPass>,
+}
+
+impl IrModule {
+    pub fn struct_def(&self, id: IrStructId) -> &IrStruct {
+        &self.structs[id.0 as usize]
+    }
+
+    pub fn resource(&self, id: IrResourceId) -> &IrResource {
+        &self.resources[id.0 as usize]
+    }
+
+    pub fn function(&self, id: IrFunctionId) -> &IrFunction {
+        &self.functions[id.0 as usize]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IrStruct {
+    pub fields: Vec<IrStructField>,
+}
+
+impl IrStruct {
+    pub fn field_index(&self, name: Symbol) -> Option<u32> {
+        self.fields
+            .iter()
+            .position(|field| field.name == name)
+            .map(|index| index as u32)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IrStructField {
+    pub name: Symbol,
+    pub ty: IrType,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IrResourceId(pub u32);
+
+/// Represents a declared `in` item.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IrResource {
+    pub id: IrResourceId,
+    pub name: Symbol,
+    pub ty: IrType,
+    pub kind: IrResourceKind,
+    /// The pass that declared the resource, `None` for top-level inputs.
+    pub pass: Option<IrPassId>,
+    /// The doc comment of the input, if any.
+    pub doc: Option<Symbol>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IrResourceKind {
+    /// A member of the unified uniform buffer.
+    Uniform,
+    /// A texture binding.
+    Texture,
+    /// A per-vertex attribute of the mesh, e.g. `@vertex = "position"`.
+    VertexAttribute { attribute: Symbol },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IrFunctionId(pub u32);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IrFunction {
+    pub id: IrFunctionId,
+    pub name: Symbol,
+    pub kind: IrFunctionKind,
+    /// Parameters of the function, in order. Each one refers to an entry of `locals`.
+    pub params: Vec<IrLocalId>,
+    pub locals: Vec<IrLocal>,
+    pub return_type: IrType,
+    pub body: IrBlock,
+    pub span: Span,
+}
+
+impl IrFunction {
+    pub fn local(&self, id: IrLocalId) -> &IrLocal {
+        &self.locals[id.0 as usize]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IrFunctionKind {
+    /// A function defined with `fn`.
+    User,
+    /// A stage program of a pass.
+    EntryPoint { pass: IrPassId, stage: IrStage },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IrLocalId(pub u32);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IrLocal {
+    pub name: Symbol,
+    pub ty: IrType,
+    pub span: Span,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct IrBlock {
+    pub statements: Vec<IrStatement>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IrStatement {
+    pub span: Span,
+    pub kind: IrStatementKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IrStatementKind {
+    Let {
+        local: IrLocalId,
+        init: Option<IrExpr>,
+    },
+    /// Compound assignments are lowered into plain assignments, e.g. `a += b` -> `a = a + b`.
+    Assign {
+        /// A place expression; one of `Local`, `Swizzle`, `Field` or `Index` rooted at a local.
+        target: IrExpr,
+        value: IrExpr,
+    },
+    Expr(IrExpr),
+    Return(Option<IrExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IrExpr {
+    pub ty: IrType,
+    pub span: Span,
+    pub kind: IrExprKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum IrExprKind {
+    Constant(IrConstant),
+    Local(IrLocalId),
+    Resource(IrResourceId),
+    Unary {
+        op: IrUnaryOp,
+        rhs: Box<IrExpr>,
+    },
+    Binary {
+        op: IrBinaryOp,
+        lhs: Box<IrExpr>,
+        rhs: Box<IrExpr>,
+    },
+    Call {
+        function: IrFunctionId,
+        args: Vec<IrExpr>,
+    },
+    Builtin {
+        builtin: IrBuiltin,
+        args: Vec<IrExpr>,
+    },
+    /// Constructs a value of the expression type; vectors, matrices and structs.
+    Construct {
+        args: Vec<IrExpr>,
+    },
+    Swizzle {
+        base: Box<IrExpr>,
+        /// Component indices, e.g. `.zyx` -> `[2, 1, 0]`.
+        components: Vec<u8>,
+    },
+    Field {
+        base: Box<IrExpr>,
+        index: u32,
+    },
+    Index {
+        base: Box<IrExpr>,
+        index: Box<IrExpr>,
+    },
+}
+
+impl IrExpr {
+    /// Returns `true` if the expression can be assigned to.
+    pub fn is_place(&self) -> bool {
+        match &self.kind {
+            IrExprKind::Local(_) => true,
+            IrExprKind::Swizzle { base, .. }
+            | IrExprKind::Field { base, .. }
+            | IrExprKind::Index { base, .. } => base.is_place(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum IrConstant {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+}
+
+impl IrConstant {
+    pub fn ty(self) -> IrType {
+        match self {
+            Self::Bool(_) => IrType::BOOL,
+            Self::Int(_) => IrType::INT,
+            Self::UInt(_) => IrType::UINT,
+            Self::Float(_) => IrType::FLOAT,
+        }
+    }
+}
+
+// NOTE: floats are compared bitwise, so that constants can be used as keys (e.g. `NaN == NaN`)
+impl PartialEq for IrConstant {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Bool(lhs), Self::Bool(rhs)) => lhs == rhs,
+            (Self::Int(lhs), Self::Int(rhs)) => lhs == rhs,
+            (Self::UInt(lhs), Self::UInt(rhs)) => lhs == rhs,
+            (Self::Float(lhs), Self::Float(rhs)) => lhs.to_bits() == rhs.to_bits(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for IrConstant {}
+
+impl Hash for IrConstant {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+
+        match self {
+            Self::Bool(value) => value.hash(state),
+            Self::Int(value) => value.hash(state),
+            Self::UInt(value) => value.hash(state),
+            Self::Float(value) => value.to_bits().hash(state),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IrUnaryOp {
+    Neg,
+    LogNot,
+    BitNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IrBinaryOp {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+    Shl,
+    Shr,
+    BitOr,
+    BitAnd,
+    BitXor,
+    LogOr,
+    LogAnd,
+}
+
+impl IrBinaryOp {
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Self::Eq | Self::Ne | Self::Lt | Self::Gt | Self::Le | Self::Ge
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IrPassId(pub u32);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IrPass {
+    pub id: IrPassId,
+    pub name: Symbol,
+    /// Attributes attached to the pass, e.g. `@mode = "Base"`.
+    pub attributes: Vec<IrAttribute>,
+    pub entry_points: Vec<IrEntryPoint>,
+    pub span: Span,
+}
+
+impl IrPass {
+    pub fn attribute(&self, name: &str) -> Option<Symbol> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name.to_str() == name)
+            .map(|attribute| attribute.value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IrAttribute {
+    pub name: Symbol,
+    pub value: Symbol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IrEntryPoint {
+    pub stage: IrStage,
+    pub function: IrFunctionId,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IrStage {
+    Vertex,
+    Fragment,
+}
+
+impl IrStage {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "vertex" => Some(Self::Vertex),
+            "fragment" => Some(Self::Fragment),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Vertex => "vertex",
+            Self::Fragment => "fragment",
+        }
+    }
+}
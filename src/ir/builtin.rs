@@ -0,0 +1,252 @@
+use super::{IrScalarType, IrType};
+
+/// Built-in functions of the language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IrBuiltin {
+    /// `sample(texture, coord) -> f4`
+    Sample,
+    Abs,
+    Min,
+    Max,
+    Clamp,
+    Saturate,
+    Mix,
+    Step,
+    Floor,
+    Ceil,
+    Fract,
+    Sqrt,
+    Exp,
+    Log,
+    Sin,
+    Cos,
+    Tan,
+    Dot,
+    Cross,
+    Length,
+    Distance,
+    Normalize,
+    Reflect,
+    Transpose,
+}
+
+impl IrBuiltin {
+    /// Every built-in, in declaration order.
+    pub const ALL: [Self; 24] = [
+        Self::Sample,
+        Self::Abs,
+        Self::Min,
+        Self::Max,
+        Self::Clamp,
+        Self::Saturate,
+        Self::Mix,
+        Self::Step,
+        Self::Floor,
+        Self::Ceil,
+        Self::Fract,
+        Self::Sqrt,
+        Self::Exp,
+        Self::Log,
+        Self::Sin,
+        Self::Cos,
+        Self::Tan,
+        Self::Dot,
+        Self::Cross,
+        Self::Length,
+        Self::Distance,
+        Self::Normalize,
+        Self::Reflect,
+        Self::Transpose,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "sample" => Self::Sample,
+            "abs" => Self::Abs,
+            "min" => Self::Min,
+            "max" => Self::Max,
+            "clamp" => Self::Clamp,
+            "saturate" => Self::Saturate,
+            "mix" => Self::Mix,
+            "step" => Self::Step,
+            "floor" => Self::Floor,
+            "ceil" => Self::Ceil,
+            "fract" => Self::Fract,
+            "sqrt" => Self::Sqrt,
+            "exp" => Self::Exp,
+            "log" => Self::Log,
+            "sin" => Self::Sin,
+            "cos" => Self::Cos,
+            "tan" => Self::Tan,
+            "dot" => Self::Dot,
+            "cross" => Self::Cross,
+            "length" => Self::Length,
+            "distance" => Self::Distance,
+            "normalize" => Self::Normalize,
+            "reflect" => Self::Reflect,
+            "transpose" => Self::Transpose,
+            _ => return None,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sample => "sample",
+            Self::Abs => "abs",
+            Self::Min => "min",
+            Self::Max => "max",
+            Self::Clamp => "clamp",
+            Self::Saturate => "saturate",
+            Self::Mix => "mix",
+            Self::Step => "step",
+            Self::Floor => "floor",
+            Self::Ceil => "ceil",
+            Self::Fract => "fract",
+            Self::Sqrt => "sqrt",
+            Self::Exp => "exp",
+            Self::Log => "log",
+            Self::Sin => "sin",
+            Self::Cos => "cos",
+            Self::Tan => "tan",
+            Self::Dot => "dot",
+            Self::Cross => "cross",
+            Self::Length => "length",
+            Self::Distance => "distance",
+            Self::Normalize => "normalize",
+            Self::Reflect => "reflect",
+            Self::Transpose => "transpose",
+        }
+    }
+
+    /// Checks the argument types and returns the result type of the call.
+    /// Returns `None` if the arguments are not accepted by the built-in.
+    pub fn result_type(self, args: &[IrType]) -> Option<IrType> {
+        match (self, args) {
+            (Self::Sample, [IrType::Texture(_), coord]) if is_float_like(*coord) => {
+                Some(IrType::Vector {
+                    scalar: IrScalarType::Float,
+                    size: 4,
+                })
+            }
+            (Self::Abs, [ty]) if is_signed_like(*ty) => Some(*ty),
+            (Self::Min | Self::Max, [lhs, rhs]) if lhs == rhs && is_numeric_like(*lhs) => {
+                Some(*lhs)
+            }
+            (Self::Clamp, [value, low, high])
+                if value == low && low == high && is_numeric_like(*value) =>
+            {
+                Some(*value)
+            }
+            (Self::Mix, [lhs, rhs, t]) if lhs == rhs && is_float_like(*lhs) => {
+                (t == lhs || *t == IrType::FLOAT).then_some(*lhs)
+            }
+            (Self::Step, [edge, value]) if edge == value && is_float_like(*value) => Some(*value),
+            (
+                Self::Saturate
+                | Self::Floor
+                | Self::Ceil
+                | Self::Fract
+                | Self::Sqrt
+                | Self::Exp
+                | Self::Log
+                | Self::Sin
+                | Self::Cos
+                | Self::Tan,
+                [ty],
+            ) if is_float_like(*ty) => Some(*ty),
+            (Self::Dot, [lhs, rhs]) if lhs == rhs && is_float_vector(*lhs) => Some(IrType::FLOAT),
+            (Self::Cross, [lhs, rhs])
+                if lhs == rhs
+                    && *lhs
+                        == (IrType::Vector {
+                            scalar: IrScalarType::Float,
+                            size: 3,
+                        }) =>
+            {
+                Some(*lhs)
+            }
+            (Self::Length, [ty]) if is_float_like(*ty) => Some(IrType::FLOAT),
+            (Self::Distance, [lhs, rhs]) if lhs == rhs && is_float_like(*lhs) => {
+                Some(IrType::FLOAT)
+            }
+            (Self::Normalize, [ty]) if is_float_vector(*ty) => Some(*ty),
+            (Self::Reflect, [lhs, rhs]) if lhs == rhs && is_float_vector(*lhs) => Some(*lhs),
+            (Self::Transpose, [ty @ IrType::Matrix { .. }]) => Some(*ty),
+            _ => None,
+        }
+    }
+}
+
+fn is_float_like(ty: IrType) -> bool {
+    matches!(
+        ty,
+        IrType::Scalar(IrScalarType::Float)
+            | IrType::Vector {
+                scalar: IrScalarType::Float,
+                ..
+            }
+    )
+}
+
+fn is_float_vector(ty: IrType) -> bool {
+    matches!(
+        ty,
+        IrType::Vector {
+            scalar: IrScalarType::Float,
+            ..
+        }
+    )
+}
+
+fn is_numeric_like(ty: IrType) -> bool {
+    matches!(ty, IrType::Scalar(_) | IrType::Vector { .. }) && ty.is_numeric()
+}
+
+fn is_signed_like(ty: IrType) -> bool {
+    is_numeric_like(ty) && ty.scalar() != Some(IrScalarType::UInt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::IrTextureKind;
+
+    const F3: IrType = IrType::Vector {
+        scalar: IrScalarType::Float,
+        size: 3,
+    };
+
+    #[test]
+    fn test_ir_builtin_name_round_trip() {
+        for builtin in IrBuiltin::ALL {
+            assert_eq!(IrBuiltin::from_name(builtin.name()), Some(builtin));
+        }
+        assert_eq!(IrBuiltin::from_name("unknown"), None);
+    }
+
+    #[test]
+    fn test_ir_builtin_result_type() {
+        assert_eq!(
+            IrBuiltin::Sample.result_type(&[
+                IrType::Texture(IrTextureKind::Texture2D),
+                IrType::Vector {
+                    scalar: IrScalarType::Float,
+                    size: 2
+                }
+            ]),
+            Some(IrType::Vector {
+                scalar: IrScalarType::Float,
+                size: 4
+            })
+        );
+        assert_eq!(IrBuiltin::Dot.result_type(&[F3, F3]), Some(IrType::FLOAT));
+        assert_eq!(IrBuiltin::Cross.result_type(&[F3, F3]), Some(F3));
+        assert_eq!(
+            IrBuiltin::Mix.result_type(&[F3, F3, IrType::FLOAT]),
+            Some(F3)
+        );
+        assert_eq!(IrBuiltin::Dot.result_type(&[F3, IrType::FLOAT]), None);
+        assert_eq!(IrBuiltin::Normalize.result_type(&[IrType::FLOAT]), None);
+        assert_eq!(IrBuiltin::Abs.result_type(&[IrType::UINT]), None);
+    }
+}
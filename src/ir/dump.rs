@@ -0,0 +1,323 @@
+use super::{
+    IrBinaryOp, IrBlock, IrConstant, IrExpr, IrExprKind, IrFunction, IrFunctionKind, IrModule,
+    IrResourceKind, IrStatementKind, IrType, IrUnaryOp,
+};
+use std::fmt::{Display, Formatter, Result};
+
+/// Textual dump of the IR, used by `--emit=ir`.
+///
+/// Example:
+///
+/// ```text
+/// resource#0 color: f3 = uniform
+///
+/// fn#0 brighten(%0 value: f3) -> f3 {
+///     return (%0 value * 2.0f);
+/// }
+/// ```
+impl Display for IrModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let mut sections = 0;
+        let mut separate = |f: &mut Formatter<'_>| {
+            sections += 1;
+
+            if sections != 1 {
+                writeln!(f)?;
+            }
+
+            Ok(())
+        };
+
+        for (index, struct_def) in self.structs.iter().enumerate() {
+            separate(f)?;
+            writeln!(f, "struct#{} {{", index)?;
+
+            for field in &struct_def.fields {
+                writeln!(f, "    {}: {},", field.name.to_str(), field.ty)?;
+            }
+
+            writeln!(f, "}}")?;
+        }
+
+        if !self.resources.is_empty() {
+            separate(f)?;
+        }
+
+        for resource in &self.resources {
+            write!(
+                f,
+                "resource#{} {}: {} = ",
+                resource.id.0,
+                resource.name.to_str(),
+                resource.ty
+            )?;
+
+            match resource.kind {
+                IrResourceKind::Uniform => write!(f, "uniform")?,
+                IrResourceKind::Texture => write!(f, "texture")?,
+                IrResourceKind::VertexAttribute { attribute } => {
+                    write!(f, "vertex_attribute(\"{}\")", attribute.to_str())?
+                }
+            }
+
+            match resource.pass {
+                Some(pass) => writeln!(f, " in pass#{}", pass.0)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        for function in &self.functions {
+            separate(f)?;
+            FunctionDumper {
+                module: self,
+                function,
+            }
+            .fmt(f)?;
+        }
+
+        for pass in &self.passes {
+            separate(f)?;
+            write!(f, "pass#{} {}", pass.id.0, pass.name.to_str())?;
+
+            for attribute in &pass.attributes {
+                write!(
+                    f,
+                    " @{} = \"{}\"",
+                    attribute.name.to_str(),
+                    attribute.value.to_str()
+                )?;
+            }
+
+            writeln!(f, " {{")?;
+
+            for entry_point in &pass.entry_points {
+                writeln!(
+                    f,
+                    "    {} = fn#{}",
+                    entry_point.stage.name(),
+                    entry_point.function.0
+                )?;
+            }
+
+            writeln!(f, "}}")?;
+        }
+
+        Ok(())
+    }
+}
+
+struct FunctionDumper<'a> {
+    module: &'a IrModule,
+    function: &'a IrFunction,
+}
+
+impl FunctionDumper<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.function.kind {
+            IrFunctionKind::User => write!(f, "fn#{} ", self.function.id.0)?,
+            IrFunctionKind::EntryPoint { pass, stage } => write!(
+                f,
+                "{} fn#{} of pass#{} ",
+                stage.name(),
+                self.function.id.0,
+                pass.0
+            )?,
+        }
+
+        write!(f, "{}(", self.function.name.to_str())?;
+
+        for (index, param) in self.function.params.iter().enumerate() {
+            if index != 0 {
+                write!(f, ", ")?;
+            }
+
+            let local = self.function.local(*param);
+            write!(f, "%{} {}: {}", param.0, local.name.to_str(), local.ty)?;
+        }
+
+        write!(f, ")")?;
+
+        if self.function.return_type != IrType::Void {
+            write!(f, " -> {}", self.function.return_type)?;
+        }
+
+        writeln!(f, " {{")?;
+        self.fmt_block(f, &self.function.body, 1)?;
+        writeln!(f, "}}")
+    }
+
+    fn fmt_block(&self, f: &mut Formatter<'_>, block: &IrBlock, depth: usize) -> Result {
+        let indent = "    ".repeat(depth);
+
+        for statement in &block.statements {
+            write!(f, "{}", indent)?;
+
+            match &statement.kind {
+                IrStatementKind::Let { local, init } => {
+                    let ty = self.function.local(*local).ty;
+                    write!(f, "let ")?;
+                    self.fmt_local(f, local.0)?;
+                    write!(f, ": {}", ty)?;
+
+                    if let Some(init) = init {
+                        write!(f, " = ")?;
+                        self.fmt_expr(f, init)?;
+                    }
+                }
+                IrStatementKind::Assign { target, value } => {
+                    self.fmt_expr(f, target)?;
+                    write!(f, " = ")?;
+                    self.fmt_expr(f, value)?;
+                }
+                IrStatementKind::Expr(expr) => {
+                    self.fmt_expr(f, expr)?;
+                }
+                IrStatementKind::Return(expr) => {
+                    write!(f, "return")?;
+
+                    if let Some(expr) = expr {
+                        write!(f, " ")?;
+                        self.fmt_expr(f, expr)?;
+                    }
+                }
+            }
+
+            writeln!(f, ";")?;
+        }
+
+        Ok(())
+    }
+
+    fn fmt_local(&self, f: &mut Formatter<'_>, index: u32) -> Result {
+        let local = &self.function.locals[index as usize];
+        write!(f, "%{} {}", index, local.name.to_str())
+    }
+
+    fn fmt_args(&self, f: &mut Formatter<'_>, args: &[IrExpr]) -> Result {
+        write!(f, "(")?;
+
+        for (index, arg) in args.iter().enumerate() {
+            if index != 0 {
+                write!(f, ", ")?;
+            }
+
+            self.fmt_expr(f, arg)?;
+        }
+
+        write!(f, ")")
+    }
+
+    fn fmt_expr(&self, f: &mut Formatter<'_>, expr: &IrExpr) -> Result {
+        match &expr.kind {
+            IrExprKind::Constant(constant) => write!(f, "{}", constant),
+            IrExprKind::Local(local) => self.fmt_local(f, local.0),
+            IrExprKind::Resource(resource) => write!(
+                f,
+                "resource#{} {}",
+                resource.0,
+                self.module.resource(*resource).name.to_str()
+            ),
+            IrExprKind::Unary { op, rhs } => {
+                let op = match op {
+                    IrUnaryOp::Neg => "-",
+                    IrUnaryOp::LogNot => "!",
+                    IrUnaryOp::BitNot => "~",
+                };
+                write!(f, "{}", op)?;
+                self.fmt_expr(f, rhs)
+            }
+            IrExprKind::Binary { op, lhs, rhs } => {
+                write!(f, "(")?;
+                self.fmt_expr(f, lhs)?;
+                write!(f, " {} ", op)?;
+                self.fmt_expr(f, rhs)?;
+                write!(f, ")")
+            }
+            IrExprKind::Call { function, args } => {
+                write!(
+                    f,
+                    "fn#{} {}",
+                    function.0,
+                    self.module.function(*function).name.to_str()
+                )?;
+                self.fmt_args(f, args)
+            }
+            IrExprKind::Builtin { builtin, args } => {
+                write!(f, "{}", builtin.name())?;
+                self.fmt_args(f, args)
+            }
+            IrExprKind::Construct { args } => {
+                write!(f, "{}", expr.ty)?;
+                self.fmt_args(f, args)
+            }
+            IrExprKind::Swizzle { base, components } => {
+                self.fmt_expr(f, base)?;
+                write!(f, ".")?;
+
+                for component in components {
+                    write!(f, "{}", ['x', 'y', 'z', 'w'][*component as usize])?;
+                }
+
+                Ok(())
+            }
+            IrExprKind::Field { base, index } => {
+                self.fmt_expr(f, base)?;
+
+                match base.ty {
+                    IrType::Struct(id) => write!(
+                        f,
+                        ".{}",
+                        self.module.struct_def(id).fields[*index as usize]
+                            .name
+                            .to_str()
+                    ),
+                    _ => write!(f, ".{}", index),
+                }
+            }
+            IrExprKind::Index { base, index } => {
+                self.fmt_expr(f, base)?;
+                write!(f, "[")?;
+                self.fmt_expr(f, index)?;
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl Display for IrConstant {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Int(value) => write!(f, "{}i", value),
+            Self::UInt(value) => write!(f, "{}u", value),
+            Self::Float(value) => write!(f, "{:?}f", value),
+        }
+    }
+}
+
+impl Display for IrBinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let op = match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Gt => ">",
+            Self::Le => "<=",
+            Self::Ge => ">=",
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::Pow => "**",
+            Self::Shl => "<<",
+            Self::Shr => ">>",
+            Self::BitOr => "|",
+            Self::BitAnd => "&",
+            Self::BitXor => "^",
+            Self::LogOr => "||",
+            Self::LogAnd => "&&",
+        };
+        write!(f, "{}", op)
+    }
+}
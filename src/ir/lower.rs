@@ -0,0 +1,1884 @@
+use super::{
+    IrAttribute, IrBinaryOp, IrBlock, IrBuiltin, IrConstant, IrEntryPoint, IrExpr, IrExprKind,
+    IrFunction, IrFunctionId, IrFunctionKind, IrLocal, IrLocalId, IrModule, IrPass, IrPassId,
+    IrResource, IrResourceId, IrResourceKind, IrScalarType, IrStage, IrStatement, IrStatementKind,
+    IrStruct, IrStructField, IrStructId, IrType, IrUnaryOp,
+};
+use crate::{
+    diagnostics::{
+        codes::{
+            COMPTIME_ERR_NOT_EXPANDED, IMPORT_ERR_NOT_RESOLVED, TYPE_ERR_DUPLICATE_NAME,
+            TYPE_ERR_INVALID_ASSIGNMENT, TYPE_ERR_INVALID_ATTRIBUTE, TYPE_ERR_INVALID_CALL,
+            TYPE_ERR_INVALID_LITERAL, TYPE_ERR_INVALID_MEMBER, TYPE_ERR_INVALID_STAGE,
+            TYPE_ERR_MISMATCHED_TYPES, TYPE_ERR_TYPE_ANNOTATION_NEEDED, TYPE_ERR_UNDEFINED_NAME,
+            TYPE_ERR_UNKNOWN_TYPE, TYPE_WARN_UNUSED_INPUT,
+        },
+        find_similar_name, Applicability, ItemSender, LintLevel, LintLevels, Suggestion,
+    },
+    parse::{
+        ast::{
+            AstAssignmentOpKind, AstAttribute, AstAttributeItem, AstBinaryExprOpKind, AstCallExpr,
+            AstExpr, AstExprKind, AstFnDef, AstIdentifier, AstIdentifierKind, AstIndexExpr,
+            AstInput, AstLiteral, AstLiteralKind, AstMemberExpr, AstObjectExpr, AstPass,
+            AstPassLevelKind, AstShaderPack, AstStage, AstStatement, AstStatementKind,
+            AstTopLevelKind, AstTypeName, AstUnaryExprOpKind,
+        },
+        lexer::{decode_number, NumberLiteralError, NumberLiteralValue, TokenNumberLiteralKind},
+    },
+    span::Span,
+    symbol::Symbol,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// Lowers an expanded shader pack into the IR.
+///
+/// Names are resolved and expressions are type-checked while lowering. Every error is reported
+/// through the `reporter`; `None` is returned if any error has been reported. Lowering stops early
+/// once the reporter is full.
+pub fn lower(pack: &AstShaderPack, reporter: &ItemSender) -> Option<IrModule> {
+    let mut lowerer = Lowerer::new(reporter.clone());
+    lowerer.lower_shader_pack(pack);
+
+    if lowerer.has_error || reporter.is_full() {
+        None
+    } else {
+        Some(lowerer.module)
+    }
+}
+
+struct Lowerer {
+    /// Swapped for the reporter of the lint scope of every item while lowering it.
+    reporter: ItemSender,
+    module: IrModule,
+    has_error: bool,
+    /// The reporter of the lint scope every resource has been declared in, by id.
+    resource_reporters: Vec<ItemSender>,
+    used_resources: FxHashSet<IrResourceId>,
+    resources: FxHashMap<Symbol, IrResourceId>,
+    pass_resources: FxHashMap<Symbol, IrResourceId>,
+    functions: FxHashMap<Symbol, IrFunctionId>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Binding {
+    Local(IrLocalId),
+    /// A field of a struct-typed local, used for the varyings of fragment programs.
+    Field {
+        local: IrLocalId,
+        index: u32,
+    },
+}
+
+struct FunctionBuilder {
+    id: IrFunctionId,
+    locals: Vec<IrLocal>,
+    bindings: FxHashMap<Symbol, Binding>,
+    return_type: ReturnType,
+}
+
+enum ReturnType {
+    Declared(IrType),
+    /// Stage programs infer their return type from the first `return` statement.
+    Inferred(Option<IrType>),
+}
+
+impl FunctionBuilder {
+    fn new(id: IrFunctionId, return_type: ReturnType) -> Self {
+        Self {
+            id,
+            locals: Vec::new(),
+            bindings: FxHashMap::default(),
+            return_type,
+        }
+    }
+
+    fn add_local(&mut self, name: Symbol, ty: IrType, span: Span) -> IrLocalId {
+        let id = IrLocalId(self.locals.len() as u32);
+        self.locals.push(IrLocal { name, ty, span });
+        self.bindings.insert(name, Binding::Local(id));
+        id
+    }
+
+    fn return_type(&self) -> IrType {
+        match self.return_type {
+            ReturnType::Declared(ty) => ty,
+            ReturnType::Inferred(ty) => ty.unwrap_or(IrType::Void),
+        }
+    }
+}
+
+impl Lowerer {
+    fn new(reporter: ItemSender) -> Self {
+        Self {
+            reporter,
+            module: IrModule::default(),
+            has_error: false,
+            resource_reporters: Vec::new(),
+            used_resources: FxHashSet::default(),
+            resources: FxHashMap::default(),
+            pass_resources: FxHashMap::default(),
+            functions: FxHashMap::default(),
+        }
+    }
+
+    fn error(&mut self, code: u32, span: Span, message: impl Into<String>) {
+        self.has_error = true;
+        self.reporter.error(code, span, message);
+    }
+
+    fn error_fix(
+        &mut self,
+        code: u32,
+        span: Span,
+        message: impl Into<String>,
+        suggestions: Vec<Suggestion>,
+    ) {
+        self.has_error = true;
+        self.reporter.error_fix(code, span, message, suggestions);
+    }
+
+    /// Returns the reporter of the lint scope of an item, nesting the lint levels set by its
+    /// attributes, e.g. `@allow = "unused-input"`, in the current ones.
+    fn lint_scope(&mut self, attributes: &[AstAttribute]) -> ItemSender {
+        let mut levels = LintLevels::new();
+
+        for item in attributes.iter().flat_map(|attribute| &attribute.items) {
+            let level = match lint_level(item) {
+                Some(level) => level,
+                None => continue,
+            };
+
+            if let Err(err) = levels.set_by_name(item.expr.value().to_str(), level) {
+                self.error(TYPE_ERR_INVALID_ATTRIBUTE, item.expr.span, err);
+            }
+        }
+
+        self.reporter.with_lint_levels(levels)
+    }
+
+    /// Runs `f` with the reporter swapped for the given one.
+    fn with_reporter<R>(&mut self, reporter: ItemSender, f: impl FnOnce(&mut Self) -> R) -> R {
+        let outer = std::mem::replace(&mut self.reporter, reporter);
+        let result = f(self);
+        self.reporter = outer;
+        result
+    }
+
+    fn lower_shader_pack(&mut self, pack: &AstShaderPack) {
+        let mut fn_defs = Vec::new();
+        let mut passes = Vec::new();
+
+        for top_level in &pack.top_levels {
+            if self.reporter.is_full() {
+                break;
+            }
+
+            match &top_level.kind {
+                AstTopLevelKind::CompTime(comptime) => {
+                    self.error(
+                        COMPTIME_ERR_NOT_EXPANDED,
+                        comptime.span,
+                        "`comptime` items must be expanded before lowering",
+                    );
+                }
+                AstTopLevelKind::Import(import) => {
+                    self.error(
+                        IMPORT_ERR_NOT_RESOLVED,
+                        import.span,
+                        "`import` items must be resolved before lowering",
+                    );
+                }
+                AstTopLevelKind::FnDef(fn_def) => {
+                    let reporter = self.lint_scope(&fn_def.attributes);
+
+                    if let Some(id) =
+                        self.with_reporter(reporter.clone(), |this| this.declare_fn_def(fn_def))
+                    {
+                        fn_defs.push((id, fn_def, reporter));
+                    }
+                }
+                AstTopLevelKind::Input(input) => {
+                    let reporter = self.lint_scope(&input.attributes);
+
+                    if let Some((name, id)) =
+                        self.with_reporter(reporter, |this| this.lower_input(input, None))
+                    {
+                        self.resources.insert(name, id);
+                    }
+                }
+                AstTopLevelKind::Pass(pass) => {
+                    passes.push(pass);
+                }
+            }
+        }
+
+        for (id, fn_def, reporter) in fn_defs {
+            if self.reporter.is_full() {
+                break;
+            }
+
+            self.with_reporter(reporter, |this| this.lower_fn_def_body(id, fn_def));
+        }
+
+        if !self.reporter.is_full() {
+            self.report_recursive_calls();
+        }
+
+        let mut pass_names = FxHashMap::default();
+
+        for pass in passes {
+            if self.reporter.is_full() {
+                break;
+            }
+
+            let name = match self.identifier_symbol(&pass.ident) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            if let Some(previous) = pass_names.insert(name, pass.ident.span) {
+                self.report_duplicate(pass.ident.span, previous, "pass", name);
+                continue;
+            }
+
+            let reporter = self.lint_scope(&pass.attributes);
+            self.with_reporter(reporter, |this| this.lower_pass(name, pass));
+        }
+
+        // names may be left unresolved after an error
+        if !self.has_error {
+            self.report_unused_resources();
+        }
+    }
+
+    /// Reports the calls closing a cycle of calls between functions; calls of functions to
+    /// themselves have already been reported while lowering.
+    fn report_recursive_calls(&mut self) {
+        for (cycle, span) in find_recursive_calls(&self.module) {
+            let name = self.module.function(cycle[0]).name;
+            let through = Vec::from_iter(
+                cycle[1..]
+                    .iter()
+                    .map(|id| self.module.function(*id).name.to_string()),
+            );
+
+            self.error(
+                TYPE_ERR_INVALID_CALL,
+                span,
+                format!(
+                    "{} cannot call itself through {}; recursion is not supported",
+                    name,
+                    through.join(" -> ")
+                ),
+            );
+        }
+    }
+
+    fn report_unused_resources(&self) {
+        for (resource, reporter) in self.module.resources.iter().zip(&self.resource_reporters) {
+            if !self.used_resources.contains(&resource.id) {
+                reporter.warning(
+                    TYPE_WARN_UNUSED_INPUT,
+                    resource.span,
+                    format!("input {} is never used", resource.name),
+                );
+            }
+        }
+    }
+
+    fn report_duplicate(&mut self, span: Span, previous: Span, kind: &str, name: Symbol) {
+        self.has_error = true;
+        self.reporter.error_sub(
+            TYPE_ERR_DUPLICATE_NAME,
+            span,
+            format!("{} {} is defined multiple times", kind, name),
+            vec![self
+                .reporter
+                .sub_hint(previous, format!("{} is first defined here", name))],
+        );
+    }
+
+    fn identifier_symbol(&mut self, ident: &AstIdentifier) -> Option<Symbol> {
+        match &ident.kind {
+            AstIdentifierKind::Invalid => {
+                // the parser already reported the error
+                self.has_error = true;
+                None
+            }
+            AstIdentifierKind::Symbol(symbol) => Some(*symbol),
+            AstIdentifierKind::Composed(_) => {
+                self.error(
+                    COMPTIME_ERR_NOT_EXPANDED,
+                    ident.span,
+                    "`!ident` must be expanded before lowering",
+                );
+                None
+            }
+        }
+    }
+
+    fn resolve_type(&mut self, type_name: &AstTypeName) -> Option<IrType> {
+        let name = self.identifier_symbol(&type_name.ident)?;
+
+        match IrType::from_name(name.to_str()) {
+            Some(ty) => Some(ty),
+            None => {
+                self.error(
+                    TYPE_ERR_UNKNOWN_TYPE,
+                    type_name.span,
+                    format!("unknown type {}", name),
+                );
+                None
+            }
+        }
+    }
+
+    fn reject_attributes(&mut self, attributes: &[AstAttribute], target: &str) {
+        for item in attributes
+            .iter()
+            .flat_map(|attribute| &attribute.items)
+            .filter(|item| lint_level(item).is_none())
+        {
+            self.error(
+                TYPE_ERR_INVALID_ATTRIBUTE,
+                item.span,
+                format!("attributes are not allowed on {}", target),
+            );
+        }
+    }
+
+    fn lower_input(
+        &mut self,
+        input: &AstInput,
+        pass: Option<IrPassId>,
+    ) -> Option<(Symbol, IrResourceId)> {
+        let name = self.identifier_symbol(&input.ident)?;
+        let ty = self.resolve_type(&input.type_name)?;
+
+        let previous = self
+            .resources
+            .get(&name)
+            .or_else(|| self.pass_resources.get(&name))
+            .map(|id| self.module.resource(*id).span);
+
+        if let Some(previous) = previous {
+            self.report_duplicate(input.ident.span, previous, "input", name);
+            return None;
+        }
+
+        let mut kind = if ty.is_texture() {
+            IrResourceKind::Texture
+        } else {
+            IrResourceKind::Uniform
+        };
+
+        for item in input
+            .attributes
+            .iter()
+            .flat_map(|attribute| &attribute.items)
+            .filter(|item| lint_level(item).is_none())
+        {
+            let attribute_name = match self.identifier_symbol(&item.ident) {
+                Some(name) => name,
+                None => continue,
+            };
+
+            match attribute_name.to_str() {
+                "vertex" => {
+                    if !matches!(ty, IrType::Scalar(_) | IrType::Vector { .. }) {
+                        self.error(
+                            TYPE_ERR_INVALID_ATTRIBUTE,
+                            item.span,
+                            format!("vertex attributes must be scalars or vectors, not `{}`", ty),
+                        );
+                        continue;
+                    }
+
+                    kind = IrResourceKind::VertexAttribute {
+                        attribute: item.expr.value(),
+                    };
+                }
+                _ => {
+                    self.error_fix(
+                        TYPE_ERR_INVALID_ATTRIBUTE,
+                        item.span,
+                        format!("unknown input attribute {}", attribute_name),
+                        suggest_similar_name(item.ident.span, attribute_name, &["vertex"]),
+                    );
+                }
+            }
+        }
+
+        let id = IrResourceId(self.module.resources.len() as u32);
+        self.module.resources.push(IrResource {
+            id,
+            name,
+            ty,
+            kind,
+            pass,
+            doc: input.doc.as_ref().map(|doc| Symbol::from_str(doc.text())),
+            span: input.span,
+        });
+        self.resource_reporters.push(self.reporter.clone());
+        Some((name, id))
+    }
+
+    fn declare_fn_def(&mut self, fn_def: &AstFnDef) -> Option<IrFunctionId> {
+        self.reject_attributes(&fn_def.attributes, "functions");
+
+        let name = self.identifier_symbol(&fn_def.ident)?;
+
+        if let Some(previous) = self.functions.get(&name) {
+            let previous = self.module.function(*previous).span;
+            self.report_duplicate(fn_def.ident.span, previous, "function", name);
+            return None;
+        }
+
+        let mut locals = Vec::with_capacity(fn_def.params.len());
+        let mut params = Vec::with_capacity(fn_def.params.len());
+
+        for param in &fn_def.params {
+            self.reject_attributes(&param.attributes, "parameters");
+
+            let name = self.identifier_symbol(&param.ident);
+            let ty = self.resolve_type(&param.type_name);
+            let (name, ty) = match (name, ty) {
+                (Some(name), Some(ty)) => (name, ty),
+                _ => continue,
+            };
+
+            if let Some(previous) = locals.iter().find(|local: &&IrLocal| local.name == name) {
+                let previous = previous.span;
+                self.report_duplicate(param.ident.span, previous, "parameter", name);
+                continue;
+            }
+
+            params.push(IrLocalId(locals.len() as u32));
+            locals.push(IrLocal {
+                name,
+                ty,
+                span: param.span,
+            });
+        }
+
+        let return_type = match &fn_def.return_type {
+            Some(return_type) => self.resolve_type(&return_type.type_name)?,
+            None => IrType::Void,
+        };
+
+        let id = IrFunctionId(self.module.functions.len() as u32);
+        self.module.functions.push(IrFunction {
+            id,
+            name,
+            kind: IrFunctionKind::User,
+            params,
+            locals,
+            return_type,
+            body: IrBlock::default(),
+            span: fn_def.span,
+        });
+        self.functions.insert(name, id);
+        Some(id)
+    }
+
+    fn lower_fn_def_body(&mut self, id: IrFunctionId, fn_def: &AstFnDef) {
+        let function = self.module.function(id);
+        let mut builder = FunctionBuilder::new(id, ReturnType::Declared(function.return_type));
+
+        for param in &function.params {
+            let local = function.local(*param).clone();
+            builder.add_local(local.name, local.ty, local.span);
+        }
+
+        let body = self.lower_statements(&mut builder, &fn_def.statements);
+        let function = &mut self.module.functions[id.0 as usize];
+        function.locals = builder.locals;
+        function.body = body;
+    }
+
+    fn lower_pass(&mut self, name: Symbol, pass: &AstPass) {
+        let id = IrPassId(self.module.passes.len() as u32);
+        let mut attributes = Vec::new();
+
+        for item in pass
+            .attributes
+            .iter()
+            .flat_map(|attribute| &attribute.items)
+            .filter(|item| lint_level(item).is_none())
+        {
+            if let Some(attribute_name) = self.identifier_symbol(&item.ident) {
+                attributes.push(IrAttribute {
+                    name: attribute_name,
+                    value: item.expr.value(),
+                });
+            }
+        }
+
+        self.module.passes.push(IrPass {
+            id,
+            name,
+            attributes,
+            entry_points: Vec::new(),
+            span: pass.span,
+        });
+
+        self.pass_resources.clear();
+        let mut stages = Vec::new();
+
+        for pass_level in &pass.pass_levels {
+            match &pass_level.kind {
+                AstPassLevelKind::Input(input) => {
+                    let reporter = self.lint_scope(&input.attributes);
+
+                    if let Some((name, resource)) =
+                        self.with_reporter(reporter, |this| this.lower_input(input, Some(id)))
+                    {
+                        self.pass_resources.insert(name, resource);
+                    }
+                }
+                AstPassLevelKind::Stage(stage) => {
+                    let stage_name = match self.identifier_symbol(&stage.stage) {
+                        Some(stage_name) => stage_name,
+                        None => continue,
+                    };
+
+                    match IrStage::from_name(stage_name.to_str()) {
+                        Some(kind) => {
+                            if let Some((_, previous)) = stages
+                                .iter()
+                                .find(|(previous, _): &&(IrStage, &AstStage)| *previous == kind)
+                            {
+                                let previous = previous.stage.span;
+                                self.report_duplicate(
+                                    stage.stage.span,
+                                    previous,
+                                    "stage",
+                                    stage_name,
+                                );
+                                continue;
+                            }
+
+                            stages.push((kind, stage));
+                        }
+                        None => {
+                            self.error_fix(
+                                TYPE_ERR_INVALID_STAGE,
+                                stage.stage.span,
+                                format!(
+                                    "unknown stage {}; expected `vertex` or `fragment`",
+                                    stage_name
+                                ),
+                                suggest_similar_name(
+                                    stage.stage.span,
+                                    stage_name,
+                                    &["vertex", "fragment"],
+                                ),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        // the fragment program depends on the return type of the vertex program
+        stages.sort_by_key(|(kind, _)| *kind);
+
+        let mut varyings = None;
+
+        for (kind, stage) in stages {
+            let reporter = self.lint_scope(&stage.attributes);
+            let function = self.with_reporter(reporter, |this| {
+                this.lower_stage(name, id, kind, stage, varyings)
+            });
+
+            if let Some(function) = function {
+                if kind == IrStage::Vertex {
+                    varyings = match self.module.function(function).return_type {
+                        IrType::Struct(id) => Some(id),
+                        _ => None,
+                    };
+                }
+
+                self.module.passes[id.0 as usize]
+                    .entry_points
+                    .push(IrEntryPoint {
+                        stage: kind,
+                        function,
+                    });
+            }
+        }
+
+        self.pass_resources.clear();
+    }
+
+    fn lower_stage(
+        &mut self,
+        pass_name: Symbol,
+        pass: IrPassId,
+        kind: IrStage,
+        stage: &AstStage,
+        varyings: Option<IrStructId>,
+    ) -> Option<IrFunctionId> {
+        let id = IrFunctionId(self.module.functions.len() as u32);
+        let mut builder = FunctionBuilder::new(id, ReturnType::Inferred(None));
+        let mut params = Vec::new();
+
+        if let (IrStage::Fragment, Some(varyings)) = (kind, varyings) {
+            let local = builder.add_local(
+                Symbol::from_str("input"),
+                IrType::Struct(varyings),
+                stage.span,
+            );
+            params.push(local);
+
+            for (index, field) in self.module.struct_def(varyings).fields.iter().enumerate() {
+                builder.bindings.insert(
+                    field.name,
+                    Binding::Field {
+                        local,
+                        index: index as u32,
+                    },
+                );
+            }
+        }
+
+        let body = self.lower_statements(&mut builder, &stage.statements);
+        let return_type = builder.return_type();
+
+        self.module.functions.push(IrFunction {
+            id,
+            name: Symbol::from_str(format!("{}_{}", pass_name.to_str(), kind.name())),
+            kind: IrFunctionKind::EntryPoint { pass, stage: kind },
+            params,
+            locals: builder.locals,
+            return_type,
+            body,
+            span: stage.span,
+        });
+        Some(id)
+    }
+
+    fn lower_statements(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        statements: &[AstStatement],
+    ) -> IrBlock {
+        let mut block = IrBlock::default();
+
+        for statement in statements {
+            if let Some(kind) = self.lower_statement(builder, statement) {
+                block.statements.push(IrStatement {
+                    span: statement.span,
+                    kind,
+                });
+            }
+        }
+
+        block
+    }
+
+    fn lower_statement(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        statement: &AstStatement,
+    ) -> Option<IrStatementKind> {
+        match &statement.kind {
+            AstStatementKind::VarDecl(var_decl) => {
+                let name = self.identifier_symbol(&var_decl.ident)?;
+                let declared = match &var_decl.type_name {
+                    Some(type_name) => Some(self.resolve_type(&type_name.type_name)?),
+                    None => None,
+                };
+                let init = match &var_decl.assignment {
+                    Some(assignment) => Some(self.lower_expr(builder, &assignment.rhs)?),
+                    None => None,
+                };
+
+                let ty = match (declared, &init) {
+                    (Some(declared), Some(init)) => {
+                        self.expect_type(declared, init)?;
+                        declared
+                    }
+                    (Some(declared), None) => declared,
+                    (None, Some(init)) => init.ty,
+                    (None, None) => {
+                        self.error(
+                            TYPE_ERR_TYPE_ANNOTATION_NEEDED,
+                            var_decl.ident.span,
+                            format!("type annotation needed for {}", name),
+                        );
+                        return None;
+                    }
+                };
+
+                if ty == IrType::Void {
+                    self.error(
+                        TYPE_ERR_MISMATCHED_TYPES,
+                        var_decl.ident.span,
+                        "variables cannot have the `void` type",
+                    );
+                    return None;
+                }
+
+                let local = builder.add_local(name, ty, var_decl.ident.span);
+                Some(IrStatementKind::Let { local, init })
+            }
+            AstStatementKind::Assignment(assignment) => {
+                let target = self.lower_expr(builder, &assignment.lhs)?;
+                let value = self.lower_expr(builder, &assignment.rhs)?;
+
+                if !target.is_place() {
+                    self.error(
+                        TYPE_ERR_INVALID_ASSIGNMENT,
+                        assignment.lhs.span,
+                        "the left-hand side of an assignment must be a local variable",
+                    );
+                    return None;
+                }
+
+                let op = match assignment.op.kind {
+                    AstAssignmentOpKind::Invalid => {
+                        self.has_error = true;
+                        return None;
+                    }
+                    AstAssignmentOpKind::Assign => None,
+                    AstAssignmentOpKind::AssignAdd => Some(IrBinaryOp::Add),
+                    AstAssignmentOpKind::AssignSub => Some(IrBinaryOp::Sub),
+                    AstAssignmentOpKind::AssignMul => Some(IrBinaryOp::Mul),
+                    AstAssignmentOpKind::AssignDiv => Some(IrBinaryOp::Div),
+                    AstAssignmentOpKind::AssignMod => Some(IrBinaryOp::Mod),
+                    AstAssignmentOpKind::AssignPow => Some(IrBinaryOp::Pow),
+                    AstAssignmentOpKind::AssignShl => Some(IrBinaryOp::Shl),
+                    AstAssignmentOpKind::AssignShr => Some(IrBinaryOp::Shr),
+                    AstAssignmentOpKind::AssignBitOr => Some(IrBinaryOp::BitOr),
+                    AstAssignmentOpKind::AssignBitAnd => Some(IrBinaryOp::BitAnd),
+                    AstAssignmentOpKind::AssignBitXor => Some(IrBinaryOp::BitXor),
+                };
+
+                let value = match op {
+                    Some(op) => self.make_binary(assignment.op.span, op, target.clone(), value)?,
+                    None => value,
+                };
+                self.expect_type(target.ty, &value)?;
+
+                Some(IrStatementKind::Assign { target, value })
+            }
+            AstStatementKind::Return(ret) => {
+                let expr = match &ret.expr {
+                    Some(expr) => Some(self.lower_expr(builder, expr)?),
+                    None => None,
+                };
+                let ty = expr.as_ref().map_or(IrType::Void, |expr| expr.ty);
+
+                match &mut builder.return_type {
+                    ReturnType::Inferred(inferred @ None) => {
+                        *inferred = Some(ty);
+                    }
+                    ReturnType::Inferred(Some(expected)) | ReturnType::Declared(expected) => {
+                        if *expected != ty {
+                            let expected = *expected;
+                            self.error(
+                                TYPE_ERR_MISMATCHED_TYPES,
+                                statement.span,
+                                format!(
+                                    "mismatched return types; expected `{}`, found `{}`",
+                                    expected, ty
+                                ),
+                            );
+                            return None;
+                        }
+                    }
+                }
+
+                Some(IrStatementKind::Return(expr))
+            }
+            AstStatementKind::Expr(expr) => {
+                let expr = self.lower_expr(builder, expr)?;
+                Some(IrStatementKind::Expr(expr))
+            }
+        }
+    }
+
+    fn expect_type(&mut self, expected: IrType, expr: &IrExpr) -> Option<()> {
+        if expected == expr.ty {
+            return Some(());
+        }
+
+        self.error(
+            TYPE_ERR_MISMATCHED_TYPES,
+            expr.span,
+            format!(
+                "mismatched types; expected `{}`, found `{}`",
+                expected, expr.ty
+            ),
+        );
+        None
+    }
+
+    fn lower_expr(&mut self, builder: &mut FunctionBuilder, expr: &AstExpr) -> Option<IrExpr> {
+        match &expr.kind {
+            AstExprKind::Invalid => {
+                // the parser already reported the error
+                self.has_error = true;
+                None
+            }
+            AstExprKind::Binary(binary) => {
+                let lhs = self.lower_expr(builder, &binary.lhs)?;
+                let rhs = self.lower_expr(builder, &binary.rhs)?;
+                let op = match binary.op.kind {
+                    AstBinaryExprOpKind::Invalid => {
+                        self.has_error = true;
+                        return None;
+                    }
+                    AstBinaryExprOpKind::Eq => IrBinaryOp::Eq,
+                    AstBinaryExprOpKind::Ne => IrBinaryOp::Ne,
+                    AstBinaryExprOpKind::Lt => IrBinaryOp::Lt,
+                    AstBinaryExprOpKind::Gt => IrBinaryOp::Gt,
+                    AstBinaryExprOpKind::Le => IrBinaryOp::Le,
+                    AstBinaryExprOpKind::Ge => IrBinaryOp::Ge,
+                    AstBinaryExprOpKind::Add => IrBinaryOp::Add,
+                    AstBinaryExprOpKind::Sub => IrBinaryOp::Sub,
+                    AstBinaryExprOpKind::Mul => IrBinaryOp::Mul,
+                    AstBinaryExprOpKind::Div => IrBinaryOp::Div,
+                    AstBinaryExprOpKind::Mod => IrBinaryOp::Mod,
+                    AstBinaryExprOpKind::Pow => IrBinaryOp::Pow,
+                    AstBinaryExprOpKind::Shl => IrBinaryOp::Shl,
+                    AstBinaryExprOpKind::Shr => IrBinaryOp::Shr,
+                    AstBinaryExprOpKind::BitOr => IrBinaryOp::BitOr,
+                    AstBinaryExprOpKind::BitAnd => IrBinaryOp::BitAnd,
+                    AstBinaryExprOpKind::BitXor => IrBinaryOp::BitXor,
+                    AstBinaryExprOpKind::LogOr => IrBinaryOp::LogOr,
+                    AstBinaryExprOpKind::LogAnd => IrBinaryOp::LogAnd,
+                };
+                let mut expr = self.make_binary(binary.op.span, op, lhs, rhs)?;
+                expr.span = binary.span;
+                Some(expr)
+            }
+            AstExprKind::Unary(unary) => {
+                let rhs = self.lower_expr(builder, &unary.rhs)?;
+                let (op, valid) = match unary.op.kind {
+                    AstUnaryExprOpKind::Invalid => {
+                        self.has_error = true;
+                        return None;
+                    }
+                    AstUnaryExprOpKind::Pos => (None, rhs.ty.is_numeric()),
+                    AstUnaryExprOpKind::Neg => (
+                        Some(IrUnaryOp::Neg),
+                        rhs.ty.is_numeric() && rhs.ty.scalar() != Some(IrScalarType::UInt),
+                    ),
+                    AstUnaryExprOpKind::LogNot => (
+                        Some(IrUnaryOp::LogNot),
+                        rhs.ty.scalar() == Some(IrScalarType::Bool),
+                    ),
+                    AstUnaryExprOpKind::BitNot => (
+                        Some(IrUnaryOp::BitNot),
+                        rhs.ty.scalar().is_some_and(IrScalarType::is_integer)
+                            && !matches!(rhs.ty, IrType::Matrix { .. }),
+                    ),
+                };
+
+                if !valid {
+                    self.error(
+                        TYPE_ERR_MISMATCHED_TYPES,
+                        unary.op.span,
+                        format!("the unary operator cannot be applied to `{}`", rhs.ty),
+                    );
+                    return None;
+                }
+
+                Some(match op {
+                    Some(op) => IrExpr {
+                        ty: rhs.ty,
+                        span: unary.span,
+                        kind: IrExprKind::Unary {
+                            op,
+                            rhs: Box::new(rhs),
+                        },
+                    },
+                    None => rhs,
+                })
+            }
+            AstExprKind::Literal(literal) => self.lower_literal(literal),
+            AstExprKind::Identifier(ident) => self.lower_identifier(builder, ident),
+            AstExprKind::Call(call) => self.lower_call(builder, call),
+            AstExprKind::Member(member) => self.lower_member(builder, member),
+            AstExprKind::Index(index) => self.lower_index(builder, index),
+            AstExprKind::Object(object) => self.lower_object(builder, object),
+        }
+    }
+
+    fn make_binary(
+        &mut self,
+        span: Span,
+        op: IrBinaryOp,
+        lhs: IrExpr,
+        rhs: IrExpr,
+    ) -> Option<IrExpr> {
+        let ty = match binary_result_type(op, lhs.ty, rhs.ty) {
+            Some(ty) => ty,
+            None => {
+                self.error(
+                    TYPE_ERR_MISMATCHED_TYPES,
+                    span,
+                    format!(
+                        "the binary operator cannot be applied to `{}` and `{}`",
+                        lhs.ty, rhs.ty
+                    ),
+                );
+                return None;
+            }
+        };
+
+        Some(IrExpr {
+            ty,
+            span: Span::merge(lhs.span, rhs.span),
+            kind: IrExprKind::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            },
+        })
+    }
+
+    fn lower_literal(&mut self, literal: &AstLiteral) -> Option<IrExpr> {
+        let constant = match &literal.kind {
+            AstLiteralKind::Bool { content } => IrConstant::Bool(content.to_str() == "true"),
+            AstLiteralKind::Number {
+                kind,
+                content,
+                suffix,
+            } => match decode_number(*kind, content.to_str(), suffix.map(Symbol::to_str)) {
+                Ok(NumberLiteralValue::Int(value)) => IrConstant::Int(value),
+                Ok(NumberLiteralValue::UInt(value)) => IrConstant::UInt(value),
+                Ok(NumberLiteralValue::Float(value)) => IrConstant::Float(value),
+                Err(err) => {
+                    let message = match err {
+                        NumberLiteralError::Overflow => {
+                            let ty = match suffix.map(Symbol::to_str) {
+                                Some("u") => IrType::UINT,
+                                Some("f") => IrType::FLOAT,
+                                None if *kind == TokenNumberLiteralKind::Float => IrType::FLOAT,
+                                _ => IrType::INT,
+                            };
+                            format!("number literal is out of range for `{}`", ty)
+                        }
+                        // already reported by the lexer
+                        _ => "invalid number literal".to_string(),
+                    };
+                    self.error(TYPE_ERR_INVALID_LITERAL, literal.span, message);
+                    return None;
+                }
+            },
+            AstLiteralKind::String(_) => {
+                self.error(
+                    TYPE_ERR_INVALID_LITERAL,
+                    literal.span,
+                    "string literals cannot be used as values",
+                );
+                return None;
+            }
+        };
+
+        Some(IrExpr {
+            ty: constant.ty(),
+            span: literal.span,
+            kind: IrExprKind::Constant(constant),
+        })
+    }
+
+    fn lower_identifier(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        ident: &AstIdentifier,
+    ) -> Option<IrExpr> {
+        let name = self.identifier_symbol(ident)?;
+
+        if let Some(binding) = builder.bindings.get(&name) {
+            return Some(match *binding {
+                Binding::Local(local) => IrExpr {
+                    ty: builder.locals[local.0 as usize].ty,
+                    span: ident.span,
+                    kind: IrExprKind::Local(local),
+                },
+                Binding::Field { local, index } => {
+                    let base = IrExpr {
+                        ty: builder.locals[local.0 as usize].ty,
+                        span: ident.span,
+                        kind: IrExprKind::Local(local),
+                    };
+                    let ty = match base.ty {
+                        IrType::Struct(id) => self.module.struct_def(id).fields[index as usize].ty,
+                        _ => unreachable!(),
+                    };
+                    IrExpr {
+                        ty,
+                        span: ident.span,
+                        kind: IrExprKind::Field {
+                            base: Box::new(base),
+                            index,
+                        },
+                    }
+                }
+            });
+        }
+
+        let resource = self
+            .pass_resources
+            .get(&name)
+            .or_else(|| self.resources.get(&name));
+
+        if let Some(resource) = resource {
+            self.used_resources.insert(*resource);
+        }
+
+        match resource {
+            Some(resource) => Some(IrExpr {
+                ty: self.module.resource(*resource).ty,
+                span: ident.span,
+                kind: IrExprKind::Resource(*resource),
+            }),
+            None => {
+                self.error(
+                    TYPE_ERR_UNDEFINED_NAME,
+                    ident.span,
+                    format!("cannot find {} in this scope", name),
+                );
+                None
+            }
+        }
+    }
+
+    fn lower_call(&mut self, builder: &mut FunctionBuilder, call: &AstCallExpr) -> Option<IrExpr> {
+        if let Some(qualifier) = &call.qualifier {
+            self.error(
+                IMPORT_ERR_NOT_RESOLVED,
+                qualifier.span,
+                "qualified paths must be resolved before lowering",
+            );
+            return None;
+        }
+
+        let name = self.identifier_symbol(&call.callee)?;
+        let mut args = Vec::with_capacity(call.args.len());
+
+        for arg in &call.args {
+            if let Some(arg) = self.lower_expr(builder, &arg.expr) {
+                args.push(arg);
+            }
+        }
+
+        if args.len() != call.args.len() {
+            return None;
+        }
+
+        if let Some(function) = self.functions.get(&name).copied() {
+            if function == builder.id {
+                self.error(
+                    TYPE_ERR_INVALID_CALL,
+                    call.span,
+                    format!("{} cannot call itself; recursion is not supported", name),
+                );
+                return None;
+            }
+
+            let function = self.module.function(function);
+            let param_types = Vec::from_iter(
+                function
+                    .params
+                    .iter()
+                    .map(|param| function.local(*param).ty),
+            );
+            let return_type = function.return_type;
+            let function = function.id;
+
+            if param_types.len() != args.len()
+                || param_types.iter().zip(&args).any(|(ty, arg)| *ty != arg.ty)
+            {
+                self.error(
+                    TYPE_ERR_INVALID_CALL,
+                    call.span,
+                    format!(
+                        "{} expects ({}), found ({})",
+                        name,
+                        join_types(param_types.iter().copied()),
+                        join_types(args.iter().map(|arg| arg.ty))
+                    ),
+                );
+                return None;
+            }
+
+            return Some(IrExpr {
+                ty: return_type,
+                span: call.span,
+                kind: IrExprKind::Call { function, args },
+            });
+        }
+
+        if let Some(ty) = IrType::from_name(name.to_str()) {
+            if !is_constructible(ty, &args) {
+                self.error(
+                    TYPE_ERR_INVALID_CALL,
+                    call.span,
+                    format!(
+                        "`{}` cannot be constructed from ({})",
+                        ty,
+                        join_types(args.iter().map(|arg| arg.ty))
+                    ),
+                );
+                return None;
+            }
+
+            return Some(IrExpr {
+                ty,
+                span: call.span,
+                kind: IrExprKind::Construct { args },
+            });
+        }
+
+        if let Some(builtin) = IrBuiltin::from_name(name.to_str()) {
+            let arg_types = Vec::from_iter(args.iter().map(|arg| arg.ty));
+
+            return match builtin.result_type(&arg_types) {
+                Some(ty) => Some(IrExpr {
+                    ty,
+                    span: call.span,
+                    kind: IrExprKind::Builtin { builtin, args },
+                }),
+                None => {
+                    self.error(
+                        TYPE_ERR_INVALID_CALL,
+                        call.span,
+                        format!(
+                            "built-in {} cannot be called with ({})",
+                            name,
+                            join_types(arg_types)
+                        ),
+                    );
+                    None
+                }
+            };
+        }
+
+        self.error(
+            TYPE_ERR_UNDEFINED_NAME,
+            call.callee.span,
+            format!("cannot find function {}", name),
+        );
+        None
+    }
+
+    fn lower_member(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        member: &AstMemberExpr,
+    ) -> Option<IrExpr> {
+        let base = self.lower_expr(builder, &member.lhs)?;
+        let name = self.identifier_symbol(&member.member)?;
+
+        match base.ty {
+            IrType::Scalar(scalar) | IrType::Vector { scalar, .. } => {
+                let size = base.ty.component_count().unwrap();
+                let components = match parse_swizzle(name.to_str(), size) {
+                    Some(components) => components,
+                    None => {
+                        self.error(
+                            TYPE_ERR_INVALID_MEMBER,
+                            member.member.span,
+                            format!("invalid swizzle {} on `{}`", name, base.ty),
+                        );
+                        return None;
+                    }
+                };
+
+                Some(IrExpr {
+                    ty: IrType::with_components(scalar, components.len() as u8),
+                    span: member.span,
+                    kind: IrExprKind::Swizzle {
+                        base: Box::new(base),
+                        components,
+                    },
+                })
+            }
+            IrType::Struct(id) => {
+                let struct_def = self.module.struct_def(id);
+
+                match struct_def.field_index(name) {
+                    Some(index) => Some(IrExpr {
+                        ty: struct_def.fields[index as usize].ty,
+                        span: member.span,
+                        kind: IrExprKind::Field {
+                            base: Box::new(base),
+                            index,
+                        },
+                    }),
+                    None => {
+                        self.error(
+                            TYPE_ERR_INVALID_MEMBER,
+                            member.member.span,
+                            format!("no field {} on the object", name),
+                        );
+                        None
+                    }
+                }
+            }
+            _ => {
+                self.error(
+                    TYPE_ERR_INVALID_MEMBER,
+                    member.member.span,
+                    format!("`{}` has no members", base.ty),
+                );
+                None
+            }
+        }
+    }
+
+    fn lower_index(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        index: &AstIndexExpr,
+    ) -> Option<IrExpr> {
+        let base = self.lower_expr(builder, &index.lhs)?;
+        let index_expr = self.lower_expr(builder, &index.index)?;
+
+        if !matches!(
+            index_expr.ty,
+            IrType::Scalar(IrScalarType::Int | IrScalarType::UInt)
+        ) {
+            self.error(
+                TYPE_ERR_MISMATCHED_TYPES,
+                index_expr.span,
+                format!("indices must be `i` or `u`, found `{}`", index_expr.ty),
+            );
+            return None;
+        }
+
+        let ty = match base.ty {
+            IrType::Vector { scalar, .. } => IrType::Scalar(scalar),
+            IrType::Matrix { size } => IrType::Vector {
+                scalar: IrScalarType::Float,
+                size,
+            },
+            _ => {
+                self.error(
+                    TYPE_ERR_MISMATCHED_TYPES,
+                    index.lhs.span,
+                    format!("`{}` cannot be indexed", base.ty),
+                );
+                return None;
+            }
+        };
+
+        Some(IrExpr {
+            ty,
+            span: index.span,
+            kind: IrExprKind::Index {
+                base: Box::new(base),
+                index: Box::new(index_expr),
+            },
+        })
+    }
+
+    fn lower_object(
+        &mut self,
+        builder: &mut FunctionBuilder,
+        object: &AstObjectExpr,
+    ) -> Option<IrExpr> {
+        let mut fields = Vec::<IrStructField>::with_capacity(object.fields.len());
+        let mut args = Vec::with_capacity(object.fields.len());
+
+        for field in &object.fields {
+            let name = self.identifier_symbol(&field.ident);
+            let expr = self.lower_expr(builder, &field.expr);
+            let (name, expr) = match (name, expr) {
+                (Some(name), Some(expr)) => (name, expr),
+                _ => continue,
+            };
+
+            if let Some(previous) = fields.iter().find(|previous| previous.name == name) {
+                let previous = previous.span;
+                self.report_duplicate(field.ident.span, previous, "field", name);
+                continue;
+            }
+
+            fields.push(IrStructField {
+                name,
+                ty: expr.ty,
+                span: field.ident.span,
+            });
+            args.push(expr);
+        }
+
+        if args.len() != object.fields.len() {
+            return None;
+        }
+
+        Some(IrExpr {
+            ty: IrType::Struct(self.intern_struct(fields)),
+            span: object.span,
+            kind: IrExprKind::Construct { args },
+        })
+    }
+
+    /// Returns a struct with the same field names and types if any, or adds a new one.
+    fn intern_struct(&mut self, fields: Vec<IrStructField>) -> IrStructId {
+        let existing = self.module.structs.iter().position(|struct_def| {
+            struct_def.fields.len() == fields.len()
+                && struct_def
+                    .fields
+                    .iter()
+                    .zip(&fields)
+                    .all(|(lhs, rhs)| lhs.name == rhs.name && lhs.ty == rhs.ty)
+        });
+
+        match existing {
+            Some(index) => IrStructId(index as u32),
+            None => {
+                self.module.structs.push(IrStruct { fields });
+                IrStructId(self.module.structs.len() as u32 - 1)
+            }
+        }
+    }
+}
+
+/// Returns the level set by a lint attribute, e.g. `@allow = "unused-input"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VisitState {
+    Unvisited,
+    OnPath,
+    Done,
+}
+
+/// Returns every cycle of the call graph, as the functions of the cycle in call order and the span
+/// of the call closing it.
+fn find_recursive_calls(module: &IrModule) -> Vec<(Vec<IrFunctionId>, Span)> {
+    let calls = Vec::from_iter(module.functions.iter().map(|function| {
+        let mut calls = Vec::new();
+
+        function.body.visit_exprs(&mut |expr| {
+            if let IrExprKind::Call { function, .. } = &expr.kind {
+                calls.push((*function, expr.span));
+            }
+        });
+
+        calls
+    }));
+    let mut states = vec![VisitState::Unvisited; calls.len()];
+    let mut path = Vec::new();
+    let mut cycles = Vec::new();
+
+    for function in &module.functions {
+        if states[function.id.0 as usize] == VisitState::Unvisited {
+            visit_calls(function.id, &calls, &mut states, &mut path, &mut cycles);
+        }
+    }
+
+    cycles
+}
+
+fn visit_calls(
+    id: IrFunctionId,
+    calls: &[Vec<(IrFunctionId, Span)>],
+    states: &mut [VisitState],
+    path: &mut Vec<IrFunctionId>,
+    cycles: &mut Vec<(Vec<IrFunctionId>, Span)>,
+) {
+    states[id.0 as usize] = VisitState::OnPath;
+    path.push(id);
+
+    for (callee, span) in &calls[id.0 as usize] {
+        match states[callee.0 as usize] {
+            VisitState::Unvisited => visit_calls(*callee, calls, states, path, cycles),
+            VisitState::OnPath => {
+                let start = path.iter().position(|id| id == callee).unwrap();
+                cycles.push((path[start..].to_vec(), *span));
+            }
+            VisitState::Done => {}
+        }
+    }
+
+    path.pop();
+    states[id.0 as usize] = VisitState::Done;
+}
+
+fn lint_level(item: &AstAttributeItem) -> Option<LintLevel> {
+    match &item.ident.kind {
+        AstIdentifierKind::Symbol(symbol) => symbol.to_str().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Suggests replacing a misspelled name with the closest of the expected names.
+fn suggest_similar_name(span: Span, name: Symbol, expected: &[&'static str]) -> Vec<Suggestion> {
+    match find_similar_name(name.to_str(), expected.iter().copied()) {
+        Some(similar) => vec![Suggestion::new(
+            format!("did you mean `{}`?", similar),
+            span,
+            similar,
+            Applicability::MaybeIncorrect,
+        )],
+        None => vec![],
+    }
+}
+
+fn join_types(types: impl IntoIterator<Item = IrType>) -> String {
+    Vec::from_iter(types.into_iter().map(|ty| format!("`{}`", ty))).join(", ")
+}
+
+fn parse_swizzle(name: &str, size: u8) -> Option<Vec<u8>> {
+    const SETS: [&str; 2] = ["xyzw", "rgba"];
+
+    if name.is_empty() || 4 < name.len() {
+        return None;
+    }
+
+    SETS.iter().find_map(|set| {
+        name.chars()
+            .map(|char| set.find(char).map(|index| index as u8))
+            .collect::<Option<Vec<_>>>()
+            .filter(|components| components.iter().all(|component| *component < size))
+    })
+}
+
+fn is_constructible(ty: IrType, args: &[IrExpr]) -> bool {
+    match ty {
+        IrType::Scalar(_) => {
+            // a conversion between scalar types
+            matches!(args, [arg] if matches!(arg.ty, IrType::Scalar(_)))
+        }
+        IrType::Vector { size, .. } => {
+            if let [arg] = args {
+                // a conversion or a splat
+                if matches!(arg.ty, IrType::Scalar(_)) || arg.ty.component_count() == Some(size) {
+                    return true;
+                }
+            }
+
+            args.iter().all(|arg| arg.ty.scalar() == ty.scalar())
+                && args
+                    .iter()
+                    .map(|arg| arg.ty.component_count().unwrap_or(u8::MAX) as u32)
+                    .sum::<u32>()
+                    == size as u32
+        }
+        IrType::Matrix { size } => {
+            let column = IrType::Vector {
+                scalar: IrScalarType::Float,
+                size,
+            };
+
+            (args.len() == size as usize && args.iter().all(|arg| arg.ty == column))
+                || (args.len() == (size * size) as usize
+                    && args.iter().all(|arg| arg.ty == IrType::FLOAT))
+        }
+        _ => false,
+    }
+}
+
+fn binary_result_type(op: IrBinaryOp, lhs: IrType, rhs: IrType) -> Option<IrType> {
+    let is_scalar_or_vector = |ty: IrType| matches!(ty, IrType::Scalar(_) | IrType::Vector { .. });
+
+    match op {
+        IrBinaryOp::LogOr | IrBinaryOp::LogAnd => {
+            (lhs == IrType::BOOL && rhs == IrType::BOOL).then_some(IrType::BOOL)
+        }
+        IrBinaryOp::Eq
+        | IrBinaryOp::Ne
+        | IrBinaryOp::Lt
+        | IrBinaryOp::Gt
+        | IrBinaryOp::Le
+        | IrBinaryOp::Ge => {
+            let is_ordering = !matches!(op, IrBinaryOp::Eq | IrBinaryOp::Ne);
+
+            if lhs != rhs || !is_scalar_or_vector(lhs) || (is_ordering && !lhs.is_numeric()) {
+                return None;
+            }
+
+            Some(IrType::with_components(
+                IrScalarType::Bool,
+                lhs.component_count().unwrap(),
+            ))
+        }
+        IrBinaryOp::Add
+        | IrBinaryOp::Sub
+        | IrBinaryOp::Mul
+        | IrBinaryOp::Div
+        | IrBinaryOp::Mod
+        | IrBinaryOp::Pow
+        | IrBinaryOp::Shl
+        | IrBinaryOp::Shr
+        | IrBinaryOp::BitOr
+        | IrBinaryOp::BitAnd
+        | IrBinaryOp::BitXor => {
+            if let (IrType::Matrix { size }, _) | (_, IrType::Matrix { size }) = (lhs, rhs) {
+                let column = IrType::Vector {
+                    scalar: IrScalarType::Float,
+                    size,
+                };
+
+                return match (op, lhs, rhs) {
+                    (IrBinaryOp::Add | IrBinaryOp::Sub | IrBinaryOp::Mul, _, _) if lhs == rhs => {
+                        Some(lhs)
+                    }
+                    (IrBinaryOp::Mul, IrType::Matrix { .. }, _) if rhs == column => Some(column),
+                    (IrBinaryOp::Mul, _, IrType::Matrix { .. }) if lhs == column => Some(column),
+                    (IrBinaryOp::Mul, IrType::Matrix { .. }, IrType::FLOAT)
+                    | (IrBinaryOp::Mul, IrType::FLOAT, IrType::Matrix { .. }) => {
+                        Some(IrType::Matrix { size })
+                    }
+                    _ => None,
+                };
+            }
+
+            if !is_scalar_or_vector(lhs)
+                || !is_scalar_or_vector(rhs)
+                || lhs.scalar() != rhs.scalar()
+            {
+                return None;
+            }
+
+            let scalar = lhs.scalar().unwrap();
+            let is_valid = match op {
+                IrBinaryOp::Pow => scalar == IrScalarType::Float,
+                IrBinaryOp::Shl
+                | IrBinaryOp::Shr
+                | IrBinaryOp::BitOr
+                | IrBinaryOp::BitAnd
+                | IrBinaryOp::BitXor => scalar.is_integer(),
+                _ => scalar.is_numeric(),
+            };
+
+            if !is_valid {
+                return None;
+            }
+
+            match (lhs, rhs) {
+                _ if lhs == rhs => Some(lhs),
+                (IrType::Vector { .. }, IrType::Scalar(_)) => Some(lhs),
+                (IrType::Scalar(_), IrType::Vector { .. }) => Some(rhs),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{Item, ItemCollector, ItemLevel, LimitSink},
+        span::SourceMap,
+        test_utils::*,
+    };
+    use std::sync::Arc;
+
+    fn lower_pack(pack: &AstShaderPack) -> (Option<IrModule>, Vec<Item>) {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("", "test", None);
+        let collector = Arc::new(ItemCollector::new());
+        let reporter = ItemSender::new(file, collector.clone());
+        let module = lower(pack, &reporter);
+        let items = collector.take();
+
+        (module, items)
+    }
+
+    fn vertex_input(name: &str, ty: &str, vertex: &str) -> AstTopLevelKind {
+        let mut input = ast_input(ident(name), ty);
+        input.attributes.push(attribute("vertex", vertex));
+        AstTopLevelKind::Input(input)
+    }
+
+    #[test]
+    fn test_lower_inputs() {
+        let pack = shader_pack(vec![
+            input(ident("main_tex"), "t2"),
+            input(ident("color"), "f3"),
+            vertex_input("pos", "f3", "position"),
+        ]);
+        let (module, items) = lower_pack(&pack);
+        assert_eq!(items.len(), 3);
+        assert!(items
+            .iter()
+            .all(|item| item.code == TYPE_WARN_UNUSED_INPUT && item.level == ItemLevel::Warning));
+        assert_eq!(items[1].message, "input `color` is never used");
+        assert_eq!(
+            module.unwrap().to_string(),
+            "resource#0 main_tex: t2 = texture
+resource#1 color: f3 = uniform
+resource#2 pos: f3 = vertex_attribute(\"position\")
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_fn_def() {
+        let pack = shader_pack(vec![
+            input(ident("color"), "f3"),
+            fn_def(
+                ident("brighten"),
+                vec![(ident("value"), "f3")],
+                Some("f3"),
+                vec![
+                    statement_let(
+                        ident("scaled"),
+                        expr_binary(
+                            AstBinaryExprOpKind::Mul,
+                            expr_ident("value"),
+                            expr_float("2.0"),
+                        ),
+                    ),
+                    statement_return(expr_binary(
+                        AstBinaryExprOpKind::Add,
+                        expr_ident("scaled"),
+                        expr_ident("color"),
+                    )),
+                ],
+            ),
+        ]);
+        let (module, items) = lower_pack(&pack);
+        assert!(items.is_empty());
+        assert_eq!(
+            module.unwrap().to_string(),
+            "resource#0 color: f3 = uniform
+
+fn#0 brighten(%0 value: f3) -> f3 {
+    let %1 scaled: f3 = (%0 value * 2.0f);
+    return (%1 scaled + resource#0 color);
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_pass_varyings() {
+        let pack = shader_pack(vec![
+            vertex_input("normal", "f3", "normal"),
+            pass_with(
+                ident("first"),
+                vec![
+                    stage(
+                        ident("fragment"),
+                        vec![statement_return(expr_call(
+                            ident("f4"),
+                            vec![expr_ident("n"), expr_float("1.0")],
+                        ))],
+                    ),
+                    stage(
+                        ident("vertex"),
+                        vec![statement_return(expr_object(vec![(
+                            "n",
+                            expr_member(expr_ident("normal"), "xyz"),
+                        )]))],
+                    ),
+                ],
+            ),
+        ]);
+        let (module, items) = lower_pack(&pack);
+        assert!(items.is_empty());
+        assert_eq!(
+            module.unwrap().to_string(),
+            "struct#0 {
+    n: f3,
+}
+
+resource#0 normal: f3 = vertex_attribute(\"normal\")
+
+vertex fn#0 of pass#0 first_vertex() -> struct#0 {
+    return struct#0(resource#0 normal.xyz);
+}
+
+fragment fn#1 of pass#0 first_fragment(%0 input: struct#0) -> f4 {
+    return f4(%0 input.n, 1.0f);
+}
+
+pass#0 first {
+    vertex = fn#0
+    fragment = fn#1
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_lower_mismatched_types() {
+        let pack = shader_pack(vec![fn_def(
+            ident("test"),
+            vec![(ident("value"), "f3"), (ident("scale"), "i")],
+            None,
+            vec![statement_let_typed(
+                ident("scaled"),
+                Some("f3"),
+                expr_binary(
+                    AstBinaryExprOpKind::Mul,
+                    expr_ident("value"),
+                    expr_ident("scale"),
+                ),
+            )],
+        )]);
+        let (module, items) = lower_pack(&pack);
+        assert!(module.is_none());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].code, TYPE_ERR_MISMATCHED_TYPES);
+    }
+
+    #[test]
+    fn test_lower_undefined_name() {
+        let pack = shader_pack(vec![fn_def(
+            ident("test"),
+            vec![],
+            Some("f"),
+            vec![statement_return(expr_ident("missing"))],
+        )]);
+        let (module, items) = lower_pack(&pack);
+        assert!(module.is_none());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].code, TYPE_ERR_UNDEFINED_NAME);
+    }
+
+    #[test]
+    fn test_lower_mutual_recursion() {
+        let calling = |name: &str, callee: &str| {
+            fn_def(
+                ident(name),
+                vec![],
+                Some("f"),
+                vec![statement_return(expr_call(ident(callee), vec![]))],
+            )
+        };
+        let pack = shader_pack(vec![
+            calling("outer", "first"),
+            calling("first", "second"),
+            calling("second", "third"),
+            calling("third", "first"),
+        ]);
+        let (module, items) = lower_pack(&pack);
+        assert!(module.is_none());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].code, TYPE_ERR_INVALID_CALL);
+        assert_eq!(
+            items[0].message,
+            "`first` cannot call itself through `second` -> `third`; recursion is not supported"
+        );
+    }
+
+    #[test]
+    fn test_lower_duplicate_input() {
+        let pack = shader_pack(vec![
+            input(ident("color"), "f3"),
+            input(ident("color"), "f4"),
+        ]);
+        let (module, items) = lower_pack(&pack);
+        assert!(module.is_none());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].code, TYPE_ERR_DUPLICATE_NAME);
+        assert_eq!(items[0].sub_items.len(), 1);
+    }
+
+    #[test]
+    fn test_lower_lint_attributes() {
+        let with_attribute = |mut input: AstInput, level: &str| {
+            input.attributes.push(attribute(level, "unused-input"));
+            input
+        };
+        let mut denying = ast_pass(
+            ident("denying"),
+            vec![
+                AstPassLevelKind::Input(ast_input(ident("denied"), "f")),
+                AstPassLevelKind::Input(with_attribute(ast_input(ident("allowed"), "f"), "allow")),
+            ],
+        );
+        denying.attributes.push(attribute("deny", "unused-input"));
+        let mut forbidding = ast_pass(
+            ident("forbidding"),
+            vec![AstPassLevelKind::Input(with_attribute(
+                ast_input(ident("forbidden"), "f"),
+                "allow",
+            ))],
+        );
+        forbidding
+            .attributes
+            .push(attribute("forbid", "unused-input"));
+        let pack = shader_pack(vec![
+            AstTopLevelKind::Input(with_attribute(ast_input(ident("color"), "f3"), "allow")),
+            AstTopLevelKind::Pass(denying),
+            AstTopLevelKind::Pass(forbidding),
+        ]);
+
+        let (module, items) = lower_pack(&pack);
+        // denied lints are reported as errors, but do not fail the lowering
+        assert!(module.is_some());
+        assert_eq!(
+            Vec::from_iter(items.iter().map(|item| (item.level, item.message.as_str()))),
+            [
+                (ItemLevel::Error, "input `denied` is never used"),
+                (ItemLevel::Error, "input `forbidden` is never used"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lower_unknown_lint() {
+        let mut color = ast_input(ident("color"), "f3");
+        color.attributes.push(attribute("allow", "unused-inputs"));
+        let pack = shader_pack(vec![AstTopLevelKind::Input(color)]);
+        let (module, items) = lower_pack(&pack);
+        assert!(module.is_none());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].code, TYPE_ERR_INVALID_ATTRIBUTE);
+        assert_eq!(items[0].message, "unknown lint `unused-inputs`");
+    }
+
+    #[test]
+    fn test_lower_error_limit() {
+        let undefined = |name: &str| {
+            fn_def(
+                ident(name),
+                vec![],
+                Some("f"),
+                vec![statement_return(expr_ident("missing"))],
+            )
+        };
+        let pack = shader_pack(vec![undefined("a"), undefined("b"), undefined("c")]);
+        let file = SourceMap::new().add_file("", "test", None);
+        let sink = Arc::new(LimitSink::new(ItemCollector::new(), 1));
+        let module = lower(&pack, &ItemSender::new(file, sink.clone()));
+        assert!(module.is_none());
+        // lowering stops right after the first error
+        assert_eq!(sink.error_count(), 1);
+        assert_eq!(sink.inner().take().len(), 2);
+    }
+
+    #[test]
+    fn test_lower_unknown_stage() {
+        let pack = shader_pack(vec![pass_with(
+            ident("first"),
+            vec![
+                stage(ident("vertx"), vec![]),
+                stage(ident("compute"), vec![]),
+            ],
+        )]);
+        let (module, items) = lower_pack(&pack);
+        assert!(module.is_none());
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].code, TYPE_ERR_INVALID_STAGE);
+        assert_eq!(
+            items[0].suggestions,
+            vec![Suggestion::new(
+                "did you mean `vertex`?",
+                Span::ZERO,
+                "vertex",
+                Applicability::MaybeIncorrect,
+            )]
+        );
+        assert!(items[1].suggestions.is_empty());
+    }
+
+    #[test]
+    fn test_parse_swizzle() {
+        assert_eq!(parse_swizzle("xyz", 4), Some(vec![0, 1, 2]));
+        assert_eq!(parse_swizzle("bgr", 3), Some(vec![2, 1, 0]));
+        assert_eq!(parse_swizzle("w", 3), None);
+        assert_eq!(parse_swizzle("xg", 4), None);
+        assert_eq!(parse_swizzle("xyzwx", 4), None);
+    }
+}
@@ -0,0 +1,232 @@
+mod common_subexpression_elimination;
+mod constant_folding;
+mod dead_code_elimination;
+mod function_inlining;
+mod unused_input_stripping;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use common_subexpression_elimination::*;
+pub use constant_folding::*;
+pub use dead_code_elimination::*;
+pub use function_inlining::*;
+pub use unused_input_stripping::*;
+
+use super::{IrExpr, IrExprKind, IrLocalId, IrModule};
+use std::str::FromStr;
+
+/// Represents a single optimization pass over an [`IrModule`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OptPass {
+    /// Inlines calls to user functions.
+    FunctionInlining,
+    /// Evaluates operations on constants and propagates constant locals.
+    ConstantFolding,
+    /// Reuses the result of an identical expression instead of recomputing it.
+    CommonSubexpressionElimination,
+    /// Removes unreachable statements, unused locals and unused functions.
+    DeadCodeElimination,
+    /// Removes `in` items that are not referenced by any stage.
+    UnusedInputStripping,
+}
+
+impl OptPass {
+    /// Every pass, in the order the [`PassManager`] runs them.
+    pub const ALL: [Self; 5] = [
+        Self::FunctionInlining,
+        Self::ConstantFolding,
+        Self::CommonSubexpressionElimination,
+        Self::DeadCodeElimination,
+        Self::UnusedInputStripping,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FunctionInlining => "inline",
+            Self::ConstantFolding => "const-fold",
+            Self::CommonSubexpressionElimination => "cse",
+            Self::DeadCodeElimination => "dce",
+            Self::UnusedInputStripping => "strip-inputs",
+        }
+    }
+
+    /// Runs the pass once. Returns `true` if the module has been changed.
+    pub fn run(self, module: &mut IrModule) -> bool {
+        match self {
+            Self::FunctionInlining => inline_functions(module),
+            Self::ConstantFolding => fold_constants(module),
+            Self::CommonSubexpressionElimination => eliminate_common_subexpressions(module),
+            Self::DeadCodeElimination => eliminate_dead_code(module),
+            Self::UnusedInputStripping => strip_unused_inputs(module),
+        }
+    }
+}
+
+impl FromStr for OptPass {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|pass| pass.name() == str)
+            .ok_or_else(|| format!("unknown optimization pass `{}`", str))
+    }
+}
+
+/// Selects a default set of optimization passes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OptLevel {
+    /// No optimization; the module is kept as lowered.
+    #[default]
+    None,
+    /// Cheap clean-ups: constant folding, dead code elimination and unused input stripping.
+    Basic,
+    /// Every pass.
+    Full,
+}
+
+impl OptLevel {
+    pub fn passes(self) -> &'static [OptPass] {
+        match self {
+            Self::None => &[],
+            Self::Basic => &[
+                OptPass::ConstantFolding,
+                OptPass::DeadCodeElimination,
+                OptPass::UnusedInputStripping,
+            ],
+            Self::Full => &OptPass::ALL,
+        }
+    }
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str {
+            "0" => Ok(Self::None),
+            "1" => Ok(Self::Basic),
+            "2" => Ok(Self::Full),
+            _ => Err(format!("unknown optimization level `{}`", str)),
+        }
+    }
+}
+
+/// Runs a set of optimization passes over a module.
+///
+/// Passes always run in the order of [`OptPass::ALL`], regardless of the order they have been
+/// enabled in. The whole pipeline is repeated until no pass changes the module anymore, bounded
+/// by [`PassManager::MAX_ITERATIONS`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct PassManager {
+    passes: Vec<OptPass>,
+}
+
+impl PassManager {
+    pub const MAX_ITERATIONS: usize = 8;
+
+    pub fn new(level: OptLevel) -> Self {
+        Self {
+            passes: level.passes().to_vec(),
+        }
+    }
+
+    pub fn passes(&self) -> &[OptPass] {
+        &self.passes
+    }
+
+    pub fn is_enabled(&self, pass: OptPass) -> bool {
+        self.passes.contains(&pass)
+    }
+
+    pub fn enable(&mut self, pass: OptPass) -> &mut Self {
+        if !self.is_enabled(pass) {
+            self.passes.push(pass);
+            self.passes.sort();
+        }
+
+        self
+    }
+
+    pub fn disable(&mut self, pass: OptPass) -> &mut Self {
+        self.passes.retain(|enabled| *enabled != pass);
+        self
+    }
+
+    /// Runs the enabled passes. Returns `true` if the module has been changed.
+    pub fn run(&self, module: &mut IrModule) -> bool {
+        let mut changed = false;
+
+        for _ in 0..Self::MAX_ITERATIONS {
+            let mut iteration_changed = false;
+
+            for pass in &self.passes {
+                iteration_changed |= pass.run(module);
+            }
+
+            if !iteration_changed {
+                break;
+            }
+
+            changed = true;
+        }
+
+        changed
+    }
+}
+
+/// Returns the local that a place expression is rooted at.
+fn place_root(expr: &IrExpr) -> Option<IrLocalId> {
+    match &expr.kind {
+        IrExprKind::Local(local) => Some(*local),
+        IrExprKind::Swizzle { base, .. }
+        | IrExprKind::Field { base, .. }
+        | IrExprKind::Index { base, .. } => place_root(base),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opt_level_passes() {
+        assert!(PassManager::new(OptLevel::None).passes().is_empty());
+        assert_eq!(
+            PassManager::new(OptLevel::Basic).passes(),
+            &[
+                OptPass::ConstantFolding,
+                OptPass::DeadCodeElimination,
+                OptPass::UnusedInputStripping
+            ]
+        );
+        assert_eq!(PassManager::new(OptLevel::Full).passes(), &OptPass::ALL);
+    }
+
+    #[test]
+    fn test_pass_manager_toggle() {
+        let mut manager = PassManager::new(OptLevel::Basic);
+        manager
+            .enable(OptPass::FunctionInlining)
+            .disable(OptPass::DeadCodeElimination);
+
+        assert_eq!(
+            manager.passes(),
+            &[
+                OptPass::FunctionInlining,
+                OptPass::ConstantFolding,
+                OptPass::UnusedInputStripping
+            ]
+        );
+    }
+
+    #[test]
+    fn test_opt_pass_name_round_trip() {
+        for pass in OptPass::ALL {
+            assert_eq!(pass.name().parse::<OptPass>(), Ok(pass));
+        }
+        assert!("unknown".parse::<OptPass>().is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use super::place_root;
+use crate::{
+    ir::{
+        IrExpr, IrExprKind, IrFunction, IrLocal, IrLocalId, IrModule, IrStatement, IrStatementKind,
+    },
+    span::Span,
+    symbol::Symbol,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// Replaces repeated computations of the same expression with a local holding its value.
+///
+/// Within a statement, an expression that occurs more than once is hoisted into a new `let`
+/// right before the statement. Across statements, an expression equal to the initializer of an
+/// earlier `let` reuses that local. Expressions reading a local are forgotten once the local is
+/// assigned, and locals that are assigned anywhere are never reused.
+///
+/// Example:
+///
+/// ```text
+/// return ((%0 a * %1 b) + (%0 a * %1 b));
+/// ```
+///
+/// becomes
+///
+/// ```text
+/// let %2 cse: f = (%0 a * %1 b);
+/// return (%2 cse + %2 cse);
+/// ```
+pub fn eliminate_common_subexpressions(module: &mut IrModule) -> bool {
+    let mut changed = false;
+
+    for function in &mut module.functions {
+        changed |= eliminate_in_function(function);
+    }
+
+    changed
+}
+
+fn eliminate_in_function(function: &mut IrFunction) -> bool {
+    let mut assigned = FxHashSet::default();
+
+    for statement in &function.body.statements {
+        if let IrStatementKind::Assign { target, .. } = &statement.kind {
+            if let Some(local) = place_root(target) {
+                assigned.insert(local);
+            }
+        }
+    }
+
+    let mut changed = false;
+    let mut available = FxHashMap::default();
+    let mut statements = Vec::with_capacity(function.body.statements.len());
+
+    for mut statement in std::mem::take(&mut function.body.statements) {
+        for_each_value_mut(&mut statement, |expr| {
+            changed |= replace_available(expr, &available);
+        });
+
+        while let Some(repeated) = find_repeated(&statement) {
+            let local = IrLocalId(function.locals.len() as u32);
+            function.locals.push(IrLocal {
+                name: Symbol::from_str("cse"),
+                ty: repeated.ty,
+                span: Span::ZERO,
+            });
+            available.insert(repeated.clone(), local);
+            for_each_value_mut(&mut statement, |expr| {
+                replace_available(expr, &available);
+            });
+            statements.push(IrStatement {
+                span: statement.span,
+                kind: IrStatementKind::Let {
+                    local,
+                    init: Some(repeated),
+                },
+            });
+            changed = true;
+        }
+
+        match &statement.kind {
+            IrStatementKind::Let {
+                local,
+                init: Some(init),
+            } if !is_leaf(init) && !assigned.contains(local) => {
+                available.entry(key(init)).or_insert(*local);
+            }
+            IrStatementKind::Assign { target, .. } => {
+                if let Some(local) = place_root(target) {
+                    available.retain(|expr, _| !reads_local(expr, local));
+                }
+            }
+            _ => {}
+        }
+
+        statements.push(statement);
+    }
+
+    function.body.statements = statements;
+    changed
+}
+
+/// Calls `f` for each expression of the statement that is evaluated for its value; the place of
+/// an assignment target is skipped.
+fn for_each_value_mut(statement: &mut IrStatement, mut f: impl FnMut(&mut IrExpr)) {
+    match &mut statement.kind {
+        IrStatementKind::Assign { value, .. } => f(value),
+        _ => statement.for_each_expr_mut(f),
+    }
+}
+
+fn for_each_value(statement: &IrStatement, mut f: impl FnMut(&IrExpr)) {
+    match &statement.kind {
+        IrStatementKind::Assign { value, .. } => f(value),
+        _ => statement.for_each_expr(f),
+    }
+}
+
+fn replace_available(expr: &mut IrExpr, available: &FxHashMap<IrExpr, IrLocalId>) -> bool {
+    if is_leaf(expr) {
+        return false;
+    }
+
+    if let Some(local) = available.get(&key(expr)) {
+        expr.kind = IrExprKind::Local(*local);
+        return true;
+    }
+
+    let mut changed = false;
+    expr.for_each_child_mut(|child| changed |= replace_available(child, available));
+    changed
+}
+
+/// Returns the outermost expression that occurs more than once in the statement.
+fn find_repeated(statement: &IrStatement) -> Option<IrExpr> {
+    let mut order = Vec::new();
+    let mut counts = FxHashMap::<IrExpr, usize>::default();
+
+    for_each_value(statement, |expr| {
+        expr.visit(&mut |expr| {
+            if is_leaf(expr) {
+                return;
+            }
+
+            let key = key(expr);
+            let count = counts.entry(key.clone()).or_default();
+            *count += 1;
+
+            if *count == 1 {
+                order.push(key);
+            }
+        })
+    });
+
+    order.into_iter().find(|key| counts[key] > 1)
+}
+
+fn is_leaf(expr: &IrExpr) -> bool {
+    matches!(
+        expr.kind,
+        IrExprKind::Constant(_) | IrExprKind::Local(_) | IrExprKind::Resource(_)
+    )
+}
+
+fn reads_local(expr: &IrExpr, local: IrLocalId) -> bool {
+    let mut reads = false;
+    expr.visit(&mut |expr| reads |= matches!(expr.kind, IrExprKind::Local(id) if id == local));
+    reads
+}
+
+/// Returns a copy of the expression without spans, so that equal computations compare equal.
+fn key(expr: &IrExpr) -> IrExpr {
+    let mut key = expr.clone();
+    key.visit_mut_post(&mut |expr| expr.span = Span::ZERO);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{opt::test_utils::*, IrBinaryOp, IrType};
+
+    #[test]
+    fn test_eliminate_common_subexpressions() {
+        let product = || {
+            binary(
+                IrBinaryOp::Mul,
+                local(0, IrType::FLOAT),
+                local(1, IrType::FLOAT),
+            )
+        };
+        let mut module = module(
+            vec![],
+            vec![function(
+                0,
+                "first_vertex",
+                vertex_kind(),
+                &[IrType::FLOAT, IrType::FLOAT, IrType::FLOAT],
+                2,
+                IrType::FLOAT,
+                vec![
+                    statement_let(2, binary(IrBinaryOp::Add, product(), product())),
+                    statement_assign(
+                        local(2, IrType::FLOAT),
+                        binary(IrBinaryOp::Sub, local(2, IrType::FLOAT), product()),
+                    ),
+                    statement_assign(local(0, IrType::FLOAT), float(1.0)),
+                    statement_return(binary(IrBinaryOp::Add, product(), local(2, IrType::FLOAT))),
+                ],
+            )],
+            0,
+        );
+
+        assert!(eliminate_common_subexpressions(&mut module));
+        assert_eq!(
+            module.to_string(),
+            "vertex fn#0 of pass#0 first_vertex(%0 a: f, %1 b: f) -> f {
+    let %3 cse: f = (%0 a * %1 b);
+    let %2 c: f = (%3 cse + %3 cse);
+    %2 c = (%2 c - %3 cse);
+    %0 a = 1.0f;
+    return ((%0 a * %1 b) + %2 c);
+}
+
+pass#0 first {
+    vertex = fn#0
+}
+"
+        );
+        assert!(!eliminate_common_subexpressions(&mut module));
+    }
+}
@@ -0,0 +1,382 @@
+use super::place_root;
+use crate::ir::{
+    IrBinaryOp, IrConstant, IrExpr, IrExprKind, IrFunction, IrModule, IrScalarType,
+    IrStatementKind, IrType, IrUnaryOp,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// Evaluates unary, binary and conversion operations whose operands are constants, and replaces
+/// reads of locals that are initialized with a constant and never assigned.
+///
+/// Integers are evaluated as 32 bit values and floats in single precision, matching the targets.
+/// Operations that would be undefined on the GPU (e.g. division by zero) are left untouched.
+///
+/// Example:
+///
+/// ```text
+/// let %0 a: f = 2.0f;
+/// return (%0 a * 3.0f);
+/// ```
+///
+/// becomes
+///
+/// ```text
+/// let %0 a: f = 2.0f;
+/// return 6.0f;
+/// ```
+pub fn fold_constants(module: &mut IrModule) -> bool {
+    let mut changed = false;
+
+    for function in &mut module.functions {
+        changed |= propagate_constants(function);
+        function.body.visit_exprs_mut_post(&mut |expr| {
+            if let Some(constant) = fold_expr(expr) {
+                expr.kind = IrExprKind::Constant(constant);
+                changed = true;
+            }
+        });
+    }
+
+    changed
+}
+
+fn propagate_constants(function: &mut IrFunction) -> bool {
+    let mut assigned = FxHashSet::default();
+
+    for statement in &function.body.statements {
+        if let IrStatementKind::Assign { target, .. } = &statement.kind {
+            if let Some(local) = place_root(target) {
+                assigned.insert(local);
+            }
+        }
+    }
+
+    let mut constants = FxHashMap::default();
+
+    for statement in &function.body.statements {
+        if let IrStatementKind::Let {
+            local,
+            init:
+                Some(IrExpr {
+                    kind: IrExprKind::Constant(constant),
+                    ..
+                }),
+        } = &statement.kind
+        {
+            if !assigned.contains(local) {
+                constants.insert(*local, *constant);
+            }
+        }
+    }
+
+    if constants.is_empty() {
+        return false;
+    }
+
+    let mut changed = false;
+
+    function.body.visit_exprs_mut_post(&mut |expr| {
+        if let IrExprKind::Local(local) = &expr.kind {
+            if let Some(constant) = constants.get(local) {
+                expr.kind = IrExprKind::Constant(*constant);
+                changed = true;
+            }
+        }
+    });
+
+    changed
+}
+
+fn as_constant(expr: &IrExpr) -> Option<IrConstant> {
+    match &expr.kind {
+        IrExprKind::Constant(constant) => Some(*constant),
+        _ => None,
+    }
+}
+
+fn fold_expr(expr: &IrExpr) -> Option<IrConstant> {
+    match &expr.kind {
+        IrExprKind::Unary { op, rhs } => fold_unary(*op, as_constant(rhs)?),
+        IrExprKind::Binary { op, lhs, rhs } => {
+            fold_binary(*op, as_constant(lhs)?, as_constant(rhs)?)
+        }
+        IrExprKind::Construct { args } => match (expr.ty, args.as_slice()) {
+            (IrType::Scalar(scalar), [arg]) => convert(as_constant(arg)?, scalar),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn fold_unary(op: IrUnaryOp, rhs: IrConstant) -> Option<IrConstant> {
+    Some(match (op, rhs) {
+        (IrUnaryOp::Neg, IrConstant::Int(rhs)) => int_constant((rhs as i32).wrapping_neg()),
+        (IrUnaryOp::Neg, IrConstant::Float(rhs)) => float_constant(-(rhs as f32)),
+        (IrUnaryOp::LogNot, IrConstant::Bool(rhs)) => IrConstant::Bool(!rhs),
+        (IrUnaryOp::BitNot, IrConstant::Int(rhs)) => int_constant(!(rhs as i32)),
+        (IrUnaryOp::BitNot, IrConstant::UInt(rhs)) => uint_constant(!(rhs as u32)),
+        _ => return None,
+    })
+}
+
+fn fold_binary(op: IrBinaryOp, lhs: IrConstant, rhs: IrConstant) -> Option<IrConstant> {
+    if op.is_comparison() {
+        let ordering = match (lhs, rhs) {
+            (IrConstant::Bool(lhs), IrConstant::Bool(rhs)) => lhs.partial_cmp(&rhs),
+            (IrConstant::Int(lhs), IrConstant::Int(rhs)) => (lhs as i32).partial_cmp(&(rhs as i32)),
+            (IrConstant::UInt(lhs), IrConstant::UInt(rhs)) => {
+                (lhs as u32).partial_cmp(&(rhs as u32))
+            }
+            (IrConstant::Float(lhs), IrConstant::Float(rhs)) => {
+                (lhs as f32).partial_cmp(&(rhs as f32))
+            }
+            _ => return None,
+        };
+
+        // NaN compares unequal to everything
+        let result = match ordering {
+            Some(ordering) => match op {
+                IrBinaryOp::Eq => ordering.is_eq(),
+                IrBinaryOp::Ne => ordering.is_ne(),
+                IrBinaryOp::Lt => ordering.is_lt(),
+                IrBinaryOp::Gt => ordering.is_gt(),
+                IrBinaryOp::Le => ordering.is_le(),
+                _ => ordering.is_ge(),
+            },
+            None => op == IrBinaryOp::Ne,
+        };
+
+        return Some(IrConstant::Bool(result));
+    }
+
+    match (lhs, rhs) {
+        (IrConstant::Bool(lhs), IrConstant::Bool(rhs)) => Some(IrConstant::Bool(match op {
+            IrBinaryOp::LogOr => lhs || rhs,
+            IrBinaryOp::LogAnd => lhs && rhs,
+            _ => return None,
+        })),
+        (IrConstant::Int(lhs), IrConstant::Int(rhs)) => {
+            fold_int(op, lhs as i32, rhs as i32).map(int_constant)
+        }
+        (IrConstant::UInt(lhs), IrConstant::UInt(rhs)) => {
+            fold_uint(op, lhs as u32, rhs as u32).map(uint_constant)
+        }
+        (IrConstant::Float(lhs), IrConstant::Float(rhs)) => {
+            fold_float(op, lhs as f32, rhs as f32).map(float_constant)
+        }
+        _ => None,
+    }
+}
+
+fn fold_int(op: IrBinaryOp, lhs: i32, rhs: i32) -> Option<i32> {
+    Some(match op {
+        IrBinaryOp::Add => lhs.wrapping_add(rhs),
+        IrBinaryOp::Sub => lhs.wrapping_sub(rhs),
+        IrBinaryOp::Mul => lhs.wrapping_mul(rhs),
+        IrBinaryOp::Div if rhs != 0 => lhs.wrapping_div(rhs),
+        IrBinaryOp::Mod if rhs != 0 => lhs.wrapping_rem(rhs),
+        IrBinaryOp::Shl if (0..32).contains(&rhs) => lhs << rhs,
+        IrBinaryOp::Shr if (0..32).contains(&rhs) => lhs >> rhs,
+        IrBinaryOp::BitOr => lhs | rhs,
+        IrBinaryOp::BitAnd => lhs & rhs,
+        IrBinaryOp::BitXor => lhs ^ rhs,
+        _ => return None,
+    })
+}
+
+fn fold_uint(op: IrBinaryOp, lhs: u32, rhs: u32) -> Option<u32> {
+    Some(match op {
+        IrBinaryOp::Add => lhs.wrapping_add(rhs),
+        IrBinaryOp::Sub => lhs.wrapping_sub(rhs),
+        IrBinaryOp::Mul => lhs.wrapping_mul(rhs),
+        IrBinaryOp::Div if rhs != 0 => lhs / rhs,
+        IrBinaryOp::Mod if rhs != 0 => lhs % rhs,
+        IrBinaryOp::Shl if rhs < 32 => lhs << rhs,
+        IrBinaryOp::Shr if rhs < 32 => lhs >> rhs,
+        IrBinaryOp::BitOr => lhs | rhs,
+        IrBinaryOp::BitAnd => lhs & rhs,
+        IrBinaryOp::BitXor => lhs ^ rhs,
+        _ => return None,
+    })
+}
+
+fn fold_float(op: IrBinaryOp, lhs: f32, rhs: f32) -> Option<f32> {
+    Some(match op {
+        IrBinaryOp::Add => lhs + rhs,
+        IrBinaryOp::Sub => lhs - rhs,
+        IrBinaryOp::Mul => lhs * rhs,
+        IrBinaryOp::Div if rhs != 0.0 => lhs / rhs,
+        // floored, as shading languages define it; the result has the sign of `rhs`
+        IrBinaryOp::Mod if rhs != 0.0 => lhs - rhs * (lhs / rhs).floor(),
+        IrBinaryOp::Pow => lhs.powf(rhs),
+        _ => return None,
+    })
+}
+
+fn convert(constant: IrConstant, scalar: IrScalarType) -> Option<IrConstant> {
+    Some(match (constant, scalar) {
+        (IrConstant::Bool(value), IrScalarType::Bool) => IrConstant::Bool(value),
+        (IrConstant::Bool(value), IrScalarType::Int) => int_constant(value as i32),
+        (IrConstant::Bool(value), IrScalarType::UInt) => uint_constant(value as u32),
+        (IrConstant::Bool(value), IrScalarType::Float) => float_constant(value as u8 as f32),
+        (IrConstant::Int(value), IrScalarType::Bool) => IrConstant::Bool(value as i32 != 0),
+        (IrConstant::Int(value), IrScalarType::Int) => int_constant(value as i32),
+        (IrConstant::Int(value), IrScalarType::UInt) => uint_constant(value as i32 as u32),
+        (IrConstant::Int(value), IrScalarType::Float) => float_constant(value as i32 as f32),
+        (IrConstant::UInt(value), IrScalarType::Bool) => IrConstant::Bool(value as u32 != 0),
+        (IrConstant::UInt(value), IrScalarType::Int) => int_constant(value as u32 as i32),
+        (IrConstant::UInt(value), IrScalarType::UInt) => uint_constant(value as u32),
+        (IrConstant::UInt(value), IrScalarType::Float) => float_constant(value as u32 as f32),
+        (IrConstant::Float(value), IrScalarType::Bool) => IrConstant::Bool(value as f32 != 0.0),
+        // out of range values are undefined on the GPU
+        (IrConstant::Float(value), IrScalarType::Int)
+            if (i32::MIN as f64..=i32::MAX as f64).contains(&value) =>
+        {
+            int_constant(value as i32)
+        }
+        (IrConstant::Float(value), IrScalarType::UInt)
+            if (0.0..=u32::MAX as f64).contains(&value) =>
+        {
+            uint_constant(value as u32)
+        }
+        (IrConstant::Float(value), IrScalarType::Float) => float_constant(value as f32),
+        _ => return None,
+    })
+}
+
+fn int_constant(value: i32) -> IrConstant {
+    IrConstant::Int(value as i64)
+}
+
+fn uint_constant(value: u32) -> IrConstant {
+    IrConstant::UInt(value as u64)
+}
+
+fn float_constant(value: f32) -> IrConstant {
+    IrConstant::Float(value as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::opt::test_utils::*;
+
+    #[test]
+    fn test_fold_binary() {
+        assert_eq!(
+            fold_binary(IrBinaryOp::Add, IrConstant::Int(1), IrConstant::Int(2)),
+            Some(IrConstant::Int(3))
+        );
+        assert_eq!(
+            fold_binary(
+                IrBinaryOp::Add,
+                IrConstant::Int(i32::MAX as i64),
+                IrConstant::Int(1)
+            ),
+            Some(IrConstant::Int(i32::MIN as i64))
+        );
+        assert_eq!(
+            fold_binary(IrBinaryOp::Div, IrConstant::Int(1), IrConstant::Int(0)),
+            None
+        );
+        assert_eq!(
+            fold_binary(IrBinaryOp::Shl, IrConstant::UInt(1), IrConstant::UInt(32)),
+            None
+        );
+        assert_eq!(
+            fold_binary(
+                IrBinaryOp::Mul,
+                IrConstant::Float(1.5),
+                IrConstant::Float(2.0)
+            ),
+            Some(IrConstant::Float(3.0))
+        );
+        assert_eq!(
+            fold_binary(
+                IrBinaryOp::Mod,
+                IrConstant::Float(-1.5),
+                IrConstant::Float(1.0)
+            ),
+            Some(IrConstant::Float(0.5))
+        );
+        assert_eq!(
+            fold_binary(
+                IrBinaryOp::Mod,
+                IrConstant::Float(1.5),
+                IrConstant::Float(-1.0)
+            ),
+            Some(IrConstant::Float(-0.5))
+        );
+        assert_eq!(
+            fold_binary(
+                IrBinaryOp::Lt,
+                IrConstant::Float(1.0),
+                IrConstant::Float(2.0)
+            ),
+            Some(IrConstant::Bool(true))
+        );
+        assert_eq!(
+            fold_binary(
+                IrBinaryOp::Ne,
+                IrConstant::Float(f64::NAN),
+                IrConstant::Float(f64::NAN)
+            ),
+            Some(IrConstant::Bool(true))
+        );
+        assert_eq!(
+            fold_binary(
+                IrBinaryOp::LogAnd,
+                IrConstant::Bool(true),
+                IrConstant::Bool(false)
+            ),
+            Some(IrConstant::Bool(false))
+        );
+        assert_eq!(
+            fold_binary(IrBinaryOp::Add, IrConstant::Int(1), IrConstant::Float(1.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        let mut module = module(
+            vec![],
+            vec![function(
+                0,
+                "first_vertex",
+                vertex_kind(),
+                &[IrType::FLOAT, IrType::FLOAT],
+                0,
+                IrType::FLOAT,
+                vec![
+                    statement_let(0, float(2.0)),
+                    statement_let(1, float(1.0)),
+                    statement_assign(local(1, IrType::FLOAT), float(4.0)),
+                    statement_return(binary(
+                        IrBinaryOp::Add,
+                        binary(IrBinaryOp::Mul, local(0, IrType::FLOAT), float(3.0)),
+                        local(1, IrType::FLOAT),
+                    )),
+                ],
+            )],
+            0,
+        );
+
+        assert!(fold_constants(&mut module));
+        assert_eq!(
+            module.to_string(),
+            "vertex fn#0 of pass#0 first_vertex() -> f {
+    let %0 a: f = 2.0f;
+    let %1 b: f = 1.0f;
+    %1 b = 4.0f;
+    return (6.0f + %1 b);
+}
+
+pass#0 first {
+    vertex = fn#0
+}
+"
+        );
+        assert!(!fold_constants(&mut module));
+    }
+}
@@ -0,0 +1,242 @@
+use super::place_root;
+use crate::ir::{
+    IrExpr, IrExprKind, IrFunction, IrFunctionId, IrFunctionKind, IrLocalId, IrModule,
+    IrStatementKind,
+};
+use rustc_hash::FxHashSet;
+
+/// Removes code that cannot affect the output of any stage:
+///
+/// - statements following a `return`
+/// - expression statements, since every expression of the IR is free of side effects
+/// - `let` statements and assignments of locals that are never read
+/// - user functions that are not reachable from any entry point
+///
+/// Locals are never renumbered; removed ones simply stay unused in [`IrFunction::locals`].
+pub fn eliminate_dead_code(module: &mut IrModule) -> bool {
+    let mut changed = false;
+
+    for function in &mut module.functions {
+        changed |= remove_unreachable_statements(function);
+        changed |= remove_unused_statements(function);
+    }
+
+    changed |= remove_unreachable_functions(module);
+    changed
+}
+
+fn remove_unreachable_statements(function: &mut IrFunction) -> bool {
+    let statements = &mut function.body.statements;
+
+    match statements
+        .iter()
+        .position(|statement| matches!(statement.kind, IrStatementKind::Return(_)))
+    {
+        Some(index) if index + 1 < statements.len() => {
+            statements.truncate(index + 1);
+            true
+        }
+        _ => false,
+    }
+}
+
+fn remove_unused_statements(function: &mut IrFunction) -> bool {
+    let mut changed = false;
+
+    // removing a statement may leave other locals unread, so repeat until nothing changes
+    loop {
+        let read = read_locals(function);
+        let count = function.body.statements.len();
+
+        function
+            .body
+            .statements
+            .retain(|statement| match &statement.kind {
+                IrStatementKind::Let { local, .. } => read.contains(local),
+                IrStatementKind::Assign { target, .. } => {
+                    place_root(target).is_none_or(|local| read.contains(&local))
+                }
+                IrStatementKind::Expr(_) => false,
+                IrStatementKind::Return(_) => true,
+            });
+
+        if function.body.statements.len() == count {
+            return changed;
+        }
+
+        changed = true;
+    }
+}
+
+/// Collects the locals whose value is observed. Writing to a local through an assignment target
+/// does not count as reading it, but indices within the target do.
+fn read_locals(function: &IrFunction) -> FxHashSet<IrLocalId> {
+    let mut read = FxHashSet::default();
+    let mut collect = |expr: &IrExpr| {
+        if let IrExprKind::Local(local) = &expr.kind {
+            read.insert(*local);
+        }
+    };
+
+    for statement in &function.body.statements {
+        match &statement.kind {
+            IrStatementKind::Assign { target, value } => {
+                visit_place_indices(target, &mut collect);
+                value.visit(&mut collect);
+            }
+            _ => statement.for_each_expr(|expr| expr.visit(&mut collect)),
+        }
+    }
+
+    read
+}
+
+fn visit_place_indices(expr: &IrExpr, f: &mut impl FnMut(&IrExpr)) {
+    match &expr.kind {
+        IrExprKind::Swizzle { base, .. } | IrExprKind::Field { base, .. } => {
+            visit_place_indices(base, f)
+        }
+        IrExprKind::Index { base, index } => {
+            visit_place_indices(base, f);
+            index.visit(f);
+        }
+        _ => {}
+    }
+}
+
+fn remove_unreachable_functions(module: &mut IrModule) -> bool {
+    let mut reachable = FxHashSet::default();
+    let mut queue = Vec::from_iter(
+        module
+            .functions
+            .iter()
+            .filter(|function| matches!(function.kind, IrFunctionKind::EntryPoint { .. }))
+            .map(|function| function.id),
+    );
+
+    while let Some(id) = queue.pop() {
+        if !reachable.insert(id) {
+            continue;
+        }
+
+        module.function(id).body.visit_exprs(&mut |expr| {
+            if let IrExprKind::Call { function, .. } = &expr.kind {
+                queue.push(*function);
+            }
+        });
+    }
+
+    if reachable.len() == module.functions.len() {
+        return false;
+    }
+
+    // function ids are indices, so the remaining ones have to be renumbered
+    let mut remap = vec![None; module.functions.len()];
+    let mut next = 0;
+
+    for function in &module.functions {
+        if reachable.contains(&function.id) {
+            remap[function.id.0 as usize] = Some(IrFunctionId(next));
+            next += 1;
+        }
+    }
+
+    module
+        .functions
+        .retain(|function| reachable.contains(&function.id));
+
+    for function in &mut module.functions {
+        function.id = remap[function.id.0 as usize].unwrap();
+    }
+
+    let remap = |id: &mut IrFunctionId| *id = remap[id.0 as usize].unwrap();
+
+    for function in &mut module.functions {
+        function.body.visit_exprs_mut_post(&mut |expr| {
+            if let IrExprKind::Call { function, .. } = &mut expr.kind {
+                remap(function);
+            }
+        });
+    }
+
+    for pass in &mut module.passes {
+        for entry_point in &mut pass.entry_points {
+            remap(&mut entry_point.function);
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{opt::test_utils::*, IrBinaryOp, IrType};
+
+    #[test]
+    fn test_eliminate_dead_code() {
+        let mut module = module(
+            vec![],
+            vec![
+                function(
+                    0,
+                    "unused",
+                    IrFunctionKind::User,
+                    &[],
+                    0,
+                    IrType::FLOAT,
+                    vec![statement_return(float(1.0))],
+                ),
+                function(
+                    1,
+                    "double",
+                    IrFunctionKind::User,
+                    &[IrType::FLOAT],
+                    1,
+                    IrType::FLOAT,
+                    vec![statement_return(binary(
+                        IrBinaryOp::Mul,
+                        local(0, IrType::FLOAT),
+                        float(2.0),
+                    ))],
+                ),
+                function(
+                    2,
+                    "first_vertex",
+                    vertex_kind(),
+                    &[IrType::FLOAT, IrType::FLOAT, IrType::FLOAT],
+                    0,
+                    IrType::FLOAT,
+                    vec![
+                        statement_let(0, float(1.0)),
+                        statement_let(1, local(0, IrType::FLOAT)),
+                        statement_assign(local(1, IrType::FLOAT), float(2.0)),
+                        statement_let(2, call(1, IrType::FLOAT, vec![float(3.0)])),
+                        statement_return(local(2, IrType::FLOAT)),
+                        statement_return(float(0.0)),
+                    ],
+                ),
+            ],
+            2,
+        );
+
+        assert!(eliminate_dead_code(&mut module));
+        assert_eq!(
+            module.to_string(),
+            "fn#0 double(%0 a: f) -> f {
+    return (%0 a * 2.0f);
+}
+
+vertex fn#1 of pass#0 first_vertex() -> f {
+    let %2 c: f = fn#0 double(3.0f);
+    return %2 c;
+}
+
+pass#0 first {
+    vertex = fn#1
+}
+"
+        );
+        assert!(!eliminate_dead_code(&mut module));
+    }
+}
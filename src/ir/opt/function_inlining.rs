@@ -0,0 +1,299 @@
+use crate::{
+    ir::{
+        IrExpr, IrExprKind, IrFunction, IrFunctionId, IrFunctionKind, IrLocalId, IrModule,
+        IrStatement, IrStatementKind, IrType,
+    },
+    span::Span,
+};
+use std::collections::VecDeque;
+
+/// Replaces calls to user functions with the body of the callee.
+///
+/// Arguments are bound to fresh locals of the caller, followed by the statements of the callee
+/// up to its first `return`; the call itself is replaced with the returned expression. Functions
+/// that do not return a value and functions that (indirectly) call themselves are left as is.
+/// Callees that become unused are removed later by dead code elimination.
+///
+/// Example:
+///
+/// ```text
+/// fn#0 double(%0 value: f) -> f {
+///     return (%0 value * 2.0f);
+/// }
+///
+/// ... return fn#0 double(%0 a);
+/// ```
+///
+/// becomes
+///
+/// ```text
+/// ... let %1 value: f = %0 a;
+/// ... return (%1 value * 2.0f);
+/// ```
+pub fn inline_functions(module: &mut IrModule) -> bool {
+    let inlinable = Vec::from_iter(
+        module
+            .functions
+            .iter()
+            .map(|function| is_inlinable(module, function).then(|| function.clone())),
+    );
+
+    if inlinable.iter().all(Option::is_none) {
+        return false;
+    }
+
+    let mut changed = false;
+
+    for function in &mut module.functions {
+        changed |= inline_into(function, &inlinable);
+    }
+
+    changed
+}
+
+fn is_inlinable(module: &IrModule, function: &IrFunction) -> bool {
+    function.kind == IrFunctionKind::User
+        && function.return_type != IrType::Void
+        && function
+            .body
+            .statements
+            .iter()
+            .any(|statement| matches!(statement.kind, IrStatementKind::Return(Some(_))))
+        && !is_recursive(module, function.id)
+}
+
+fn is_recursive(module: &IrModule, id: IrFunctionId) -> bool {
+    let mut visited = vec![false; module.functions.len()];
+    let mut queue = vec![id];
+
+    while let Some(caller) = queue.pop() {
+        let mut recursive = false;
+
+        module.function(caller).body.visit_exprs(&mut |expr| {
+            if let IrExprKind::Call { function, .. } = &expr.kind {
+                recursive |= *function == id;
+
+                if !visited[function.0 as usize] {
+                    visited[function.0 as usize] = true;
+                    queue.push(*function);
+                }
+            }
+        });
+
+        if recursive {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn inline_into(function: &mut IrFunction, inlinable: &[Option<IrFunction>]) -> bool {
+    let mut changed = false;
+    let mut queue = VecDeque::from(std::mem::take(&mut function.body.statements));
+    let mut statements = Vec::with_capacity(queue.len());
+
+    while let Some(mut statement) = queue.pop_front() {
+        let span = statement.span;
+        let mut prelude = Vec::new();
+        let mut inline =
+            |expr: &mut IrExpr| inline_first_call(expr, function, inlinable, span, &mut prelude);
+
+        let inlined = match &mut statement.kind {
+            IrStatementKind::Assign { value, .. } => inline(value),
+            _ => {
+                let mut inlined = false;
+                statement.for_each_expr_mut(|expr| inlined = inlined || inline(expr));
+                inlined
+            }
+        };
+
+        if !inlined {
+            statements.push(statement);
+            continue;
+        }
+
+        // the inlined statements may contain calls too, so process them before the statement
+        queue.push_front(statement);
+
+        for statement in prelude.into_iter().rev() {
+            queue.push_front(statement);
+        }
+
+        changed = true;
+    }
+
+    function.body.statements = statements;
+    changed
+}
+
+/// Replaces the first call to an inlinable function within the expression.
+fn inline_first_call(
+    expr: &mut IrExpr,
+    caller: &mut IrFunction,
+    inlinable: &[Option<IrFunction>],
+    span: Span,
+    prelude: &mut Vec<IrStatement>,
+) -> bool {
+    if let IrExprKind::Call { function, args } = &mut expr.kind {
+        if let Some(callee) = &inlinable[function.0 as usize] {
+            let args = std::mem::take(args);
+            *expr = inline_call(caller, callee, args, span, prelude);
+            return true;
+        }
+    }
+
+    let mut inlined = false;
+    expr.for_each_child_mut(|child| {
+        inlined = inlined || inline_first_call(child, caller, inlinable, span, prelude);
+    });
+    inlined
+}
+
+/// Copies the locals of `callee` into `caller`, pushes the argument bindings and the statements
+/// of the callee into `prelude`, and returns the expression the call evaluates to.
+fn inline_call(
+    caller: &mut IrFunction,
+    callee: &IrFunction,
+    args: Vec<IrExpr>,
+    span: Span,
+    prelude: &mut Vec<IrStatement>,
+) -> IrExpr {
+    let base = caller.locals.len() as u32;
+    let remap = |local: &mut IrLocalId| *local = IrLocalId(base + local.0);
+    caller.locals.extend(callee.locals.iter().cloned());
+
+    for (param, arg) in callee.params.iter().zip(args) {
+        prelude.push(IrStatement {
+            span,
+            kind: IrStatementKind::Let {
+                local: IrLocalId(base + param.0),
+                init: Some(arg),
+            },
+        });
+    }
+
+    for statement in &callee.body.statements {
+        let mut statement = statement.clone();
+
+        if let IrStatementKind::Let { local, .. } = &mut statement.kind {
+            remap(local);
+        }
+
+        statement.for_each_expr_mut(|expr| {
+            expr.visit_mut_post(&mut |expr| {
+                if let IrExprKind::Local(local) = &mut expr.kind {
+                    remap(local);
+                }
+            })
+        });
+
+        match statement.kind {
+            IrStatementKind::Return(Some(expr)) => return expr,
+            _ => prelude.push(statement),
+        }
+    }
+
+    unreachable!("inlined functions always return a value")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{opt::test_utils::*, IrBinaryOp};
+
+    #[test]
+    fn test_inline_functions() {
+        let mut module = module(
+            vec![],
+            vec![
+                function(
+                    0,
+                    "double",
+                    IrFunctionKind::User,
+                    &[IrType::FLOAT, IrType::FLOAT],
+                    1,
+                    IrType::FLOAT,
+                    vec![
+                        statement_let(
+                            1,
+                            binary(IrBinaryOp::Mul, local(0, IrType::FLOAT), float(2.0)),
+                        ),
+                        statement_return(local(1, IrType::FLOAT)),
+                    ],
+                ),
+                function(
+                    1,
+                    "loop",
+                    IrFunctionKind::User,
+                    &[],
+                    0,
+                    IrType::FLOAT,
+                    vec![statement_return(call(2, IrType::FLOAT, vec![]))],
+                ),
+                function(
+                    2,
+                    "pool",
+                    IrFunctionKind::User,
+                    &[],
+                    0,
+                    IrType::FLOAT,
+                    vec![statement_return(call(1, IrType::FLOAT, vec![]))],
+                ),
+                function(
+                    3,
+                    "first_vertex",
+                    vertex_kind(),
+                    &[IrType::FLOAT],
+                    0,
+                    IrType::FLOAT,
+                    vec![
+                        statement_let(0, float(1.0)),
+                        statement_return(binary(
+                            IrBinaryOp::Add,
+                            call(
+                                0,
+                                IrType::FLOAT,
+                                vec![call(0, IrType::FLOAT, vec![local(0, IrType::FLOAT)])],
+                            ),
+                            call(1, IrType::FLOAT, vec![]),
+                        )),
+                    ],
+                ),
+            ],
+            3,
+        );
+
+        assert!(inline_functions(&mut module));
+        assert_eq!(
+            module.to_string(),
+            "fn#0 double(%0 a: f) -> f {
+    let %1 b: f = (%0 a * 2.0f);
+    return %1 b;
+}
+
+fn#1 loop() -> f {
+    return fn#2 pool();
+}
+
+fn#2 pool() -> f {
+    return fn#1 loop();
+}
+
+vertex fn#3 of pass#0 first_vertex() -> f {
+    let %0 a: f = 1.0f;
+    let %3 a: f = %0 a;
+    let %4 b: f = (%3 a * 2.0f);
+    let %1 a: f = %4 b;
+    let %2 b: f = (%1 a * 2.0f);
+    return (%2 b + fn#1 loop());
+}
+
+pass#0 first {
+    vertex = fn#3
+}
+"
+        );
+        assert!(!inline_functions(&mut module));
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    ir::{
+        IrBinaryOp, IrBlock, IrConstant, IrEntryPoint, IrExpr, IrExprKind, IrFunction,
+        IrFunctionId, IrFunctionKind, IrLocal, IrLocalId, IrModule, IrPass, IrPassId, IrResource,
+        IrResourceId, IrResourceKind, IrStage, IrStatement, IrStatementKind, IrType,
+    },
+    span::Span,
+    symbol::Symbol,
+};
+
+pub fn expr(ty: IrType, kind: IrExprKind) -> IrExpr {
+    IrExpr {
+        ty,
+        span: Span::ZERO,
+        kind,
+    }
+}
+
+pub fn constant(constant: IrConstant) -> IrExpr {
+    expr(constant.ty(), IrExprKind::Constant(constant))
+}
+
+pub fn float(value: f64) -> IrExpr {
+    constant(IrConstant::Float(value))
+}
+
+pub fn local(id: u32, ty: IrType) -> IrExpr {
+    expr(ty, IrExprKind::Local(IrLocalId(id)))
+}
+
+pub fn resource(id: u32, ty: IrType) -> IrExpr {
+    expr(ty, IrExprKind::Resource(IrResourceId(id)))
+}
+
+pub fn binary(op: IrBinaryOp, lhs: IrExpr, rhs: IrExpr) -> IrExpr {
+    let ty = if op.is_comparison() {
+        IrType::BOOL
+    } else {
+        lhs.ty
+    };
+
+    expr(
+        ty,
+        IrExprKind::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    )
+}
+
+pub fn call(function: u32, ty: IrType, args: Vec<IrExpr>) -> IrExpr {
+    expr(
+        ty,
+        IrExprKind::Call {
+            function: IrFunctionId(function),
+            args,
+        },
+    )
+}
+
+pub fn statement(kind: IrStatementKind) -> IrStatement {
+    IrStatement {
+        span: Span::ZERO,
+        kind,
+    }
+}
+
+pub fn statement_let(local: u32, init: IrExpr) -> IrStatement {
+    statement(IrStatementKind::Let {
+        local: IrLocalId(local),
+        init: Some(init),
+    })
+}
+
+pub fn statement_assign(target: IrExpr, value: IrExpr) -> IrStatement {
+    statement(IrStatementKind::Assign { target, value })
+}
+
+pub fn statement_return(expr: IrExpr) -> IrStatement {
+    statement(IrStatementKind::Return(Some(expr)))
+}
+
+/// Creates a function; `locals` are named `a`, `b`, `c`, ... and the first `param_count` of them
+/// are parameters.
+pub fn function(
+    id: u32,
+    name: &str,
+    kind: IrFunctionKind,
+    locals: &[IrType],
+    param_count: usize,
+    return_type: IrType,
+    statements: Vec<IrStatement>,
+) -> IrFunction {
+    IrFunction {
+        id: IrFunctionId(id),
+        name: Symbol::from_str(name),
+        kind,
+        params: Vec::from_iter((0..param_count as u32).map(IrLocalId)),
+        locals: Vec::from_iter(locals.iter().enumerate().map(|(index, ty)| IrLocal {
+            name: Symbol::from_str(((b'a' + index as u8) as char).to_string()),
+            ty: *ty,
+            span: Span::ZERO,
+        })),
+        return_type,
+        body: IrBlock { statements },
+        span: Span::ZERO,
+    }
+}
+
+pub fn vertex_kind() -> IrFunctionKind {
+    IrFunctionKind::EntryPoint {
+        pass: IrPassId(0),
+        stage: IrStage::Vertex,
+    }
+}
+
+pub fn uniform(id: u32, name: &str, ty: IrType) -> IrResource {
+    IrResource {
+        id: IrResourceId(id),
+        name: Symbol::from_str(name),
+        ty,
+        kind: IrResourceKind::Uniform,
+        pass: None,
+        doc: None,
+        span: Span::ZERO,
+    }
+}
+
+/// Creates a module with a single pass whose vertex stage is `entry_point`.
+pub fn module(
+    resources: Vec<IrResource>,
+    functions: Vec<IrFunction>,
+    entry_point: u32,
+) -> IrModule {
+    IrModule {
+        structs: vec![],
+        resources,
+        functions,
+        passes: vec![IrPass {
+            id: IrPassId(0),
+            name: Symbol::from_str("first"),
+            attributes: vec![],
+            entry_points: vec![IrEntryPoint {
+                stage: IrStage::Vertex,
+                function: IrFunctionId(entry_point),
+            }],
+            span: Span::ZERO,
+        }],
+    }
+}
@@ -0,0 +1,95 @@
+use crate::ir::{IrExprKind, IrModule, IrResourceId};
+
+/// Removes `in` items that are not read by any function, so that they do not occupy uniform
+/// buffer space, texture bindings or vertex attributes.
+///
+/// Resource ids are indices into [`IrModule::resources`], so the remaining ones are renumbered.
+pub fn strip_unused_inputs(module: &mut IrModule) -> bool {
+    let mut used = vec![false; module.resources.len()];
+
+    for function in &module.functions {
+        function.body.visit_exprs(&mut |expr| {
+            if let IrExprKind::Resource(resource) = &expr.kind {
+                used[resource.0 as usize] = true;
+            }
+        });
+    }
+
+    if used.iter().all(|used| *used) {
+        return false;
+    }
+
+    let mut remap = vec![None; module.resources.len()];
+    let mut next = 0;
+
+    for (index, used) in used.iter().enumerate() {
+        if *used {
+            remap[index] = Some(IrResourceId(next));
+            next += 1;
+        }
+    }
+
+    module
+        .resources
+        .retain(|resource| used[resource.id.0 as usize]);
+
+    for resource in &mut module.resources {
+        resource.id = remap[resource.id.0 as usize].unwrap();
+    }
+
+    for function in &mut module.functions {
+        function.body.visit_exprs_mut_post(&mut |expr| {
+            if let IrExprKind::Resource(resource) = &mut expr.kind {
+                *resource = remap[resource.0 as usize].unwrap();
+            }
+        });
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{opt::test_utils::*, IrBinaryOp, IrType};
+
+    #[test]
+    fn test_strip_unused_inputs() {
+        let mut module = module(
+            vec![
+                uniform(0, "unused", IrType::FLOAT),
+                uniform(1, "scale", IrType::FLOAT),
+            ],
+            vec![function(
+                0,
+                "first_vertex",
+                vertex_kind(),
+                &[],
+                0,
+                IrType::FLOAT,
+                vec![statement_return(binary(
+                    IrBinaryOp::Mul,
+                    resource(1, IrType::FLOAT),
+                    float(2.0),
+                ))],
+            )],
+            0,
+        );
+
+        assert!(strip_unused_inputs(&mut module));
+        assert_eq!(
+            module.to_string(),
+            "resource#0 scale: f = uniform
+
+vertex fn#0 of pass#0 first_vertex() -> f {
+    return (resource#0 scale * 2.0f);
+}
+
+pass#0 first {
+    vertex = fn#0
+}
+"
+        );
+        assert!(!strip_unused_inputs(&mut module));
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IrScalarType {
+    Bool,
+    Int,
+    UInt,
+    Float,
+}
+
+impl IrScalarType {
+    pub fn is_numeric(self) -> bool {
+        self != Self::Bool
+    }
+
+    pub fn is_integer(self) -> bool {
+        matches!(self, Self::Int | Self::UInt)
+    }
+
+    /// Returns the type name prefix used in the source language, e.g. `f` for `f3`.
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Self::Bool => "b",
+            Self::Int => "i",
+            Self::UInt => "u",
+            Self::Float => "f",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IrTextureKind {
+    /// `t2`
+    Texture2D,
+    /// `t3`
+    Texture3D,
+    /// `tc`
+    TextureCube,
+}
+
+/// Represents a type of the lowered IR.
+///
+/// Unlike the AST, every value in the IR has a type. Structs are referenced by their index in
+/// [`IrModule::structs`](super::IrModule::structs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IrType {
+    Void,
+    Scalar(IrScalarType),
+    /// `f2`, `i3`, `b4`, ...
+    Vector {
+        scalar: IrScalarType,
+        size: u8,
+    },
+    /// `m2`, `m3`, `m4`; always a square float matrix.
+    Matrix {
+        size: u8,
+    },
+    Texture(IrTextureKind),
+    Struct(IrStructId),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IrStructId(pub u32);
+
+impl IrType {
+    pub const BOOL: Self = Self::Scalar(IrScalarType::Bool);
+    pub const INT: Self = Self::Scalar(IrScalarType::Int);
+    pub const UINT: Self = Self::Scalar(IrScalarType::UInt);
+    pub const FLOAT: Self = Self::Scalar(IrScalarType::Float);
+
+    /// Every type name of the source language.
+    pub const NAMES: [&'static str; 22] = [
+        "b", "b2", "b3", "b4", "i", "i2", "i3", "i4", "u", "u2", "u3", "u4", "f", "f2", "f3", "f4",
+        "m2", "m3", "m4", "t2", "t3", "tc",
+    ];
+
+    /// Resolves a type name of the source language.
+    ///
+    /// Example:
+    /// - `f` -> `Scalar(Float)`
+    /// - `f3` -> `Vector { Float, 3 }`
+    /// - `m4` -> `Matrix { 4 }`
+    /// - `t2` -> `Texture(Texture2D)`
+    pub fn from_name(name: &str) -> Option<Self> {
+        let mut chars = name.chars();
+        let prefix = chars.next()?;
+        let rest = chars.as_str();
+
+        let size = match rest {
+            "" => None,
+            "2" => Some(2),
+            "3" => Some(3),
+            "4" => Some(4),
+            _ => {
+                return match name {
+                    "tc" => Some(Self::Texture(IrTextureKind::TextureCube)),
+                    _ => None,
+                }
+            }
+        };
+
+        let scalar = match prefix {
+            'b' => IrScalarType::Bool,
+            'i' => IrScalarType::Int,
+            'u' => IrScalarType::UInt,
+            'f' => IrScalarType::Float,
+            'm' => return size.map(|size| Self::Matrix { size }),
+            't' => {
+                return match size {
+                    Some(2) => Some(Self::Texture(IrTextureKind::Texture2D)),
+                    Some(3) => Some(Self::Texture(IrTextureKind::Texture3D)),
+                    _ => None,
+                }
+            }
+            _ => return None,
+        };
+
+        Some(match size {
+            None => Self::Scalar(scalar),
+            Some(size) => Self::Vector { scalar, size },
+        })
+    }
+
+    pub fn scalar(self) -> Option<IrScalarType> {
+        match self {
+            Self::Scalar(scalar) => Some(scalar),
+            Self::Vector { scalar, .. } => Some(scalar),
+            Self::Matrix { .. } => Some(IrScalarType::Float),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of components of scalar and vector types.
+    pub fn component_count(self) -> Option<u8> {
+        match self {
+            Self::Scalar(_) => Some(1),
+            Self::Vector { size, .. } => Some(size),
+            _ => None,
+        }
+    }
+
+    pub fn is_numeric(self) -> bool {
+        match self {
+            Self::Scalar(scalar) | Self::Vector { scalar, .. } => scalar.is_numeric(),
+            Self::Matrix { .. } => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_texture(self) -> bool {
+        matches!(self, Self::Texture(_))
+    }
+
+    /// Returns a scalar or vector type with the given number of components.
+    pub fn with_components(scalar: IrScalarType, count: u8) -> Self {
+        if count == 1 {
+            Self::Scalar(scalar)
+        } else {
+            Self::Vector {
+                scalar,
+                size: count,
+            }
+        }
+    }
+}
+
+impl Display for IrType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Void => write!(f, "void"),
+            Self::Scalar(scalar) => write!(f, "{}", scalar.prefix()),
+            Self::Vector { scalar, size } => write!(f, "{}{}", scalar.prefix(), size),
+            Self::Matrix { size } => write!(f, "m{}", size),
+            Self::Texture(IrTextureKind::Texture2D) => write!(f, "t2"),
+            Self::Texture(IrTextureKind::Texture3D) => write!(f, "t3"),
+            Self::Texture(IrTextureKind::TextureCube) => write!(f, "tc"),
+            Self::Struct(id) => write!(f, "struct#{}", id.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ir_type_from_name() {
+        assert_eq!(IrType::from_name("f"), Some(IrType::FLOAT));
+        assert_eq!(IrType::from_name("b"), Some(IrType::BOOL));
+        assert_eq!(
+            IrType::from_name("f3"),
+            Some(IrType::Vector {
+                scalar: IrScalarType::Float,
+                size: 3
+            })
+        );
+        assert_eq!(
+            IrType::from_name("u2"),
+            Some(IrType::Vector {
+                scalar: IrScalarType::UInt,
+                size: 2
+            })
+        );
+        assert_eq!(IrType::from_name("m4"), Some(IrType::Matrix { size: 4 }));
+        assert_eq!(
+            IrType::from_name("t2"),
+            Some(IrType::Texture(IrTextureKind::Texture2D))
+        );
+        assert_eq!(
+            IrType::from_name("tc"),
+            Some(IrType::Texture(IrTextureKind::TextureCube))
+        );
+        assert_eq!(IrType::from_name(""), None);
+        assert_eq!(IrType::from_name("m"), None);
+        assert_eq!(IrType::from_name("f5"), None);
+        assert_eq!(IrType::from_name("float"), None);
+    }
+
+    #[test]
+    fn test_ir_type_display_round_trip() {
+        for name in IrType::NAMES {
+            assert_eq!(IrType::from_name(name).unwrap().to_string(), name);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use super::{IrBlock, IrExpr, IrExprKind, IrStatement, IrStatementKind};
+
+impl IrExpr {
+    /// Calls `f` for each direct sub-expression, in evaluation order.
+    pub fn for_each_child(&self, mut f: impl FnMut(&IrExpr)) {
+        match &self.kind {
+            IrExprKind::Constant(_) | IrExprKind::Local(_) | IrExprKind::Resource(_) => {}
+            IrExprKind::Unary { rhs, .. } => f(rhs),
+            IrExprKind::Binary { lhs, rhs, .. } => {
+                f(lhs);
+                f(rhs);
+            }
+            IrExprKind::Call { args, .. }
+            | IrExprKind::Builtin { args, .. }
+            | IrExprKind::Construct { args } => {
+                for arg in args {
+                    f(arg);
+                }
+            }
+            IrExprKind::Swizzle { base, .. } | IrExprKind::Field { base, .. } => f(base),
+            IrExprKind::Index { base, index } => {
+                f(base);
+                f(index);
+            }
+        }
+    }
+
+    /// Calls `f` for each direct sub-expression, in evaluation order.
+    pub fn for_each_child_mut(&mut self, mut f: impl FnMut(&mut IrExpr)) {
+        match &mut self.kind {
+            IrExprKind::Constant(_) | IrExprKind::Local(_) | IrExprKind::Resource(_) => {}
+            IrExprKind::Unary { rhs, .. } => f(rhs),
+            IrExprKind::Binary { lhs, rhs, .. } => {
+                f(lhs);
+                f(rhs);
+            }
+            IrExprKind::Call { args, .. }
+            | IrExprKind::Builtin { args, .. }
+            | IrExprKind::Construct { args } => {
+                for arg in args {
+                    f(arg);
+                }
+            }
+            IrExprKind::Swizzle { base, .. } | IrExprKind::Field { base, .. } => f(base),
+            IrExprKind::Index { base, index } => {
+                f(base);
+                f(index);
+            }
+        }
+    }
+
+    /// Visits the expression and all of its sub-expressions, parents first.
+    pub fn visit(&self, f: &mut impl FnMut(&IrExpr)) {
+        f(self);
+        self.for_each_child(|child| child.visit(f));
+    }
+
+    /// Visits the expression and all of its sub-expressions mutably, children first.
+    pub fn visit_mut_post(&mut self, f: &mut impl FnMut(&mut IrExpr)) {
+        self.for_each_child_mut(|child| child.visit_mut_post(f));
+        f(self);
+    }
+}
+
+impl IrStatement {
+    /// Calls `f` for each top-level expression of the statement.
+    pub fn for_each_expr(&self, mut f: impl FnMut(&IrExpr)) {
+        match &self.kind {
+            IrStatementKind::Let { init, .. } => {
+                if let Some(init) = init {
+                    f(init);
+                }
+            }
+            IrStatementKind::Assign { target, value } => {
+                f(target);
+                f(value);
+            }
+            IrStatementKind::Expr(expr) => f(expr),
+            IrStatementKind::Return(expr) => {
+                if let Some(expr) = expr {
+                    f(expr);
+                }
+            }
+        }
+    }
+
+    /// Calls `f` for each top-level expression of the statement.
+    pub fn for_each_expr_mut(&mut self, mut f: impl FnMut(&mut IrExpr)) {
+        match &mut self.kind {
+            IrStatementKind::Let { init, .. } => {
+                if let Some(init) = init {
+                    f(init);
+                }
+            }
+            IrStatementKind::Assign { target, value } => {
+                f(target);
+                f(value);
+            }
+            IrStatementKind::Expr(expr) => f(expr),
+            IrStatementKind::Return(expr) => {
+                if let Some(expr) = expr {
+                    f(expr);
+                }
+            }
+        }
+    }
+}
+
+impl IrBlock {
+    /// Visits every expression of the block, parents first.
+    pub fn visit_exprs(&self, f: &mut impl FnMut(&IrExpr)) {
+        for statement in &self.statements {
+            statement.for_each_expr(|expr| expr.visit(f));
+        }
+    }
+
+    /// Visits every expression of the block mutably, children first.
+    pub fn visit_exprs_mut_post(&mut self, f: &mut impl FnMut(&mut IrExpr)) {
+        for statement in &mut self.statements {
+            statement.for_each_expr_mut(|expr| expr.visit_mut_post(f));
+        }
+    }
+}
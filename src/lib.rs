@@ -1,8 +1,20 @@
+pub mod archive;
+pub mod cache;
+pub mod compile;
+pub mod comptime;
 pub mod diagnostics;
+pub mod ir;
+pub mod lsp;
 pub mod parse;
+pub mod reflect;
 pub mod span;
 pub mod symbol;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 
+use compile::{compile, CompileOptions, Emit};
+use diagnostics::{stringify_item, stringify_item_html, Item, ItemLevel, ItemOrigin};
+use span::{ColUnit, SourceMap, Span};
 use wasm_bindgen::prelude::*;
 
 /// Represents a compilation result of a single shader pack.
@@ -10,6 +22,9 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 pub struct Compiled {
     errors: Vec<String>,
+    diagnostics: Vec<Diagnostic>,
+    emitted: Option<String>,
+    reflection: Option<String>,
 }
 
 #[wasm_bindgen]
@@ -18,10 +33,253 @@ impl Compiled {
     pub fn errors(&self) -> Vec<String> {
         self.errors.clone()
     }
+
+    /// Returns the items reported during compilation as structured objects.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.clone()
+    }
+
+    /// Returns the output requested by `emit`, if any.
+    pub fn emitted(&self) -> Option<String> {
+        self.emitted.clone()
+    }
+
+    /// Returns the reflection of the compiled passes as JSON; `None` if compilation failed.
+    pub fn reflection(&self) -> Option<String> {
+        self.reflection.clone()
+    }
 }
 
 /// Compiles a shader pack from source code.
+/// `emit` optionally requests an intermediate output, e.g. `"ir"`.
 #[wasm_bindgen]
-pub fn compile_shader_pack(source: &str) -> Compiled {
-    Compiled { errors: vec![] }
+pub fn compile_shader_pack(source: &str, emit: Option<String>) -> Compiled {
+    let emit = match emit.as_deref().map(str::parse::<Emit>) {
+        Some(Ok(emit)) => Some(emit),
+        Some(Err(err)) => {
+            let item = Item {
+                code: 0,
+                level: ItemLevel::Error,
+                message: err.clone(),
+                origin: None,
+                sub_items: vec![],
+                suggestions: vec![],
+            };
+            return Compiled {
+                errors: vec![err],
+                diagnostics: vec![to_diagnostic(&item)],
+                emitted: None,
+                reflection: None,
+            };
+        }
+        None => None,
+    };
+
+    let mut source_map = SourceMap::new();
+    let file = source_map.add_file(source, "input", None);
+    let options = CompileOptions {
+        emit: Vec::from_iter(emit),
+        ..Default::default()
+    };
+    let mut output = compile(file, &options);
+
+    Compiled {
+        errors: Vec::from_iter(output.items.iter().map(|item| stringify_item(item, false))),
+        diagnostics: Vec::from_iter(output.items.iter().map(to_diagnostic)),
+        emitted: output.emitted.pop().map(|(_, emitted)| emitted),
+        reflection: output.reflection.map(|reflection| reflection.to_json()),
+    }
+}
+
+/// An item reported by [`compile_shader_pack`].
+#[derive(Debug, Clone, Hash)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct Diagnostic {
+    /// The name of the code, e.g. `SPK2010`; `undefined` for helper items.
+    pub code: Option<String>,
+    /// One of `error`, `warning` and `hint`.
+    pub level: String,
+    pub message: String,
+    pub range: Option<DiagnosticRange>,
+    pub sub_items: Vec<DiagnosticSubItem>,
+    pub fixes: Vec<DiagnosticFix>,
+    /// The item rendered like on the terminal, as a `<pre>` element.
+    pub html: String,
+}
+
+#[derive(Debug, Clone, Hash)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct DiagnosticSubItem {
+    pub level: String,
+    pub message: String,
+    pub range: Option<DiagnosticRange>,
+}
+
+/// A change of the source which fixes the problem of a [`Diagnostic`].
+#[derive(Debug, Clone, Hash)]
+#[wasm_bindgen(getter_with_clone)]
+pub struct DiagnosticFix {
+    pub message: String,
+    /// The replaced range; empty to insert the replacement.
+    pub range: DiagnosticRange,
+    pub replacement: String,
+    /// One of `machine_applicable`, `maybe_incorrect` and `has_placeholders`.
+    pub applicability: String,
+}
+
+/// A range of the source, counted in UTF-16 code units like JavaScript strings; lines and columns
+/// are 0-based and the end is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[wasm_bindgen]
+pub struct DiagnosticRange {
+    /// The offset of the start from the start of the source.
+    pub start: u32,
+    pub end: u32,
+    pub start_line: u32,
+    pub start_col: u32,
+    pub end_line: u32,
+    pub end_col: u32,
+}
+
+fn to_diagnostic(item: &Item) -> Diagnostic {
+    Diagnostic {
+        code: match item.code {
+            0 => None,
+            code => Some(diagnostics::codes::code_name(code)),
+        },
+        level: item.level.name().to_owned(),
+        message: item.message.clone(),
+        range: item.origin.as_ref().map(to_range),
+        sub_items: Vec::from_iter(item.sub_items.iter().map(|sub_item| DiagnosticSubItem {
+            level: sub_item.level.name().to_owned(),
+            message: sub_item.message.clone(),
+            range: sub_item.origin.as_ref().map(to_range),
+        })),
+        fixes: Vec::from_iter(item.suggestions.iter().filter_map(|suggestion| {
+            let origin = item.origin.as_ref()?;
+            Some(DiagnosticFix {
+                message: suggestion.message.clone(),
+                range: to_range(&ItemOrigin {
+                    file: origin.file.clone(),
+                    span: suggestion.span,
+                }),
+                replacement: suggestion.replacement.clone(),
+                applicability: serde_json::to_value(suggestion.applicability)
+                    .unwrap()
+                    .as_str()
+                    .unwrap()
+                    .to_owned(),
+            })
+        })),
+        html: stringify_item_html(item),
+    }
+}
+
+fn to_range(origin: &ItemOrigin) -> DiagnosticRange {
+    let file = &origin.file;
+    let offset = |pos: u32| {
+        let pos = pos.clamp(file.span().low(), file.span().high());
+        file.slice(Span::new(file.span().low(), pos))
+            .encode_utf16()
+            .count() as u32
+    };
+    let start = file.to_line_col(origin.span.low(), ColUnit::Utf16);
+    let end = file.to_line_col(origin.span.high(), ColUnit::Utf16);
+
+    DiagnosticRange {
+        start: offset(origin.span.low()),
+        end: offset(origin.span.high()),
+        start_line: start.line,
+        start_col: start.col,
+        end_line: end.line,
+        end_col: end.col,
+    }
+}
+
+/// Returns the Markdown explanation of a diagnostic code, e.g. `"SPK2010"`; `None` if the code is
+/// unknown.
+#[wasm_bindgen]
+pub fn explain_diagnostic(code: &str) -> Option<String> {
+    diagnostics::codes::explain(code)
+}
+
+/// Classifies the tokens of a shader pack for syntax highlighting.
+///
+/// Returns five integers per token, encoded like LSP semantic tokens; the kind and the modifiers
+/// index into [`semantic_token_kinds`] and [`semantic_token_modifiers`].
+#[wasm_bindgen]
+pub fn classify_shader_pack(source: &str) -> Vec<u32> {
+    let mut source_map = SourceMap::new();
+    let file = source_map.add_file(source, "input", None);
+    let (analysis, _) = lsp::analyze(file.clone());
+    let tokens = lsp::classify(&file, analysis.as_ref());
+    lsp::encode_semantic_tokens(&file, &tokens)
+}
+
+/// Returns the names of the kinds returned by [`classify_shader_pack`], in order.
+#[wasm_bindgen]
+pub fn semantic_token_kinds() -> Vec<String> {
+    Vec::from_iter(
+        lsp::SemanticKind::ALL
+            .iter()
+            .map(|kind| kind.name().to_owned()),
+    )
+}
+
+/// Returns the names of the modifiers returned by [`classify_shader_pack`], one per bit.
+#[wasm_bindgen]
+pub fn semantic_token_modifiers() -> Vec<String> {
+    Vec::from_iter(
+        lsp::SemanticModifiers::NAMES
+            .iter()
+            .map(|name| (*name).to_owned()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use diagnostics::{Applicability, Suggestion};
+
+    #[test]
+    fn test_to_diagnostic() {
+        let mut map = SourceMap::new();
+        let file = map.add_file("in 😀: f;\nin 😀: g;\n", "input", None);
+        let item = Item {
+            code: diagnostics::codes::TYPE_ERR_UNKNOWN_TYPE,
+            level: ItemLevel::Error,
+            message: "unknown type `g`".into(),
+            origin: Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(21, 22),
+            }),
+            sub_items: vec![],
+            suggestions: vec![Suggestion::new(
+                "did you mean `f`?",
+                Span::new(21, 22),
+                "f",
+                Applicability::MaybeIncorrect,
+            )],
+        };
+
+        let diagnostic = to_diagnostic(&item);
+        assert_eq!(diagnostic.code.as_deref(), Some("SPK2010"));
+        assert_eq!(diagnostic.level, "error");
+        assert_eq!(
+            diagnostic.range,
+            Some(DiagnosticRange {
+                start: 17,
+                end: 18,
+                start_line: 1,
+                start_col: 7,
+                end_line: 1,
+                end_col: 8,
+            })
+        );
+        assert_eq!(diagnostic.fixes.len(), 1);
+        assert_eq!(diagnostic.fixes[0].applicability, "maybe_incorrect");
+        assert!(diagnostic
+            .html
+            .starts_with("<pre class=\"spk-diagnostic\">"));
+    }
 }
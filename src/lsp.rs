@@ -0,0 +1,18 @@
+mod analysis;
+mod document;
+mod position;
+mod protocol;
+mod semantic;
+mod server;
+mod transport;
+
+#[cfg(test)]
+mod test_utils;
+
+pub use analysis::*;
+pub use document::*;
+pub use position::*;
+pub use protocol::*;
+pub use semantic::*;
+pub use server::*;
+pub use transport::*;
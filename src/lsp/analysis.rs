@@ -0,0 +1,720 @@
+use crate::{
+    comptime::expand,
+    diagnostics::{Item, ItemCollector, ItemSender},
+    ir::{lower, IrBuiltin, IrModule, IrType},
+    parse::{
+        ast::{
+            AstAttribute, AstCompTimeIfPredicateExpr, AstCompTimeIfPredicateExprKind,
+            AstCompTimeIfPredicateExprSingleKind, AstCompTimeKind, AstDocComment, AstExpr,
+            AstExprKind, AstFnDef, AstIdentifier, AstIdentifierKind, AstInput, AstPass,
+            AstPassLevelKind, AstShaderPack, AstStatement, AstStatementKind, AstTopLevel,
+            AstTopLevelKind, AstTypeName,
+        },
+        IncrementalParse,
+    },
+    span::{SourceFile, Span},
+    symbol::Symbol,
+};
+use rustc_hash::FxHashSet;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DefinitionKind {
+    Input,
+    Function,
+    Pass,
+    Stage,
+    Parameter,
+    Local,
+}
+
+/// A named item of a document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Definition {
+    pub name: Symbol,
+    pub kind: DefinitionKind,
+    /// Span of the name.
+    pub span: Span,
+    /// Span of the whole item, e.g. the whole pass.
+    pub item_span: Span,
+    /// Type of inputs, parameters and locals, or the signature of functions; `None` if the type
+    /// of a local cannot be resolved.
+    pub detail: Option<String>,
+    /// Index of the enclosing pass, stage or function.
+    pub parent: Option<usize>,
+    /// Text of the doc comment of inputs, functions and passes, if any.
+    pub doc: Option<String>,
+}
+
+impl Definition {
+    /// Returns the declaration of the item as it would be written in the source.
+    ///
+    /// Example:
+    ///
+    /// - `in color: f3`
+    /// - `fn brighten(value: f3) -> f3`
+    /// - `let scaled: f3`
+    pub fn signature(&self) -> String {
+        let name = self.name.to_str();
+        let ty = match &self.detail {
+            Some(detail) => format!(": {}", detail),
+            None => String::new(),
+        };
+
+        match self.kind {
+            DefinitionKind::Input => format!("in {}{}", name, ty),
+            DefinitionKind::Function => match &self.detail {
+                Some(detail) => detail.clone(),
+                None => format!("fn {}", name),
+            },
+            DefinitionKind::Pass => format!("pass {}", name),
+            DefinitionKind::Stage => format!("stage {}", name),
+            DefinitionKind::Parameter => format!("{}{}", name, ty),
+            DefinitionKind::Local => format!("let {}{}", name, ty),
+        }
+    }
+}
+
+/// Index of every definition of a document and of every use of them.
+///
+/// Every branch of `comptime if` items is indexed, not only the ones enabled by some flags.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Analysis {
+    definitions: Vec<Definition>,
+    /// Span of the name and definition index of every resolved use.
+    references: Vec<(Span, usize)>,
+    /// Span of the callee of every call to a built-in.
+    builtins: Vec<(Span, IrBuiltin)>,
+    /// Span of every type name, including the callee of constructors, e.g. `f3(...)`.
+    types: Vec<Span>,
+    /// Span of the name of every attribute.
+    attributes: Vec<Span>,
+    /// Span of the string literal of every `comptime if` flag.
+    flags: Vec<Span>,
+}
+
+/// Parses the file and indexes it; the types of the locals are then resolved by lowering the pack
+/// with no comptime flags enabled.
+///
+/// Returns every diagnostics item reported on the way, and `None` if the file cannot be parsed.
+pub fn analyze(file: Arc<SourceFile>) -> (Option<Analysis>, Vec<Item>) {
+    analyze_parse(&IncrementalParse::new(file))
+}
+
+/// Indexes an already parsed file, like [`analyze`]; the diagnostics of the parse come first.
+pub fn analyze_parse(parse: &IncrementalParse) -> (Option<Analysis>, Vec<Item>) {
+    let collector = Arc::new(ItemCollector::new());
+    let reporter = ItemSender::new(parse.file().clone(), collector.clone());
+
+    let analysis = parse.pack().map(|pack| {
+        let mut analysis = Analysis::new(pack);
+
+        if let Some(module) =
+            expand(pack, &FxHashSet::default(), &reporter).and_then(|pack| lower(&pack, &reporter))
+        {
+            analysis.resolve_types(&module);
+        }
+
+        analysis
+    });
+    drop(reporter);
+
+    let mut items = Vec::from_iter(parse.diagnostics().cloned());
+    items.extend(collector.take());
+    (analysis, items)
+}
+
+impl Analysis {
+    pub fn new(pack: &AstShaderPack) -> Self {
+        let mut indexer = Indexer::default();
+        let mut items = Vec::new();
+        indexer.declare_top_levels(&pack.top_levels, &mut items);
+
+        for (kind, definition) in items {
+            match kind {
+                AstTopLevelKind::FnDef(fn_def) => indexer.index_fn_def(fn_def, definition),
+                AstTopLevelKind::Pass(pass) => indexer.index_pass(pass, definition),
+                _ => {}
+            }
+        }
+
+        indexer.analysis
+    }
+
+    /// Fills in the types of the locals declared without a type annotation.
+    pub fn resolve_types(&mut self, module: &IrModule) {
+        for local in module
+            .functions
+            .iter()
+            .flat_map(|function| &function.locals)
+        {
+            let definition = self.definitions.iter_mut().find(|definition| {
+                definition.kind == DefinitionKind::Local
+                    && definition.span == local.span
+                    && definition.name == local.name
+            });
+
+            if let Some(definition) = definition {
+                if definition.detail.is_none() {
+                    definition.detail = Some(local.ty.to_string());
+                }
+            }
+        }
+    }
+
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    pub fn definition(&self, index: usize) -> &Definition {
+        &self.definitions[index]
+    }
+
+    pub fn references(&self) -> &[(Span, usize)] {
+        &self.references
+    }
+
+    pub fn builtins(&self) -> &[(Span, IrBuiltin)] {
+        &self.builtins
+    }
+
+    pub fn types(&self) -> &[Span] {
+        &self.types
+    }
+
+    pub fn attributes(&self) -> &[Span] {
+        &self.attributes
+    }
+
+    pub fn flags(&self) -> &[Span] {
+        &self.flags
+    }
+
+    /// Returns the definition whose name, or a use of whose name, is at the given position.
+    /// A position right after a name is considered to be on the name.
+    pub fn definition_at(&self, pos: u32) -> Option<usize> {
+        self.definitions
+            .iter()
+            .enumerate()
+            .map(|(index, definition)| (definition.span, index))
+            .chain(self.references.iter().copied())
+            .find(|(span, _)| touches(*span, pos))
+            .map(|(_, index)| index)
+    }
+
+    /// Returns the built-in called at the given position, along with the span of its name.
+    pub fn builtin_at(&self, pos: u32) -> Option<(Span, IrBuiltin)> {
+        self.builtins
+            .iter()
+            .copied()
+            .find(|(span, _)| touches(*span, pos))
+    }
+
+    /// Returns the span of the name of the definition and of every use of it, in order.
+    pub fn occurrences(&self, index: usize) -> Vec<Span> {
+        let mut spans = Vec::from_iter(
+            self.references
+                .iter()
+                .filter(|(_, definition)| *definition == index)
+                .map(|(span, _)| *span),
+        );
+        spans.push(self.definitions[index].span);
+        spans.sort();
+        spans.dedup();
+        spans
+    }
+
+    /// Returns the definitions that can be referenced at the given position: top-level inputs
+    /// and functions, the inputs and parameters of the enclosing items, and the locals whose
+    /// declaration ends before the position.
+    pub fn visible_at(&self, pos: u32) -> impl Iterator<Item = &Definition> {
+        self.definitions
+            .iter()
+            .filter(move |definition| match definition.kind {
+                DefinitionKind::Input | DefinitionKind::Function if definition.parent.is_none() => {
+                    true
+                }
+                DefinitionKind::Input | DefinitionKind::Parameter | DefinitionKind::Local => {
+                    let declared = definition.kind != DefinitionKind::Local
+                        || definition.item_span.high() <= pos;
+                    declared
+                        && definition
+                            .parent
+                            .is_some_and(|parent| self.definitions[parent].item_span.contains(pos))
+                }
+                _ => false,
+            })
+    }
+}
+
+fn touches(span: Span, pos: u32) -> bool {
+    span.low() <= pos && pos <= span.high()
+}
+
+#[derive(Default)]
+struct Indexer {
+    analysis: Analysis,
+    /// Variables in scope, innermost last.
+    scopes: Vec<(Symbol, usize)>,
+    functions: Vec<(Symbol, usize)>,
+}
+
+impl Indexer {
+    fn define(
+        &mut self,
+        ident: &AstIdentifier,
+        kind: DefinitionKind,
+        item_span: Span,
+        detail: Option<String>,
+        parent: Option<usize>,
+    ) -> Option<usize> {
+        // composed identifiers are only named once expanded
+        let name = match &ident.kind {
+            AstIdentifierKind::Symbol(symbol) => *symbol,
+            _ => return None,
+        };
+
+        self.analysis.definitions.push(Definition {
+            name,
+            kind,
+            span: ident.span,
+            item_span,
+            detail,
+            parent,
+            doc: None,
+        });
+        Some(self.analysis.definitions.len() - 1)
+    }
+
+    fn document(&mut self, definition: Option<usize>, doc: &Option<AstDocComment>) {
+        if let (Some(definition), Some(doc)) = (definition, doc) {
+            self.analysis.definitions[definition].doc = Some(doc.text());
+        }
+    }
+
+    fn declare_top_levels<'a>(
+        &mut self,
+        top_levels: &'a [AstTopLevel],
+        items: &mut Vec<(&'a AstTopLevelKind, Option<usize>)>,
+    ) {
+        for top_level in top_levels {
+            let definition = match &top_level.kind {
+                AstTopLevelKind::CompTime(comptime) => {
+                    match &comptime.kind {
+                        AstCompTimeKind::Invalid => {}
+                        AstCompTimeKind::If(comptime_if) => {
+                            self.index_predicate(&comptime_if.if_part.predicate);
+                            self.declare_top_levels(&comptime_if.if_part.block.items, items);
+
+                            for part in &comptime_if.else_if_parts {
+                                self.index_predicate(&part.predicate);
+                                self.declare_top_levels(&part.block.items, items);
+                            }
+
+                            if let Some(part) = &comptime_if.else_part {
+                                self.declare_top_levels(&part.block.items, items);
+                            }
+                        }
+                        AstCompTimeKind::Loop(comptime_loop) => {
+                            self.declare_top_levels(&comptime_loop.block.items, items);
+                        }
+                    }
+                    continue;
+                }
+                AstTopLevelKind::FnDef(fn_def) => {
+                    let definition = self.define(
+                        &fn_def.ident,
+                        DefinitionKind::Function,
+                        fn_def.span,
+                        Some(signature(fn_def)),
+                        None,
+                    );
+                    self.document(definition, &fn_def.doc);
+
+                    if let (Some(definition), AstIdentifierKind::Symbol(name)) =
+                        (definition, &fn_def.ident.kind)
+                    {
+                        self.functions.push((*name, definition));
+                    }
+
+                    definition
+                }
+                AstTopLevelKind::Import(_) => continue,
+                AstTopLevelKind::Input(input) => self.declare_input(input, None),
+                AstTopLevelKind::Pass(pass) => {
+                    let definition =
+                        self.define(&pass.ident, DefinitionKind::Pass, pass.span, None, None);
+                    self.document(definition, &pass.doc);
+                    definition
+                }
+            };
+
+            items.push((&top_level.kind, definition));
+        }
+    }
+
+    fn declare_input(&mut self, input: &AstInput, parent: Option<usize>) -> Option<usize> {
+        self.index_attributes(&input.attributes);
+        self.index_type_name(&input.type_name);
+        let definition = self.define(
+            &input.ident,
+            DefinitionKind::Input,
+            input.span,
+            type_name(&input.type_name),
+            parent,
+        );
+        self.document(definition, &input.doc);
+
+        if let (Some(definition), AstIdentifierKind::Symbol(name)) = (definition, &input.ident.kind)
+        {
+            self.scopes.push((*name, definition));
+        }
+
+        definition
+    }
+
+    fn index_type_name(&mut self, type_name: &AstTypeName) {
+        if let AstIdentifierKind::Symbol(_) = &type_name.ident.kind {
+            self.analysis.types.push(type_name.ident.span);
+        }
+    }
+
+    fn index_attributes(&mut self, attributes: &[AstAttribute]) {
+        for item in attributes.iter().flat_map(|attribute| &attribute.items) {
+            self.analysis.attributes.push(item.ident.span);
+        }
+    }
+
+    fn index_predicate(&mut self, predicate: &AstCompTimeIfPredicateExpr) {
+        match &predicate.kind {
+            AstCompTimeIfPredicateExprKind::Invalid => {}
+            AstCompTimeIfPredicateExprKind::Single(single) => match &single.kind {
+                AstCompTimeIfPredicateExprSingleKind::Invalid => {}
+                AstCompTimeIfPredicateExprSingleKind::Flag(flag) => {
+                    self.analysis.flags.push(flag.flag.span);
+                }
+                AstCompTimeIfPredicateExprSingleKind::Paren(paren) => {
+                    self.index_predicate(&paren.expr)
+                }
+                AstCompTimeIfPredicateExprSingleKind::Not(not) => self.index_predicate(&not.expr),
+            },
+            AstCompTimeIfPredicateExprKind::And(and) => {
+                self.index_predicate(&and.lhs);
+                self.index_predicate(&and.rhs);
+            }
+            AstCompTimeIfPredicateExprKind::Or(or) => {
+                self.index_predicate(&or.lhs);
+                self.index_predicate(&or.rhs);
+            }
+        }
+    }
+
+    fn index_fn_def(&mut self, fn_def: &AstFnDef, definition: Option<usize>) {
+        let scope = self.scopes.len();
+        self.index_attributes(&fn_def.attributes);
+
+        for param in &fn_def.params {
+            self.index_attributes(&param.attributes);
+            self.index_type_name(&param.type_name);
+            let param_definition = self.define(
+                &param.ident,
+                DefinitionKind::Parameter,
+                param.span,
+                type_name(&param.type_name),
+                definition,
+            );
+
+            if let (Some(param_definition), AstIdentifierKind::Symbol(name)) =
+                (param_definition, &param.ident.kind)
+            {
+                self.scopes.push((*name, param_definition));
+            }
+        }
+
+        if let Some(return_type) = &fn_def.return_type {
+            self.index_type_name(&return_type.type_name);
+        }
+
+        self.index_statements(&fn_def.statements, definition);
+        self.scopes.truncate(scope);
+    }
+
+    fn index_pass(&mut self, pass: &AstPass, definition: Option<usize>) {
+        let scope = self.scopes.len();
+        self.index_attributes(&pass.attributes);
+
+        // pass inputs are visible from every stage, regardless of their order
+        for pass_level in &pass.pass_levels {
+            if let AstPassLevelKind::Input(input) = &pass_level.kind {
+                self.declare_input(input, definition);
+            }
+        }
+
+        for pass_level in &pass.pass_levels {
+            if let AstPassLevelKind::Stage(stage) = &pass_level.kind {
+                let stage_scope = self.scopes.len();
+                self.index_attributes(&stage.attributes);
+                let stage_definition = self.define(
+                    &stage.stage,
+                    DefinitionKind::Stage,
+                    stage.span,
+                    None,
+                    definition,
+                );
+                self.index_statements(&stage.statements, stage_definition);
+                self.scopes.truncate(stage_scope);
+            }
+        }
+
+        self.scopes.truncate(scope);
+    }
+
+    fn index_statements(&mut self, statements: &[AstStatement], parent: Option<usize>) {
+        for statement in statements {
+            match &statement.kind {
+                AstStatementKind::VarDecl(var_decl) => {
+                    if let Some(type_name) = &var_decl.type_name {
+                        self.index_type_name(&type_name.type_name);
+                    }
+
+                    if let Some(assignment) = &var_decl.assignment {
+                        self.index_expr(&assignment.rhs);
+                    }
+
+                    let definition = self.define(
+                        &var_decl.ident,
+                        DefinitionKind::Local,
+                        statement.span,
+                        var_decl
+                            .type_name
+                            .as_ref()
+                            .and_then(|type_name| self::type_name(&type_name.type_name)),
+                        parent,
+                    );
+
+                    if let (Some(definition), AstIdentifierKind::Symbol(name)) =
+                        (definition, &var_decl.ident.kind)
+                    {
+                        self.scopes.push((*name, definition));
+                    }
+                }
+                AstStatementKind::Assignment(assignment) => {
+                    self.index_expr(&assignment.lhs);
+                    self.index_expr(&assignment.rhs);
+                }
+                AstStatementKind::Return(statement) => {
+                    if let Some(expr) = &statement.expr {
+                        self.index_expr(expr);
+                    }
+                }
+                AstStatementKind::Expr(expr) => self.index_expr(expr),
+            }
+        }
+    }
+
+    fn index_expr(&mut self, expr: &AstExpr) {
+        match &expr.kind {
+            AstExprKind::Invalid | AstExprKind::Literal(_) => {}
+            AstExprKind::Binary(binary) => {
+                self.index_expr(&binary.lhs);
+                self.index_expr(&binary.rhs);
+            }
+            AstExprKind::Unary(unary) => self.index_expr(&unary.rhs),
+            AstExprKind::Identifier(ident) => {
+                if let AstIdentifierKind::Symbol(name) = &ident.kind {
+                    let definition = self
+                        .scopes
+                        .iter()
+                        .rev()
+                        .find(|(scope_name, _)| scope_name == name);
+
+                    if let Some((_, definition)) = definition {
+                        self.analysis.references.push((ident.span, *definition));
+                    }
+                }
+            }
+            AstExprKind::Call(call) => {
+                // functions of imported modules are defined in other files
+                if let (None, AstIdentifierKind::Symbol(name)) =
+                    (&call.qualifier, &call.callee.kind)
+                {
+                    let function = self
+                        .functions
+                        .iter()
+                        .find(|(function_name, _)| function_name == name);
+
+                    match function {
+                        Some((_, definition)) => {
+                            self.analysis
+                                .references
+                                .push((call.callee.span, *definition));
+                        }
+                        // constructors take precedence over built-ins, like in lowering
+                        None if IrType::from_name(name.to_str()).is_some() => {
+                            self.analysis.types.push(call.callee.span);
+                        }
+                        None => {
+                            if let Some(builtin) = IrBuiltin::from_name(name.to_str()) {
+                                self.analysis.builtins.push((call.callee.span, builtin));
+                            }
+                        }
+                    }
+                }
+
+                for arg in &call.args {
+                    self.index_expr(&arg.expr);
+                }
+            }
+            // members are fields or swizzles, which are not definitions
+            AstExprKind::Member(member) => self.index_expr(&member.lhs),
+            AstExprKind::Index(index) => {
+                self.index_expr(&index.lhs);
+                self.index_expr(&index.index);
+            }
+            AstExprKind::Object(object) => {
+                for field in &object.fields {
+                    self.index_expr(&field.expr);
+                }
+            }
+        }
+    }
+}
+
+fn type_name(type_name: &AstTypeName) -> Option<String> {
+    match &type_name.ident.kind {
+        AstIdentifierKind::Symbol(symbol) => Some(symbol.to_str().to_owned()),
+        _ => None,
+    }
+}
+
+fn signature(fn_def: &AstFnDef) -> String {
+    let name = match &fn_def.ident.kind {
+        AstIdentifierKind::Symbol(symbol) => symbol.to_str(),
+        _ => "?",
+    };
+    let params = Vec::from_iter(fn_def.params.iter().map(|param| {
+        let name = match &param.ident.kind {
+            AstIdentifierKind::Symbol(symbol) => symbol.to_str(),
+            _ => "?",
+        };
+        format!(
+            "{}: {}",
+            name,
+            type_name(&param.type_name).unwrap_or_else(|| "?".to_owned())
+        )
+    }));
+
+    match fn_def
+        .return_type
+        .as_ref()
+        .and_then(|return_type| type_name(&return_type.type_name))
+    {
+        Some(return_type) => format!("fn {}({}) -> {}", name, params.join(", "), return_type),
+        None => format!("fn {}({})", name, params.join(", ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lsp::test_utils::{pack, SOURCE},
+        span::SourceMap,
+    };
+
+    fn pos_of(name: &str, nth: usize) -> u32 {
+        crate::test_utils::ident_at(SOURCE, name, nth).span.low()
+    }
+
+    #[test]
+    fn test_analysis_definitions() {
+        let analysis = Analysis::new(&pack());
+        let definitions = Vec::from_iter(analysis.definitions().iter().map(|definition| {
+            (
+                definition.name.to_str().to_owned(),
+                definition.kind,
+                definition.parent,
+            )
+        }));
+
+        assert_eq!(
+            definitions,
+            [
+                ("color".to_owned(), DefinitionKind::Input, None),
+                ("brighten".to_owned(), DefinitionKind::Function, None),
+                ("main".to_owned(), DefinitionKind::Pass, None),
+                ("value".to_owned(), DefinitionKind::Parameter, Some(1)),
+                ("scaled".to_owned(), DefinitionKind::Local, Some(1)),
+                ("tint".to_owned(), DefinitionKind::Input, Some(2)),
+                ("fragment".to_owned(), DefinitionKind::Stage, Some(2)),
+                ("base".to_owned(), DefinitionKind::Local, Some(6)),
+            ]
+        );
+        assert_eq!(
+            analysis.definition(1).signature(),
+            "fn brighten(value: f3) -> f3"
+        );
+    }
+
+    #[test]
+    fn test_analysis_references() {
+        let analysis = Analysis::new(&pack());
+
+        assert_eq!(analysis.definition_at(pos_of("color", 2)), Some(0));
+        assert_eq!(analysis.definition_at(pos_of("brighten", 1)), Some(1));
+        assert_eq!(analysis.definition_at(pos_of("tint", 1) + 4), Some(5));
+        assert_eq!(analysis.definition_at(pos_of("abs", 0)), None);
+        assert_eq!(
+            analysis.builtin_at(pos_of("abs", 0)),
+            Some((
+                Span::new(pos_of("abs", 0), pos_of("abs", 0) + 3),
+                IrBuiltin::Abs
+            ))
+        );
+        assert_eq!(
+            analysis.occurrences(0),
+            [
+                Span::new(pos_of("color", 0), pos_of("color", 0) + 5),
+                Span::new(pos_of("color", 1), pos_of("color", 1) + 5),
+                Span::new(pos_of("color", 2), pos_of("color", 2) + 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_analysis_resolve_types() {
+        let mut pack = pack();
+        let mut analysis = Analysis::new(&pack);
+        // the pass has no vertex stage, so that only the function can be lowered
+        pack.top_levels.truncate(2);
+
+        let file = SourceMap::new().add_file(SOURCE, "test", None);
+        let module = lower(&pack, &ItemSender::new(file, ItemCollector::new())).unwrap();
+        analysis.resolve_types(&module);
+
+        assert_eq!(analysis.definition(4).signature(), "let scaled: f3");
+        assert_eq!(analysis.definition(7).signature(), "let base");
+    }
+
+    #[test]
+    fn test_analysis_visible_at() {
+        let analysis = Analysis::new(&pack());
+        let names = |pos| {
+            Vec::from_iter(
+                analysis
+                    .visible_at(pos)
+                    .map(|definition| definition.name.to_str().to_owned()),
+            )
+        };
+
+        assert_eq!(
+            names(pos_of("scaled", 1)),
+            ["color", "brighten", "value", "scaled"]
+        );
+        assert_eq!(names(pos_of("tint", 1)), ["color", "brighten", "tint"]);
+        assert_eq!(
+            names(pos_of("base", 1)),
+            ["color", "brighten", "tint", "base"]
+        );
+    }
+}
@@ -0,0 +1,636 @@
+use super::{
+    analyze_parse, classify, encode_semantic_tokens, to_pos, to_range, Analysis, CompletionItem,
+    DefinitionKind, Diagnostic, DiagnosticRelatedInformation, DocumentSymbol, Hover, Location,
+    MarkupContent, Position, Range, TextDocumentContentChangeEvent, TextEdit, WorkspaceEdit,
+    COMPLETION_KIND_FUNCTION, COMPLETION_KIND_KEYWORD, COMPLETION_KIND_PROPERTY,
+    COMPLETION_KIND_TYPE_PARAMETER, COMPLETION_KIND_VARIABLE, SYMBOL_KIND_CLASS,
+    SYMBOL_KIND_FUNCTION, SYMBOL_KIND_METHOD, SYMBOL_KIND_VARIABLE,
+};
+use crate::{
+    diagnostics::{codes::code_name, Item, ItemLevel, ItemOrigin},
+    ir::{IrBuiltin, IrType},
+    parse::{
+        low_lexer::{is_id_continue, is_id_start},
+        IncrementalParse,
+    },
+    span::{SourceEdit, SourceFile, SourceMap, Span},
+};
+use rustc_hash::FxHashMap;
+use std::{fmt::Write, path::Path, sync::Arc};
+
+/// Words with a meaning of their own in the language.
+pub const KEYWORDS: [&str; 15] = [
+    "in", "fn", "pass", "let", "return", "comptime", "if", "else", "loop", "times", "and", "or",
+    "not", "true", "false",
+];
+
+/// Attributes understood by the compiler, along with the items they apply to.
+pub const ATTRIBUTES: [(&str, &str); 2] =
+    [("vertex", "input attribute"), ("mode", "pass attribute")];
+
+/// An open document; it is parsed again incrementally when it changes, then analyzed again.
+#[derive(Debug, Clone)]
+pub struct Document {
+    uri: String,
+    version: Option<i64>,
+    parse: IncrementalParse,
+    /// The file of `parse`, which the analysis is about.
+    file: Arc<SourceFile>,
+    analysis: Option<Analysis>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Document {
+    pub fn new(uri: impl Into<String>, text: impl Into<String>, version: Option<i64>) -> Self {
+        let uri = uri.into();
+        let file = SourceMap::new().add_file(text, uri.clone(), None);
+        let mut document = Self {
+            uri,
+            version,
+            parse: IncrementalParse::new(file.clone()),
+            file,
+            analysis: None,
+            diagnostics: vec![],
+        };
+        document.analyze();
+        document
+    }
+
+    /// Applies the changes in order; each range is in the document as left by the previous
+    /// changes.
+    pub fn edit(&mut self, changes: &[TextDocumentContentChangeEvent], version: Option<i64>) {
+        for change in changes {
+            let file = self.parse.file();
+            let span = match change.range {
+                Some(range) => Span::new(to_pos(file, range.start), to_pos(file, range.end)),
+                None => file.span(),
+            };
+            self.parse.edit(&SourceEdit::new(span, change.text.clone()));
+        }
+
+        self.version = version;
+        self.file = self.parse.file().clone();
+        self.analyze();
+    }
+
+    fn analyze(&mut self) {
+        let (analysis, items) = analyze_parse(&self.parse);
+        self.analysis = analysis;
+        self.diagnostics = Vec::from_iter(items.iter().map(|item| to_diagnostic(&self.uri, item)));
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn version(&self) -> Option<i64> {
+        self.version
+    }
+
+    pub fn file(&self) -> &Arc<SourceFile> {
+        &self.file
+    }
+
+    /// Returns the index of the document; `None` if it cannot be parsed.
+    pub fn analysis(&self) -> Option<&Analysis> {
+        self.analysis.as_ref()
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Returns the semantic tokens of the whole document, encoded for LSP.
+    pub fn semantic_tokens(&self) -> Vec<u32> {
+        let tokens = classify(&self.file, self.analysis.as_ref());
+        encode_semantic_tokens(&self.file, &tokens)
+    }
+
+    /// Shows the signature of the definition at the position, including its resolved type, then
+    /// its doc comment.
+    pub fn hover(&self, position: Position) -> Option<Hover> {
+        let analysis = self.analysis.as_ref()?;
+        let pos = to_pos(&self.file, position);
+
+        let (span, value) = match analysis.definition_at(pos) {
+            Some(index) => {
+                let definition = analysis.definition(index);
+                let span = analysis
+                    .occurrences(index)
+                    .into_iter()
+                    .find(|span| span.low() <= pos && pos <= span.high())
+                    .unwrap_or(definition.span);
+                let value = match &definition.doc {
+                    Some(doc) => format!("```spk\n{}\n```\n{}", definition.signature(), doc),
+                    None => format!("```spk\n{}\n```", definition.signature()),
+                };
+                (span, value)
+            }
+            None => {
+                let (span, builtin) = analysis.builtin_at(pos)?;
+                (
+                    span,
+                    format!("```spk\nfn {}\n```\nbuilt-in function", builtin.name()),
+                )
+            }
+        };
+
+        Some(Hover {
+            contents: MarkupContent {
+                kind: "markdown".to_owned(),
+                value,
+            },
+            range: to_range(&self.file, span),
+        })
+    }
+
+    pub fn definition(&self, position: Position) -> Option<Location> {
+        let analysis = self.analysis.as_ref()?;
+        let index = analysis.definition_at(to_pos(&self.file, position))?;
+
+        Some(Location {
+            uri: self.uri.clone(),
+            range: to_range(&self.file, analysis.definition(index).span),
+        })
+    }
+
+    /// Returns the passes, their stages and inputs, the functions, and the top-level inputs.
+    pub fn document_symbols(&self) -> Vec<DocumentSymbol> {
+        let analysis = match &self.analysis {
+            Some(analysis) => analysis,
+            None => return vec![],
+        };
+        let mut symbols = Vec::new();
+        let mut children = FxHashMap::<usize, Vec<DocumentSymbol>>::default();
+
+        // children always come after their parent
+        for (index, definition) in analysis.definitions().iter().enumerate().rev() {
+            let kind = match definition.kind {
+                DefinitionKind::Input => SYMBOL_KIND_VARIABLE,
+                DefinitionKind::Function => SYMBOL_KIND_FUNCTION,
+                DefinitionKind::Pass => SYMBOL_KIND_CLASS,
+                DefinitionKind::Stage => SYMBOL_KIND_METHOD,
+                DefinitionKind::Parameter | DefinitionKind::Local => continue,
+            };
+            let mut own_children = children.remove(&index).unwrap_or_default();
+            own_children.reverse();
+
+            let symbol = DocumentSymbol {
+                name: definition.name.to_str().to_owned(),
+                detail: definition.detail.clone(),
+                kind,
+                range: to_range(&self.file, definition.item_span),
+                selection_range: to_range(&self.file, definition.span),
+                children: own_children,
+            };
+
+            match definition.parent {
+                Some(parent) => children.entry(parent).or_default().push(symbol),
+                None => symbols.push(symbol),
+            }
+        }
+
+        symbols.reverse();
+        symbols
+    }
+
+    /// Returns attributes right after a `@`, and keywords, type names, built-ins and the visible
+    /// definitions otherwise.
+    pub fn completion(&self, position: Position) -> Vec<CompletionItem> {
+        let pos = to_pos(&self.file, position);
+        let before = &self.file.content()[..(pos - self.file.span().low()) as usize];
+        let before = before.trim_end_matches(is_id_continue);
+
+        if before.ends_with('@') {
+            return attribute_completions();
+        }
+
+        let mut items = static_completions();
+
+        if let Some(analysis) = &self.analysis {
+            items.extend(analysis.visible_at(pos).map(|definition| CompletionItem {
+                label: definition.name.to_str().to_owned(),
+                kind: match definition.kind {
+                    DefinitionKind::Function => COMPLETION_KIND_FUNCTION,
+                    _ => COMPLETION_KIND_VARIABLE,
+                },
+                detail: Some(definition.signature()),
+            }));
+        }
+
+        items
+    }
+
+    /// Renames the definition at the position, along with every use of it.
+    pub fn rename(&self, position: Position, new_name: &str) -> Result<WorkspaceEdit, String> {
+        if !is_identifier(new_name) || KEYWORDS.contains(&new_name) {
+            return Err(format!("`{}` is not a valid name", new_name));
+        }
+
+        let index = self
+            .analysis
+            .as_ref()
+            .and_then(|analysis| analysis.definition_at(to_pos(&self.file, position)));
+        let (analysis, index) = match (&self.analysis, index) {
+            (Some(analysis), Some(index)) => (analysis, index),
+            _ => return Err("there is nothing to rename here".to_owned()),
+        };
+
+        if analysis.definition(index).kind == DefinitionKind::Stage {
+            return Err("stages are named after their kind and cannot be renamed".to_owned());
+        }
+
+        let edits = Vec::from_iter(
+            analysis
+                .occurrences(index)
+                .into_iter()
+                .map(|span| TextEdit {
+                    range: to_range(&self.file, span),
+                    new_text: new_name.to_owned(),
+                }),
+        );
+        let mut changes = FxHashMap::default();
+        changes.insert(self.uri.clone(), edits);
+        Ok(WorkspaceEdit { changes })
+    }
+}
+
+/// Returns the completions that do not depend on a document.
+pub fn static_completions() -> Vec<CompletionItem> {
+    let keywords = KEYWORDS.iter().map(|keyword| CompletionItem {
+        label: keyword.to_string(),
+        kind: COMPLETION_KIND_KEYWORD,
+        detail: None,
+    });
+    let types = IrType::NAMES.iter().map(|name| CompletionItem {
+        label: name.to_string(),
+        kind: COMPLETION_KIND_TYPE_PARAMETER,
+        detail: Some("type".to_owned()),
+    });
+    let builtins = IrBuiltin::ALL.iter().map(|builtin| CompletionItem {
+        label: builtin.name().to_owned(),
+        kind: COMPLETION_KIND_FUNCTION,
+        detail: Some("built-in function".to_owned()),
+    });
+
+    Vec::from_iter(keywords.chain(types).chain(builtins))
+}
+
+fn attribute_completions() -> Vec<CompletionItem> {
+    Vec::from_iter(ATTRIBUTES.iter().map(|(name, detail)| CompletionItem {
+        label: name.to_string(),
+        kind: COMPLETION_KIND_PROPERTY,
+        detail: Some(detail.to_string()),
+    }))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(is_id_start) && chars.all(is_id_continue)
+}
+
+/// Converts a diagnostics item of the document at `uri`; sub-items with an origin become related
+/// information, the other ones are appended to the message.
+pub fn to_diagnostic(uri: &str, item: &Item) -> Diagnostic {
+    let range = |origin: &Option<ItemOrigin>| match origin {
+        Some(origin) => to_range(&origin.file, origin.span),
+        None => Range::default(),
+    };
+    let mut message = item.message.clone();
+    let mut related_information = Vec::new();
+
+    for sub_item in &item.sub_items {
+        match &sub_item.origin {
+            Some(origin) => related_information.push(DiagnosticRelatedInformation {
+                location: Location {
+                    // files without a path are open documents, e.g. this one
+                    uri: origin
+                        .file
+                        .path()
+                        .map_or_else(|| uri.to_owned(), path_to_uri),
+                    range: range(&sub_item.origin),
+                },
+                message: sub_item.message.clone(),
+            }),
+            None => {
+                message.push_str(&format!(
+                    "\n{}: {}",
+                    sub_item.level.name(),
+                    sub_item.message
+                ));
+            }
+        }
+    }
+
+    Diagnostic {
+        range: range(&item.origin),
+        severity: match item.level {
+            ItemLevel::Error => 1,
+            ItemLevel::Warning => 2,
+            ItemLevel::Hint => 4,
+        },
+        code: if item.code == 0 {
+            None
+        } else {
+            Some(code_name(item.code))
+        },
+        source: "spk".to_owned(),
+        message,
+        related_information,
+    }
+}
+
+/// Converts a path into a `file` URI, percent-encoding the characters not allowed in URI paths.
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = match path.starts_with('/') {
+        true => "file://".to_owned(),
+        // Windows paths start with a drive letter
+        false => "file:///".to_owned(),
+    };
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => write!(uri, "%{:02X}", byte).unwrap(),
+        }
+    }
+
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::SubItem,
+        lsp::{
+            test_utils::{pack, SOURCE},
+            to_position,
+        },
+        parse::ast::{AstDocComment, AstTopLevelKind},
+        span::Span,
+        symbol::Symbol,
+    };
+
+    const URI: &str = "file:///test.spk";
+
+    /// Opens [`SOURCE`] without parsing it; the parse is of an empty file.
+    fn document() -> Document {
+        Document {
+            uri: URI.to_owned(),
+            version: Some(1),
+            parse: IncrementalParse::new(SourceMap::new().add_file("", URI, None)),
+            file: SourceMap::new().add_file(SOURCE, URI, None),
+            analysis: Some(Analysis::new(&pack())),
+            diagnostics: vec![],
+        }
+    }
+
+    fn position_of(needle: &str) -> Position {
+        let document = document();
+        to_position(&document.file, SOURCE.find(needle).unwrap() as u32)
+    }
+
+    fn labels(items: &[CompletionItem]) -> Vec<&str> {
+        Vec::from_iter(items.iter().map(|item| item.label.as_str()))
+    }
+
+    #[test]
+    fn test_document_hover() {
+        let document = document();
+        let hover = document.hover(position_of("value * value")).unwrap();
+        assert_eq!(hover.contents.value, "```spk\nvalue: f3\n```");
+        assert_eq!(
+            hover.range,
+            Range {
+                start: Position {
+                    line: 3,
+                    character: 17
+                },
+                end: Position {
+                    line: 3,
+                    character: 22
+                },
+            }
+        );
+
+        let hover = document.hover(position_of("brighten(tint)")).unwrap();
+        assert_eq!(
+            hover.contents.value,
+            "```spk\nfn brighten(value: f3) -> f3\n```"
+        );
+
+        let hover = document.hover(position_of("abs(base)")).unwrap();
+        assert_eq!(
+            hover.contents.value,
+            "```spk\nfn abs\n```\nbuilt-in function"
+        );
+
+        assert_eq!(document.hover(position_of("* value")), None);
+    }
+
+    #[test]
+    fn test_document_hover_doc() {
+        let doc = |lines: &[&str]| AstDocComment {
+            span: Span::ZERO,
+            lines: Vec::from_iter(lines.iter().map(Symbol::from_str)),
+        };
+        let mut pack = pack();
+
+        for top_level in &mut pack.top_levels {
+            match &mut top_level.kind {
+                AstTopLevelKind::Input(input) => {
+                    input.doc = Some(doc(&[" Tint of the surface.", " Linear RGB."]))
+                }
+                AstTopLevelKind::FnDef(fn_def) => fn_def.doc = Some(doc(&[" Squares."])),
+                _ => {}
+            }
+        }
+
+        let document = Document {
+            analysis: Some(Analysis::new(&pack)),
+            ..document()
+        };
+        let hover = document.hover(position_of("color;\n}")).unwrap();
+        assert_eq!(
+            hover.contents.value,
+            "```spk\nin color: f3\n```\nTint of the surface.\nLinear RGB."
+        );
+
+        let hover = document.hover(position_of("brighten(tint)")).unwrap();
+        assert_eq!(
+            hover.contents.value,
+            "```spk\nfn brighten(value: f3) -> f3\n```\nSquares."
+        );
+
+        let hover = document.hover(position_of("tint)")).unwrap();
+        assert_eq!(hover.contents.value, "```spk\nin tint: f3\n```");
+    }
+
+    #[test]
+    fn test_document_definition() {
+        let document = document();
+        assert_eq!(
+            document.definition(position_of("tint);")),
+            Some(Location {
+                uri: URI.to_owned(),
+                range: Range {
+                    start: Position {
+                        line: 8,
+                        character: 7
+                    },
+                    end: Position {
+                        line: 8,
+                        character: 11
+                    },
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let symbols = document().document_symbols();
+        let names = |symbols: &[DocumentSymbol]| {
+            Vec::from_iter(
+                symbols
+                    .iter()
+                    .map(|symbol| (symbol.name.clone(), symbol.kind)),
+            )
+        };
+
+        assert_eq!(
+            names(&symbols),
+            [
+                ("color".to_owned(), SYMBOL_KIND_VARIABLE),
+                ("brighten".to_owned(), SYMBOL_KIND_FUNCTION),
+                ("main".to_owned(), SYMBOL_KIND_CLASS),
+            ]
+        );
+        assert_eq!(
+            names(&symbols[2].children),
+            [
+                ("tint".to_owned(), SYMBOL_KIND_VARIABLE),
+                ("fragment".to_owned(), SYMBOL_KIND_METHOD),
+            ]
+        );
+        assert_eq!(symbols[2].range.start.line, 7);
+        assert_eq!(symbols[2].range.end.line, 14);
+    }
+
+    #[test]
+    fn test_document_completion() {
+        let document = document();
+        let items = document.completion(position_of("return abs"));
+        let labels = labels(&items);
+        assert!(labels.contains(&"pass"));
+        assert!(labels.contains(&"f3"));
+        assert!(labels.contains(&"sample"));
+        assert_eq!(
+            &labels[labels.len() - 4..],
+            ["color", "brighten", "tint", "base"]
+        );
+
+        let mut source = SOURCE.to_owned();
+        source.insert_str(0, "@ver");
+        let document = Document {
+            file: SourceMap::new().add_file(source, URI, None),
+            analysis: None,
+            ..document
+        };
+        let items = document.completion(Position {
+            line: 0,
+            character: 4,
+        });
+        assert_eq!(self::labels(&items), ["vertex", "mode"]);
+    }
+
+    #[test]
+    fn test_document_rename() {
+        let document = document();
+        let edit = document
+            .rename(position_of("brighten(tint)"), "lighten")
+            .unwrap();
+        let edits = &edit.changes[URI];
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range.start.line, 2);
+        assert_eq!(edits[1].range.start.line, 11);
+        assert!(edits.iter().all(|edit| edit.new_text == "lighten"));
+
+        assert!(document.rename(position_of("color;"), "1color").is_err());
+        assert!(document.rename(position_of("color;"), "pass").is_err());
+        assert!(document.rename(position_of("fragment"), "vertex").is_err());
+        assert!(document.rename(position_of("* value"), "other").is_err());
+    }
+
+    #[test]
+    fn test_to_diagnostic() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file(SOURCE, URI, None);
+        let imported = source_map.add_file(
+            "in color: f3;",
+            "common lighting.spk",
+            Some("/shaders/common lighting.spk".into()),
+        );
+        let origin = |file: &Arc<SourceFile>, low, high| {
+            Some(ItemOrigin {
+                file: file.clone(),
+                span: Span::new(file.span().low() + low, file.span().low() + high),
+            })
+        };
+        let item = Item {
+            code: 2030,
+            level: ItemLevel::Error,
+            message: "duplicate input color".to_owned(),
+            origin: origin(&file, 3, 8),
+            sub_items: vec![
+                SubItem {
+                    level: ItemLevel::Hint,
+                    message: "previously declared here".to_owned(),
+                    origin: origin(&file, 0, 2),
+                },
+                SubItem {
+                    level: ItemLevel::Hint,
+                    message: "also declared here".to_owned(),
+                    origin: origin(&imported, 3, 8),
+                },
+                SubItem {
+                    level: ItemLevel::Hint,
+                    message: "rename one of them".to_owned(),
+                    origin: None,
+                },
+            ],
+            suggestions: vec![],
+        };
+        let diagnostic = to_diagnostic(URI, &item);
+
+        assert_eq!(diagnostic.severity, 1);
+        assert_eq!(diagnostic.code.as_deref(), Some("SPK2030"));
+        assert_eq!(
+            diagnostic.message,
+            "duplicate input color\nhint: rename one of them"
+        );
+        assert_eq!(diagnostic.range.start.character, 3);
+        assert_eq!(diagnostic.related_information.len(), 2);
+        assert_eq!(
+            diagnostic.related_information[0].message,
+            "previously declared here"
+        );
+        assert_eq!(diagnostic.related_information[0].location.uri, URI);
+        assert_eq!(
+            diagnostic.related_information[1].location.uri,
+            "file:///shaders/common%20lighting.spk"
+        );
+        assert_eq!(
+            diagnostic.related_information[1]
+                .location
+                .range
+                .start
+                .character,
+            3
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use super::{Position, Range};
+use crate::span::{ColUnit, LineCol, SourceFile, Span};
+
+/// Converts a position of the file into an LSP position.
+/// The end of the file is a valid position.
+pub fn to_position(file: &SourceFile, pos: u32) -> Position {
+    let line_col = file.to_line_col(pos, ColUnit::Utf16);
+
+    Position {
+        line: line_col.line,
+        character: line_col.col,
+    }
+}
+
+pub fn to_range(file: &SourceFile, span: Span) -> Range {
+    Range {
+        start: to_position(file, span.low()),
+        end: to_position(file, span.high()),
+    }
+}
+
+/// Converts an LSP position into a position of the file.
+///
+/// Like editors do, a character past the end of the line stands for the end of the line, and a
+/// line past the end of the file stands for the end of the file.
+pub fn to_pos(file: &SourceFile, position: Position) -> u32 {
+    file.to_pos(
+        LineCol::new(position.line, position.character),
+        ColUnit::Utf16,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::span::SourceMap;
+
+    #[test]
+    fn test_position_round_trip() {
+        let mut source_map = SourceMap::new();
+        source_map.add_file("padding", "padding", None);
+        let file = source_map.add_file("in a: f;\r\nin 😀é: f;\n", "test", None);
+        let low = file.span().low();
+
+        assert_eq!(
+            to_position(&file, low),
+            Position {
+                line: 0,
+                character: 0
+            }
+        );
+        assert_eq!(
+            to_position(&file, low + 13),
+            Position {
+                line: 1,
+                character: 3
+            }
+        );
+        // the emoji takes 4 bytes, but 2 UTF-16 code units
+        assert_eq!(
+            to_position(&file, low + 17),
+            Position {
+                line: 1,
+                character: 5
+            }
+        );
+        assert_eq!(
+            to_position(&file, file.span().high()),
+            Position {
+                line: 2,
+                character: 0
+            }
+        );
+
+        for pos in [low, low + 13, low + 17, low + 19, file.span().high()] {
+            assert_eq!(to_pos(&file, to_position(&file, pos)), pos);
+        }
+    }
+
+    #[test]
+    fn test_to_pos_out_of_bounds() {
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("in a: f;\r\nin 😀: f;", "test", None);
+
+        assert_eq!(
+            to_pos(
+                &file,
+                Position {
+                    line: 0,
+                    character: 99
+                }
+            ),
+            8
+        );
+        // in the middle of the emoji
+        assert_eq!(
+            to_pos(
+                &file,
+                Position {
+                    line: 1,
+                    character: 4
+                }
+            ),
+            13
+        );
+        assert_eq!(
+            to_pos(
+                &file,
+                Position {
+                    line: 5,
+                    character: 0
+                }
+            ),
+            21
+        );
+    }
+}
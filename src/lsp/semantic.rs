@@ -0,0 +1,397 @@
+use super::{to_position, Analysis, DefinitionKind};
+use crate::{
+    parse::lexer::{token_iter, TokenKind},
+    span::{ColUnit, LineCol, SourceFile, Span},
+};
+use rustc_hash::FxHashMap;
+use std::ops::BitOr;
+
+/// Class of a token, as shown by syntax highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SemanticKind {
+    Keyword,
+    /// Keywords of compile-time constructs, e.g. `comptime`, `if`, `times`, `ident`.
+    ComptimeKeyword,
+    Type,
+    Input,
+    Function,
+    Parameter,
+    Local,
+    Pass,
+    Stage,
+    Attribute,
+    /// Flags tested by `comptime if`, e.g. `"SHADOWS"`.
+    StringFlag,
+    String,
+    Number,
+    Comment,
+}
+
+impl SemanticKind {
+    /// Every kind, in declaration order; the index of a kind is its index in the legend.
+    pub const ALL: [Self; 14] = [
+        Self::Keyword,
+        Self::ComptimeKeyword,
+        Self::Type,
+        Self::Input,
+        Self::Function,
+        Self::Parameter,
+        Self::Local,
+        Self::Pass,
+        Self::Stage,
+        Self::Attribute,
+        Self::StringFlag,
+        Self::String,
+        Self::Number,
+        Self::Comment,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::ComptimeKeyword => "comptimeKeyword",
+            Self::Type => "type",
+            Self::Input => "input",
+            Self::Function => "function",
+            Self::Parameter => "parameter",
+            Self::Local => "local",
+            Self::Pass => "pass",
+            Self::Stage => "stage",
+            Self::Attribute => "attribute",
+            Self::StringFlag => "stringFlag",
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Comment => "comment",
+        }
+    }
+
+    /// Returns the closest standard LSP token type, which editors know how to color.
+    pub fn lsp_name(self) -> &'static str {
+        match self {
+            Self::Keyword => "keyword",
+            Self::ComptimeKeyword => "macro",
+            Self::Type => "type",
+            Self::Input | Self::Local => "variable",
+            Self::Function => "function",
+            Self::Parameter => "parameter",
+            Self::Pass => "class",
+            Self::Stage => "method",
+            Self::Attribute => "decorator",
+            Self::StringFlag => "enumMember",
+            Self::String => "string",
+            Self::Number => "number",
+            Self::Comment => "comment",
+        }
+    }
+}
+
+/// Set of modifiers of a token; bit `n` stands for the `n`th entry of [`SemanticModifiers::NAMES`].
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::lsp::SemanticModifiers;
+/// let modifiers = SemanticModifiers::DECLARATION | SemanticModifiers::READONLY;
+/// assert!(modifiers.contains(SemanticModifiers::READONLY));
+/// assert_eq!(modifiers.bits(), 3);
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SemanticModifiers(u32);
+
+impl SemanticModifiers {
+    pub const NONE: Self = Self(0);
+    /// The token is the name of a definition.
+    pub const DECLARATION: Self = Self(1);
+    /// The token refers to an input, which cannot be assigned.
+    pub const READONLY: Self = Self(2);
+    /// The token refers to a built-in.
+    pub const DEFAULT_LIBRARY: Self = Self(4);
+    /// The token is a doc comment.
+    pub const DOCUMENTATION: Self = Self(8);
+
+    pub const NAMES: [&'static str; 4] =
+        ["declaration", "readonly", "defaultLibrary", "documentation"];
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for SemanticModifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SemanticToken {
+    pub span: Span,
+    pub kind: SemanticKind,
+    pub modifiers: SemanticModifiers,
+}
+
+/// Classifies the tokens of the file, in order.
+///
+/// Names are classified by what they resolve to in the `analysis`; without one, only keywords,
+/// literals and comments are classified. Punctuations and unresolved names are left out.
+pub fn classify(file: &SourceFile, analysis: Option<&Analysis>) -> Vec<SemanticToken> {
+    let mut resolved = FxHashMap::<Span, (SemanticKind, SemanticModifiers)>::default();
+
+    if let Some(analysis) = analysis {
+        for &span in analysis.types() {
+            resolved.insert(span, (SemanticKind::Type, SemanticModifiers::NONE));
+        }
+
+        for &span in analysis.attributes() {
+            resolved.insert(span, (SemanticKind::Attribute, SemanticModifiers::NONE));
+        }
+
+        for &span in analysis.flags() {
+            resolved.insert(span, (SemanticKind::StringFlag, SemanticModifiers::NONE));
+        }
+
+        for &(span, _) in analysis.builtins() {
+            resolved.insert(
+                span,
+                (SemanticKind::Function, SemanticModifiers::DEFAULT_LIBRARY),
+            );
+        }
+
+        for &(span, index) in analysis.references() {
+            let kind = analysis.definition(index).kind;
+            resolved.insert(span, (definition_kind(kind), readonly(kind)));
+        }
+
+        for definition in analysis.definitions() {
+            resolved.insert(
+                definition.span,
+                (
+                    definition_kind(definition.kind),
+                    SemanticModifiers::DECLARATION | readonly(definition.kind),
+                ),
+            );
+        }
+    }
+
+    let mut tokens = Vec::new();
+
+    for token in token_iter(file) {
+        let span = token.span();
+        let (kind, modifiers) = match token.kind {
+            TokenKind::Comment { .. } | TokenKind::BlockComment { .. } => {
+                (SemanticKind::Comment, SemanticModifiers::NONE)
+            }
+            TokenKind::DocComment { .. } => {
+                (SemanticKind::Comment, SemanticModifiers::DOCUMENTATION)
+            }
+            TokenKind::NumberLiteral { .. } => (SemanticKind::Number, SemanticModifiers::NONE),
+            TokenKind::StringLiteral { .. } => match resolved.get(&span) {
+                Some(&resolved) => resolved,
+                None => (SemanticKind::String, SemanticModifiers::NONE),
+            },
+            // the lexer keeps yielding the end of file token
+            TokenKind::EndOfFile => break,
+            TokenKind::BoolLiteral { .. } => (SemanticKind::Keyword, SemanticModifiers::NONE),
+            TokenKind::Id { symbol, .. } => match resolved.get(&span) {
+                Some(&resolved) => resolved,
+                None => match symbol.to_str() {
+                    "in" | "fn" | "pass" | "let" | "return" | "import" | "as" | "pub" => {
+                        (SemanticKind::Keyword, SemanticModifiers::NONE)
+                    }
+                    "comptime" | "if" | "else" | "loop" | "times" | "and" | "or" | "not"
+                    | "ident" => (SemanticKind::ComptimeKeyword, SemanticModifiers::NONE),
+                    _ => continue,
+                },
+            },
+            _ => continue,
+        };
+
+        tokens.push(SemanticToken {
+            span,
+            kind,
+            modifiers,
+        });
+    }
+
+    tokens
+}
+
+/// Encodes the tokens as LSP semantic token data: five integers per token, being the line and
+/// the start character relative to the previous token, the length in UTF-16 code units, the kind
+/// and the modifiers. Tokens spanning several lines are split into one token per line.
+pub fn encode_semantic_tokens(file: &SourceFile, tokens: &[SemanticToken]) -> Vec<u32> {
+    let mut data = Vec::with_capacity(tokens.len() * 5);
+    let mut previous_line = 0;
+    let mut previous_character = 0;
+
+    for token in tokens {
+        let start = to_position(file, token.span.low());
+        let end = to_position(file, token.span.high());
+
+        for line in start.line..=end.line {
+            let character = if line == start.line {
+                start.character
+            } else {
+                0
+            };
+            let length = if line == end.line {
+                end.character
+            } else {
+                let line_high = file.to_pos(LineCol::new(line, u32::MAX), ColUnit::Utf16);
+                file.to_line_col(line_high, ColUnit::Utf16).col
+            } - character;
+
+            if length == 0 {
+                continue;
+            }
+
+            let delta_character = if line == previous_line {
+                character - previous_character
+            } else {
+                character
+            };
+            let kind = SemanticKind::ALL
+                .iter()
+                .position(|kind| *kind == token.kind)
+                .unwrap();
+
+            data.extend([
+                line - previous_line,
+                delta_character,
+                length,
+                kind as u32,
+                token.modifiers.bits(),
+            ]);
+            previous_line = line;
+            previous_character = character;
+        }
+    }
+
+    data
+}
+
+fn definition_kind(kind: DefinitionKind) -> SemanticKind {
+    match kind {
+        DefinitionKind::Input => SemanticKind::Input,
+        DefinitionKind::Function => SemanticKind::Function,
+        DefinitionKind::Pass => SemanticKind::Pass,
+        DefinitionKind::Stage => SemanticKind::Stage,
+        DefinitionKind::Parameter => SemanticKind::Parameter,
+        DefinitionKind::Local => SemanticKind::Local,
+    }
+}
+
+fn readonly(kind: DefinitionKind) -> SemanticModifiers {
+    match kind {
+        DefinitionKind::Input => SemanticModifiers::READONLY,
+        _ => SemanticModifiers::NONE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lsp::test_utils::{pack, SOURCE},
+        span::SourceMap,
+    };
+
+    fn classified(file: &SourceFile, tokens: &[SemanticToken]) -> Vec<(String, SemanticKind, u32)> {
+        Vec::from_iter(tokens.iter().map(|token| {
+            (
+                file.slice(token.span).to_owned(),
+                token.kind,
+                token.modifiers.bits(),
+            )
+        }))
+    }
+
+    #[test]
+    fn test_classify() {
+        let file = SourceMap::new().add_file(SOURCE, "test", None);
+        let analysis = Analysis::new(&pack());
+        let tokens = classified(&file, &classify(&file, Some(&analysis)));
+        let declaration = SemanticModifiers::DECLARATION.bits();
+        let readonly = SemanticModifiers::READONLY.bits();
+
+        for (text, kind, modifiers) in [
+            ("in", SemanticKind::Keyword, 0),
+            ("color", SemanticKind::Input, declaration | readonly),
+            ("brighten", SemanticKind::Function, declaration),
+            ("value", SemanticKind::Parameter, declaration),
+            ("scaled", SemanticKind::Local, declaration),
+            ("main", SemanticKind::Pass, declaration),
+            ("fragment", SemanticKind::Stage, declaration),
+            ("abs", SemanticKind::Function, 4),
+        ] {
+            assert_eq!(
+                tokens.iter().find(|token| token.0 == text),
+                Some(&(text.to_owned(), kind, modifiers)),
+            );
+        }
+
+        let references = Vec::from_iter(tokens.iter().filter(|token| token.0 == "color"));
+        assert_eq!(references.len(), 3);
+        assert_eq!(references[1].2, readonly);
+
+        // without an analysis, names are left out
+        let tokens = classified(&file, &classify(&file, None));
+        assert!(tokens.iter().all(|token| token.1 == SemanticKind::Keyword));
+        assert_eq!(tokens.len(), 8);
+    }
+
+    #[test]
+    fn test_classify_literals() {
+        let source = "# lit\ncomptime if \"A\" { in a: f = 1.0; }";
+        let file = SourceMap::new().add_file(source, "test", None);
+        let tokens = classified(&file, &classify(&file, None));
+
+        assert_eq!(
+            tokens,
+            [
+                ("# lit".to_owned(), SemanticKind::Comment, 0),
+                ("comptime".to_owned(), SemanticKind::ComptimeKeyword, 0),
+                ("if".to_owned(), SemanticKind::ComptimeKeyword, 0),
+                ("\"A\"".to_owned(), SemanticKind::String, 0),
+                ("in".to_owned(), SemanticKind::Keyword, 0),
+                ("1.0".to_owned(), SemanticKind::Number, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_encode_semantic_tokens() {
+        let mut source_map = SourceMap::new();
+        source_map.add_file("padding", "padding", None);
+        let file = source_map.add_file("let é = \"a\nbc\"; let b", "test", None);
+        let low = file.span().low();
+        let token = |low_offset: u32, high_offset: u32, kind| SemanticToken {
+            span: Span::new(low + low_offset, low + high_offset),
+            kind,
+            modifiers: SemanticModifiers::NONE,
+        };
+        let tokens = [
+            token(0, 3, SemanticKind::Keyword),
+            token(4, 6, SemanticKind::Local),
+            token(9, 15, SemanticKind::String),
+            token(17, 20, SemanticKind::Keyword),
+        ];
+
+        assert_eq!(
+            encode_semantic_tokens(&file, &tokens),
+            [
+                0, 0, 3, 0, 0, //
+                0, 4, 1, 6, 0, //
+                0, 4, 2, 11, 0, //
+                1, 0, 3, 11, 0, //
+                0, 5, 3, 0, 0, //
+            ]
+        );
+    }
+}
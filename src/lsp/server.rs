@@ -0,0 +1,424 @@
+use super::{
+    read_message, static_completions, write_message, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, Document, DocumentSymbolParams,
+    PublishDiagnosticsParams, RenameParams, SemanticKind, SemanticModifiers, SemanticTokens,
+    SemanticTokensParams, TextDocumentPositionParams, ERR_INVALID_PARAMS, ERR_INVALID_REQUEST,
+    ERR_METHOD_NOT_FOUND, ERR_PARSE, ERR_REQUEST_FAILED,
+};
+use rustc_hash::FxHashMap;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+
+/// Serves a single client until it sends `exit` or closes the input.
+///
+/// Example:
+///
+/// ```no_run
+/// # use std::io::{stdin, stdout};
+/// shader_pack::lsp::run(stdin().lock(), stdout().lock()).unwrap();
+/// ```
+pub fn run(mut reader: impl BufRead, writer: impl Write) -> io::Result<()> {
+    let mut server = Server::new(writer);
+
+    loop {
+        let message = match read_message(&mut reader) {
+            Ok(Some(message)) => message,
+            Ok(None) => return Ok(()),
+            Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                server.respond(Value::Null, Err(ResponseError::new(ERR_PARSE, err)))?;
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+
+        if !server.handle(message)? {
+            return Ok(());
+        }
+    }
+}
+
+/// Language server over JSON-RPC; documents are parsed again incrementally and analyzed whenever
+/// they change, and their diagnostics are pushed to the client right away.
+#[derive(Debug)]
+pub struct Server<W> {
+    writer: W,
+    documents: FxHashMap<String, Document>,
+    shutdown: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+impl ResponseError {
+    fn new(code: i64, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl<W: Write> Server<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            documents: FxHashMap::default(),
+            shutdown: false,
+        }
+    }
+
+    /// Handles a single message; returns `false` once the client has asked the server to exit.
+    pub fn handle(&mut self, message: Value) -> io::Result<bool> {
+        let method = message.get("method").and_then(Value::as_str);
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        match (method, message.get("id")) {
+            (Some("exit"), _) => return Ok(false),
+            (Some(method), Some(id)) => {
+                let result = if self.shutdown {
+                    Err(ResponseError::new(
+                        ERR_INVALID_REQUEST,
+                        "the server is shutting down",
+                    ))
+                } else {
+                    self.request(method, params)
+                };
+                self.respond(id.clone(), result)?;
+            }
+            (Some(method), None) => self.notification(method, params)?,
+            // the server never sends requests, so there is no response to wait for
+            (None, _) => {}
+        }
+
+        Ok(true)
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Result<Value, ResponseError> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 2,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": { "triggerCharacters": ["@"] },
+                    "renameProvider": true,
+                    "semanticTokensProvider": {
+                        "legend": {
+                            "tokenTypes": SemanticKind::ALL.map(SemanticKind::lsp_name),
+                            "tokenModifiers": SemanticModifiers::NAMES,
+                        },
+                        "full": true,
+                    },
+                },
+                "serverInfo": {
+                    "name": "spk-lsp",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => {
+                let params = parse_params::<TextDocumentPositionParams>(params)?;
+                to_result(
+                    self.documents
+                        .get(&params.text_document.uri)
+                        .and_then(|document| document.hover(params.position)),
+                )
+            }
+            "textDocument/definition" => {
+                let params = parse_params::<TextDocumentPositionParams>(params)?;
+                to_result(
+                    self.documents
+                        .get(&params.text_document.uri)
+                        .and_then(|document| document.definition(params.position)),
+                )
+            }
+            "textDocument/documentSymbol" => {
+                let params = parse_params::<DocumentSymbolParams>(params)?;
+                to_result(
+                    self.documents
+                        .get(&params.text_document.uri)
+                        .map(Document::document_symbols),
+                )
+            }
+            "textDocument/completion" => {
+                let params = parse_params::<TextDocumentPositionParams>(params)?;
+                to_result(match self.documents.get(&params.text_document.uri) {
+                    Some(document) => document.completion(params.position),
+                    None => static_completions(),
+                })
+            }
+            "textDocument/rename" => {
+                let params = parse_params::<RenameParams>(params)?;
+                let document = self
+                    .documents
+                    .get(&params.text_document.uri)
+                    .ok_or_else(|| ResponseError::new(ERR_REQUEST_FAILED, "unknown document"))?;
+                document
+                    .rename(params.position, &params.new_name)
+                    .map_err(|err| ResponseError::new(ERR_REQUEST_FAILED, err))
+                    .and_then(to_result)
+            }
+            "textDocument/semanticTokens/full" => {
+                let params = parse_params::<SemanticTokensParams>(params)?;
+                to_result(SemanticTokens {
+                    data: self
+                        .documents
+                        .get(&params.text_document.uri)
+                        .map(Document::semantic_tokens)
+                        .unwrap_or_default(),
+                })
+            }
+            _ => Err(ResponseError::new(
+                ERR_METHOD_NOT_FOUND,
+                format!("unknown method `{}`", method),
+            )),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: Value) -> io::Result<()> {
+        // malformed notifications cannot be answered, so they are dropped
+        match method {
+            "textDocument/didOpen" => {
+                if let Ok(params) = parse_params::<DidOpenTextDocumentParams>(params) {
+                    let document = params.text_document;
+                    self.update(Document::new(document.uri, document.text, document.version))?;
+                }
+            }
+            "textDocument/didChange" => {
+                if let Ok(params) = parse_params::<DidChangeTextDocumentParams>(params) {
+                    let uri = params.text_document.uri;
+                    let version = params.text_document.version;
+
+                    // unknown documents start empty, so that full document changes still apply
+                    let mut document = self
+                        .documents
+                        .remove(&uri)
+                        .unwrap_or_else(|| Document::new(uri, "", version));
+                    document.edit(&params.content_changes, version);
+                    self.update(document)?;
+                }
+            }
+            "textDocument/didClose" => {
+                if let Ok(params) = parse_params::<DidCloseTextDocumentParams>(params) {
+                    let uri = params.text_document.uri;
+                    self.documents.remove(&uri);
+                    self.publish_diagnostics(PublishDiagnosticsParams {
+                        uri,
+                        version: None,
+                        diagnostics: vec![],
+                    })?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn update(&mut self, document: Document) -> io::Result<()> {
+        let params = PublishDiagnosticsParams {
+            uri: document.uri().to_owned(),
+            version: document.version(),
+            diagnostics: document.diagnostics().to_vec(),
+        };
+        self.documents.insert(document.uri().to_owned(), document);
+        self.publish_diagnostics(params)
+    }
+
+    fn publish_diagnostics(&mut self, params: PublishDiagnosticsParams) -> io::Result<()> {
+        write_message(
+            &mut self.writer,
+            &json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": params,
+            }),
+        )
+    }
+
+    fn respond(&mut self, id: Value, result: Result<Value, ResponseError>) -> io::Result<()> {
+        let message = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message },
+            }),
+        };
+        write_message(&mut self.writer, &message)
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, ResponseError> {
+    serde_json::from_value(params).map_err(|err| ResponseError::new(ERR_INVALID_PARAMS, err))
+}
+
+fn to_result(value: impl Serialize) -> Result<Value, ResponseError> {
+    serde_json::to_value(value).map_err(|err| ResponseError::new(ERR_REQUEST_FAILED, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::codes::{code_name, LEX_ERR_UNTERMINATED_BLOCK_COMMENT};
+    use std::{
+        io::{pipe, BufReader, PipeReader, PipeWriter},
+        thread,
+    };
+
+    /// In-process client talking to a server thread over pipes.
+    struct Client {
+        reader: BufReader<PipeReader>,
+        writer: PipeWriter,
+        next_id: i64,
+    }
+
+    impl Client {
+        fn request(&mut self, method: &str, params: Value) -> Value {
+            self.next_id += 1;
+            write_message(
+                &mut self.writer,
+                &json!({ "jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params }),
+            )
+            .unwrap();
+
+            let response = self.receive();
+            assert_eq!(response["id"], self.next_id);
+            response
+        }
+
+        fn notify(&mut self, method: &str, params: Value) {
+            write_message(
+                &mut self.writer,
+                &json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+            )
+            .unwrap();
+        }
+
+        fn receive(&mut self) -> Value {
+            read_message(&mut self.reader).unwrap().unwrap()
+        }
+    }
+
+    #[test]
+    fn test_server_session() {
+        let (server_reader, mut client_writer) = pipe().unwrap();
+        let (client_reader, server_writer) = pipe().unwrap();
+        let server = thread::spawn(move || run(BufReader::new(server_reader), server_writer));
+
+        // a malformed message is answered, and does not stop the server
+        client_writer
+            .write_all(b"Content-Length: 1\r\n\r\n{")
+            .unwrap();
+        let mut client = Client {
+            reader: BufReader::new(client_reader),
+            writer: client_writer,
+            next_id: 0,
+        };
+        assert_eq!(client.receive()["error"]["code"], ERR_PARSE);
+
+        let response = client.request("initialize", json!({ "capabilities": {} }));
+        assert_eq!(response["result"]["capabilities"]["hoverProvider"], true);
+        assert_eq!(response["result"]["serverInfo"]["name"], "spk-lsp");
+        assert_eq!(
+            response["result"]["capabilities"]["semanticTokensProvider"]["legend"]["tokenTypes"][1],
+            "macro"
+        );
+        client.notify("initialized", json!({}));
+
+        let position = json!({
+            "textDocument": { "uri": "file:///unknown.spk" },
+            "position": { "line": 0, "character": 0 },
+        });
+        let response = client.request("textDocument/completion", position.clone());
+        let labels = Vec::from_iter(
+            response["result"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["label"].as_str().unwrap()),
+        );
+        assert!(labels.contains(&"comptime"));
+        assert!(labels.contains(&"normalize"));
+        assert!(labels.contains(&"t2"));
+
+        let response = client.request("textDocument/semanticTokens/full", position.clone());
+        assert_eq!(response["result"], json!({ "data": [] }));
+
+        let response = client.request("textDocument/hover", position);
+        assert_eq!(response["result"], Value::Null);
+
+        let response = client.request("textDocument/hover", json!({}));
+        assert_eq!(response["error"]["code"], ERR_INVALID_PARAMS);
+
+        let response = client.request("workspace/unknown", json!({}));
+        assert_eq!(response["error"]["code"], ERR_METHOD_NOT_FOUND);
+
+        // an unterminated block comment only has a lexer error, so nothing is parsed
+        client.notify(
+            "textDocument/didOpen",
+            json!({ "textDocument": {
+                "uri": "file:///open.spk",
+                "languageId": "spk",
+                "version": 1,
+                "text": "#[ unterminated",
+            } }),
+        );
+        let notification = client.receive();
+        assert_eq!(notification["method"], "textDocument/publishDiagnostics");
+        assert_eq!(notification["params"]["uri"], "file:///open.spk");
+        assert_eq!(notification["params"]["version"], 1);
+        let diagnostics = notification["params"]["diagnostics"].as_array().unwrap();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0]["code"],
+            code_name(LEX_ERR_UNTERMINATED_BLOCK_COMMENT)
+        );
+        assert!(diagnostics[0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("unterminated block comment"));
+
+        client.notify(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": "file:///open.spk", "version": 2 },
+                "contentChanges": [{
+                    "range": {
+                        "start": { "line": 0, "character": 15 },
+                        "end": { "line": 0, "character": 15 },
+                    },
+                    "text": " ]#",
+                }],
+            }),
+        );
+        let notification = client.receive();
+        assert_eq!(notification["params"]["version"], 2);
+        assert_eq!(notification["params"]["diagnostics"], json!([]));
+
+        client.notify(
+            "textDocument/didClose",
+            json!({ "textDocument": { "uri": "file:///unknown.spk" } }),
+        );
+        let notification = client.receive();
+        assert_eq!(notification["method"], "textDocument/publishDiagnostics");
+        assert_eq!(notification["params"]["diagnostics"], json!([]));
+
+        let response = client.request("shutdown", Value::Null);
+        assert_eq!(response["result"], Value::Null);
+
+        let response = client.request("textDocument/completion", json!({}));
+        assert_eq!(response["error"]["code"], ERR_INVALID_REQUEST);
+
+        client.notify("exit", Value::Null);
+        server.join().unwrap().unwrap();
+    }
+}
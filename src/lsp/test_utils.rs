@@ -0,0 +1,102 @@
+use crate::{
+    parse::ast::{
+        AstBinaryExprOpKind, AstExprKind, AstPassLevelKind, AstShaderPack, AstTopLevelKind,
+    },
+    span::Span,
+    test_utils::*,
+};
+
+pub const SOURCE: &str = "in color: f3;
+
+fn brighten(value: f3) -> f3 {
+    let scaled = value * value;
+    return scaled + color;
+}
+
+pass main {
+    in tint: f3;
+
+    fragment {
+        let base = brighten(tint);
+        return abs(base) + color;
+    }
+}
+";
+
+/// Returns the span from the first occurrence of `start` to the end of the first occurrence of
+/// `end` after it.
+pub fn span_between(start: &str, end: &str) -> Span {
+    let low = SOURCE.find(start).unwrap();
+    let high = low + SOURCE[low..].find(end).unwrap() + end.len();
+    Span::new(low as u32, high as u32)
+}
+
+/// Builds the AST of [`SOURCE`], with the spans of every name and item.
+pub fn pack() -> AstShaderPack {
+    let at = |name, nth| ident_at(SOURCE, name, nth);
+    let mut scaled = statement_let(
+        at("scaled", 0),
+        expr_binary(
+            AstBinaryExprOpKind::Mul,
+            expr(AstExprKind::Identifier(at("value", 1))),
+            expr(AstExprKind::Identifier(at("value", 2))),
+        ),
+    );
+    scaled.span = span_between("let scaled", ";");
+
+    let mut base = statement_let(
+        at("base", 0),
+        expr_call(
+            at("brighten", 1),
+            vec![expr(AstExprKind::Identifier(at("tint", 1)))],
+        ),
+    );
+    base.span = span_between("let base", ";");
+
+    let mut brighten = fn_def(
+        at("brighten", 0),
+        vec![(at("value", 0), "f3")],
+        Some("f3"),
+        vec![
+            scaled,
+            statement_return(expr_binary(
+                AstBinaryExprOpKind::Add,
+                expr(AstExprKind::Identifier(at("scaled", 1))),
+                expr(AstExprKind::Identifier(at("color", 1))),
+            )),
+        ],
+    );
+    let mut fragment = stage(
+        at("fragment", 0),
+        vec![
+            base,
+            statement_return(expr_binary(
+                AstBinaryExprOpKind::Add,
+                expr_call(
+                    at("abs", 0),
+                    vec![expr(AstExprKind::Identifier(at("base", 1)))],
+                ),
+                expr(AstExprKind::Identifier(at("color", 2))),
+            )),
+        ],
+    );
+
+    if let AstTopLevelKind::FnDef(fn_def) = &mut brighten {
+        fn_def.span = span_between("fn brighten", "}");
+    }
+
+    if let AstPassLevelKind::Stage(stage) = &mut fragment {
+        stage.span = span_between("fragment", "}");
+    }
+
+    let mut main = pass_with(
+        at("main", 0),
+        vec![pass_input(at("tint", 0), "f3"), fragment],
+    );
+
+    if let AstTopLevelKind::Pass(pass) = &mut main {
+        pass.span = span_between("pass main", "}\n}");
+    }
+
+    shader_pack(vec![input(at("color", 0), "f3"), brighten, main])
+}
@@ -0,0 +1,108 @@
+use serde_json::Value;
+use std::io::{self, BufRead, Write};
+
+/// Reads a single `Content-Length` framed message.
+/// Returns `None` once the input has been closed between two messages.
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut content_length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+
+        if reader.read_line(&mut line)? == 0 {
+            return match content_length {
+                None => Ok(None),
+                Some(_) => Err(io::ErrorKind::UnexpectedEof.into()),
+            };
+        }
+
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let value = value.trim().parse::<usize>().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid content length `{}`", value.trim()),
+                    )
+                })?;
+                content_length = Some(value);
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing `Content-Length` header",
+        )
+    })?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content)?;
+
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Writes a single message, framed with a `Content-Length` header.
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = serde_json::to_string(message)?;
+    write!(
+        writer,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::BufReader;
+
+    #[test]
+    fn test_message_round_trip() {
+        let mut bytes = Vec::new();
+        write_message(&mut bytes, &json!({"jsonrpc": "2.0", "method": "exit"})).unwrap();
+        write_message(&mut bytes, &json!({"text": "é"})).unwrap();
+        assert!(bytes.starts_with(b"Content-Length: 33\r\n\r\n{"));
+
+        let mut reader = BufReader::new(bytes.as_slice());
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Some(json!({"jsonrpc": "2.0", "method": "exit"}))
+        );
+        assert_eq!(
+            read_message(&mut reader).unwrap(),
+            Some(json!({"text": "é"}))
+        );
+        assert_eq!(read_message(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_message_headers() {
+        let bytes = b"Content-Type: application/vscode-jsonrpc; charset=utf-8\r\ncontent-length: 2\r\n\r\n{}";
+        let mut reader = BufReader::new(&bytes[..]);
+        assert_eq!(read_message(&mut reader).unwrap(), Some(json!({})));
+
+        let mut reader = BufReader::new(&b"\r\n{}"[..]);
+        assert_eq!(
+            read_message(&mut reader).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        let mut reader = BufReader::new(&b"Content-Length: 10\r\n\r\n{}"[..]);
+        assert_eq!(
+            read_message(&mut reader).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}
@@ -1,6 +1,58 @@
 pub mod ast;
 mod cursor;
+mod doc_comment;
+mod file_loader;
+mod import;
+mod incremental;
 pub mod lexer;
 pub mod low_lexer;
+mod modules;
 pub mod parse;
 pub mod symbols;
+
+pub use file_loader::*;
+pub use import::*;
+pub use incremental::*;
+
+use self::{
+    ast::{AstShaderPack, NodeIdAllocator},
+    cursor::Cursor,
+    doc_comment::attach_doc_comments,
+    lexer::{lex, validate_token},
+    parse::Parse,
+};
+use crate::{diagnostics::ItemSender, span::SourceFile};
+use std::iter::repeat;
+
+/// Parses the given file into an AST.
+/// Whitespaces and comments are skipped, and doc comments are attached to the items after them;
+/// errors are reported through the `reporter`, those of the lexer first.
+pub fn parse_shader_pack(file: &SourceFile, reporter: &ItemSender) -> Option<AstShaderPack> {
+    parse_shader_pack_with_allocator(file, reporter, &mut NodeIdAllocator::new())
+}
+
+/// Parses the given file into an AST, allocating its node ids from the `id_allocator`, so that
+/// the ids stay unique across the files of a pack.
+pub(crate) fn parse_shader_pack_with_allocator(
+    file: &SourceFile,
+    reporter: &ItemSender,
+    id_allocator: &mut NodeIdAllocator,
+) -> Option<AstShaderPack> {
+    let tokens = lex(file);
+
+    for token in &tokens {
+        validate_token(token, reporter);
+    }
+
+    // the cursor expects the end of file token to repeat, like `token_iter` does
+    let end_of_file = *tokens.last().unwrap();
+    let token_stream = tokens
+        .iter()
+        .copied()
+        .filter(|token| !token.kind.is_trivia())
+        .chain(repeat(end_of_file));
+    let mut cursor = Cursor::new(token_stream, id_allocator, reporter);
+    let mut pack = AstShaderPack::parse(&mut cursor)?;
+    attach_doc_comments(&mut pack, &tokens);
+    Some(pack)
+}
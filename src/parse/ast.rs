@@ -1,10 +1,12 @@
+mod map_spans;
 mod node_id;
 mod node_id_allocator;
 
+pub use map_spans::*;
 pub use node_id::*;
 pub use node_id_allocator::*;
 
-use super::lexer::TokenKind;
+use super::lexer::{decode_string, TokenKind, TokenNumberLiteralKind};
 use crate::{span::Span, symbol::Symbol};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -25,10 +27,36 @@ pub struct AstTopLevel {
 pub enum AstTopLevelKind {
     CompTime(AstCompTime<AstTopLevel>),
     FnDef(AstFnDef),
+    Import(AstImport),
     Input(AstInput),
     Pass(AstPass),
 }
 
+/// Example:
+///
+/// - `import "common/lighting.spk";`
+/// - `import "noise.spk" as noise;`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstImport {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub keyword_import: AstKeyword,
+    pub path: AstStringLiteral,
+    pub alias: Option<AstImportAlias>,
+    pub punc_semicolon: AstPunc,
+}
+
+/// Example:
+///
+/// `as noise`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstImportAlias {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub keyword_as: AstKeyword,
+    pub ident: AstIdentifier,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AstAttribute {
     pub node_id: NodeId,
@@ -36,6 +64,38 @@ pub struct AstAttribute {
     pub items: Vec<AstAttributeItem>,
 }
 
+/// Example:
+///
+/// `## <text>`, on each line right before an `in`, `fn` or `pass` item
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstDocComment {
+    pub span: Span,
+    /// The text after the `##` of each line.
+    pub lines: Vec<Symbol>,
+}
+
+impl AstDocComment {
+    /// Returns the text of the comment, without the space following each `##`.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// # use shader_pack::{parse::ast::AstDocComment, span::Span, symbol::Symbol};
+    /// let doc = AstDocComment {
+    ///     span: Span::ZERO,
+    ///     lines: vec![Symbol::from_str(" Base color."), Symbol::from_str(""), Symbol::from_str("  Linear.")],
+    /// };
+    /// assert_eq!(doc.text(), "Base color.\n\n Linear.");
+    /// ```
+    pub fn text(&self) -> String {
+        let lines = self.lines.iter().map(|line| {
+            let line = line.to_str().trim_end();
+            line.strip_prefix(' ').unwrap_or(line)
+        });
+        Vec::from_iter(lines).join("\n")
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AstAttributeItem {
     pub node_id: NodeId,
@@ -203,7 +263,10 @@ pub struct AstCompTimeBlock<T> {
 pub struct AstFnDef {
     pub node_id: NodeId,
     pub span: Span,
+    pub doc: Option<AstDocComment>,
     pub attributes: Vec<AstAttribute>,
+    /// Makes the function callable from the files importing this one.
+    pub keyword_pub: Option<AstKeyword>,
     pub keyword_fn: AstKeyword,
     pub ident: AstIdentifier,
     pub punc_open_paren: AstPunc,
@@ -247,6 +310,7 @@ pub struct AstFnDefReturnType {
 pub struct AstInput {
     pub node_id: NodeId,
     pub span: Span,
+    pub doc: Option<AstDocComment>,
     pub attributes: Vec<AstAttribute>,
     pub keyword_in: AstKeyword,
     pub ident: AstIdentifier,
@@ -262,6 +326,7 @@ pub struct AstInput {
 pub struct AstPass {
     pub node_id: NodeId,
     pub span: Span,
+    pub doc: Option<AstDocComment>,
     pub attributes: Vec<AstAttribute>,
     pub keyword_pass: AstKeyword,
     pub ident: AstIdentifier,
@@ -308,6 +373,7 @@ pub struct AstStatement {
 pub enum AstStatementKind {
     VarDecl(AstStatementVarDecl),
     Assignment(AstStatementAssignment),
+    Return(AstStatementReturn),
     Expr(AstExpr),
 }
 
@@ -344,6 +410,17 @@ pub struct AstStatementAssignment {
     pub punc_semicolon: AstPunc,
 }
 
+/// Example:
+///
+/// - `return;`
+/// - `return <expr>;`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstStatementReturn {
+    pub keyword_return: AstKeyword,
+    pub expr: Option<AstExpr>,
+    pub punc_semicolon: AstPunc,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AstAssignmentOp {
     pub span: Span,
@@ -391,7 +468,12 @@ pub enum AstExprKind {
     Invalid,
     Binary(AstBinaryExpr),
     Unary(AstUnaryExpr),
-    // TODO
+    Literal(AstLiteral),
+    Identifier(AstIdentifier),
+    Call(AstCallExpr),
+    Member(AstMemberExpr),
+    Index(AstIndexExpr),
+    Object(AstObjectExpr),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -479,6 +561,107 @@ pub enum AstUnaryExprOpKind {
     BitNot,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstLiteral {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub kind: AstLiteralKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AstLiteralKind {
+    Bool {
+        content: Symbol,
+    },
+    Number {
+        kind: TokenNumberLiteralKind,
+        content: Symbol,
+        suffix: Option<Symbol>,
+    },
+    String(AstStringLiteral),
+}
+
+/// Example:
+///
+/// `<identifier> ( <expr>, ... )`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstCallExpr {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub qualifier: Option<Box<AstPathQualifier>>,
+    pub callee: AstIdentifier,
+    pub punc_open_paren: AstPunc,
+    pub args: Vec<AstCallExprArg>,
+    pub punc_close_paren: AstPunc,
+}
+
+/// Example:
+///
+/// `noise::` in `noise::perlin(p)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstPathQualifier {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub module: AstIdentifier,
+    pub punc_path_sep: AstPunc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstCallExprArg {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub expr: AstExpr,
+    pub punc_comma: Option<AstPunc>,
+}
+
+/// Example:
+///
+/// - `<expr> . <identifier>`
+/// - `color.xyz`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstMemberExpr {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub lhs: Box<AstExpr>,
+    pub punc_dot: AstPunc,
+    pub member: AstIdentifier,
+}
+
+/// Example:
+///
+/// `<expr> [ <expr> ]`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstIndexExpr {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub lhs: Box<AstExpr>,
+    pub punc_open_bracket: AstPunc,
+    pub index: Box<AstExpr>,
+    pub punc_close_bracket: AstPunc,
+}
+
+/// Example:
+///
+/// `{ <identifier> : <expr>, ... }`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstObjectExpr {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub punc_open_brace: AstPunc,
+    pub fields: Vec<AstObjectExprField>,
+    pub punc_close_brace: AstPunc,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AstObjectExprField {
+    pub node_id: NodeId,
+    pub span: Span,
+    pub ident: AstIdentifier,
+    pub punc_colon: AstPunc,
+    pub expr: AstExpr,
+    pub punc_comma: Option<AstPunc>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct AstPunc {
     pub span: Span,
@@ -497,6 +680,7 @@ pub enum AstPuncKind {
     Dot,
     Comma,
     Colon,
+    PathSep,
     Semicolon,
     At,
     Arrow,
@@ -548,6 +732,7 @@ impl AstPuncKind {
             AstPuncKind::Dot => token_kind == TokenKind::Dot,
             AstPuncKind::Comma => token_kind == TokenKind::Comma,
             AstPuncKind::Colon => token_kind == TokenKind::Colon,
+            AstPuncKind::PathSep => token_kind == TokenKind::PathSep,
             AstPuncKind::Semicolon => token_kind == TokenKind::Semicolon,
             AstPuncKind::At => token_kind == TokenKind::At,
             AstPuncKind::Arrow => token_kind == TokenKind::Arrow,
@@ -631,6 +816,17 @@ pub struct AstStringLiteral {
     pub terminated: bool,
 }
 
+impl AstStringLiteral {
+    /// The content with its escape sequences decoded. Invalid escape sequences, which the lexer
+    /// reports, are kept as written.
+    pub fn value(&self) -> Symbol {
+        match decode_string(self.unquoted_content.to_str()) {
+            Ok(value) => Symbol::from_str(value),
+            Err(_) => self.unquoted_content,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct AstComposedIdentifierArg {
     pub node_id: NodeId,
@@ -0,0 +1,521 @@
+use super::*;
+
+/// Replaces every span of a node with the result of `f`.
+pub trait MapSpans {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span);
+}
+
+/// Moves every span of a node by the same distance, so that it can be reused after an edit
+/// before it.
+pub trait ShiftSpans {
+    fn shift_spans(&mut self, delta: i64);
+}
+
+impl<T: MapSpans> ShiftSpans for T {
+    fn shift_spans(&mut self, delta: i64) {
+        self.map_spans(&mut |span| span.shift(delta));
+    }
+}
+
+impl<T: MapSpans> MapSpans for Vec<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        for item in self {
+            item.map_spans(f);
+        }
+    }
+}
+
+impl<T: MapSpans> MapSpans for Option<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        if let Some(item) = self {
+            item.map_spans(f);
+        }
+    }
+}
+
+impl<T: MapSpans> MapSpans for Box<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.as_mut().map_spans(f);
+    }
+}
+
+impl MapSpans for Span {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        *self = f(*self);
+    }
+}
+
+impl MapSpans for AstShaderPack {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.top_levels.map_spans(f);
+    }
+}
+
+impl MapSpans for AstTopLevel {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+
+        match &mut self.kind {
+            AstTopLevelKind::CompTime(comptime) => comptime.map_spans(f),
+            AstTopLevelKind::FnDef(fn_def) => fn_def.map_spans(f),
+            AstTopLevelKind::Import(import) => import.map_spans(f),
+            AstTopLevelKind::Input(input) => input.map_spans(f),
+            AstTopLevelKind::Pass(pass) => pass.map_spans(f),
+        }
+    }
+}
+
+impl MapSpans for AstAttribute {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.items.map_spans(f);
+    }
+}
+
+impl MapSpans for AstDocComment {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+    }
+}
+
+impl MapSpans for AstAttributeItem {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.punc_at.map_spans(f);
+        self.ident.map_spans(f);
+        self.punc_assign.map_spans(f);
+        self.expr.map_spans(f);
+    }
+}
+
+impl<T: MapSpans> MapSpans for AstCompTime<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.keyword_comptime.map_spans(f);
+
+        match &mut self.kind {
+            AstCompTimeKind::Invalid => {}
+            AstCompTimeKind::If(comptime_if) => comptime_if.map_spans(f),
+            AstCompTimeKind::Loop(comptime_loop) => comptime_loop.map_spans(f),
+        }
+    }
+}
+
+impl<T: MapSpans> MapSpans for AstCompTimeIf<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.if_part.map_spans(f);
+        self.else_if_parts.map_spans(f);
+        self.else_part.map_spans(f);
+    }
+}
+
+impl<T: MapSpans> MapSpans for AstCompTimeIfPart<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.keyword_if.map_spans(f);
+        self.predicate.map_spans(f);
+        self.block.map_spans(f);
+    }
+}
+
+impl<T: MapSpans> MapSpans for AstCompTimeElseIfPart<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.keyword_else.map_spans(f);
+        self.keyword_if.map_spans(f);
+        self.predicate.map_spans(f);
+        self.block.map_spans(f);
+    }
+}
+
+impl<T: MapSpans> MapSpans for AstCompTimeElsePart<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.keyword_else.map_spans(f);
+        self.block.map_spans(f);
+    }
+}
+
+impl MapSpans for AstCompTimeIfPredicateExpr {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+
+        match &mut self.kind {
+            AstCompTimeIfPredicateExprKind::Invalid => {}
+            AstCompTimeIfPredicateExprKind::Single(single) => single.map_spans(f),
+            AstCompTimeIfPredicateExprKind::And(and) => and.map_spans(f),
+            AstCompTimeIfPredicateExprKind::Or(or) => or.map_spans(f),
+        }
+    }
+}
+
+impl MapSpans for AstCompTimeIfPredicateExprSingle {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+
+        match &mut self.kind {
+            AstCompTimeIfPredicateExprSingleKind::Invalid => {}
+            AstCompTimeIfPredicateExprSingleKind::Flag(flag) => flag.map_spans(f),
+            AstCompTimeIfPredicateExprSingleKind::Paren(paren) => paren.map_spans(f),
+            AstCompTimeIfPredicateExprSingleKind::Not(not) => not.map_spans(f),
+        }
+    }
+}
+
+impl MapSpans for AstCompTimeIfPredicateExprAnd {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.lhs.map_spans(f);
+        self.keyword_and.map_spans(f);
+        self.rhs.map_spans(f);
+    }
+}
+
+impl MapSpans for AstCompTimeIfPredicateExprOr {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.lhs.map_spans(f);
+        self.keyword_or.map_spans(f);
+        self.rhs.map_spans(f);
+    }
+}
+
+impl MapSpans for AstCompTimeIfPredicateExprFlag {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.flag.map_spans(f);
+    }
+}
+
+impl MapSpans for AstCompTimeIfPredicateExprParen {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.punc_open_paren.map_spans(f);
+        self.expr.map_spans(f);
+        self.punc_close_paren.map_spans(f);
+    }
+}
+
+impl MapSpans for AstCompTimeIfPredicateExprNot {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.keyword_not.map_spans(f);
+        self.expr.map_spans(f);
+    }
+}
+
+impl<T: MapSpans> MapSpans for AstCompTimeLoop<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.keyword_loop.map_spans(f);
+        self.loop_var_ident.map_spans(f);
+        self.keyword_times.map_spans(f);
+        self.expr.map_spans(f);
+        self.block.map_spans(f);
+    }
+}
+
+impl<T: MapSpans> MapSpans for AstCompTimeBlock<T> {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.punc_open_brace.map_spans(f);
+        self.items.map_spans(f);
+        self.punc_close_brace.map_spans(f);
+    }
+}
+
+impl MapSpans for AstFnDef {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.doc.map_spans(f);
+        self.attributes.map_spans(f);
+        self.keyword_pub.map_spans(f);
+        self.keyword_fn.map_spans(f);
+        self.ident.map_spans(f);
+        self.punc_open_paren.map_spans(f);
+        self.params.map_spans(f);
+        self.punc_close_paren.map_spans(f);
+        self.return_type.map_spans(f);
+        self.punc_open_brace.map_spans(f);
+        self.statements.map_spans(f);
+        self.punc_close_brace.map_spans(f);
+    }
+}
+
+impl MapSpans for AstFnDefParam {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.attributes.map_spans(f);
+        self.ident.map_spans(f);
+        self.punc_colon.map_spans(f);
+        self.type_name.map_spans(f);
+        self.punc_comma.map_spans(f);
+    }
+}
+
+impl MapSpans for AstFnDefReturnType {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.punc_arrow.map_spans(f);
+        self.type_name.map_spans(f);
+    }
+}
+
+impl MapSpans for AstImport {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.keyword_import.map_spans(f);
+        self.path.map_spans(f);
+        self.alias.map_spans(f);
+        self.punc_semicolon.map_spans(f);
+    }
+}
+
+impl MapSpans for AstImportAlias {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.keyword_as.map_spans(f);
+        self.ident.map_spans(f);
+    }
+}
+
+impl MapSpans for AstInput {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.doc.map_spans(f);
+        self.attributes.map_spans(f);
+        self.keyword_in.map_spans(f);
+        self.ident.map_spans(f);
+        self.punc_colon.map_spans(f);
+        self.type_name.map_spans(f);
+        self.punc_semicolon.map_spans(f);
+    }
+}
+
+impl MapSpans for AstPass {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.doc.map_spans(f);
+        self.attributes.map_spans(f);
+        self.keyword_pass.map_spans(f);
+        self.ident.map_spans(f);
+        self.punc_open_brace.map_spans(f);
+        self.pass_levels.map_spans(f);
+        self.punc_close_brace.map_spans(f);
+    }
+}
+
+impl MapSpans for AstPassLevel {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+
+        match &mut self.kind {
+            AstPassLevelKind::Input(input) => input.map_spans(f),
+            AstPassLevelKind::Stage(stage) => stage.map_spans(f),
+        }
+    }
+}
+
+impl MapSpans for AstStage {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.attributes.map_spans(f);
+        self.stage.map_spans(f);
+        self.punc_open_brace.map_spans(f);
+        self.statements.map_spans(f);
+        self.punc_close_brace.map_spans(f);
+    }
+}
+
+impl MapSpans for AstStatement {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+
+        match &mut self.kind {
+            AstStatementKind::VarDecl(var_decl) => {
+                var_decl.keyword_let.map_spans(f);
+                var_decl.ident.map_spans(f);
+
+                if let Some(type_name) = &mut var_decl.type_name {
+                    type_name.span.map_spans(f);
+                    type_name.punc_colon.map_spans(f);
+                    type_name.type_name.map_spans(f);
+                }
+
+                if let Some(assignment) = &mut var_decl.assignment {
+                    assignment.span.map_spans(f);
+                    assignment.punc_assignment.map_spans(f);
+                    assignment.rhs.map_spans(f);
+                }
+
+                var_decl.punc_semicolon.map_spans(f);
+            }
+            AstStatementKind::Assignment(assignment) => {
+                assignment.op.span.map_spans(f);
+                assignment.lhs.map_spans(f);
+                assignment.rhs.map_spans(f);
+                assignment.punc_semicolon.map_spans(f);
+            }
+            AstStatementKind::Return(statement_return) => {
+                statement_return.keyword_return.map_spans(f);
+                statement_return.expr.map_spans(f);
+                statement_return.punc_semicolon.map_spans(f);
+            }
+            AstStatementKind::Expr(expr) => expr.map_spans(f),
+        }
+    }
+}
+
+impl MapSpans for AstExpr {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+
+        match &mut self.kind {
+            AstExprKind::Invalid => {}
+            AstExprKind::Binary(binary) => {
+                binary.span.map_spans(f);
+                binary.op.span.map_spans(f);
+                binary.lhs.map_spans(f);
+                binary.rhs.map_spans(f);
+            }
+            AstExprKind::Unary(unary) => {
+                unary.span.map_spans(f);
+                unary.op.span.map_spans(f);
+                unary.rhs.map_spans(f);
+            }
+            AstExprKind::Literal(literal) => {
+                literal.span.map_spans(f);
+
+                if let AstLiteralKind::String(string) = &mut literal.kind {
+                    string.map_spans(f);
+                }
+            }
+            AstExprKind::Identifier(ident) => ident.map_spans(f),
+            AstExprKind::Call(call) => {
+                call.span.map_spans(f);
+
+                if let Some(qualifier) = &mut call.qualifier {
+                    qualifier.span.map_spans(f);
+                    qualifier.module.map_spans(f);
+                    qualifier.punc_path_sep.map_spans(f);
+                }
+
+                call.callee.map_spans(f);
+                call.punc_open_paren.map_spans(f);
+
+                for arg in &mut call.args {
+                    arg.span.map_spans(f);
+                    arg.expr.map_spans(f);
+                    arg.punc_comma.map_spans(f);
+                }
+
+                call.punc_close_paren.map_spans(f);
+            }
+            AstExprKind::Member(member) => {
+                member.span.map_spans(f);
+                member.lhs.map_spans(f);
+                member.punc_dot.map_spans(f);
+                member.member.map_spans(f);
+            }
+            AstExprKind::Index(index) => {
+                index.span.map_spans(f);
+                index.lhs.map_spans(f);
+                index.punc_open_bracket.map_spans(f);
+                index.index.map_spans(f);
+                index.punc_close_bracket.map_spans(f);
+            }
+            AstExprKind::Object(object) => {
+                object.span.map_spans(f);
+                object.punc_open_brace.map_spans(f);
+
+                for field in &mut object.fields {
+                    field.span.map_spans(f);
+                    field.ident.map_spans(f);
+                    field.punc_colon.map_spans(f);
+                    field.expr.map_spans(f);
+                    field.punc_comma.map_spans(f);
+                }
+
+                object.punc_close_brace.map_spans(f);
+            }
+        }
+    }
+}
+
+impl MapSpans for AstPunc {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+    }
+}
+
+impl MapSpans for AstKeyword {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+    }
+}
+
+impl MapSpans for AstIdentifier {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+
+        if let AstIdentifierKind::Composed(composed) = &mut self.kind {
+            composed.punc_bang.map_spans(f);
+            composed.keyword_ident.map_spans(f);
+            composed.punc_open_paren.map_spans(f);
+            composed.rule_str.map_spans(f);
+            composed.punc_comma.map_spans(f);
+
+            for arg in &mut composed.args {
+                arg.span.map_spans(f);
+                arg.expr.map_spans(f);
+                arg.punc_comma.map_spans(f);
+            }
+
+            composed.punc_close_paren.map_spans(f);
+        }
+    }
+}
+
+impl MapSpans for AstStringLiteral {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+    }
+}
+
+impl MapSpans for AstTypeName {
+    fn map_spans(&mut self, f: &mut dyn FnMut(Span) -> Span) {
+        self.span.map_spans(f);
+        self.ident.map_spans(f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_shift_spans() {
+        let mut call = expr_call(
+            ident_at("f(a)", "f", 0),
+            vec![expr(AstExprKind::Identifier(ident_at("f(a)", "a", 0)))],
+        );
+        call.span = Span::new(0, 4);
+        call.shift_spans(10);
+
+        let AstExprKind::Call(call_expr) = &call.kind else {
+            unreachable!()
+        };
+        assert_eq!(call.span, Span::new(10, 14));
+        assert_eq!(call_expr.callee.span, Span::new(10, 11));
+        let AstExprKind::Identifier(arg) = &call_expr.args[0].expr.kind else {
+            unreachable!()
+        };
+        assert_eq!(arg.span, Span::new(12, 13));
+
+        call.shift_spans(-10);
+        assert_eq!(call.span, Span::new(0, 4));
+    }
+}
@@ -39,8 +39,9 @@ where
         }
     }
 
+    /// Returns `false` at the end of the file, or once the reporter discards every further item.
     pub fn has_token(&self) -> bool {
-        self.lookahead_0.exists()
+        self.lookahead_0.exists() && !self.diagnostics_sender.is_full()
     }
 
     pub fn lookahead_0(&self) -> LookaheadToken {
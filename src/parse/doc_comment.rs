@@ -0,0 +1,153 @@
+use super::{
+    ast::{
+        AstAttribute, AstCompTimeKind, AstDocComment, AstInput, AstKeyword, AstPassLevelKind,
+        AstShaderPack, AstTopLevel, AstTopLevelKind,
+    },
+    lexer::{Token, TokenKind},
+};
+use crate::span::Span;
+use rustc_hash::FxHashMap;
+
+/// Attaches the doc comments of the tokens to the `in`, `fn` and `pass` items right after them,
+/// including the ones in `comptime` items and passes; other doc comments are dropped.
+///
+/// Every item is given the doc comment in front of it, or `None`, so that the pack can be
+/// documented again after an edit.
+pub(crate) fn attach_doc_comments(pack: &mut AstShaderPack, tokens: &[Token]) {
+    let mut docs = collect_doc_comments(tokens);
+    attach_top_levels(&mut pack.top_levels, &mut docs);
+}
+
+/// Returns the runs of doc comments, by the position of the token after them; comments and
+/// whitespaces may come in between.
+fn collect_doc_comments(tokens: &[Token]) -> FxHashMap<u32, AstDocComment> {
+    let mut docs = FxHashMap::default();
+    let mut current: Option<AstDocComment> = None;
+
+    for token in tokens {
+        match token.kind {
+            TokenKind::DocComment { content, .. } => match &mut current {
+                Some(doc) => {
+                    doc.span = Span::merge(doc.span, token.span());
+                    doc.lines.push(content);
+                }
+                None => {
+                    current = Some(AstDocComment {
+                        span: token.span(),
+                        lines: vec![content],
+                    })
+                }
+            },
+            TokenKind::EndOfFile => break,
+            kind if kind.is_trivia() => {}
+            _ => {
+                if let Some(doc) = current.take() {
+                    docs.insert(token.span_low, doc);
+                }
+            }
+        }
+    }
+
+    docs
+}
+
+fn attach_top_levels(top_levels: &mut [AstTopLevel], docs: &mut FxHashMap<u32, AstDocComment>) {
+    for top_level in top_levels {
+        match &mut top_level.kind {
+            AstTopLevelKind::CompTime(comptime) => match &mut comptime.kind {
+                AstCompTimeKind::Invalid => {}
+                AstCompTimeKind::If(comptime_if) => {
+                    attach_top_levels(&mut comptime_if.if_part.block.items, docs);
+
+                    for part in &mut comptime_if.else_if_parts {
+                        attach_top_levels(&mut part.block.items, docs);
+                    }
+
+                    if let Some(part) = &mut comptime_if.else_part {
+                        attach_top_levels(&mut part.block.items, docs);
+                    }
+                }
+                AstCompTimeKind::Loop(comptime_loop) => {
+                    attach_top_levels(&mut comptime_loop.block.items, docs);
+                }
+            },
+            AstTopLevelKind::FnDef(fn_def) => {
+                fn_def.doc = take_doc(
+                    docs,
+                    &fn_def.attributes,
+                    [fn_def.keyword_pub.as_ref(), Some(&fn_def.keyword_fn)],
+                );
+            }
+            AstTopLevelKind::Import(_) => {}
+            AstTopLevelKind::Input(input) => attach_input(input, docs),
+            AstTopLevelKind::Pass(pass) => {
+                pass.doc = take_doc(docs, &pass.attributes, [Some(&pass.keyword_pass)]);
+
+                for pass_level in &mut pass.pass_levels {
+                    if let AstPassLevelKind::Input(input) = &mut pass_level.kind {
+                        attach_input(input, docs);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn attach_input(input: &mut AstInput, docs: &mut FxHashMap<u32, AstDocComment>) {
+    input.doc = take_doc(docs, &input.attributes, [Some(&input.keyword_in)]);
+}
+
+/// Takes the doc comment in front of the first attribute of an item, or in front of one of its
+/// keywords, since doc comments may also follow the attributes.
+fn take_doc<'a>(
+    docs: &mut FxHashMap<u32, AstDocComment>,
+    attributes: &[AstAttribute],
+    keywords: impl IntoIterator<Item = Option<&'a AstKeyword>>,
+) -> Option<AstDocComment> {
+    let lows = attributes
+        .first()
+        .map(|attribute| attribute.span.low())
+        .into_iter()
+        .chain(
+            keywords
+                .into_iter()
+                .flatten()
+                .map(|keyword| keyword.span.low()),
+        );
+
+    for low in lows {
+        if let Some(doc) = docs.remove(&low) {
+            return Some(doc);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse::lexer::lex, span::SourceMap, symbol::Symbol};
+
+    #[test]
+    fn test_collect_doc_comments() {
+        let source = "## Base color.\n# plain\n##\n## Linear.\nin color: f3;\n## dropped\n";
+        let file = SourceMap::new().add_file(source, "test", None);
+        let docs = collect_doc_comments(&lex(&file));
+
+        let low = source.find("in color").unwrap() as u32;
+        assert_eq!(docs.len(), 1);
+        assert_eq!(
+            docs[&low],
+            AstDocComment {
+                span: Span::new(0, 36),
+                lines: vec![
+                    Symbol::from_str(" Base color."),
+                    Symbol::from_str(""),
+                    Symbol::from_str(" Linear."),
+                ],
+            }
+        );
+        assert_eq!(docs[&low].text(), "Base color.\n\nLinear.");
+    }
+}
@@ -0,0 +1,109 @@
+use rustc_hash::FxHashMap;
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Component, Path, PathBuf},
+};
+
+/// Loads the files imported by a shader pack.
+pub trait FileLoader {
+    /// Returns the path identifying the file at `path`, so that a file imported through
+    /// different paths, e.g. `a/../b.spk` and `b.spk`, is loaded only once.
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        normalize_path(path)
+    }
+
+    /// Returns the content of the file at the canonical `path`.
+    fn load(&self, path: &Path) -> Result<String>;
+}
+
+/// Loads files from the file system; symbolic links are resolved when canonicalizing.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FsFileLoader;
+
+impl FileLoader for FsFileLoader {
+    fn canonicalize(&self, path: &Path) -> PathBuf {
+        fs::canonicalize(path).unwrap_or_else(|_| normalize_path(path))
+    }
+
+    fn load(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// Loads files from memory, e.g. in tests or in a browser.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryFileLoader {
+    files: FxHashMap<PathBuf, String>,
+}
+
+impl MemoryFileLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_file(&mut self, path: impl AsRef<Path>, content: impl Into<String>) {
+        self.files
+            .insert(normalize_path(path.as_ref()), content.into());
+    }
+}
+
+impl FileLoader for MemoryFileLoader {
+    fn load(&self, path: &Path) -> Result<String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "file not found"))
+    }
+}
+
+/// Removes the `.` components of a path, and the `..` components along with their parent,
+/// without looking at the file system.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `..` cannot go above the root
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(component),
+            },
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        let normalize = |path: &str| normalize_path(Path::new(path));
+        assert_eq!(normalize("a/./b.spk"), Path::new("a/b.spk"));
+        assert_eq!(normalize("a/../b.spk"), Path::new("b.spk"));
+        assert_eq!(normalize("../a/../../b.spk"), Path::new("../../b.spk"));
+        assert_eq!(normalize("/../b.spk"), Path::new("/b.spk"));
+    }
+
+    #[test]
+    fn test_memory_file_loader() {
+        let mut loader = MemoryFileLoader::new();
+        loader.add_file("./common/lighting.spk", "fn light() {}");
+
+        let path = loader.canonicalize(Path::new("shaders/../common/lighting.spk"));
+        assert_eq!(path, Path::new("common/lighting.spk"));
+        assert_eq!(loader.load(&path).unwrap(), "fn light() {}");
+        assert_eq!(
+            loader.load(Path::new("missing.spk")).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,618 @@
+use super::{
+    ast::{
+        AstCompTime, AstIdentifierKind, AstImport, AstShaderPack, AstTopLevel, AstTopLevelKind,
+        NodeIdAllocator,
+    },
+    modules::{comptime_blocks, resolve_paths, Module, ModuleImport},
+    parse_shader_pack_with_allocator, FileLoader,
+};
+use crate::{
+    diagnostics::{
+        codes::{
+            IMPORT_ERR_AMBIGUOUS, IMPORT_ERR_CANNOT_LOAD, IMPORT_ERR_CYCLE,
+            IMPORT_ERR_NOT_TOP_LEVEL,
+        },
+        ItemSender,
+    },
+    span::{SourceFile, SourceMap, Span},
+};
+use rustc_hash::FxHashMap;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Parses the given file along with the files it imports, transitively, into a single AST.
+///
+/// Every `import` item is replaced by the items of the imported file, which is added to the
+/// `source_map`; a file imported more than once is only included at its first import. Paths are
+/// relative to the directory of the importing file.
+///
+/// Every file is a module: its functions can only be called from the files importing it if they
+/// are `pub`, either by their name, or through the alias of the import, e.g. `noise::perlin(p)`.
+/// Functions of imported files are renamed after their file, and calls are resolved to the new
+/// names. Returns `None` if an import or a call could not be resolved, or if any of the files
+/// could not be parsed.
+pub fn parse_shader_pack_with_imports(
+    file: &Arc<SourceFile>,
+    source_map: &mut SourceMap,
+    loader: &dyn FileLoader,
+    reporter: &ItemSender,
+) -> Option<AstShaderPack> {
+    resolve_imports(
+        file,
+        source_map,
+        loader,
+        reporter,
+        parse_shader_pack_with_allocator,
+    )
+}
+
+fn resolve_imports<P>(
+    file: &Arc<SourceFile>,
+    source_map: &mut SourceMap,
+    loader: &dyn FileLoader,
+    reporter: &ItemSender,
+    mut parse: P,
+) -> Option<AstShaderPack>
+where
+    P: FnMut(&SourceFile, &ItemSender, &mut NodeIdAllocator) -> Option<AstShaderPack>,
+{
+    let mut id_allocator = NodeIdAllocator::new();
+    let pack = parse(file, reporter, &mut id_allocator)?;
+    let mut importer = Importer {
+        source_map,
+        loader,
+        parse,
+        id_allocator,
+        included: FxHashMap::default(),
+        modules: Vec::new(),
+        stack: Vec::new(),
+        has_error: false,
+    };
+    let path = file.path().map(|path| loader.canonicalize(path));
+    let mut top_levels = Vec::with_capacity(pack.top_levels.len());
+    importer.include(
+        pack.top_levels,
+        path,
+        reporter.clone(),
+        None,
+        &mut top_levels,
+    );
+
+    if !resolve_paths(&importer.modules, &mut top_levels) || importer.has_error {
+        return None;
+    }
+
+    Some(AstShaderPack {
+        node_id: pack.node_id,
+        span: pack.span,
+        top_levels: Vec::from_iter(top_levels.into_iter().map(|(_, top_level)| top_level)),
+    })
+}
+
+struct Importer<'a, P> {
+    source_map: &'a mut SourceMap,
+    loader: &'a dyn FileLoader,
+    parse: P,
+    /// Shared by every file, so that node ids are unique across the pack.
+    id_allocator: NodeIdAllocator,
+    /// Module of every file included so far, by canonical path; `None` if it could not be parsed.
+    included: FxHashMap<PathBuf, Option<usize>>,
+    modules: Vec<Module>,
+    /// Files being included, from the root file to the innermost one.
+    stack: Vec<IncludedFile>,
+    has_error: bool,
+}
+
+struct IncludedFile {
+    path: Option<PathBuf>,
+    reporter: ItemSender,
+    /// Span of the `import` item of the file, in the file below it in the stack.
+    import_span: Option<Span>,
+}
+
+impl<P> Importer<'_, P>
+where
+    P: FnMut(&SourceFile, &ItemSender, &mut NodeIdAllocator) -> Option<AstShaderPack>,
+{
+    /// Adds the items of a file to the `output`, along with the index of its module.
+    fn include(
+        &mut self,
+        top_levels: Vec<AstTopLevel>,
+        path: Option<PathBuf>,
+        reporter: ItemSender,
+        import_span: Option<Span>,
+        output: &mut Vec<(usize, AstTopLevel)>,
+    ) -> usize {
+        let module = self.modules.len();
+        self.modules.push(Module::new(
+            reporter.clone(),
+            &top_levels,
+            import_span.is_none(),
+        ));
+
+        if let Some(path) = &path {
+            self.included.insert(path.clone(), Some(module));
+        }
+
+        self.stack.push(IncludedFile {
+            path,
+            reporter: reporter.clone(),
+            import_span,
+        });
+
+        for top_level in top_levels {
+            match &top_level.kind {
+                AstTopLevelKind::Import(import) => {
+                    if let Some(imported) = self.import(import, &reporter, output) {
+                        self.add_import(module, imported, import);
+                    }
+                }
+                AstTopLevelKind::CompTime(comptime) => {
+                    self.reject_nested_imports(comptime, &reporter);
+                    output.push((module, top_level));
+                }
+                _ => output.push((module, top_level)),
+            }
+        }
+
+        self.stack.pop();
+        module
+    }
+
+    /// Includes the imported file unless it has already been, and returns its module.
+    fn import(
+        &mut self,
+        import: &AstImport,
+        reporter: &ItemSender,
+        output: &mut Vec<(usize, AstTopLevel)>,
+    ) -> Option<usize> {
+        // already reported by the parser
+        if !import.path.terminated {
+            self.has_error = true;
+            return None;
+        }
+
+        let relative = Path::new(import.path.value().to_str());
+        let joined = match reporter.file().path().and_then(Path::parent) {
+            Some(dir) => dir.join(relative),
+            None => relative.to_path_buf(),
+        };
+        let path = self.loader.canonicalize(&joined);
+
+        if let Some(index) = self
+            .stack
+            .iter()
+            .position(|included| included.path.as_ref() == Some(&path))
+        {
+            let sub_items = Vec::from_iter(self.stack.windows(2).skip(index).filter_map(|pair| {
+                let span = pair[1].import_span?;
+                let name = pair[1].reporter.file().name().to_owned();
+                Some(
+                    pair[0]
+                        .reporter
+                        .sub_hint(span, format!("`{}` is imported here", name)),
+                )
+            }));
+            reporter.error_sub(
+                IMPORT_ERR_CYCLE,
+                import.span,
+                format!("`{}` imports itself", joined.display()),
+                sub_items,
+            );
+            self.has_error = true;
+            return None;
+        }
+
+        if let Some(module) = self.included.get(&path) {
+            return *module;
+        }
+
+        let content = match self.loader.load(&path) {
+            Ok(content) => content,
+            Err(err) => {
+                reporter.error(
+                    IMPORT_ERR_CANNOT_LOAD,
+                    import.path.span,
+                    format!("cannot load `{}`: {}", joined.display(), err),
+                );
+                self.has_error = true;
+                return None;
+            }
+        };
+
+        let file = self
+            .source_map
+            .add_file(content, joined.to_string_lossy(), Some(path.clone()));
+        let file_reporter = reporter.with_file(file.clone());
+
+        match (self.parse)(&file, &file_reporter, &mut self.id_allocator) {
+            Some(pack) => Some(self.include(
+                pack.top_levels,
+                Some(path),
+                file_reporter,
+                Some(import.span),
+                output,
+            )),
+            None => {
+                self.included.insert(path, None);
+                self.has_error = true;
+                None
+            }
+        }
+    }
+
+    fn add_import(&mut self, module: usize, imported: usize, import: &AstImport) {
+        let alias = import
+            .alias
+            .as_ref()
+            .and_then(|alias| match alias.ident.kind {
+                AstIdentifierKind::Symbol(symbol) => Some((symbol, alias.ident.span)),
+                _ => None,
+            });
+        let module = &mut self.modules[module];
+
+        if let Some((alias, span)) = alias {
+            if let Some(previous) = module
+                .imports
+                .iter()
+                .find(|previous| previous.alias == Some(alias))
+            {
+                module.reporter.error_sub(
+                    IMPORT_ERR_AMBIGUOUS,
+                    span,
+                    format!("{} is the alias of more than one import", alias),
+                    vec![module
+                        .reporter
+                        .sub_hint(previous.span, "the alias is first used here")],
+                );
+                self.has_error = true;
+                return;
+            }
+        }
+
+        module.imports.push(ModuleImport {
+            module: imported,
+            alias: alias.map(|(alias, _)| alias),
+            span: import.span,
+        });
+    }
+
+    fn reject_nested_imports(
+        &mut self,
+        comptime: &AstCompTime<AstTopLevel>,
+        reporter: &ItemSender,
+    ) {
+        for top_level in comptime_blocks(comptime).into_iter().flatten() {
+            match &top_level.kind {
+                AstTopLevelKind::Import(import) => {
+                    reporter.error(
+                        IMPORT_ERR_NOT_TOP_LEVEL,
+                        import.span,
+                        "`import` items cannot be nested in `comptime` items",
+                    );
+                    self.has_error = true;
+                }
+                AstTopLevelKind::CompTime(comptime) => {
+                    self.reject_nested_imports(comptime, reporter)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{
+            codes::{IMPORT_ERR_PRIVATE, TYPE_ERR_UNDEFINED_NAME},
+            Item, ItemCollector,
+        },
+        parse::{
+            ast::{AstExprKind, AstImportAlias, AstStatementKind, ShiftSpans},
+            MemoryFileLoader,
+        },
+        test_utils::{
+            comptime_if, flag, fn_def_calling, ident, import, input, keyword, node_id, shader_pack,
+            top_level_names,
+        },
+    };
+
+    /// Resolves the imports of `main.spk`, parsing every file into the pack given for its name;
+    /// the spans of each pack are moved to the start of its file.
+    fn resolve(packs: Vec<(&str, AstShaderPack)>) -> (Option<AstShaderPack>, SourceMap, Vec<Item>) {
+        let mut loader = MemoryFileLoader::new();
+
+        for (name, _) in &packs {
+            loader.add_file(name, format!("# {}", name));
+        }
+
+        let packs = FxHashMap::from_iter(packs);
+        let mut source_map = SourceMap::new();
+        let file = source_map.add_file("# main.spk", "main.spk", Some(PathBuf::from("main.spk")));
+        let collector = Arc::new(ItemCollector::new());
+        let reporter = ItemSender::new(file.clone(), collector.clone());
+        let pack = resolve_imports(
+            &file,
+            &mut source_map,
+            &loader,
+            &reporter,
+            |file: &SourceFile, _: &ItemSender, _: &mut NodeIdAllocator| {
+                let mut pack = packs[file.name()].clone();
+                pack.shift_spans(file.span().low() as i64);
+                Some(pack)
+            },
+        );
+        drop(reporter);
+
+        let items = collector.take();
+
+        (pack, source_map, items)
+    }
+
+    #[test]
+    fn test_resolve_imports() {
+        let (pack, source_map, items) = resolve(vec![
+            (
+                "main.spk",
+                shader_pack(vec![
+                    import("common/lighting.spk"),
+                    input(ident("main"), "f"),
+                    import("b.spk"),
+                ]),
+            ),
+            (
+                "common/lighting.spk",
+                shader_pack(vec![input(ident("light"), "f")]),
+            ),
+            (
+                "b.spk",
+                shader_pack(vec![
+                    import("./common/../common/lighting.spk"),
+                    input(ident("b"), "f"),
+                ]),
+            ),
+        ]);
+
+        assert!(items.is_empty());
+        assert_eq!(top_level_names(&pack.unwrap()), ["light", "main", "b"]);
+        assert_eq!(
+            Vec::from_iter(source_map.files().iter().map(|file| file.name())),
+            ["main.spk", "common/lighting.spk", "b.spk"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_imports_errors() {
+        let (pack, source_map, items) = resolve(vec![
+            ("main.spk", shader_pack(vec![import("a.spk")])),
+            (
+                "a.spk",
+                shader_pack(vec![
+                    import("missing.spk"),
+                    comptime_if(flag("A"), vec![import("b.spk")], None),
+                    import("b.spk"),
+                ]),
+            ),
+            ("b.spk", shader_pack(vec![import("main.spk")])),
+        ]);
+        let file_names = |item: &Item| {
+            let mut names = vec![item.origin.as_ref().unwrap().file.name().to_owned()];
+            names.extend(
+                item.sub_items
+                    .iter()
+                    .map(|sub_item| sub_item.origin.as_ref().unwrap().file.name().to_owned()),
+            );
+            names
+        };
+
+        assert!(pack.is_none());
+        assert_eq!(
+            Vec::from_iter(items.iter().map(|item| item.code)),
+            [
+                IMPORT_ERR_CANNOT_LOAD,
+                IMPORT_ERR_NOT_TOP_LEVEL,
+                IMPORT_ERR_CYCLE
+            ]
+        );
+        assert_eq!(file_names(&items[0]), ["a.spk"]);
+        assert_eq!(file_names(&items[1]), ["a.spk"]);
+        // the cycle is listed from the root file
+        assert_eq!(file_names(&items[2]), ["b.spk", "main.spk", "a.spk"]);
+        assert_eq!(items[2].message, "`main.spk` imports itself");
+
+        // spans of imported items are reported in their own file
+        let b = source_map
+            .lookup_file(source_map.files()[2].span().low())
+            .unwrap();
+        let collector = Arc::new(ItemCollector::new());
+        let reporter = ItemSender::new(source_map.files()[0].clone(), collector.clone())
+            .with_source_map(Arc::new(source_map.clone()));
+        reporter.error(0, Span::new(b.span().low(), b.span().low() + 1), "error");
+        assert_eq!(
+            collector.take()[0].origin.as_ref().unwrap().file.name(),
+            "b.spk"
+        );
+    }
+
+    fn import_as(path: &str, alias: &str) -> AstTopLevelKind {
+        let mut kind = import(path);
+
+        if let AstTopLevelKind::Import(import) = &mut kind {
+            import.alias = Some(AstImportAlias {
+                node_id: node_id(),
+                span: Span::ZERO,
+                keyword_as: keyword("as"),
+                ident: ident(alias),
+            });
+        }
+
+        kind
+    }
+
+    /// Returns every function of the pack, with the functions it calls.
+    fn functions(pack: &AstShaderPack) -> Vec<(String, Vec<String>)> {
+        let name = |kind: &AstIdentifierKind| match kind {
+            AstIdentifierKind::Symbol(symbol) => symbol.to_str().to_owned(),
+            _ => unreachable!(),
+        };
+
+        Vec::from_iter(pack.top_levels.iter().filter_map(|top_level| {
+            let AstTopLevelKind::FnDef(fn_def) = &top_level.kind else {
+                return None;
+            };
+            let calls =
+                Vec::from_iter(
+                    fn_def
+                        .statements
+                        .iter()
+                        .map(|statement| match &statement.kind {
+                            AstStatementKind::Expr(expr) => match &expr.kind {
+                                AstExprKind::Call(call) => {
+                                    assert!(call.qualifier.is_none());
+                                    name(&call.callee.kind)
+                                }
+                                _ => unreachable!(),
+                            },
+                            _ => unreachable!(),
+                        }),
+                );
+            Some((name(&fn_def.ident.kind), calls))
+        }))
+    }
+
+    #[test]
+    fn test_resolve_module_paths() {
+        let (pack, _, items) = resolve(vec![
+            (
+                "main.spk",
+                shader_pack(vec![
+                    import_as("noise.spk", "noise"),
+                    import("lighting.spk"),
+                    fn_def_calling("main", false, &["noise::perlin", "light", "hash", "f3"]),
+                    fn_def_calling("hash", false, &[]),
+                ]),
+            ),
+            (
+                "noise.spk",
+                shader_pack(vec![
+                    fn_def_calling("perlin", true, &["hash"]),
+                    fn_def_calling("hash", false, &[]),
+                ]),
+            ),
+            (
+                "lighting.spk",
+                shader_pack(vec![
+                    import("noise.spk"),
+                    fn_def_calling("light", true, &["perlin"]),
+                ]),
+            ),
+        ]);
+        let expected = |pairs: &[(&str, &[&str])]| {
+            Vec::from_iter(pairs.iter().map(|(name, calls)| {
+                let calls = Vec::from_iter(calls.iter().map(|call| call.to_string()));
+                (name.to_string(), calls)
+            }))
+        };
+
+        assert!(items.is_empty());
+        assert_eq!(
+            functions(&pack.unwrap()),
+            expected(&[
+                ("noise.spk::perlin", &["noise.spk::hash"]),
+                ("noise.spk::hash", &[]),
+                ("lighting.spk::light", &["noise.spk::perlin"]),
+                (
+                    "main",
+                    &["noise.spk::perlin", "lighting.spk::light", "hash", "f3"]
+                ),
+                ("hash", &[]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_resolve_module_paths_errors() {
+        let (pack, _, items) = resolve(vec![
+            (
+                "main.spk",
+                shader_pack(vec![
+                    import("a.spk"),
+                    import("b.spk"),
+                    import_as("a.spk", "x"),
+                    import_as("b.spk", "x"),
+                    fn_def_calling(
+                        "main",
+                        false,
+                        &["x::private", "x::missing", "y::f", "private", "f"],
+                    ),
+                ]),
+            ),
+            (
+                "a.spk",
+                shader_pack(vec![
+                    fn_def_calling("f", true, &[]),
+                    fn_def_calling("private", false, &[]),
+                ]),
+            ),
+            (
+                "b.spk",
+                shader_pack(vec![
+                    import("a.spk"),
+                    fn_def_calling("f", true, &["private"]),
+                ]),
+            ),
+        ]);
+        let errors = Vec::from_iter(items.iter().map(|item| {
+            let file = item.origin.as_ref().unwrap().file.name();
+            (item.code, file, item.message.as_str())
+        }));
+
+        assert!(pack.is_none());
+        assert_eq!(
+            errors,
+            [
+                (
+                    IMPORT_ERR_AMBIGUOUS,
+                    "main.spk",
+                    "`x` is the alias of more than one import"
+                ),
+                (
+                    IMPORT_ERR_PRIVATE,
+                    "b.spk",
+                    "function `private` is private to `a.spk`"
+                ),
+                (
+                    IMPORT_ERR_PRIVATE,
+                    "main.spk",
+                    "function `private` is private to `a.spk`"
+                ),
+                (
+                    TYPE_ERR_UNDEFINED_NAME,
+                    "main.spk",
+                    "module `x` has no function `missing`"
+                ),
+                (TYPE_ERR_UNDEFINED_NAME, "main.spk", "undefined module `y`"),
+                (
+                    IMPORT_ERR_PRIVATE,
+                    "main.spk",
+                    "function `private` is private to `a.spk`"
+                ),
+                (
+                    IMPORT_ERR_AMBIGUOUS,
+                    "main.spk",
+                    "`f` is ambiguous; it is exported by several imports"
+                ),
+            ]
+        );
+        // the definition of a private function is pointed at in its own file
+        assert_eq!(
+            items[1].sub_items[0].origin.as_ref().unwrap().file.name(),
+            "a.spk"
+        );
+        assert_eq!(items[6].sub_items.len(), 2);
+    }
+}
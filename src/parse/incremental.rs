@@ -0,0 +1,521 @@
+use super::{
+    ast::{AstShaderPack, AstTopLevel, NodeIdAllocator, ShiftSpans},
+    cursor::Cursor,
+    doc_comment::attach_doc_comments,
+    lexer::{lex, relex, validate_token, Relexed, Token},
+    parse::Parse,
+};
+use crate::{
+    diagnostics::{Item, ItemCollector, ItemOrigin, ItemSender},
+    span::{SourceEdit, SourceFile},
+};
+use std::{iter::repeat, ops::Range, sync::Arc};
+
+/// A parsed file which can be edited, e.g. on every keystroke in an editor.
+///
+/// After an edit, only the tokens around the edit are lexed again, and only the top-level items
+/// which may have changed are parsed again; the others are reused, moved along with the edit.
+#[derive(Debug, Clone)]
+pub struct IncrementalParse {
+    file: Arc<SourceFile>,
+    tokens: Vec<Token>,
+    /// Diagnostics of the tokens, in order.
+    lex_items: Vec<Item>,
+    pack: AstShaderPack,
+    /// One per top-level item of the pack.
+    parsed: Vec<ParsedTopLevel>,
+    /// Diagnostics reported after the last top-level item.
+    trailing_items: Vec<Item>,
+    /// Indicates if every top-level item could be parsed.
+    complete: bool,
+    id_allocator: NodeIdAllocator,
+}
+
+#[derive(Debug, Clone)]
+struct ParsedTopLevel {
+    /// Indices of the tokens from the start of the item to the start of the next one.
+    tokens: Range<usize>,
+    /// Index past the last token the parser looked at while parsing the item.
+    lookahead_end: usize,
+    /// Diagnostics reported while parsing the item.
+    items: Vec<Item>,
+}
+
+/// Top-level items after an edit, which are reused once parsing gets back in sync with them.
+#[derive(Debug, Default)]
+struct Suffix {
+    top_levels: Vec<AstTopLevel>,
+    parsed: Vec<ParsedTopLevel>,
+    trailing_items: Vec<Item>,
+    complete: bool,
+    old_end: usize,
+    new_end: usize,
+    delta: i64,
+}
+
+impl IncrementalParse {
+    pub fn new(file: Arc<SourceFile>) -> Self {
+        let mut id_allocator = NodeIdAllocator::new();
+        let pack = AstShaderPack {
+            node_id: id_allocator.allocate(),
+            span: file.span(),
+            top_levels: vec![],
+        };
+        let tokens = lex(&file);
+        let mut this = Self {
+            lex_items: validate_tokens(&file, &tokens),
+            tokens,
+            file,
+            pack,
+            parsed: vec![],
+            trailing_items: vec![],
+            complete: true,
+            id_allocator,
+        };
+        this.parse(0, Suffix::default());
+        attach_doc_comments(&mut this.pack, &this.tokens);
+        this
+    }
+
+    pub fn file(&self) -> &Arc<SourceFile> {
+        &self.file
+    }
+
+    /// Returns every token of the file, including whitespaces, comments and the end of file.
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Returns the AST of the file; `None` if a top-level item could not be parsed.
+    pub fn pack(&self) -> Option<&AstShaderPack> {
+        self.complete.then_some(&self.pack)
+    }
+
+    /// Returns the diagnostics reported while lexing the file, then those reported while parsing
+    /// it, in order.
+    pub fn diagnostics(&self) -> impl Iterator<Item = &Item> {
+        self.lex_items.iter().chain(
+            self.parsed
+                .iter()
+                .flat_map(|parsed| &parsed.items)
+                .chain(&self.trailing_items),
+        )
+    }
+
+    /// Applies an edit to the file, and updates the tokens and the AST.
+    pub fn edit(&mut self, edit: &SourceEdit) {
+        let file = Arc::new(self.file.with_edit(edit));
+        let relexed = relex(&self.tokens, &file, edit);
+
+        // items are parsed again if the parser looked at any token which has changed
+        let prefix = self
+            .parsed
+            .iter()
+            .take_while(|parsed| parsed.lookahead_end <= relexed.old_range.start)
+            .count();
+        let start = match prefix {
+            0 => 0,
+            _ => self.parsed[prefix - 1].tokens.end,
+        };
+        let suffix = Suffix {
+            top_levels: self.pack.top_levels.split_off(prefix),
+            parsed: self.parsed.split_off(prefix),
+            trailing_items: std::mem::take(&mut self.trailing_items),
+            complete: self.complete,
+            old_end: relexed.old_range.end,
+            new_end: relexed.new_range.end,
+            delta: edit.delta(),
+        };
+
+        for parsed in &mut self.parsed {
+            for item in &mut parsed.items {
+                move_item(item, &file, 0);
+            }
+        }
+
+        revalidate(
+            &mut self.lex_items,
+            &self.tokens,
+            &relexed,
+            &file,
+            edit.delta(),
+        );
+
+        self.pack.span = file.span();
+        self.file = file;
+        self.tokens = relexed.tokens;
+        self.parse(start, suffix);
+
+        // reused items may have been documented differently
+        attach_doc_comments(&mut self.pack, &self.tokens);
+    }
+
+    /// Parses the top-level items from the token at `start`, until the end of the file or until
+    /// the items of the `suffix` can be reused.
+    fn parse(&mut self, start: usize, mut suffix: Suffix) {
+        let Self {
+            file,
+            tokens,
+            pack,
+            parsed,
+            trailing_items,
+            complete,
+            id_allocator,
+            ..
+        } = self;
+        let index_of = |token: Token| {
+            tokens
+                .binary_search_by_key(&token.span_low, |token| token.span_low)
+                .unwrap()
+        };
+        let collector = Arc::new(ItemCollector::new());
+        let reporter = ItemSender::new(file.clone(), collector.clone());
+        let end_of_file = *tokens.last().unwrap();
+        let token_stream = tokens[start..]
+            .iter()
+            .copied()
+            .filter(|token| !token.kind.is_trivia())
+            .chain(repeat(end_of_file));
+        let mut cursor = Cursor::new(token_stream, id_allocator, &reporter);
+
+        loop {
+            let low = index_of(cursor.lookahead_0().token);
+
+            if let Some(index) = suffix.resync(low) {
+                let index_delta = suffix.new_end as i64 - suffix.old_end as i64;
+                let shift = |index: usize| (index as i64 + index_delta) as usize;
+
+                for mut top_level in suffix.top_levels.drain(index..) {
+                    top_level.shift_spans(suffix.delta);
+                    pack.top_levels.push(top_level);
+                }
+
+                for mut old in suffix.parsed.drain(index..) {
+                    for item in &mut old.items {
+                        move_item(item, file, suffix.delta);
+                    }
+
+                    parsed.push(ParsedTopLevel {
+                        tokens: shift(old.tokens.start)..shift(old.tokens.end),
+                        lookahead_end: shift(old.lookahead_end),
+                        items: old.items,
+                    });
+                }
+
+                for item in &mut suffix.trailing_items {
+                    move_item(item, file, suffix.delta);
+                }
+
+                *trailing_items = suffix.trailing_items;
+                *complete = suffix.complete;
+                return;
+            }
+
+            if !cursor.has_token() {
+                break;
+            }
+
+            let top_level = AstTopLevel::parse(&mut cursor);
+            let items = collector.take();
+
+            match top_level {
+                Some(top_level) => {
+                    pack.top_levels.push(top_level);
+                    parsed.push(ParsedTopLevel {
+                        tokens: low..index_of(cursor.lookahead_0().token),
+                        lookahead_end: index_of(cursor.lookahead_1().token) + 1,
+                        items,
+                    });
+                }
+                None => {
+                    *trailing_items = items;
+                    *complete = false;
+                    return;
+                }
+            }
+        }
+
+        trailing_items.clear();
+        *complete = true;
+    }
+}
+
+impl Suffix {
+    /// Returns the index of the item starting at the token at `index`, if it can be reused.
+    fn resync(&self, index: usize) -> Option<usize> {
+        if index < self.new_end {
+            return None;
+        }
+
+        let old_index = index - self.new_end + self.old_end;
+        self.parsed
+            .binary_search_by_key(&old_index, |parsed| parsed.tokens.start)
+            .ok()
+    }
+}
+
+/// Moves a diagnostic into the edited file, along with the item it was reported for.
+fn move_item(item: &mut Item, file: &Arc<SourceFile>, delta: i64) {
+    let move_origin = |origin: &mut Option<ItemOrigin>| {
+        if let Some(origin) = origin {
+            origin.file = file.clone();
+            origin.span = origin.span.shift(delta);
+        }
+    };
+
+    move_origin(&mut item.origin);
+
+    for sub_item in &mut item.sub_items {
+        move_origin(&mut sub_item.origin);
+    }
+
+    for suggestion in &mut item.suggestions {
+        suggestion.span = suggestion.span.shift(delta);
+    }
+}
+
+/// Updates the diagnostics of the tokens after an edit; only the relexed tokens are validated
+/// again, and the diagnostics of the others are moved along with them.
+fn revalidate(
+    lex_items: &mut Vec<Item>,
+    old_tokens: &[Token],
+    relexed: &Relexed,
+    file: &Arc<SourceFile>,
+    delta: i64,
+) {
+    let old_low = old_tokens[relexed.old_range.start].span_low;
+    let old_high = old_tokens[relexed.old_range.end].span_low;
+    let position = |item: &Item| item.origin.as_ref().map_or(0, |origin| origin.span.low());
+    let start = lex_items.partition_point(|item| position(item) < old_low);
+    let end = lex_items.partition_point(|item| position(item) < old_high);
+
+    for item in &mut lex_items[..start] {
+        move_item(item, file, 0);
+    }
+
+    for item in &mut lex_items[end..] {
+        move_item(item, file, delta);
+    }
+
+    let new_items = validate_tokens(file, &relexed.tokens[relexed.new_range.clone()]);
+    lex_items.splice(start..end, new_items);
+}
+
+fn validate_tokens(file: &Arc<SourceFile>, tokens: &[Token]) -> Vec<Item> {
+    let collector = Arc::new(ItemCollector::new());
+    let reporter = ItemSender::new(file.clone(), collector.clone());
+
+    for token in tokens {
+        validate_token(token, &reporter);
+    }
+
+    collector.take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        parse::parse_shader_pack,
+        span::{SourceMap, Span},
+    };
+    use rand::Rng;
+
+    #[test]
+    fn test_incremental_parse_trivia() {
+        let file = SourceMap::new().add_file("", "test", None);
+        let mut parse = IncrementalParse::new(file);
+        assert_eq!(parse.pack().unwrap().top_levels, []);
+
+        let mut rng = rand::thread_rng();
+        let pieces = [" ", "\n", "\t", "#", "##"];
+
+        // without items, the parser is never called, so the tokens can be checked on their own
+        for _ in 0..200 {
+            let span = parse.file().span();
+            let low = rng.gen_range(span.low()..=span.high());
+            let high = rng.gen_range(low..=span.high());
+            let text = pieces[rng.gen_range(0..pieces.len())];
+            parse.edit(&SourceEdit::new(Span::new(low, high), text));
+            assert_eq!(parse.tokens(), lex(parse.file()));
+            assert_eq!(parse.pack().unwrap().span, parse.file().span());
+            assert_eq!(parse.diagnostics().count(), 0);
+        }
+    }
+
+    /// Returns the debug form of an AST without its node ids, which depend on the order the
+    /// nodes are parsed in.
+    fn without_node_ids(pack: Option<&AstShaderPack>) -> String {
+        let debug = format!("{:#?}", pack);
+        let mut stripped = String::with_capacity(debug.len());
+        let mut rest = debug.as_str();
+
+        while let Some(start) = rest.find("NodeId(") {
+            stripped.push_str(&rest[..start]);
+            rest = &rest[start..];
+            rest = &rest[rest.find(')').unwrap() + 1..];
+        }
+
+        stripped.push_str(rest);
+        stripped
+    }
+
+    #[test]
+    fn test_incremental_parse_items() {
+        let source = "\
+## Base color.
+in color: f3;
+
+fn scale(value: f3) -> f3 {
+    let scaled = value * 2.0;
+    return scaled;
+}
+
+pass main {
+    fragment {
+        let base = scale(color);
+    }
+}
+";
+        let file = SourceMap::new().add_file(source, "test", None);
+        let mut parse = IncrementalParse::new(file);
+        let mut rng = rand::thread_rng();
+        let pieces = [
+            " ",
+            "\n",
+            "x",
+            "1",
+            "2.0",
+            ";",
+            ",",
+            "(",
+            ")",
+            "{",
+            "}",
+            "*",
+            "let a = 1;",
+            "#",
+            "##",
+            "\"",
+        ];
+        let summary = |items: Vec<&Item>| {
+            Vec::from_iter(items.into_iter().map(|item| {
+                let span = item.origin.as_ref().map(|origin| origin.span);
+                (item.code, item.message.clone(), span)
+            }))
+        };
+
+        for _ in 0..300 {
+            // edits are made inside the items, so that most of them are parsed again
+            let span = match parse.pack() {
+                Some(pack) if !pack.top_levels.is_empty() => {
+                    pack.top_levels[rng.gen_range(0..pack.top_levels.len())].span
+                }
+                _ => parse.file().span(),
+            };
+            let low = rng.gen_range(span.low()..=span.high());
+            let high = rng.gen_range(low..=span.high().min(low + 4));
+            let file_low = parse.file().span().low();
+            let boundaries = |pos: u32| {
+                parse
+                    .file()
+                    .content()
+                    .is_char_boundary((pos - file_low) as usize)
+            };
+
+            if !boundaries(low) || !boundaries(high) {
+                continue;
+            }
+
+            let text = pieces[rng.gen_range(0..pieces.len())];
+            parse.edit(&SourceEdit::new(Span::new(low, high), text));
+
+            let collector = Arc::new(ItemCollector::new());
+            let reporter = ItemSender::new(parse.file().clone(), collector.clone());
+            let pack = parse_shader_pack(parse.file(), &reporter);
+            drop(reporter);
+            let items = collector.take();
+
+            assert_eq!(
+                without_node_ids(parse.pack()),
+                without_node_ids(pack.as_ref()),
+                "{:?}",
+                parse.file().content()
+            );
+            assert_eq!(
+                summary(Vec::from_iter(parse.diagnostics())),
+                summary(Vec::from_iter(&items)),
+                "{:?}",
+                parse.file().content()
+            );
+        }
+    }
+
+    #[test]
+    fn test_revalidate() {
+        let mut file = SourceMap::new().add_file("", "test", None);
+        let mut tokens = lex(&file);
+        let mut lex_items = vec![];
+        let mut rng = rand::thread_rng();
+        let pieces = [" ", "\n", "a", "0b2", "1px", "$", "；", "\"", "\\q", "#"];
+        let summary = |items: &[Item]| {
+            Vec::from_iter(items.iter().map(|item| {
+                let origin = item.origin.as_ref().unwrap();
+                (
+                    item.code,
+                    item.message.clone(),
+                    origin.span,
+                    origin.file.span(),
+                )
+            }))
+        };
+
+        for _ in 0..300 {
+            let span = file.span();
+            let low = rng.gen_range(span.low()..=span.high());
+            let high = rng.gen_range(low..=span.high());
+            let boundaries =
+                |pos: u32| file.content().is_char_boundary((pos - span.low()) as usize);
+
+            if !boundaries(low) || !boundaries(high) {
+                continue;
+            }
+
+            let edit =
+                SourceEdit::new(Span::new(low, high), pieces[rng.gen_range(0..pieces.len())]);
+            let edited = Arc::new(file.with_edit(&edit));
+            let relexed = relex(&tokens, &edited, &edit);
+            revalidate(&mut lex_items, &tokens, &relexed, &edited, edit.delta());
+
+            file = edited;
+            tokens = relexed.tokens;
+            assert_eq!(
+                summary(&lex_items),
+                summary(&validate_tokens(&file, &tokens)),
+                "{:?}",
+                file.content()
+            );
+        }
+    }
+
+    #[test]
+    fn test_suffix_resync() {
+        let parsed = |start: usize| ParsedTopLevel {
+            tokens: start..start + 4,
+            lookahead_end: start + 6,
+            items: vec![],
+        };
+        let suffix = Suffix {
+            parsed: vec![parsed(10), parsed(14), parsed(18)],
+            old_end: 12,
+            new_end: 15,
+            ..Default::default()
+        };
+
+        // the items starting within the edited tokens cannot be reused
+        assert_eq!(suffix.resync(13), None);
+        assert_eq!(suffix.resync(16), None);
+        assert_eq!(suffix.resync(17), Some(1));
+        assert_eq!(suffix.resync(21), Some(2));
+    }
+}
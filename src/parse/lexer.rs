@@ -1,20 +1,96 @@
+mod literal;
 mod token;
 mod token_kind;
 mod token_number_literal_kind;
+mod validate;
 
+pub use literal::*;
 pub use token::*;
 pub use token_kind::*;
 pub use token_number_literal_kind::*;
+pub use validate::*;
 
 use super::low_lexer::{low_token_iter, LowToken, LowTokenKind, LowTokenNumberLiteralKind};
 use crate::{
-    span::{SourceFile, Span},
+    span::{SourceEdit, SourceFile, Span},
     symbol::Symbol,
 };
-use std::iter::from_fn as iter_from_fn;
+use std::{iter::from_fn as iter_from_fn, ops::Range};
+
+/// Number of characters the low lexer may look at past the end of a token.
+const LOOKAHEAD: u32 = 2;
 
 pub fn token_iter(file: &SourceFile) -> impl Iterator<Item = Token> + '_ {
-    let mut iter = unglued_token_iter(file);
+    token_iter_at(file, file.span().low())
+}
+
+/// Collects the tokens of the file, up to and including the end of file token.
+pub fn lex(file: &SourceFile) -> Vec<Token> {
+    let mut tokens = Vec::new();
+
+    for token in token_iter(file) {
+        tokens.push(token);
+
+        if token.kind == TokenKind::EndOfFile {
+            break;
+        }
+    }
+
+    tokens
+}
+
+/// Tokens of a file after an edit, along with the ranges of tokens that have been replaced.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Relexed {
+    pub tokens: Vec<Token>,
+    /// Indices of the replaced tokens, before the edit.
+    pub old_range: Range<usize>,
+    /// Indices of the tokens replacing them, after the edit.
+    pub new_range: Range<usize>,
+}
+
+/// Updates the tokens returned by [`lex`] after an edit of the file, without lexing the whole file
+/// again; `file` is the file after the edit.
+///
+/// Tokens are lexed again from a little before the edit, until a token starts where a token
+/// started before the edit; since the low lexer is context-free, the remaining tokens are the
+/// same, only moved.
+pub fn relex(tokens: &[Token], file: &SourceFile, edit: &SourceEdit) -> Relexed {
+    // tokens which the lexer stopped reading before the edit are left untouched, except for the
+    // last one, which may be glued with the tokens after it
+    let unchanged =
+        tokens.partition_point(|token| token.span().high() + LOOKAHEAD <= edit.span.low());
+    let start = unchanged.saturating_sub(1);
+    let mut new_tokens = tokens[..start].to_vec();
+    let new_high = edit.new_span().high();
+
+    for token in token_iter_at(file, tokens[start].span_low) {
+        if new_high <= token.span_low {
+            let old_low = (token.span_low as i64 - edit.delta()) as u32;
+
+            if let Ok(index) = tokens.binary_search_by_key(&old_low, |token| token.span_low) {
+                let new_range = start..new_tokens.len();
+                new_tokens.extend(tokens[index..].iter().map(|token| Token {
+                    span_low: (token.span_low as i64 + edit.delta()) as u32,
+                    kind: token.kind,
+                }));
+
+                return Relexed {
+                    tokens: new_tokens,
+                    old_range: start..index,
+                    new_range,
+                };
+            }
+        }
+
+        new_tokens.push(token);
+    }
+
+    unreachable!("the end of file token always follows the edit")
+}
+
+fn token_iter_at(file: &SourceFile, span_low: u32) -> impl Iterator<Item = Token> + '_ {
+    let mut iter = unglued_token_iter(file, span_low);
     let mut current = iter.next();
     let mut next = iter.next();
 
@@ -39,9 +115,8 @@ pub fn token_iter(file: &SourceFile) -> impl Iterator<Item = Token> + '_ {
     })
 }
 
-fn unglued_token_iter(file: &SourceFile) -> impl Iterator<Item = Token> + '_ {
-    let mut span_low = file.span().low();
-    let mut iter = low_token_iter(file.content());
+fn unglued_token_iter(file: &SourceFile, mut span_low: u32) -> impl Iterator<Item = Token> + '_ {
+    let mut iter = low_token_iter(&file.content()[(span_low - file.span().low()) as usize..]);
 
     iter_from_fn(move || {
         let token = iter.next()?;
@@ -65,6 +140,17 @@ fn from_low_token(token: LowToken, span_low: u32, file: &SourceFile) -> Token {
         LowTokenKind::EndOfFile => TokenKind::EndOfFile,
         LowTokenKind::Whitespace => TokenKind::Whitespace { len: token.len },
         LowTokenKind::Comment => TokenKind::Comment { len: token.len },
+        LowTokenKind::BlockComment { terminated } => TokenKind::BlockComment {
+            len: token.len,
+            terminated,
+        },
+        LowTokenKind::DocComment => {
+            let span = Span::new(span_low + 2, span_low + token.len);
+            TokenKind::DocComment {
+                len: token.len,
+                content: Symbol::from_str(file.slice(span)),
+            }
+        }
         LowTokenKind::OpenParen => TokenKind::OpenParen,
         LowTokenKind::CloseParen => TokenKind::CloseParen,
         LowTokenKind::OpenBrace => TokenKind::OpenBrace,
@@ -164,6 +250,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_relex() {
+        let file = SourceFile::new(10, "in a: f;\nin b: f;\nin c: f;", "test", None);
+        let tokens = lex(&file);
+        let edit = SourceEdit::new(Span::new(22, 23), "color");
+        let edited = file.with_edit(&edit);
+        let relexed = relex(&tokens, &edited, &edit);
+
+        assert_eq!(relexed.tokens, lex(&edited));
+        // only `b` and the tokens right before it are lexed again
+        assert_eq!(relexed.old_range, 7..11);
+        assert_eq!(relexed.new_range, 7..11);
+    }
+
+    #[test]
+    fn test_relex_random_edits() {
+        let mut rng = rand::thread_rng();
+        let pieces = [
+            "a", "in", "1", "0x", "e", ".", " ", "\n", "\"", "#", "[", "]", "=", "<", "-", ">",
+            "$", "é", ";",
+        ];
+        let random_text = |rng: &mut rand::rngs::ThreadRng, len: usize| {
+            String::from_iter((0..len).map(|_| pieces[rng.gen_range(0..pieces.len())]))
+        };
+
+        for _ in 0..500 {
+            let span_low = random_span_low();
+            let len = rng.gen_range(0..40);
+            let mut file = SourceFile::new(span_low, random_text(&mut rng, len), "test", None);
+            let mut tokens = lex(&file);
+
+            for _ in 0..5 {
+                let boundaries = Vec::from_iter(
+                    file.content()
+                        .char_indices()
+                        .map(|(offset, _)| offset)
+                        .chain([file.content().len()]),
+                );
+                let mut low = boundaries[rng.gen_range(0..boundaries.len())];
+                let mut high = boundaries[rng.gen_range(0..boundaries.len())];
+
+                if high < low {
+                    std::mem::swap(&mut low, &mut high);
+                }
+
+                let len = rng.gen_range(0..4);
+                let edit = SourceEdit::new(
+                    Span::new(span_low + low as u32, span_low + high as u32),
+                    random_text(&mut rng, len),
+                );
+                let edited = file.with_edit(&edit);
+                let relexed = relex(&tokens, &edited, &edit);
+                assert_eq!(
+                    relexed.tokens,
+                    lex(&edited),
+                    "{:?} edited by {:?}",
+                    file.content(),
+                    edit
+                );
+                assert_eq!(
+                    tokens.len() - relexed.old_range.len(),
+                    relexed.tokens.len() - relexed.new_range.len()
+                );
+
+                file = edited;
+                tokens = relexed.tokens;
+            }
+        }
+    }
+
     fn random_span_low() -> u32 {
         rand::thread_rng().gen_range(0..u32::MAX / 2)
     }
@@ -243,10 +399,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_token_iter_comments() {
+        let span_low = random_span_low();
+        check_tokens(
+            span_low,
+            "#[ a #[ b ]# ]#\n## doc\n### line\n#[ open",
+            [
+                TokenKind::BlockComment {
+                    len: 15,
+                    terminated: true,
+                },
+                TokenKind::Whitespace { len: 1 },
+                TokenKind::DocComment {
+                    len: 6,
+                    content: Symbol::from_str(" doc"),
+                },
+                TokenKind::Whitespace { len: 1 },
+                TokenKind::Comment { len: 8 },
+                TokenKind::Whitespace { len: 1 },
+                TokenKind::BlockComment {
+                    len: 7,
+                    terminated: false,
+                },
+            ]
+            .into_iter(),
+        );
+    }
+
     #[test]
     fn test_token_iter() {
         let span_low = random_span_low();
-        let input = " \r\n\t# test\n(){}[].,:;@->=+=-=*=/=%=**=<<=>>=|=&=^===!=<><=>=+-*/%**<<>>|&^||&&~!identifier keyword 0b01_01suffix 0o01234_567suffix 0x0123456789_abcdefsuffix 01234_56789suffix 0123456789.0123456789e-0123456789suffix \"hello, world\" \"hello, world";
+        let input = " \r\n\t# test\n(){}[].,:;::@->=+=-=*=/=%=**=<<=>>=|=&=^===!=<><=>=+-*/%**<<>>|&^||&&~!identifier keyword 0b01_01suffix 0o01234_567suffix 0x0123456789_abcdefsuffix 01234_56789suffix 0123456789.0123456789e-0123456789suffix \"hello, world\" \"hello, world";
         check_tokens(
             span_low,
             input,
@@ -264,6 +448,7 @@ mod tests {
                 TokenKind::Comma,
                 TokenKind::Colon,
                 TokenKind::Semicolon,
+                TokenKind::PathSep,
                 TokenKind::At,
                 TokenKind::Arrow,
                 TokenKind::Assign,
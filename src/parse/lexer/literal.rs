@@ -0,0 +1,338 @@
+use super::TokenNumberLiteralKind;
+use std::{num::IntErrorKind, ops::Range};
+
+/// The value of a number literal, typed by its suffix: `i` (or none) for `int`, `u` for `uint`
+/// and `f` for `float`. Float literals without a suffix are `float`s too.
+///
+/// Values are stored widened, but always fit in the 32-bit types of the IR.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NumberLiteralValue {
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NumberLiteralError {
+    /// A digit out of the radix of the literal, or no digits at all after the radix prefix.
+    InvalidDigit,
+    /// A suffix which is unknown, or not allowed on this kind of literal.
+    InvalidSuffix,
+    /// The value does not fit in the 32-bit type given by the suffix.
+    Overflow,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EscapeError {
+    /// A backslash at the end of the content, which happens in unterminated strings.
+    LoneBackslash,
+    /// An escape sequence other than `\\`, `\"`, `\n`, `\r`, `\t`, `\0` and `\u{...}`.
+    Unknown,
+    /// A `\u` which is not followed by braces.
+    UnicodeMissingBraces,
+    /// A `\u{...}` without 1 to 6 hexadecimal digits.
+    UnicodeInvalidDigits,
+    /// A `\u{...}` which is not a Unicode scalar value, e.g. a surrogate.
+    UnicodeNotAChar,
+}
+
+impl TokenNumberLiteralKind {
+    pub fn radix(self) -> u32 {
+        match self {
+            Self::IntegerBinary => 2,
+            Self::IntegerOctal => 8,
+            Self::IntegerHexadecimal => 16,
+            Self::IntegerDecimal | Self::Float => 10,
+        }
+    }
+
+    /// The suffixes allowed on this kind of literal.
+    pub fn suffixes(self) -> &'static [&'static str] {
+        match self {
+            Self::IntegerBinary | Self::IntegerOctal | Self::IntegerHexadecimal => &["i", "u"],
+            Self::IntegerDecimal => &["i", "u", "f"],
+            Self::Float => &["f"],
+        }
+    }
+}
+
+/// Decodes the content of a number literal, with its radix prefix and `_` separators, into a
+/// value of the type given by its suffix.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::parse::lexer::{decode_number, NumberLiteralError, NumberLiteralValue, TokenNumberLiteralKind};
+/// assert_eq!(
+///     decode_number(TokenNumberLiteralKind::IntegerHexadecimal, "0xff_ff", Some("u")),
+///     Ok(NumberLiteralValue::UInt(65535))
+/// );
+/// assert_eq!(
+///     decode_number(TokenNumberLiteralKind::IntegerDecimal, "3000000000", Some("u")),
+///     Ok(NumberLiteralValue::UInt(3000000000))
+/// );
+/// assert_eq!(
+///     decode_number(TokenNumberLiteralKind::IntegerDecimal, "3000000000", None),
+///     Err(NumberLiteralError::Overflow)
+/// );
+/// ```
+pub fn decode_number(
+    kind: TokenNumberLiteralKind,
+    content: &str,
+    suffix: Option<&str>,
+) -> Result<NumberLiteralValue, NumberLiteralError> {
+    let suffix = suffix.unwrap_or(match kind {
+        TokenNumberLiteralKind::Float => "f",
+        _ => "i",
+    });
+
+    if !kind.suffixes().contains(&suffix) {
+        return Err(NumberLiteralError::InvalidSuffix);
+    }
+
+    let digits = content.replace('_', "");
+    let digits = match kind {
+        TokenNumberLiteralKind::IntegerDecimal | TokenNumberLiteralKind::Float => &digits[..],
+        // skips the radix prefix
+        _ => &digits[2..],
+    };
+
+    // `int`, `uint` and `float` are 32-bit; negative values are negations of literals, so the
+    // minimum `int` cannot be written as a literal
+    match suffix {
+        "i" => decode_integer(digits, kind.radix()).and_then(|value| {
+            i32::try_from(value)
+                .map(|value| NumberLiteralValue::Int(value.into()))
+                .map_err(|_| NumberLiteralError::Overflow)
+        }),
+        "u" => decode_integer(digits, kind.radix()).and_then(|value| {
+            u32::try_from(value)
+                .map(|value| NumberLiteralValue::UInt(value.into()))
+                .map_err(|_| NumberLiteralError::Overflow)
+        }),
+        _ => match digits.parse::<f64>() {
+            Ok(value) if (value as f32).is_finite() => Ok(NumberLiteralValue::Float(value)),
+            Ok(_) => Err(NumberLiteralError::Overflow),
+            Err(_) => Err(NumberLiteralError::InvalidDigit),
+        },
+    }
+}
+
+fn decode_integer(digits: &str, radix: u32) -> Result<u64, NumberLiteralError> {
+    u64::from_str_radix(digits, radix).map_err(|err| match err.kind() {
+        IntErrorKind::PosOverflow => NumberLiteralError::Overflow,
+        _ => NumberLiteralError::InvalidDigit,
+    })
+}
+
+/// Decodes the escape sequences of the unquoted content of a string literal.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::parse::lexer::{decode_string, EscapeError};
+/// assert_eq!(decode_string(r#"a\tb \"\u{1F600}\""#), Ok("a\tb \"😀\"".to_string()));
+/// assert_eq!(decode_string(r"\q"), Err(EscapeError::Unknown));
+/// ```
+pub fn decode_string(unquoted_content: &str) -> Result<String, EscapeError> {
+    let mut decoded = String::with_capacity(unquoted_content.len());
+    let mut error = None;
+
+    unescape(unquoted_content, |_, char| match char {
+        Ok(char) => decoded.push(char),
+        Err(err) => {
+            error.get_or_insert(err);
+        }
+    });
+
+    match error {
+        Some(err) => Err(err),
+        None => Ok(decoded),
+    }
+}
+
+/// Calls `callback` with each character of the unquoted content of a string literal, or the
+/// error of each invalid escape sequence, along with the byte range it comes from.
+pub fn unescape(
+    unquoted_content: &str,
+    mut callback: impl FnMut(Range<usize>, Result<char, EscapeError>),
+) {
+    let mut chars = unquoted_content.char_indices();
+
+    while let Some((offset, char)) = chars.next() {
+        if char != '\\' {
+            callback(offset..offset + char.len_utf8(), Ok(char));
+            continue;
+        }
+
+        let escape = match chars.next() {
+            Some((_, escape)) => escape,
+            None => {
+                callback(offset..offset + 1, Err(EscapeError::LoneBackslash));
+                return;
+            }
+        };
+        let range = offset..offset + 1 + escape.len_utf8();
+
+        let char = match escape {
+            '\\' => '\\',
+            '"' => '"',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            'u' => {
+                let rest = &unquoted_content[range.end..];
+                let len = match rest.strip_prefix('{').and_then(|rest| rest.find('}')) {
+                    Some(close) => close + 2,
+                    None => {
+                        callback(range, Err(EscapeError::UnicodeMissingBraces));
+                        continue;
+                    }
+                };
+
+                // skips the braces and the digits
+                for _ in rest[..len].chars() {
+                    chars.next();
+                }
+
+                let range = offset..range.end + len;
+                let digits = &rest[1..len - 1];
+
+                if digits.is_empty()
+                    || 6 < digits.len()
+                    || !digits.chars().all(|char| char.is_ascii_hexdigit())
+                {
+                    callback(range, Err(EscapeError::UnicodeInvalidDigits));
+                } else {
+                    match char::from_u32(u32::from_str_radix(digits, 16).unwrap()) {
+                        Some(char) => callback(range, Ok(char)),
+                        None => callback(range, Err(EscapeError::UnicodeNotAChar)),
+                    }
+                }
+
+                continue;
+            }
+            _ => {
+                callback(range, Err(EscapeError::Unknown));
+                continue;
+            }
+        };
+
+        callback(range, Ok(char));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_number() {
+        use NumberLiteralError as E;
+        use NumberLiteralValue as V;
+        use TokenNumberLiteralKind as K;
+
+        let cases: &[(K, &str, Option<&str>, _)] = &[
+            (K::IntegerDecimal, "1_000", None, Ok(V::Int(1000))),
+            (K::IntegerDecimal, "1_000", Some("u"), Ok(V::UInt(1000))),
+            (K::IntegerDecimal, "2", Some("f"), Ok(V::Float(2.0))),
+            (K::IntegerBinary, "0b1_01", None, Ok(V::Int(5))),
+            (K::IntegerOctal, "0o777", Some("u"), Ok(V::UInt(511))),
+            (K::IntegerHexadecimal, "0xFF", Some("i"), Ok(V::Int(255))),
+            (K::Float, "1.5e1", None, Ok(V::Float(15.0))),
+            (K::Float, "2.5e-1", Some("f"), Ok(V::Float(0.25))),
+            (
+                K::IntegerDecimal,
+                "2147483647",
+                None,
+                Ok(V::Int(i32::MAX.into())),
+            ),
+            (K::IntegerDecimal, "2147483648", None, Err(E::Overflow)),
+            (K::IntegerDecimal, "4294967296", None, Err(E::Overflow)),
+            (K::IntegerHexadecimal, "0xffff_ffff", None, Err(E::Overflow)),
+            (
+                K::IntegerHexadecimal,
+                "0xffff_ffff",
+                Some("u"),
+                Ok(V::UInt(u32::MAX.into())),
+            ),
+            (
+                K::IntegerDecimal,
+                "3000000000",
+                Some("u"),
+                Ok(V::UInt(3000000000)),
+            ),
+            (K::IntegerDecimal, "4294967296", Some("u"), Err(E::Overflow)),
+            (
+                K::IntegerHexadecimal,
+                "0x1_0000_0000_0000_0000",
+                Some("u"),
+                Err(E::Overflow),
+            ),
+            (K::Float, "3.4e38", None, Ok(V::Float(3.4e38))),
+            (K::Float, "3.5e38", None, Err(E::Overflow)),
+            (K::Float, "1e999", None, Err(E::Overflow)),
+            (K::IntegerBinary, "0b102", None, Err(E::InvalidDigit)),
+            (K::IntegerHexadecimal, "0x", None, Err(E::InvalidDigit)),
+            (
+                K::IntegerHexadecimal,
+                "0x1",
+                Some("f"),
+                Err(E::InvalidSuffix),
+            ),
+            (K::Float, "1.0", Some("u"), Err(E::InvalidSuffix)),
+            (K::IntegerDecimal, "1", Some("h"), Err(E::InvalidSuffix)),
+        ];
+
+        for (kind, content, suffix, expected) in cases {
+            assert_eq!(
+                decode_number(*kind, content, *suffix),
+                *expected,
+                "{}{}",
+                content,
+                suffix.unwrap_or("")
+            );
+        }
+    }
+
+    #[test]
+    fn test_unescape() {
+        let mut chars = Vec::new();
+        unescape(r"a\n\u{e9}\u{d800}\x\u12", |range, char| {
+            chars.push((range, char))
+        });
+
+        assert_eq!(
+            chars,
+            vec![
+                (0..1, Ok('a')),
+                (1..3, Ok('\n')),
+                (3..9, Ok('é')),
+                (9..17, Err(EscapeError::UnicodeNotAChar)),
+                (17..19, Err(EscapeError::Unknown)),
+                (19..21, Err(EscapeError::UnicodeMissingBraces)),
+                (21..22, Ok('1')),
+                (22..23, Ok('2')),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_string() {
+        assert_eq!(decode_string("plain"), Ok("plain".to_string()));
+        assert_eq!(
+            decode_string(r#"\\\"\n\r\t\0"#),
+            Ok("\\\"\n\r\t\0".to_string())
+        );
+        assert_eq!(
+            decode_string(r"\u{}"),
+            Err(EscapeError::UnicodeInvalidDigits)
+        );
+        assert_eq!(
+            decode_string(r"\u{1234567}"),
+            Err(EscapeError::UnicodeInvalidDigits)
+        );
+        assert_eq!(decode_string("\\"), Err(EscapeError::LoneBackslash));
+    }
+}
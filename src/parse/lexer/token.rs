@@ -42,27 +42,28 @@ impl Token {
                     len: lhs_len + rhs_len,
                 }
             }
-            (TokenKind::Assign, TokenKind::Assign) => TokenKind::Eq, // `==`
-            (TokenKind::Lt, TokenKind::Assign) => TokenKind::Le,     // `<=`
-            (TokenKind::Lt, TokenKind::Lt) => TokenKind::Shl,        // `<<`
-            (TokenKind::Gt, TokenKind::Assign) => TokenKind::Ge,     // `>=`
-            (TokenKind::Gt, TokenKind::Gt) => TokenKind::Shr,        // `>>`
+            (TokenKind::Colon, TokenKind::Colon) => TokenKind::PathSep, // `::`
+            (TokenKind::Assign, TokenKind::Assign) => TokenKind::Eq,    // `==`
+            (TokenKind::Lt, TokenKind::Assign) => TokenKind::Le,        // `<=`
+            (TokenKind::Lt, TokenKind::Lt) => TokenKind::Shl,           // `<<`
+            (TokenKind::Gt, TokenKind::Assign) => TokenKind::Ge,        // `>=`
+            (TokenKind::Gt, TokenKind::Gt) => TokenKind::Shr,           // `>>`
             (TokenKind::Add, TokenKind::Assign) => TokenKind::AssignAdd, // `+=`
             (TokenKind::Sub, TokenKind::Assign) => TokenKind::AssignSub, // `-=`
-            (TokenKind::Sub, TokenKind::Gt) => TokenKind::Arrow,     // `->`
+            (TokenKind::Sub, TokenKind::Gt) => TokenKind::Arrow,        // `->`
             (TokenKind::Mul, TokenKind::Assign) => TokenKind::AssignMul, // `*=`
-            (TokenKind::Mul, TokenKind::Mul) => TokenKind::Pow,      // `**`
+            (TokenKind::Mul, TokenKind::Mul) => TokenKind::Pow,         // `**`
             (TokenKind::Div, TokenKind::Assign) => TokenKind::AssignDiv, // `/=`
             (TokenKind::Mod, TokenKind::Assign) => TokenKind::AssignMod, // `%=`
             (TokenKind::Pow, TokenKind::Assign) => TokenKind::AssignPow, // `**=`
             (TokenKind::Shl, TokenKind::Assign) => TokenKind::AssignShl, // `<<=`
             (TokenKind::Shr, TokenKind::Assign) => TokenKind::AssignShr, // `>>=`
             (TokenKind::BitOr, TokenKind::Assign) => TokenKind::AssignBitOr, // `|=`
-            (TokenKind::BitOr, TokenKind::BitOr) => TokenKind::LogOr, // `||`
+            (TokenKind::BitOr, TokenKind::BitOr) => TokenKind::LogOr,   // `||`
             (TokenKind::BitAnd, TokenKind::Assign) => TokenKind::AssignBitAnd, // `&=`
             (TokenKind::BitAnd, TokenKind::BitAnd) => TokenKind::LogAnd, // `&&`
             (TokenKind::BitXor, TokenKind::Assign) => TokenKind::AssignBitXor, // `^=`
-            (TokenKind::LogNot, TokenKind::Assign) => TokenKind::Ne, // `!=`
+            (TokenKind::LogNot, TokenKind::Assign) => TokenKind::Ne,    // `!=`
             _ => {
                 return None;
             }
@@ -76,6 +77,16 @@ impl Token {
                 // `Unknown` and `Whitespace` are cannot be unglued
                 unglued.push_back(this);
             }
+            TokenKind::PathSep => {
+                unglued.push_back(Self {
+                    span_low: this.span_low,
+                    kind: TokenKind::Colon,
+                });
+                unglued.push_back(Self {
+                    span_low: this.span_low + 1,
+                    kind: TokenKind::Colon,
+                });
+            }
             TokenKind::Eq => {
                 unglued.push_back(Self {
                     span_low: this.span_low + 0,
@@ -350,6 +361,7 @@ mod tests {
             TokenKind::Dot,
             TokenKind::Comma,
             TokenKind::Colon,
+            TokenKind::PathSep,
             TokenKind::Semicolon,
             TokenKind::At,
             TokenKind::Arrow,
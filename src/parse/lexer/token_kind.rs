@@ -14,6 +14,20 @@ pub enum TokenKind {
     Comment {
         len: u32,
     }, // "#"
+    BlockComment {
+        len: u32,
+        /// Indicates if every nested block comment is closed, like `terminated` of string
+        /// literals.
+        terminated: bool,
+    }, // "#[ ... ]#"
+    DocComment {
+        len: u32,
+        /// The text after the `##`.
+        ///
+        /// Example:
+        /// - `## Base color` -> ` Base color`
+        content: Symbol,
+    }, // "##"
     OpenParen,    // "("
     CloseParen,   // ")"
     OpenBrace,    // "{"
@@ -23,6 +37,7 @@ pub enum TokenKind {
     Dot,          // "."
     Comma,        // ","
     Colon,        // ":"
+    PathSep,      // "::"
     Semicolon,    // ";"
     At,           // "@"
     Arrow,        // "->"
@@ -117,12 +132,26 @@ pub enum TokenKind {
 }
 
 impl TokenKind {
+    /// Indicates if the parser skips the token: whitespaces and every kind of comment. Doc
+    /// comments are attached to the items after the parsing.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            Self::Whitespace { .. }
+                | Self::Comment { .. }
+                | Self::BlockComment { .. }
+                | Self::DocComment { .. }
+        )
+    }
+
     pub fn len(self) -> u32 {
         match self {
             Self::Unknown { len, .. } => len,
             Self::EndOfFile => 0,
             Self::Whitespace { len } => len,
             Self::Comment { len } => len,
+            Self::BlockComment { len, .. } => len,
+            Self::DocComment { len, .. } => len,
             Self::OpenParen => 1,
             Self::CloseParen => 1,
             Self::OpenBrace => 1,
@@ -132,6 +161,7 @@ impl TokenKind {
             Self::Dot => 1,
             Self::Comma => 1,
             Self::Colon => 1,
+            Self::PathSep => 2,
             Self::Semicolon => 1,
             Self::At => 1,
             Self::Arrow => 2,
@@ -0,0 +1,430 @@
+use super::{unescape, EscapeError, Token, TokenKind, TokenNumberLiteralKind};
+use crate::{
+    diagnostics::{
+        codes::{
+            LEX_ERR_INVALID_DIGIT, LEX_ERR_INVALID_ESCAPE, LEX_ERR_INVALID_SUFFIX,
+            LEX_ERR_UNKNOWN_CHAR, LEX_ERR_UNTERMINATED_BLOCK_COMMENT, LEX_ERR_UNTERMINATED_STRING,
+        },
+        Applicability, ItemSender, Suggestion,
+    },
+    span::Span,
+};
+
+/// Characters which look like ASCII ones, with their name and the ASCII text they are mistaken
+/// for; invisible characters are mistaken for nothing. Fullwidth forms are handled separately.
+const CONFUSABLES: &[(char, &str, &str)] = &[
+    ('\u{037e}', "Greek question mark", ";"),
+    ('\u{2010}', "hyphen", "-"),
+    ('\u{2013}', "en dash", "-"),
+    ('\u{2014}', "em dash", "-"),
+    ('\u{2018}', "left single quotation mark", "\""),
+    ('\u{2019}', "right single quotation mark", "\""),
+    ('\u{201c}', "left double quotation mark", "\""),
+    ('\u{201d}', "right double quotation mark", "\""),
+    ('\u{2044}', "fraction slash", "/"),
+    ('\u{2212}', "minus sign", "-"),
+    ('\u{2215}', "division slash", "/"),
+    ('\u{00d7}', "multiplication sign", "*"),
+    ('\u{200b}', "zero width space", ""),
+    ('\u{200c}', "zero width non-joiner", ""),
+    ('\u{200d}', "zero width joiner", ""),
+    ('\u{2060}', "word joiner", ""),
+    ('\u{feff}', "zero width no-break space", ""),
+];
+
+/// Reports the problems of a token which the lexer lets through to carry on lexing: unknown
+/// characters, unterminated strings and block comments, invalid escape sequences, digits out of
+/// the radix of a number and invalid number suffixes.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::{diagnostics::{ItemCollector, ItemSender}, parse::lexer::{lex, validate_token}, span::SourceMap};
+/// # use std::sync::Arc;
+/// let file = SourceMap::new().add_file("in a: f；\n", "test", None);
+/// let collector = Arc::new(ItemCollector::new());
+/// let reporter = ItemSender::new(file.clone(), collector.clone());
+///
+/// for token in lex(&file) {
+///     validate_token(&token, &reporter);
+/// }
+///
+/// let items = collector.take();
+/// assert_eq!(items[0].message, "unknown character `；`");
+/// assert_eq!(items[0].suggestions[0].replacement, ";");
+/// ```
+pub fn validate_token(token: &Token, reporter: &ItemSender) {
+    match token.kind {
+        TokenKind::Unknown { symbol, .. } => {
+            validate_unknown(token.span(), symbol.to_str(), reporter);
+        }
+        TokenKind::NumberLiteral {
+            kind,
+            content,
+            suffix,
+            ..
+        } => {
+            let content = content.to_str();
+            validate_digits(token.span_low, kind, content, reporter);
+
+            if let Some(suffix) = suffix {
+                let low = token.span_low + content.len() as u32;
+                let span = Span::new(low, token.span().high());
+                validate_suffix(span, kind, suffix.to_str(), reporter);
+            }
+        }
+        TokenKind::StringLiteral {
+            unquoted_content,
+            terminated,
+            ..
+        } => {
+            let unquoted_content = unquoted_content.to_str();
+
+            if !terminated {
+                let quote = Span::new(token.span_low, token.span_low + 1);
+                let suggestions = match unquoted_content.find('\n') {
+                    Some(offset) => {
+                        let end_of_line = token.span_low + 1 + offset as u32;
+                        let end_of_line = match unquoted_content[..offset].ends_with('\r') {
+                            true => end_of_line - 1,
+                            false => end_of_line,
+                        };
+                        vec![Suggestion::new(
+                            "close the string at the end of the line",
+                            Span::empty(end_of_line),
+                            "\"",
+                            Applicability::MaybeIncorrect,
+                        )]
+                    }
+                    None => vec![],
+                };
+                reporter.error_fix(
+                    LEX_ERR_UNTERMINATED_STRING,
+                    quote,
+                    "unterminated string literal",
+                    suggestions,
+                );
+            }
+
+            validate_escapes(token.span_low + 1, unquoted_content, reporter);
+        }
+        TokenKind::BlockComment {
+            terminated: false, ..
+        } => {
+            reporter.error_sub(
+                LEX_ERR_UNTERMINATED_BLOCK_COMMENT,
+                Span::new(token.span_low, token.span_low + 2),
+                "unterminated block comment",
+                vec![reporter
+                    .sub_hint_simple("block comments nest; each `#[` needs a matching `]#`")],
+            );
+        }
+        _ => {}
+    }
+}
+
+fn validate_unknown(span: Span, chars: &str, reporter: &ItemSender) {
+    let message = match chars.chars().count() {
+        1 => format!("unknown character `{}`", display_chars(chars)),
+        _ => format!("unknown characters `{}`", display_chars(chars)),
+    };
+    let confusables = chars
+        .chars()
+        .map(find_confusable)
+        .collect::<Option<Vec<_>>>();
+    let suggestions = match confusables.as_deref() {
+        Some([(name, ascii)]) if ascii.is_empty() => vec![Suggestion::new(
+            format!(
+                "`{}` ({}) is invisible; remove it",
+                display_chars(chars),
+                name
+            ),
+            span,
+            "",
+            Applicability::MachineApplicable,
+        )],
+        Some([(name, ascii)]) => vec![Suggestion::new(
+            format!(
+                "`{}` ({}) looks like `{}`, but it is not",
+                chars, name, ascii
+            ),
+            span,
+            ascii.clone(),
+            Applicability::MaybeIncorrect,
+        )],
+        Some(confusables) => {
+            let ascii = String::from_iter(confusables.iter().map(|(_, ascii)| ascii.as_str()));
+            vec![Suggestion::new(
+                format!("did you mean `{}`?", ascii),
+                span,
+                ascii,
+                Applicability::MaybeIncorrect,
+            )]
+        }
+        None => vec![],
+    };
+
+    reporter.error_fix(LEX_ERR_UNKNOWN_CHAR, span, message, suggestions);
+}
+
+/// Returns the name of a character looking like ASCII text, and the text.
+fn find_confusable(char: char) -> Option<(String, String)> {
+    if let Some((_, name, ascii)) = CONFUSABLES
+        .iter()
+        .find(|(confusable, ..)| *confusable == char)
+    {
+        return Some(((*name).to_owned(), (*ascii).to_owned()));
+    }
+
+    match char {
+        '\u{ff01}'..='\u{ff5e}' => {
+            let ascii = char::from_u32(char as u32 - 0xfee0).unwrap();
+            Some((format!("fullwidth `{}`", ascii), ascii.to_string()))
+        }
+        '\u{3002}' => Some(("ideographic full stop".to_owned(), ".".to_owned())),
+        '\u{3001}' => Some(("ideographic comma".to_owned(), ",".to_owned())),
+        _ => None,
+    }
+}
+
+/// Escapes the characters which would not be visible in a message.
+fn display_chars(chars: &str) -> String {
+    String::from_iter(chars.chars().map(|char| {
+        if char.is_control() || find_confusable(char).is_some_and(|(_, ascii)| ascii.is_empty()) {
+            char.escape_unicode().to_string()
+        } else {
+            char.to_string()
+        }
+    }))
+}
+
+fn validate_digits(
+    span_low: u32,
+    kind: TokenNumberLiteralKind,
+    content: &str,
+    reporter: &ItemSender,
+) {
+    let name = match kind {
+        TokenNumberLiteralKind::IntegerBinary => "binary",
+        TokenNumberLiteralKind::IntegerOctal => "octal",
+        _ => return,
+    };
+
+    // skips the radix prefix
+    let invalid = content
+        .char_indices()
+        .skip(2)
+        .find(|(_, char)| *char != '_' && !char.is_digit(kind.radix()));
+
+    if let Some((offset, digit)) = invalid {
+        let low = span_low + offset as u32;
+        reporter.error(
+            LEX_ERR_INVALID_DIGIT,
+            Span::new(low, low + 1),
+            format!("invalid digit `{}` in {} literal", digit, name),
+        );
+    }
+}
+
+fn validate_suffix(span: Span, kind: TokenNumberLiteralKind, suffix: &str, reporter: &ItemSender) {
+    let allowed = kind.suffixes();
+    let name = match kind {
+        TokenNumberLiteralKind::IntegerBinary => "binary",
+        TokenNumberLiteralKind::IntegerOctal => "octal",
+        TokenNumberLiteralKind::IntegerHexadecimal => "hexadecimal",
+        TokenNumberLiteralKind::IntegerDecimal => "integer",
+        TokenNumberLiteralKind::Float => "float",
+    };
+
+    if allowed.contains(&suffix) {
+        return;
+    }
+
+    let message = match suffix {
+        "i" | "u" | "f" => format!("suffix `{}` is not allowed on a {} literal", suffix, name),
+        _ => format!("unknown suffix `{}`", suffix),
+    };
+    let allowed = Vec::from_iter(allowed.iter().map(|suffix| format!("`{}`", suffix)));
+    let hint = match allowed.as_slice() {
+        [suffix] => format!("the only suffix of {} literals is {}", name, suffix),
+        [init @ .., last] => format!(
+            "the suffixes of {} literals are {} and {}",
+            name,
+            init.join(", "),
+            last
+        ),
+        [] => unreachable!(),
+    };
+
+    reporter.error_sub(
+        LEX_ERR_INVALID_SUFFIX,
+        span,
+        message,
+        vec![reporter.sub_hint_simple(hint)],
+    );
+}
+
+fn validate_escapes(span_low: u32, unquoted_content: &str, reporter: &ItemSender) {
+    unescape(unquoted_content, |range, char| {
+        let err = match char {
+            Ok(_) => return,
+            Err(err) => err,
+        };
+        let span = Span::new(span_low + range.start as u32, span_low + range.end as u32);
+
+        match err {
+            // the string is unterminated, which is reported already
+            EscapeError::LoneBackslash => {}
+            EscapeError::Unknown => reporter.error_sub(
+                LEX_ERR_INVALID_ESCAPE,
+                span,
+                format!("unknown escape sequence `{}`", &unquoted_content[range]),
+                vec![reporter.sub_hint_simple(
+                    "the escape sequences are `\\\\`, `\\\"`, `\\n`, `\\r`, `\\t`, `\\0` and `\\u{...}`",
+                )],
+            ),
+            EscapeError::UnicodeMissingBraces => reporter.error_sub(
+                LEX_ERR_INVALID_ESCAPE,
+                span,
+                "invalid unicode escape sequence",
+                vec![reporter.sub_hint_simple(
+                    "unicode escape sequences are written `\\u{...}`, with 1 to 6 hexadecimal digits",
+                )],
+            ),
+            EscapeError::UnicodeInvalidDigits => reporter.error_sub(
+                LEX_ERR_INVALID_ESCAPE,
+                span,
+                "invalid unicode escape sequence",
+                vec![reporter.sub_hint_simple(
+                    "unicode escape sequences have 1 to 6 hexadecimal digits",
+                )],
+            ),
+            EscapeError::UnicodeNotAChar => reporter.error(
+                LEX_ERR_INVALID_ESCAPE,
+                span,
+                format!(
+                    "`{}` is not a Unicode character",
+                    &unquoted_content[range.start + 3..range.end - 1]
+                ),
+            ),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{Item, ItemCollector},
+        parse::lexer::lex,
+        span::SourceMap,
+    };
+    use std::sync::Arc;
+
+    fn validate(content: &str) -> Vec<Item> {
+        let file = SourceMap::new().add_file(content, "test", None);
+        let collector = Arc::new(ItemCollector::new());
+        let reporter = ItemSender::new(file.clone(), collector.clone());
+
+        for token in lex(&file) {
+            validate_token(&token, &reporter);
+        }
+
+        collector.take()
+    }
+
+    fn messages(items: &[Item]) -> Vec<(u32, &str)> {
+        Vec::from_iter(items.iter().map(|item| (item.code, item.message.as_str())))
+    }
+
+    #[test]
+    fn test_validate_valid_tokens() {
+        let items = validate(
+            "in a: f3; let b = 0b01_1u + 0o17i + 0xfFu + 12f + 1.5e3f; @name = \"a\\t\\\"\\u{1F600}\";\n",
+        );
+        assert_eq!(messages(&items), []);
+    }
+
+    #[test]
+    fn test_validate_unknown_chars() {
+        let items = validate("in a: f；\nin b$: f;\n“x”\u{200b}\n");
+        assert_eq!(
+            messages(&items),
+            [
+                (LEX_ERR_UNKNOWN_CHAR, "unknown character `；`"),
+                (LEX_ERR_UNKNOWN_CHAR, "unknown character `$`"),
+                (LEX_ERR_UNKNOWN_CHAR, "unknown character `“`"),
+                (LEX_ERR_UNKNOWN_CHAR, "unknown characters `”\\u{200b}`"),
+            ]
+        );
+        assert_eq!(
+            items[0].suggestions[0].message,
+            "`；` (fullwidth `;`) looks like `;`, but it is not"
+        );
+        assert!(items[1].suggestions.is_empty());
+        assert_eq!(items[2].suggestions[0].replacement, "\"");
+        assert_eq!(items[3].suggestions[0].replacement, "\"");
+    }
+
+    #[test]
+    fn test_validate_strings() {
+        let items = validate("@a = \"C:\\shaders\";\n@b = \"\\u{d800}\\u{}\\u12\";\n@c = \"open\n");
+        assert_eq!(
+            messages(&items),
+            [
+                (LEX_ERR_INVALID_ESCAPE, "unknown escape sequence `\\s`"),
+                (LEX_ERR_INVALID_ESCAPE, "`d800` is not a Unicode character"),
+                (LEX_ERR_INVALID_ESCAPE, "invalid unicode escape sequence"),
+                (LEX_ERR_INVALID_ESCAPE, "invalid unicode escape sequence"),
+                (LEX_ERR_UNTERMINATED_STRING, "unterminated string literal"),
+            ]
+        );
+        assert_eq!(items[0].origin.as_ref().unwrap().span, Span::new(8, 10));
+        assert_eq!(items[1].origin.as_ref().unwrap().span, Span::new(25, 33));
+        assert_eq!(items[4].origin.as_ref().unwrap().span, Span::new(49, 50));
+        assert_eq!(items[4].suggestions[0].span, Span::empty(54));
+    }
+
+    #[test]
+    fn test_validate_block_comments() {
+        let items = validate("#[ a #[ b ]# c ]#\n## doc\nin a: f;\n#[ d #[ e ]#\n");
+        assert_eq!(
+            messages(&items),
+            [(
+                LEX_ERR_UNTERMINATED_BLOCK_COMMENT,
+                "unterminated block comment"
+            )]
+        );
+        assert_eq!(items[0].origin.as_ref().unwrap().span, Span::new(34, 36));
+    }
+
+    #[test]
+    fn test_validate_numbers() {
+        let items = validate("0b0120 0o758 1px 0b1f 1.5u 0x1i\n");
+        assert_eq!(
+            messages(&items),
+            [
+                (LEX_ERR_INVALID_DIGIT, "invalid digit `2` in binary literal"),
+                (LEX_ERR_INVALID_DIGIT, "invalid digit `8` in octal literal"),
+                (LEX_ERR_INVALID_SUFFIX, "unknown suffix `px`"),
+                (
+                    LEX_ERR_INVALID_SUFFIX,
+                    "suffix `f` is not allowed on a binary literal"
+                ),
+                (
+                    LEX_ERR_INVALID_SUFFIX,
+                    "suffix `u` is not allowed on a float literal"
+                ),
+            ]
+        );
+        assert_eq!(items[0].origin.as_ref().unwrap().span, Span::new(4, 5));
+        assert_eq!(
+            items[2].sub_items[0].message,
+            "the suffixes of integer literals are `i`, `u` and `f`"
+        );
+        assert_eq!(
+            items[4].sub_items[0].message,
+            "the only suffix of float literals is `f`"
+        );
+    }
+}
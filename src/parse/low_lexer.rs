@@ -46,10 +46,22 @@ fn next(input: impl AsRef<str>) -> LowToken {
 
             LowTokenKind::NumberLiteral { kind, suffix_start }
         }
-        '#' => {
-            consume_comment(&mut cursor);
-            LowTokenKind::Comment
-        }
+        '#' => match (cursor.first(), cursor.second()) {
+            ('[', _) => {
+                cursor.consume();
+                LowTokenKind::BlockComment {
+                    terminated: consume_block_comment(&mut cursor),
+                }
+            }
+            ('#', second) if second != '#' => {
+                consume_comment(&mut cursor);
+                LowTokenKind::DocComment
+            }
+            _ => {
+                consume_comment(&mut cursor);
+                LowTokenKind::Comment
+            }
+        },
         '(' => LowTokenKind::OpenParen,
         ')' => LowTokenKind::CloseParen,
         '{' => LowTokenKind::OpenBrace,
@@ -97,14 +109,40 @@ fn consume_comment(cursor: &mut Cursor) {
     }
 }
 
-fn is_id_start(char: char) -> bool {
+/// Consumes a block comment after its opening `#[`, including the nested ones; returns `false`
+/// if it is not closed before the end of the input.
+fn consume_block_comment(cursor: &mut Cursor) -> bool {
+    let mut depth = 1;
+
+    while let Some(char) = cursor.consume() {
+        match (char, cursor.first()) {
+            ('#', '[') => {
+                cursor.consume();
+                depth += 1;
+            }
+            (']', '#') => {
+                cursor.consume();
+                depth -= 1;
+
+                if depth == 0 {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    false
+}
+
+pub(crate) fn is_id_start(char: char) -> bool {
     char.is_ascii_lowercase()
         || char.is_ascii_uppercase()
         || (char == '_')
         || (char > '\x7f' && char.is_xid_start())
 }
 
-fn is_id_continue(char: char) -> bool {
+pub(crate) fn is_id_continue(char: char) -> bool {
     char.is_ascii_lowercase()
         || char.is_ascii_uppercase()
         || char.is_ascii_digit()
@@ -115,14 +153,16 @@ fn is_id_continue(char: char) -> bool {
 fn consume_literal_number(cursor: &mut Cursor, first_char: char) -> LowTokenNumberLiteralKind {
     let kind = if first_char == '0' {
         match cursor.first() {
-            'b' | 'B' if cursor.second().is_digit(2) => {
+            // digits out of the radix are consumed to be reported, instead of starting a
+            // new literal
+            'b' | 'B' if cursor.second().is_ascii_digit() => {
                 cursor.consume();
-                consume_while(cursor, |char| char.is_digit(2) || char == '_');
+                consume_while(cursor, |char| char.is_ascii_digit() || char == '_');
                 LowTokenNumberLiteralKind::IntegerBinary
             }
-            'o' | 'O' if cursor.second().is_digit(8) => {
+            'o' | 'O' if cursor.second().is_ascii_digit() => {
                 cursor.consume();
-                consume_while(cursor, |char| char.is_digit(8) || char == '_');
+                consume_while(cursor, |char| char.is_ascii_digit() || char == '_');
                 LowTokenNumberLiteralKind::IntegerOctal
             }
             'x' | 'X' if cursor.second().is_ascii_hexdigit() => {
@@ -247,6 +287,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_low_token_next_block_comment() {
+        assert_eq!(
+            next("#[ hello ]# world"),
+            LowToken::new(LowTokenKind::BlockComment { terminated: true }, 11)
+        );
+        assert_eq!(
+            next("#[ a\n #[ b ]# c ]#]#"),
+            LowToken::new(LowTokenKind::BlockComment { terminated: true }, 18)
+        );
+        assert_eq!(
+            next("#[]#"),
+            LowToken::new(LowTokenKind::BlockComment { terminated: true }, 4)
+        );
+        assert_eq!(
+            next("#[ a #[ b ]#"),
+            LowToken::new(LowTokenKind::BlockComment { terminated: false }, 12)
+        );
+        assert_eq!(
+            next("#[]"),
+            LowToken::new(LowTokenKind::BlockComment { terminated: false }, 3)
+        );
+    }
+
+    #[test]
+    fn test_low_token_next_doc_comment() {
+        assert_eq!(
+            next("## hello\n"),
+            LowToken::new(LowTokenKind::DocComment, 8)
+        );
+        assert_eq!(next("##"), LowToken::new(LowTokenKind::DocComment, 2));
+        assert_eq!(next("##[ x ]#"), LowToken::new(LowTokenKind::DocComment, 8));
+        assert_eq!(next("### x"), LowToken::new(LowTokenKind::Comment, 5));
+    }
+
     #[test]
     fn test_low_token_next_punc() {
         assert_eq!(next("("), LowToken::new(LowTokenKind::OpenParen, 1));
@@ -451,6 +526,27 @@ mod tests {
                 14 + 6
             )
         );
+        // digits out of the radix are part of the literal
+        assert_eq!(
+            next("0b0123"),
+            LowToken::new(
+                LowTokenKind::NumberLiteral {
+                    kind: LowTokenNumberLiteralKind::IntegerBinary,
+                    suffix_start: 6
+                },
+                6
+            )
+        );
+        assert_eq!(
+            next("0o789u"),
+            LowToken::new(
+                LowTokenKind::NumberLiteral {
+                    kind: LowTokenNumberLiteralKind::IntegerOctal,
+                    suffix_start: 5
+                },
+                6
+            )
+        );
         assert_eq!(
             next("0x0123456789abcdef"),
             LowToken::new(
@@ -6,6 +6,7 @@ pub enum LowTokenKind {
     EndOfFile,
     Whitespace,
     Comment,      // "#"
+    DocComment,   // "##"
     OpenParen,    // "("
     CloseParen,   // ")"
     OpenBrace,    // "{"
@@ -31,6 +32,10 @@ pub enum LowTokenKind {
     Caret,        // "^"
     Tilde,        // "~"
     Id,           // identifier or keyword
+    // "#[ ... ]#", which may nest
+    BlockComment {
+        terminated: bool,
+    },
     NumberLiteral {
         kind: LowTokenNumberLiteralKind,
         suffix_start: u32,
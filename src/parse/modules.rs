@@ -0,0 +1,560 @@
+use super::ast::{
+    AstCallExpr, AstCompTime, AstCompTimeKind, AstExpr, AstExprKind, AstIdentifierKind,
+    AstPassLevelKind, AstStatement, AstStatementKind, AstTopLevel, AstTopLevelKind,
+};
+use crate::{
+    diagnostics::{
+        codes::{IMPORT_ERR_AMBIGUOUS, IMPORT_ERR_PRIVATE, TYPE_ERR_UNDEFINED_NAME},
+        ItemSender,
+    },
+    span::Span,
+    symbol::Symbol,
+};
+use rustc_hash::FxHashMap;
+
+/// A file of a pack, along with the functions it defines and the modules it imports.
+pub struct Module {
+    pub reporter: ItemSender,
+    pub imports: Vec<ModuleImport>,
+    pub functions: FxHashMap<Symbol, ModuleFunction>,
+}
+
+pub struct ModuleImport {
+    pub module: usize,
+    pub alias: Option<Symbol>,
+    pub span: Span,
+}
+
+pub struct ModuleFunction {
+    /// The name of the function in the whole pack.
+    pub symbol: Symbol,
+    pub is_pub: bool,
+    pub span: Span,
+}
+
+impl Module {
+    /// Collects the functions defined by the top-level items of a file, including the ones in
+    /// `comptime` items. Functions of imported files are renamed after the file, so that they
+    /// cannot clash with the functions of other files.
+    pub fn new(reporter: ItemSender, top_levels: &[AstTopLevel], is_root: bool) -> Self {
+        let mut module = Self {
+            reporter,
+            imports: Vec::new(),
+            functions: FxHashMap::default(),
+        };
+        module.collect_functions(top_levels, is_root);
+        module
+    }
+
+    fn collect_functions(&mut self, top_levels: &[AstTopLevel], is_root: bool) {
+        for top_level in top_levels {
+            match &top_level.kind {
+                AstTopLevelKind::CompTime(comptime) => {
+                    for items in comptime_blocks(comptime) {
+                        self.collect_functions(items, is_root);
+                    }
+                }
+                AstTopLevelKind::FnDef(fn_def) => {
+                    // composed names are only known after expansion, so they stay global
+                    let AstIdentifierKind::Symbol(name) = &fn_def.ident.kind else {
+                        continue;
+                    };
+                    let symbol = match is_root {
+                        true => *name,
+                        false => Symbol::from_str(format!(
+                            "{}::{}",
+                            self.reporter.file().name(),
+                            name.to_str()
+                        )),
+                    };
+
+                    // duplicates are reported by the lowering
+                    self.functions.entry(*name).or_insert(ModuleFunction {
+                        symbol,
+                        is_pub: fn_def.keyword_pub.is_some(),
+                        span: fn_def.ident.span,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Renames the functions of every module, and resolves every call to the function it refers to;
+/// `top_levels` are paired with the index of the module they are defined in.
+///
+/// Returns `false` if a call could not be resolved.
+pub fn resolve_paths(modules: &[Module], top_levels: &mut [(usize, AstTopLevel)]) -> bool {
+    let mut resolver = PathResolver {
+        modules,
+        module: 0,
+        has_error: false,
+    };
+
+    for (module, top_level) in top_levels {
+        resolver.module = *module;
+        resolver.resolve_top_level(top_level);
+    }
+
+    !resolver.has_error
+}
+
+struct PathResolver<'a> {
+    modules: &'a [Module],
+    module: usize,
+    has_error: bool,
+}
+
+impl PathResolver<'_> {
+    fn resolve_top_level(&mut self, top_level: &mut AstTopLevel) {
+        match &mut top_level.kind {
+            AstTopLevelKind::CompTime(comptime) => {
+                for items in comptime_blocks_mut(comptime) {
+                    for item in items {
+                        self.resolve_top_level(item);
+                    }
+                }
+            }
+            AstTopLevelKind::FnDef(fn_def) => {
+                if let AstIdentifierKind::Symbol(name) = &fn_def.ident.kind {
+                    let function = &self.modules[self.module].functions[name];
+                    fn_def.ident.kind = AstIdentifierKind::Symbol(function.symbol);
+                }
+
+                self.resolve_statements(&mut fn_def.statements);
+            }
+            AstTopLevelKind::Pass(pass) => {
+                for pass_level in &mut pass.pass_levels {
+                    if let AstPassLevelKind::Stage(stage) = &mut pass_level.kind {
+                        self.resolve_statements(&mut stage.statements);
+                    }
+                }
+            }
+            AstTopLevelKind::Import(_) | AstTopLevelKind::Input(_) => {}
+        }
+    }
+
+    fn resolve_statements(&mut self, statements: &mut [AstStatement]) {
+        for statement in statements {
+            match &mut statement.kind {
+                AstStatementKind::VarDecl(var_decl) => {
+                    if let Some(assignment) = &mut var_decl.assignment {
+                        self.resolve_expr(&mut assignment.rhs);
+                    }
+                }
+                AstStatementKind::Assignment(assignment) => {
+                    self.resolve_expr(&mut assignment.lhs);
+                    self.resolve_expr(&mut assignment.rhs);
+                }
+                AstStatementKind::Return(statement) => {
+                    if let Some(expr) = &mut statement.expr {
+                        self.resolve_expr(expr);
+                    }
+                }
+                AstStatementKind::Expr(expr) => self.resolve_expr(expr),
+            }
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &mut AstExpr) {
+        match &mut expr.kind {
+            AstExprKind::Invalid | AstExprKind::Literal(_) | AstExprKind::Identifier(_) => {}
+            AstExprKind::Binary(binary) => {
+                self.resolve_expr(&mut binary.lhs);
+                self.resolve_expr(&mut binary.rhs);
+            }
+            AstExprKind::Unary(unary) => self.resolve_expr(&mut unary.rhs),
+            AstExprKind::Call(call) => {
+                self.resolve_call(call);
+
+                for arg in &mut call.args {
+                    self.resolve_expr(&mut arg.expr);
+                }
+            }
+            AstExprKind::Member(member) => self.resolve_expr(&mut member.lhs),
+            AstExprKind::Index(index) => {
+                self.resolve_expr(&mut index.lhs);
+                self.resolve_expr(&mut index.index);
+            }
+            AstExprKind::Object(object) => {
+                for field in &mut object.fields {
+                    self.resolve_expr(&mut field.expr);
+                }
+            }
+        }
+    }
+
+    fn resolve_call(&mut self, call: &mut AstCallExpr) {
+        let AstIdentifierKind::Symbol(name) = call.callee.kind else {
+            return;
+        };
+        let modules = self.modules;
+        let module = &modules[self.module];
+
+        let symbol = match call.qualifier.take() {
+            Some(qualifier) => {
+                let alias = match qualifier.module.kind {
+                    AstIdentifierKind::Symbol(alias) => alias,
+                    // already reported by the parser
+                    AstIdentifierKind::Invalid => {
+                        self.has_error = true;
+                        return;
+                    }
+                    AstIdentifierKind::Composed(_) => {
+                        self.error(
+                            TYPE_ERR_UNDEFINED_NAME,
+                            qualifier.module.span,
+                            "module names cannot be composed",
+                        );
+                        return;
+                    }
+                };
+                let Some(import) = module
+                    .imports
+                    .iter()
+                    .find(|import| import.alias == Some(alias))
+                else {
+                    self.error(
+                        TYPE_ERR_UNDEFINED_NAME,
+                        qualifier.module.span,
+                        format!("undefined module {}", alias),
+                    );
+                    return;
+                };
+
+                match modules[import.module].functions.get(&name) {
+                    Some(function) if function.is_pub => function.symbol,
+                    Some(function) => {
+                        self.report_private(call.callee.span, name, import.module, function);
+                        return;
+                    }
+                    None => {
+                        self.error(
+                            TYPE_ERR_UNDEFINED_NAME,
+                            call.callee.span,
+                            format!("module {} has no function {}", alias, name),
+                        );
+                        return;
+                    }
+                }
+            }
+            None => {
+                if let Some(function) = module.functions.get(&name) {
+                    call.callee.kind = AstIdentifierKind::Symbol(function.symbol);
+                    return;
+                }
+
+                let mut candidates = Vec::<&ModuleImport>::new();
+
+                for import in &module.imports {
+                    if import.alias.is_none()
+                        && modules[import.module].functions.contains_key(&name)
+                        && candidates.iter().all(|other| other.module != import.module)
+                    {
+                        candidates.push(import);
+                    }
+                }
+
+                let public = Vec::from_iter(
+                    candidates
+                        .iter()
+                        .copied()
+                        .filter(|import| modules[import.module].functions[&name].is_pub),
+                );
+
+                match (public.as_slice(), candidates.first()) {
+                    ([import], _) => modules[import.module].functions[&name].symbol,
+                    // built-ins and constructors, or undefined names reported by the lowering
+                    ([], None) => return,
+                    ([], Some(import)) => {
+                        let function = &modules[import.module].functions[&name];
+                        self.report_private(call.callee.span, name, import.module, function);
+                        return;
+                    }
+                    (public, _) => {
+                        let sub_items = Vec::from_iter(public.iter().map(|import| {
+                            module.reporter.sub_hint(
+                                import.span,
+                                format!(
+                                    "{} is exported by `{}`",
+                                    name,
+                                    modules[import.module].reporter.file().name()
+                                ),
+                            )
+                        }));
+                        module.reporter.error_sub(
+                            IMPORT_ERR_AMBIGUOUS,
+                            call.callee.span,
+                            format!("{} is ambiguous; it is exported by several imports", name),
+                            sub_items,
+                        );
+                        self.has_error = true;
+                        return;
+                    }
+                }
+            }
+        };
+
+        call.callee.kind = AstIdentifierKind::Symbol(symbol);
+    }
+
+    fn report_private(
+        &mut self,
+        span: Span,
+        name: Symbol,
+        target: usize,
+        function: &ModuleFunction,
+    ) {
+        let target = &self.modules[target].reporter;
+        self.modules[self.module].reporter.error_sub(
+            IMPORT_ERR_PRIVATE,
+            span,
+            format!("function {} is private to `{}`", name, target.file().name()),
+            vec![target.sub_hint(function.span, "add `pub` to call it from other files")],
+        );
+        self.has_error = true;
+    }
+
+    fn error(&mut self, code: u32, span: Span, message: impl Into<String>) {
+        self.modules[self.module]
+            .reporter
+            .error(code, span, message);
+        self.has_error = true;
+    }
+}
+
+pub fn comptime_blocks(comptime: &AstCompTime<AstTopLevel>) -> Vec<&Vec<AstTopLevel>> {
+    match &comptime.kind {
+        AstCompTimeKind::Invalid => vec![],
+        AstCompTimeKind::If(comptime_if) => {
+            let mut blocks = vec![&comptime_if.if_part.block.items];
+            blocks.extend(
+                comptime_if
+                    .else_if_parts
+                    .iter()
+                    .map(|part| &part.block.items),
+            );
+            blocks.extend(comptime_if.else_part.iter().map(|part| &part.block.items));
+            blocks
+        }
+        AstCompTimeKind::Loop(comptime_loop) => vec![&comptime_loop.block.items],
+    }
+}
+
+fn comptime_blocks_mut(comptime: &mut AstCompTime<AstTopLevel>) -> Vec<&mut Vec<AstTopLevel>> {
+    match &mut comptime.kind {
+        AstCompTimeKind::Invalid => vec![],
+        AstCompTimeKind::If(comptime_if) => {
+            let mut blocks = vec![&mut comptime_if.if_part.block.items];
+            blocks.extend(
+                comptime_if
+                    .else_if_parts
+                    .iter_mut()
+                    .map(|part| &mut part.block.items),
+            );
+            blocks.extend(
+                comptime_if
+                    .else_part
+                    .iter_mut()
+                    .map(|part| &mut part.block.items),
+            );
+            blocks
+        }
+        AstCompTimeKind::Loop(comptime_loop) => vec![&mut comptime_loop.block.items],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{Item, ItemCollector},
+        span::SourceMap,
+        test_utils::{fn_def_calling, shader_pack},
+    };
+    use std::sync::Arc;
+
+    /// The names of the functions, with the names of the functions they call.
+    type Functions = Vec<(String, Vec<String>)>;
+
+    /// Creates the modules of `files`, the first one being the root, and resolves the paths of
+    /// their functions; imports are given as `(importer, imported, alias)`.
+    fn resolve(
+        files: Vec<(&str, Vec<AstTopLevelKind>)>,
+        imports: &[(usize, usize, Option<&str>)],
+    ) -> (bool, Functions, Vec<Item>) {
+        let mut source_map = SourceMap::new();
+        let collector = Arc::new(ItemCollector::new());
+        let mut modules = Vec::new();
+        let mut top_levels = Vec::new();
+
+        for (index, (name, kinds)) in files.into_iter().enumerate() {
+            let file = source_map.add_file(format!("# {}", name), name, None);
+            let reporter = ItemSender::new(file, collector.clone());
+            let pack = shader_pack(kinds);
+            modules.push(Module::new(reporter, &pack.top_levels, index == 0));
+            top_levels.extend(
+                pack.top_levels
+                    .into_iter()
+                    .map(|top_level| (index, top_level)),
+            );
+        }
+
+        for (importer, imported, alias) in imports {
+            modules[*importer].imports.push(ModuleImport {
+                module: *imported,
+                alias: alias.map(Symbol::from_str),
+                span: Span::ZERO,
+            });
+        }
+
+        let is_resolved = resolve_paths(&modules, &mut top_levels);
+        drop(modules);
+
+        (is_resolved, functions(&top_levels), collector.take())
+    }
+
+    /// Returns every function, with the functions it calls.
+    fn functions(top_levels: &[(usize, AstTopLevel)]) -> Functions {
+        let name = |ident: &AstIdentifierKind| match ident {
+            AstIdentifierKind::Symbol(symbol) => symbol.to_str().to_owned(),
+            _ => unreachable!(),
+        };
+
+        Vec::from_iter(top_levels.iter().filter_map(|(_, top_level)| {
+            let AstTopLevelKind::FnDef(fn_def) = &top_level.kind else {
+                return None;
+            };
+            let calls =
+                Vec::from_iter(fn_def.statements.iter().filter_map(
+                    |statement| match &statement.kind {
+                        AstStatementKind::Expr(AstExpr {
+                            kind: AstExprKind::Call(call),
+                            ..
+                        }) => Some(name(&call.callee.kind)),
+                        _ => None,
+                    },
+                ));
+            Some((name(&fn_def.ident.kind), calls))
+        }))
+    }
+
+    fn errors(items: &[Item]) -> Vec<(u32, &str)> {
+        Vec::from_iter(items.iter().map(|item| (item.code, item.message.as_str())))
+    }
+
+    #[test]
+    fn test_resolve_paths_name_clashes() {
+        let (is_resolved, functions, items) = resolve(
+            vec![
+                (
+                    "main.spk",
+                    vec![
+                        fn_def_calling("main", false, &["hash", "perlin", "light::hash"]),
+                        fn_def_calling("hash", false, &[]),
+                    ],
+                ),
+                (
+                    "noise.spk",
+                    vec![
+                        fn_def_calling("perlin", true, &["hash"]),
+                        fn_def_calling("hash", true, &[]),
+                    ],
+                ),
+                (
+                    "lighting.spk",
+                    vec![fn_def_calling("hash", true, &["perlin"])],
+                ),
+            ],
+            &[(0, 1, None), (0, 2, Some("light")), (2, 1, None)],
+        );
+        let expected = |pairs: &[(&str, &[&str])]| {
+            Vec::from_iter(pairs.iter().map(|(name, calls)| {
+                let calls = Vec::from_iter(calls.iter().map(|call| call.to_string()));
+                (name.to_string(), calls)
+            }))
+        };
+
+        assert!(is_resolved);
+        assert!(items.is_empty());
+        // functions of the same name in different files are renamed apart, and a local function
+        // shadows the imported ones
+        assert_eq!(
+            functions,
+            expected(&[
+                ("main", &["hash", "noise.spk::perlin", "lighting.spk::hash"]),
+                ("hash", &[]),
+                ("noise.spk::perlin", &["noise.spk::hash"]),
+                ("noise.spk::hash", &[]),
+                ("lighting.spk::hash", &["noise.spk::perlin"]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_resolve_paths_ambiguous_names() {
+        let files = || {
+            vec![
+                ("main.spk", vec![fn_def_calling("main", false, &["hash"])]),
+                ("a.spk", vec![fn_def_calling("hash", true, &[])]),
+                ("b.spk", vec![fn_def_calling("hash", true, &[])]),
+                ("c.spk", vec![fn_def_calling("hash", false, &[])]),
+            ]
+        };
+
+        let (is_resolved, _, items) = resolve(files(), &[(0, 1, None), (0, 2, None)]);
+        assert!(!is_resolved);
+        assert_eq!(
+            errors(&items),
+            [(
+                IMPORT_ERR_AMBIGUOUS,
+                "`hash` is ambiguous; it is exported by several imports"
+            )]
+        );
+        assert_eq!(items[0].sub_items.len(), 2);
+
+        // the same file imported twice, and private functions, do not clash
+        let (is_resolved, functions, items) =
+            resolve(files(), &[(0, 1, None), (0, 1, None), (0, 3, None)]);
+        assert!(is_resolved);
+        assert!(items.is_empty());
+        assert_eq!(functions[0].1, ["a.spk::hash"]);
+    }
+
+    #[test]
+    fn test_resolve_paths_unknown_aliases() {
+        let (is_resolved, _, items) = resolve(
+            vec![
+                (
+                    "main.spk",
+                    vec![fn_def_calling(
+                        "main",
+                        false,
+                        &["y::f", "a::f", "inner::f", "x::missing", "x::f"],
+                    )],
+                ),
+                ("a.spk", vec![fn_def_calling("f", true, &[])]),
+                ("b.spk", vec![fn_def_calling("g", true, &["inner::f"])]),
+            ],
+            &[(0, 1, None), (0, 2, Some("x")), (2, 1, Some("inner"))],
+        );
+
+        assert!(!is_resolved);
+        assert_eq!(
+            errors(&items),
+            [
+                (TYPE_ERR_UNDEFINED_NAME, "undefined module `y`"),
+                // files imported without an alias cannot be named
+                (TYPE_ERR_UNDEFINED_NAME, "undefined module `a`"),
+                // aliases are private to the file importing them
+                (TYPE_ERR_UNDEFINED_NAME, "undefined module `inner`"),
+                (
+                    TYPE_ERR_UNDEFINED_NAME,
+                    "module `x` has no function `missing`"
+                ),
+                (TYPE_ERR_UNDEFINED_NAME, "module `x` has no function `f`"),
+            ]
+        );
+    }
+}
@@ -4,14 +4,18 @@ use super::{
         AstCompTimeElsePart, AstCompTimeIf, AstCompTimeIfPart, AstCompTimeIfPredicateExpr,
         AstCompTimeIfPredicateExprKind, AstCompTimeIfPredicateExprNot,
         AstCompTimeIfPredicateExprParen, AstCompTimeLoop, AstComposedIdentifier,
-        AstComposedIdentifierArg, AstExpr, AstIdentifier, AstIdentifierKind, AstKeyword, AstPunc,
-        AstPuncKind, AstShaderPack, AstStringLiteral, AstTopLevel,
+        AstComposedIdentifierArg, AstExpr, AstIdentifier, AstIdentifierKind, AstImport,
+        AstImportAlias, AstKeyword, AstPunc, AstPuncKind, AstShaderPack, AstStringLiteral,
+        AstTopLevel,
     },
     cursor::Cursor,
     lexer::Token,
+    symbols::{KEYWORD_AS, KEYWORD_IMPORT},
 };
 use crate::{
-    diagnostics::codes::PARSE_ERR_INVALID_COMPTIME,
+    diagnostics::{
+        codes::PARSE_ERR_INVALID_COMPTIME, find_similar_name, Applicability, Suggestion,
+    },
     parse::{
         ast::{
             AstCompTimeIfPredicateExprAnd, AstCompTimeIfPredicateExprFlag,
@@ -115,10 +119,24 @@ where
             });
         }
 
-        cursor.reporter().error(
+        let mut suggestions = Vec::new();
+
+        if let TokenKind::Id { symbol, .. } = cursor.lookahead_0().token.kind {
+            if let Some(similar) = find_similar_name(symbol.to_str(), ["if", "loop"]) {
+                suggestions.push(Suggestion::new(
+                    format!("did you mean `{}`?", similar),
+                    cursor.lookahead_0().token.span(),
+                    similar,
+                    Applicability::MaybeIncorrect,
+                ));
+            }
+        }
+
+        cursor.reporter().error_fix(
             PARSE_ERR_INVALID_COMPTIME,
             keyword_comptime.span,
             "`comptime` must be followed by `if` or `loop`",
+            suggestions,
         );
 
         None
@@ -273,7 +291,7 @@ where
         } else {
             cursor.reporter().error(
                 PARSE_ERR_INVALID_COMPTIME,
-                cursor.lookahead_0().token.unwrap().span(),
+                cursor.lookahead_0().token.span(),
                 "`if` predicate must be a string literal, a parenthesized expression, or a `not` expression",
             );
 
@@ -364,10 +382,16 @@ where
             if let Some(punc_close_paren) = parse_punc(cursor, AstPuncKind::CloseParen) {
                 punc_close_paren
             } else {
-                cursor.reporter().error(
+                cursor.reporter().error_fix(
                     PARSE_ERR_INVALID_COMPTIME,
-                    cursor.lookahead_0().token.unwrap().span(),
+                    cursor.lookahead_0().token.span(),
                     "`)` is expected",
+                    vec![Suggestion::new(
+                        "insert `)`",
+                        Span::empty(expr.span.high()),
+                        ")",
+                        Applicability::MachineApplicable,
+                    )],
                 );
 
                 AstPunc {
@@ -464,6 +488,52 @@ where
     }
 }
 
+impl<T> Parse<T> for AstImport
+where
+    T: Iterator<Item = Token>,
+{
+    fn parse(cursor: &mut Cursor<T>) -> Option<Self> {
+        let node_id = cursor.node_id();
+        let keyword_import = parse_keyword(cursor, *KEYWORD_IMPORT)?;
+        let path = AstStringLiteral::parse(cursor)?;
+        let alias = if cursor.lookahead_0().is_keyword(*KEYWORD_AS) {
+            Some(AstImportAlias::parse(cursor)?)
+        } else {
+            None
+        };
+        let punc_semicolon = parse_punc(cursor, AstPuncKind::Semicolon);
+        let span = keyword_import.span.expand_to(punc_semicolon.span.high());
+
+        Some(AstImport {
+            node_id,
+            span,
+            keyword_import,
+            path,
+            alias,
+            punc_semicolon,
+        })
+    }
+}
+
+impl<T> Parse<T> for AstImportAlias
+where
+    T: Iterator<Item = Token>,
+{
+    fn parse(cursor: &mut Cursor<T>) -> Option<Self> {
+        let node_id = cursor.node_id();
+        let keyword_as = parse_keyword(cursor, *KEYWORD_AS)?;
+        let ident = AstIdentifier::parse(cursor)?;
+        let span = keyword_as.span.expand_to(ident.span.high());
+
+        Some(AstImportAlias {
+            node_id,
+            span,
+            keyword_as,
+            ident,
+        })
+    }
+}
+
 impl<T> Parse<T> for AstExpr
 where
     T: Iterator<Item = Token>,
@@ -11,4 +11,6 @@ lazy_static! {
     pub static ref SYMBOL_OR: Symbol = Symbol::from_str("or");
     pub static ref SYMBOL_AND: Symbol = Symbol::from_str("and");
     pub static ref SYMBOL_NOT: Symbol = Symbol::from_str("not");
+    pub static ref KEYWORD_IMPORT: Symbol = Symbol::from_str("import");
+    pub static ref KEYWORD_AS: Symbol = Symbol::from_str("as");
 }
@@ -0,0 +1,620 @@
+mod layout;
+
+pub use layout::*;
+
+use crate::{
+    diagnostics::{
+        codes::{LAYOUT_ERR_NO_UNIFORM_LAYOUT, LAYOUT_ERR_NO_VERTEX_FORMAT},
+        ItemSender,
+    },
+    ir::{
+        IrExprKind, IrModule, IrPass, IrResource, IrResourceKind, IrScalarType, IrStage,
+        IrTextureKind, IrType,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// Describes the interface of every pass of a compiled shader pack (or of a single variant of
+/// it), so that engines and asset tools do not have to parse the generated shader code.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Reflection {
+    pub passes: Vec<PassReflection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PassReflection {
+    pub name: String,
+    /// The `@mode` attribute of the pass, if any.
+    pub mode: Option<String>,
+    pub entry_points: Vec<EntryPointReflection>,
+    pub vertex_attributes: Vec<VertexAttributeReflection>,
+    /// The unified buffer holding every uniform input; `None` if the pass has no uniforms.
+    pub uniform_buffer: Option<UniformBufferReflection>,
+    /// Every resource binding of the pass, including the uniform buffer.
+    pub bindings: Vec<BindingReflection>,
+    /// Values passed from the vertex stage to the fragment stage.
+    pub varyings: Vec<VaryingReflection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EntryPointReflection {
+    pub stage: IrStage,
+    /// Name of the generated function, e.g. `first_vertex`.
+    pub function: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VertexAttributeReflection {
+    pub name: String,
+    /// The mesh attribute, e.g. `position` for `@vertex = "position"`.
+    pub attribute: String,
+    pub location: u32,
+    pub format: VertexFormat,
+    /// The doc comment of the input, e.g. to be shown by material editors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VertexFormat {
+    Uint32,
+    Uint32x2,
+    Uint32x3,
+    Uint32x4,
+    Sint32,
+    Sint32x2,
+    Sint32x3,
+    Sint32x4,
+    Float32,
+    Float32x2,
+    Float32x3,
+    Float32x4,
+}
+
+impl VertexFormat {
+    /// Returns the format of a vertex attribute type; booleans are passed as `u32`.
+    pub fn of(ty: IrType) -> Option<Self> {
+        let (scalar, count) = match ty {
+            IrType::Scalar(scalar) => (scalar, 1),
+            IrType::Vector { scalar, size } => (scalar, size),
+            _ => return None,
+        };
+
+        Some(match (scalar, count) {
+            (IrScalarType::Bool | IrScalarType::UInt, 1) => Self::Uint32,
+            (IrScalarType::Bool | IrScalarType::UInt, 2) => Self::Uint32x2,
+            (IrScalarType::Bool | IrScalarType::UInt, 3) => Self::Uint32x3,
+            (IrScalarType::Bool | IrScalarType::UInt, _) => Self::Uint32x4,
+            (IrScalarType::Int, 1) => Self::Sint32,
+            (IrScalarType::Int, 2) => Self::Sint32x2,
+            (IrScalarType::Int, 3) => Self::Sint32x3,
+            (IrScalarType::Int, _) => Self::Sint32x4,
+            (IrScalarType::Float, 1) => Self::Float32,
+            (IrScalarType::Float, 2) => Self::Float32x2,
+            (IrScalarType::Float, 3) => Self::Float32x3,
+            (IrScalarType::Float, _) => Self::Float32x4,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UniformBufferReflection {
+    pub binding: u32,
+    /// Size of the buffer in bytes, following the std140 layout.
+    pub size: u32,
+    pub members: Vec<UniformMemberReflection>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UniformMemberReflection {
+    pub name: String,
+    /// Type name of the source language, e.g. `f3`.
+    pub ty: String,
+    pub offset: u32,
+    pub size: u32,
+    /// The doc comment of the input, e.g. to be shown by material editors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BindingReflection {
+    pub name: String,
+    pub binding: u32,
+    pub kind: BindingKind,
+    /// The doc comment of the input, e.g. to be shown by material editors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BindingKind {
+    UniformBuffer,
+    #[serde(rename = "texture_2d")]
+    Texture2D,
+    #[serde(rename = "texture_3d")]
+    Texture3D,
+    TextureCube,
+    /// The sampler paired with the texture bound right before it.
+    Sampler,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct VaryingReflection {
+    pub name: String,
+    pub location: u32,
+    /// Type name of the source language, e.g. `f3`.
+    pub ty: String,
+}
+
+impl Reflection {
+    /// Name of the uniform buffer binding.
+    pub const UNIFORM_BUFFER_NAME: &'static str = "uniforms";
+
+    /// Collects the reflection of every pass of the module.
+    ///
+    /// A pass only reports the inputs it declares itself, plus the top-level inputs that its
+    /// stages actually read. Bindings are numbered from 0 in the following order: the uniform
+    /// buffer, then a texture and its sampler for each texture input.
+    ///
+    /// Inputs whose type has no uniform layout or vertex format are reported and left out.
+    pub fn from_module(module: &IrModule, reporter: &ItemSender) -> Self {
+        Self {
+            passes: Vec::from_iter(
+                module
+                    .passes
+                    .iter()
+                    .map(|pass| reflect_pass(module, pass, reporter)),
+            ),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+fn reflect_pass(module: &IrModule, pass: &IrPass, reporter: &ItemSender) -> PassReflection {
+    let used = used_resources(module, pass);
+    let resources = module.resources.iter().filter(|resource| {
+        resource.pass == Some(pass.id) || (resource.pass.is_none() && used[resource.id.0 as usize])
+    });
+
+    let mut vertex_attributes = Vec::new();
+    let mut uniforms = Vec::new();
+    let mut textures = Vec::new();
+
+    for resource in resources {
+        match resource.kind {
+            IrResourceKind::Uniform => uniforms.push(resource),
+            IrResourceKind::Texture => textures.push(resource),
+            IrResourceKind::VertexAttribute { attribute } => {
+                let format = match VertexFormat::of(resource.ty) {
+                    Some(format) => format,
+                    None => {
+                        reporter.error(
+                            LAYOUT_ERR_NO_VERTEX_FORMAT,
+                            resource.span,
+                            format!(
+                                "input {} of type `{}` cannot be a vertex attribute",
+                                resource.name, resource.ty
+                            ),
+                        );
+                        continue;
+                    }
+                };
+
+                vertex_attributes.push(VertexAttributeReflection {
+                    name: resource.name.to_str().to_owned(),
+                    attribute: attribute.to_str().to_owned(),
+                    location: vertex_attributes.len() as u32,
+                    format,
+                    description: description(resource),
+                });
+            }
+        }
+    }
+
+    let mut bindings = Vec::new();
+    let mut uniform_buffer = None;
+
+    if !uniforms.is_empty() {
+        let mut builder = UniformLayoutBuilder::default();
+        let mut members = Vec::with_capacity(uniforms.len());
+
+        for resource in uniforms {
+            let layout = match UniformLayout::of(resource.ty) {
+                Some(layout) => layout,
+                None => {
+                    reporter.error(
+                        LAYOUT_ERR_NO_UNIFORM_LAYOUT,
+                        resource.span,
+                        format!(
+                            "input {} of type `{}` cannot be a uniform",
+                            resource.name, resource.ty
+                        ),
+                    );
+                    continue;
+                }
+            };
+
+            members.push(UniformMemberReflection {
+                name: resource.name.to_str().to_owned(),
+                ty: resource.ty.to_string(),
+                offset: builder.push(layout),
+                size: layout.size,
+                description: description(resource),
+            });
+        }
+
+        bindings.push(BindingReflection {
+            name: Reflection::UNIFORM_BUFFER_NAME.to_owned(),
+            binding: 0,
+            kind: BindingKind::UniformBuffer,
+            description: None,
+        });
+        uniform_buffer = Some(UniformBufferReflection {
+            binding: 0,
+            size: builder.size(),
+            members,
+        });
+    }
+
+    for resource in textures {
+        let kind = match resource.ty {
+            IrType::Texture(IrTextureKind::Texture2D) => BindingKind::Texture2D,
+            IrType::Texture(IrTextureKind::Texture3D) => BindingKind::Texture3D,
+            IrType::Texture(IrTextureKind::TextureCube) => BindingKind::TextureCube,
+            _ => continue,
+        };
+        let name = resource.name.to_str();
+
+        bindings.push(BindingReflection {
+            name: name.to_owned(),
+            binding: bindings.len() as u32,
+            kind,
+            description: description(resource),
+        });
+        bindings.push(BindingReflection {
+            name: format!("{}_sampler", name),
+            binding: bindings.len() as u32,
+            kind: BindingKind::Sampler,
+            description: None,
+        });
+    }
+
+    let mut entry_points = Vec::with_capacity(pass.entry_points.len());
+    let mut varyings = Vec::new();
+
+    for entry_point in &pass.entry_points {
+        let function = module.function(entry_point.function);
+
+        entry_points.push(EntryPointReflection {
+            stage: entry_point.stage,
+            function: function.name.to_str().to_owned(),
+        });
+
+        if let (IrStage::Vertex, IrType::Struct(id)) = (entry_point.stage, function.return_type) {
+            varyings.extend(module.struct_def(id).fields.iter().enumerate().map(
+                |(index, field)| VaryingReflection {
+                    name: field.name.to_str().to_owned(),
+                    location: index as u32,
+                    ty: field.ty.to_string(),
+                },
+            ));
+        }
+    }
+
+    PassReflection {
+        name: pass.name.to_str().to_owned(),
+        mode: pass.attribute("mode").map(|mode| mode.to_str().to_owned()),
+        entry_points,
+        vertex_attributes,
+        uniform_buffer,
+        bindings,
+        varyings,
+    }
+}
+
+fn description(resource: &IrResource) -> Option<String> {
+    resource.doc.map(|doc| doc.to_str().to_owned())
+}
+
+/// Marks the resources read by the stages of the pass and every function they call.
+fn used_resources(module: &IrModule, pass: &IrPass) -> Vec<bool> {
+    let mut used = vec![false; module.resources.len()];
+    let mut visited = vec![false; module.functions.len()];
+    let mut queue = Vec::from_iter(pass.entry_points.iter().map(|entry| entry.function));
+
+    while let Some(id) = queue.pop() {
+        if std::mem::replace(&mut visited[id.0 as usize], true) {
+            continue;
+        }
+
+        module
+            .function(id)
+            .body
+            .visit_exprs(&mut |expr| match &expr.kind {
+                IrExprKind::Resource(resource) => used[resource.0 as usize] = true,
+                IrExprKind::Call { function, .. } => queue.push(*function),
+                _ => {}
+            });
+    }
+
+    used
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diagnostics::{Item, ItemCollector},
+        ir::{
+            IrAttribute, IrBlock, IrEntryPoint, IrExpr, IrFunction, IrFunctionId, IrFunctionKind,
+            IrPassId, IrResource, IrResourceId, IrStatement, IrStatementKind, IrStruct,
+            IrStructField, IrStructId,
+        },
+        span::{SourceMap, Span},
+        symbol::Symbol,
+    };
+    use std::sync::Arc;
+
+    fn resource(id: u32, name: &str, ty: &str, kind: IrResourceKind, pass: bool) -> IrResource {
+        IrResource {
+            id: IrResourceId(id),
+            name: Symbol::from_str(name),
+            ty: IrType::from_name(ty).unwrap(),
+            kind,
+            pass: pass.then_some(IrPassId(0)),
+            doc: None,
+            span: Span::ZERO,
+        }
+    }
+
+    fn read(resources: &[u32]) -> Vec<IrStatement> {
+        Vec::from_iter(resources.iter().map(|id| IrStatement {
+            span: Span::ZERO,
+            kind: IrStatementKind::Expr(IrExpr {
+                ty: IrType::FLOAT,
+                span: Span::ZERO,
+                kind: IrExprKind::Resource(IrResourceId(*id)),
+            }),
+        }))
+    }
+
+    fn stage(
+        id: u32,
+        stage: IrStage,
+        return_type: IrType,
+        statements: Vec<IrStatement>,
+    ) -> IrFunction {
+        IrFunction {
+            id: IrFunctionId(id),
+            name: Symbol::from_str(format!("first_{}", stage.name())),
+            kind: IrFunctionKind::EntryPoint {
+                pass: IrPassId(0),
+                stage,
+            },
+            params: vec![],
+            locals: vec![],
+            return_type,
+            body: IrBlock { statements },
+            span: Span::ZERO,
+        }
+    }
+
+    fn module() -> IrModule {
+        IrModule {
+            structs: vec![IrStruct {
+                fields: vec![IrStructField {
+                    name: Symbol::from_str("uv"),
+                    ty: IrType::from_name("f2").unwrap(),
+                    span: Span::ZERO,
+                }],
+            }],
+            resources: vec![
+                resource(0, "unused", "f", IrResourceKind::Uniform, false),
+                IrResource {
+                    doc: Some(Symbol::from_str("Albedo in sRGB.")),
+                    ..resource(1, "main_tex", "t2", IrResourceKind::Texture, false)
+                },
+                IrResource {
+                    doc: Some(Symbol::from_str("Multiplies the albedo.")),
+                    ..resource(2, "tint", "f3", IrResourceKind::Uniform, false)
+                },
+                resource(
+                    3,
+                    "pos",
+                    "f3",
+                    IrResourceKind::VertexAttribute {
+                        attribute: Symbol::from_str("position"),
+                    },
+                    false,
+                ),
+                resource(4, "scale", "f", IrResourceKind::Uniform, true),
+            ],
+            functions: vec![
+                stage(
+                    0,
+                    IrStage::Vertex,
+                    IrType::Struct(IrStructId(0)),
+                    read(&[3]),
+                ),
+                stage(
+                    1,
+                    IrStage::Fragment,
+                    IrType::from_name("f4").unwrap(),
+                    read(&[1, 2]),
+                ),
+            ],
+            passes: vec![IrPass {
+                id: IrPassId(0),
+                name: Symbol::from_str("first"),
+                attributes: vec![IrAttribute {
+                    name: Symbol::from_str("mode"),
+                    value: Symbol::from_str("Base"),
+                }],
+                entry_points: vec![
+                    IrEntryPoint {
+                        stage: IrStage::Vertex,
+                        function: IrFunctionId(0),
+                    },
+                    IrEntryPoint {
+                        stage: IrStage::Fragment,
+                        function: IrFunctionId(1),
+                    },
+                ],
+                span: Span::ZERO,
+            }],
+        }
+    }
+
+    fn reflect(module: &IrModule) -> (Reflection, Vec<Item>) {
+        let file = SourceMap::new().add_file("", "test", None);
+        let collector = Arc::new(ItemCollector::new());
+        let reflection = Reflection::from_module(module, &ItemSender::new(file, collector.clone()));
+
+        (reflection, collector.take())
+    }
+
+    #[test]
+    fn test_reflection_from_module() {
+        let (reflection, items) = reflect(&module());
+        assert!(items.is_empty());
+        let pass = &reflection.passes[0];
+
+        assert_eq!(pass.name, "first");
+        assert_eq!(pass.mode.as_deref(), Some("Base"));
+        assert_eq!(
+            pass.entry_points,
+            vec![
+                EntryPointReflection {
+                    stage: IrStage::Vertex,
+                    function: "first_vertex".to_owned(),
+                },
+                EntryPointReflection {
+                    stage: IrStage::Fragment,
+                    function: "first_fragment".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(
+            pass.vertex_attributes,
+            vec![VertexAttributeReflection {
+                name: "pos".to_owned(),
+                attribute: "position".to_owned(),
+                location: 0,
+                format: VertexFormat::Float32x3,
+                description: None,
+            }]
+        );
+        assert_eq!(
+            pass.uniform_buffer,
+            Some(UniformBufferReflection {
+                binding: 0,
+                size: 16,
+                members: vec![
+                    UniformMemberReflection {
+                        name: "tint".to_owned(),
+                        ty: "f3".to_owned(),
+                        offset: 0,
+                        size: 12,
+                        description: Some("Multiplies the albedo.".to_owned()),
+                    },
+                    UniformMemberReflection {
+                        name: "scale".to_owned(),
+                        ty: "f".to_owned(),
+                        offset: 12,
+                        size: 4,
+                        description: None,
+                    },
+                ],
+            })
+        );
+        assert_eq!(
+            pass.bindings,
+            vec![
+                BindingReflection {
+                    name: "uniforms".to_owned(),
+                    binding: 0,
+                    kind: BindingKind::UniformBuffer,
+                    description: None,
+                },
+                BindingReflection {
+                    name: "main_tex".to_owned(),
+                    binding: 1,
+                    kind: BindingKind::Texture2D,
+                    description: Some("Albedo in sRGB.".to_owned()),
+                },
+                BindingReflection {
+                    name: "main_tex_sampler".to_owned(),
+                    binding: 2,
+                    kind: BindingKind::Sampler,
+                    description: None,
+                },
+            ]
+        );
+        assert_eq!(
+            pass.varyings,
+            vec![VaryingReflection {
+                name: "uv".to_owned(),
+                location: 0,
+                ty: "f2".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_reflection_layout_errors() {
+        let mut module = module();
+        module.resources[2].ty = IrType::Struct(IrStructId(0));
+        module.resources[3].ty = IrType::from_name("m3").unwrap();
+        let (reflection, items) = reflect(&module);
+        let pass = &reflection.passes[0];
+
+        // the inputs without a layout are reported and left out, not silently skipped
+        assert_eq!(
+            Vec::from_iter(items.iter().map(|item| (item.code, item.message.as_str()))),
+            [
+                (
+                    LAYOUT_ERR_NO_VERTEX_FORMAT,
+                    "input `pos` of type `m3` cannot be a vertex attribute"
+                ),
+                (
+                    LAYOUT_ERR_NO_UNIFORM_LAYOUT,
+                    "input `tint` of type `struct#0` cannot be a uniform"
+                ),
+            ]
+        );
+        assert!(pass.vertex_attributes.is_empty());
+        assert_eq!(
+            Vec::from_iter(
+                pass.uniform_buffer
+                    .as_ref()
+                    .unwrap()
+                    .members
+                    .iter()
+                    .map(|member| member.name.as_str())
+            ),
+            ["scale"]
+        );
+    }
+
+    #[test]
+    fn test_reflection_json_round_trip() {
+        let (reflection, _) = reflect(&module());
+        let json = reflection.to_json();
+
+        assert!(json.contains("\"stage\": \"vertex\""));
+        assert!(json.contains("\"format\": \"float32x3\""));
+        assert!(json.contains("\"kind\": \"texture_2d\""));
+        assert!(json.contains("\"description\": \"Albedo in sRGB.\""));
+        assert_eq!(Reflection::from_json(&json).unwrap(), reflection);
+    }
+}
@@ -0,0 +1,83 @@
+use crate::ir::IrType;
+
+/// Size and alignment of a value in the uniform buffer, in bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UniformLayout {
+    pub size: u32,
+    pub align: u32,
+}
+
+impl UniformLayout {
+    /// Returns the std140 layout of a uniform type; `None` for types that cannot be uniforms.
+    ///
+    /// Example:
+    /// - `f` -> size 4, align 4
+    /// - `f3` -> size 12, align 16
+    /// - `m3` -> size 48, align 16 (three `f3` columns with a stride of 16)
+    pub fn of(ty: IrType) -> Option<Self> {
+        match ty {
+            IrType::Scalar(_) => Some(Self { size: 4, align: 4 }),
+            IrType::Vector { size: 2, .. } => Some(Self { size: 8, align: 8 }),
+            IrType::Vector { size, .. } => Some(Self {
+                size: 4 * size as u32,
+                align: 16,
+            }),
+            IrType::Matrix { size } => Some(Self {
+                size: 16 * size as u32,
+                align: 16,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Places members one after another, respecting their alignment.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UniformLayoutBuilder {
+    size: u32,
+}
+
+impl UniformLayoutBuilder {
+    /// Returns the offset of the added member.
+    pub fn push(&mut self, layout: UniformLayout) -> u32 {
+        let offset = align_to(self.size, layout.align);
+        self.size = offset + layout.size;
+        offset
+    }
+
+    /// Returns the size of the whole buffer, which is always a multiple of 16.
+    pub fn size(&self) -> u32 {
+        align_to(self.size, 16)
+    }
+}
+
+fn align_to(offset: u32, align: u32) -> u32 {
+    offset.div_ceil(align) * align
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_layout_builder() {
+        let mut builder = UniformLayoutBuilder::default();
+
+        assert_eq!(builder.push(UniformLayout::of(IrType::FLOAT).unwrap()), 0);
+        assert_eq!(
+            builder.push(UniformLayout::of(IrType::from_name("f3").unwrap()).unwrap()),
+            16
+        );
+        assert_eq!(builder.push(UniformLayout::of(IrType::FLOAT).unwrap()), 28);
+        assert_eq!(
+            builder.push(UniformLayout::of(IrType::from_name("f2").unwrap()).unwrap()),
+            32
+        );
+        assert_eq!(
+            builder.push(UniformLayout::of(IrType::from_name("m3").unwrap()).unwrap()),
+            48
+        );
+        assert_eq!(builder.size(), 96);
+        assert_eq!(UniformLayout::of(IrType::from_name("t2").unwrap()), None);
+    }
+}
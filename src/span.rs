@@ -1,9 +1,15 @@
+mod col_unit;
 mod line_col;
+mod source_edit;
 mod source_file;
 mod source_map;
 mod span;
+mod syntax_context;
 
+pub use col_unit::*;
 pub use line_col::*;
+pub use source_edit::*;
 pub use source_file::*;
 pub use source_map::*;
 pub use span::*;
+pub use syntax_context::*;
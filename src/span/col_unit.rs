@@ -0,0 +1,31 @@
+/// Unit in which the columns of a line are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColUnit {
+    /// UTF-8 bytes, as used by Rust strings.
+    Utf8,
+    /// UTF-16 code units, as used by LSP clients and JavaScript editors.
+    Utf16,
+    /// Unicode scalar values, as used by [`SourceFile::find_line_col`](super::SourceFile::find_line_col).
+    Char,
+}
+
+impl ColUnit {
+    /// Returns the number of units taken by a character of `len_utf8` bytes.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// # use shader_pack::span::ColUnit;
+    /// assert_eq!(ColUnit::Utf8.len_of('😀'.len_utf8()), 4);
+    /// assert_eq!(ColUnit::Utf16.len_of('😀'.len_utf8()), 2);
+    /// assert_eq!(ColUnit::Char.len_of('😀'.len_utf8()), 1);
+    /// ```
+    pub const fn len_of(self, len_utf8: usize) -> u32 {
+        match self {
+            Self::Utf8 => len_utf8 as u32,
+            // only characters out of the basic multilingual plane take 4 bytes, or 2 code units
+            Self::Utf16 if len_utf8 == 4 => 2,
+            Self::Utf16 | Self::Char => 1,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use super::Span;
+
+/// Replacement of a span of a file with a new text.
+///
+/// Example:
+///
+/// ```
+/// # use shader_pack::span::{SourceEdit, SourceFile, Span};
+/// let file = SourceFile::new(0, "in a: f;", "test", None);
+/// let edit = SourceEdit::new(Span::new(3, 4), "color");
+/// assert_eq!(file.with_edit(&edit).content(), "in color: f;");
+/// assert_eq!(edit.delta(), 4);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceEdit {
+    /// The replaced span, in the file before the edit.
+    pub span: Span,
+    pub text: String,
+}
+
+impl SourceEdit {
+    pub fn new(span: Span, text: impl Into<String>) -> Self {
+        Self {
+            span,
+            text: text.into(),
+        }
+    }
+
+    /// Returns the span of the new text, in the file after the edit.
+    pub fn new_span(&self) -> Span {
+        Span::new(self.span.low(), self.span.low() + self.text.len() as u32)
+    }
+
+    /// Returns how far the positions after the edit have moved.
+    pub fn delta(&self) -> i64 {
+        self.text.len() as i64 - self.span.len() as i64
+    }
+}
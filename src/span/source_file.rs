@@ -1,4 +1,4 @@
-use super::{LineCol, Span};
+use super::{ColUnit, LineCol, SourceEdit, Span};
 use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Hash)]
@@ -6,10 +6,26 @@ pub struct SourceFile {
     span: Span,
     content: String,
     line_lows: Vec<u32>,
+    /// Characters of more than one byte, grouped by line and sorted; ASCII lines are left out,
+    /// so that their columns are the same in every unit.
+    non_ascii_lines: Vec<NonAsciiLine>,
     name: String,
     path: Option<PathBuf>,
 }
 
+#[derive(Debug, Clone, Hash)]
+struct NonAsciiLine {
+    line: u32,
+    chars: Vec<MultiByteChar>,
+}
+
+#[derive(Debug, Clone, Copy, Hash)]
+struct MultiByteChar {
+    /// Offset from the start of the line, in bytes.
+    offset: u32,
+    len_utf8: u8,
+}
+
 impl SourceFile {
     pub fn new(
         span_low: u32,
@@ -34,15 +50,54 @@ impl SourceFile {
                 .map(|(pos, _)| span_low + pos as u32 + 1),
         );
 
+        let mut non_ascii_lines = Vec::new();
+
+        for (line, text) in content.split_inclusive('\n').enumerate() {
+            if text.is_ascii() {
+                continue;
+            }
+
+            non_ascii_lines.push(NonAsciiLine {
+                line: line as u32,
+                chars: Vec::from_iter(
+                    text.char_indices()
+                        .filter(|(_, char)| !char.is_ascii())
+                        .map(|(offset, char)| MultiByteChar {
+                            offset: offset as u32,
+                            len_utf8: char.len_utf8() as u8,
+                        }),
+                ),
+            });
+        }
+
         Self {
             span,
             content,
             line_lows,
+            non_ascii_lines,
             name,
             path,
         }
     }
 
+    /// Returns a copy of the file with the edit applied; the file keeps its position.
+    pub fn with_edit(&self, edit: &SourceEdit) -> Self {
+        assert!(self.span.contains_span(edit.span));
+        let low = (edit.span.low() - self.span.low()) as usize;
+        let high = (edit.span.high() - self.span.low()) as usize;
+        let mut content = String::with_capacity(self.content.len() + edit.text.len());
+        content.push_str(&self.content[..low]);
+        content.push_str(&edit.text);
+        content.push_str(&self.content[high..]);
+
+        Self::new(
+            self.span.low(),
+            content,
+            self.name.clone(),
+            self.path.clone(),
+        )
+    }
+
     pub fn span(&self) -> Span {
         self.span
     }
@@ -83,13 +138,95 @@ impl SourceFile {
         }
     }
 
+    /// Returns the line and the column of the position, counted in characters.
     pub fn find_line_col(&self, pos: u32) -> LineCol {
-        let line = self.find_line(pos);
-        let line_span = self.line_span(line);
-        let col = self.slice(line_span)[..(pos - line_span.low()) as usize]
-            .chars()
-            .count();
-        LineCol::new(line, col as u32)
+        assert!(self.span.contains(pos));
+        self.to_line_col(pos, ColUnit::Char)
+    }
+
+    /// Converts a position into a line and a column counted in `unit`.
+    ///
+    /// Positions out of the file are clamped, and the end of the file is a valid position.
+    /// A position in the middle of a character stands for the start of the character.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// # use shader_pack::span::{ColUnit, LineCol, SourceFile};
+    /// let file = SourceFile::new(0, "in a: f;\nin 😀: f;", "test", None);
+    /// assert_eq!(file.to_line_col(16, ColUnit::Utf8), LineCol::new(1, 7));
+    /// assert_eq!(file.to_line_col(16, ColUnit::Utf16), LineCol::new(1, 5));
+    /// assert_eq!(file.to_line_col(16, ColUnit::Char), LineCol::new(1, 4));
+    /// ```
+    pub fn to_line_col(&self, pos: u32, unit: ColUnit) -> LineCol {
+        let pos = pos.clamp(self.span.low(), self.span.high());
+        let line = match self.line_lows.binary_search(&pos) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        } as u32;
+        let offset = pos - self.line_lows[line as usize];
+        let mut col = offset;
+
+        for char in self.multi_byte_chars(line) {
+            if offset <= char.offset {
+                break;
+            }
+
+            let char_high = char.offset + char.len_utf8 as u32;
+
+            if offset < char_high {
+                col -= offset - char.offset;
+                break;
+            }
+
+            col -= char.len_utf8 as u32 - unit.len_of(char.len_utf8 as usize);
+        }
+
+        LineCol::new(line, col)
+    }
+
+    /// Converts a line and a column counted in `unit` into a position.
+    ///
+    /// Like editors do, a column past the end of the line stands for the end of the line (before
+    /// the line break), and a line past the end of the file stands for the end of the file.
+    /// A column in the middle of a character stands for the start of the character.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// # use shader_pack::span::{ColUnit, LineCol, SourceFile};
+    /// let file = SourceFile::new(0, "in a: f;\nin 😀: f;", "test", None);
+    /// assert_eq!(file.to_pos(LineCol::new(1, 5), ColUnit::Utf16), 16);
+    /// assert_eq!(file.to_pos(LineCol::new(0, 99), ColUnit::Char), 8);
+    /// assert_eq!(file.to_pos(LineCol::new(9, 0), ColUnit::Utf8), 20);
+    /// ```
+    pub fn to_pos(&self, line_col: LineCol, unit: ColUnit) -> u32 {
+        let line_low = match self.line_lows.get(line_col.line as usize) {
+            Some(&line_low) => line_low,
+            None => return self.span.high(),
+        };
+        let text = self.slice_line(line_col.line);
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        let mut extra = 0;
+
+        for char in self.multi_byte_chars(line_col.line) {
+            let char_col = char.offset - extra;
+
+            if line_col.col <= char_col {
+                break;
+            }
+
+            let len = unit.len_of(char.len_utf8 as usize);
+
+            if line_col.col < char_col + len {
+                return line_low + char.offset;
+            }
+
+            extra += char.len_utf8 as u32 - len;
+        }
+
+        line_low + line_col.col.saturating_add(extra).min(text.len() as u32)
     }
 
     pub fn slice(&self, span: Span) -> &str {
@@ -102,6 +239,16 @@ impl SourceFile {
         let span = self.line_span(line);
         self.slice(span)
     }
+
+    fn multi_byte_chars(&self, line: u32) -> &[MultiByteChar] {
+        match self
+            .non_ascii_lines
+            .binary_search_by_key(&line, |non_ascii_line| non_ascii_line.line)
+        {
+            Ok(index) => &self.non_ascii_lines[index].chars,
+            Err(_) => &[],
+        }
+    }
 }
 
 #[cfg(test)]
@@ -253,6 +400,128 @@ mod tests {
         assert_eq!(file.find_line_col(span_low + 29), LineCol::new(4, 3));
     }
 
+    #[test]
+    fn test_source_file_find_line_col_non_ascii() {
+        let span_low = span_low();
+        let file = SourceFile::new(span_low, "héllo\n😀 wörld", "test", None);
+        assert_eq!(file.find_line_col(span_low + 3), LineCol::new(0, 2));
+        assert_eq!(file.find_line_col(span_low + 11), LineCol::new(1, 1));
+        assert_eq!(file.find_line_col(span_low + 15), LineCol::new(1, 4));
+    }
+
+    #[test]
+    fn test_source_file_to_line_col() {
+        let span_low = span_low();
+        let file = SourceFile::new(span_low, "héllo\r\n😀 wörld", "test", None);
+
+        for (offset, utf8, utf16, char) in [
+            (0, 0, 0, 0),
+            (3, 3, 2, 2),
+            (6, 6, 5, 5),
+            (8, 0, 0, 0),
+            (12, 4, 2, 1),
+            (13, 5, 3, 2),
+            (16, 8, 5, 4),
+            (19, 11, 8, 7),
+        ] {
+            let line = if offset < 8 { 0 } else { 1 };
+            assert_eq!(
+                file.to_line_col(span_low + offset, ColUnit::Utf8),
+                LineCol::new(line, utf8)
+            );
+            assert_eq!(
+                file.to_line_col(span_low + offset, ColUnit::Utf16),
+                LineCol::new(line, utf16)
+            );
+            assert_eq!(
+                file.to_line_col(span_low + offset, ColUnit::Char),
+                LineCol::new(line, char)
+            );
+        }
+
+        // in the middle of a character, and out of the file
+        assert_eq!(
+            file.to_line_col(span_low + 10, ColUnit::Utf16),
+            LineCol::new(1, 0)
+        );
+        assert_eq!(
+            file.to_line_col(span_low + 99, ColUnit::Char),
+            LineCol::new(1, 7)
+        );
+        assert_eq!(file.to_line_col(0, ColUnit::Char), LineCol::new(0, 0));
+    }
+
+    #[test]
+    fn test_source_file_to_pos() {
+        let span_low = span_low();
+        let file = SourceFile::new(span_low, "héllo\r\n😀 wörld", "test", None);
+        assert_eq!(file.to_pos(LineCol::new(0, 1), ColUnit::Utf8), span_low + 1);
+        assert_eq!(
+            file.to_pos(LineCol::new(0, 2), ColUnit::Utf16),
+            span_low + 3
+        );
+        assert_eq!(
+            file.to_pos(LineCol::new(1, 1), ColUnit::Char),
+            span_low + 12
+        );
+        assert_eq!(
+            file.to_pos(LineCol::new(1, 2), ColUnit::Utf16),
+            span_low + 12
+        );
+
+        // in the middle of a character
+        assert_eq!(file.to_pos(LineCol::new(0, 2), ColUnit::Utf8), span_low + 1);
+        assert_eq!(
+            file.to_pos(LineCol::new(1, 1), ColUnit::Utf16),
+            span_low + 8
+        );
+        assert_eq!(file.to_pos(LineCol::new(1, 3), ColUnit::Utf8), span_low + 8);
+
+        // past the end of a line, or of the file
+        assert_eq!(
+            file.to_pos(LineCol::new(0, 99), ColUnit::Char),
+            span_low + 6
+        );
+        assert_eq!(
+            file.to_pos(LineCol::new(1, u32::MAX), ColUnit::Utf16),
+            span_low + 19
+        );
+        assert_eq!(
+            file.to_pos(LineCol::new(2, 0), ColUnit::Utf8),
+            span_low + 19
+        );
+    }
+
+    #[test]
+    fn test_source_file_line_col_round_trip() {
+        let mut rng = rand::thread_rng();
+        let chars = ['a', ' ', '\n', 'é', '€', '😀'];
+
+        for _ in 0..100 {
+            let span_low = span_low();
+            let content = String::from_iter(
+                (0..rng.gen_range(0..64)).map(|_| chars[rng.gen_range(0..chars.len())]),
+            );
+            let file = SourceFile::new(span_low, content.as_str(), "test", None);
+
+            for (offset, _) in content.char_indices().chain([(content.len(), ' ')]) {
+                let pos = span_low + offset as u32;
+
+                for unit in [ColUnit::Utf8, ColUnit::Utf16, ColUnit::Char] {
+                    let line_col = file.to_line_col(pos, unit);
+                    let line_low = file.line_lows()[line_col.line as usize];
+                    let prefix = &content[(line_low - span_low) as usize..offset];
+                    let col = prefix
+                        .chars()
+                        .map(|char| unit.len_of(char.len_utf8()))
+                        .sum::<u32>();
+                    assert_eq!(line_col.col, col);
+                    assert_eq!(file.to_pos(line_col, unit), pos);
+                }
+            }
+        }
+    }
+
     #[test]
     #[should_panic]
     fn test_source_file_fine_line_col_out_of_bounds() {
@@ -15,6 +15,31 @@ impl SourceMap {
         }
     }
 
+    /// Creates a map holding a file which has been added to another map; files added afterwards
+    /// are placed after it.
+    pub fn from_file(file: Arc<SourceFile>) -> Self {
+        Self {
+            span_high: file.span().high(),
+            files: vec![file],
+        }
+    }
+
+    pub fn files(&self) -> &[Arc<SourceFile>] {
+        &self.files
+    }
+
+    /// Returns the file containing the given position; the end of a file belongs to it, unless
+    /// another file starts there.
+    pub fn lookup_file(&self, pos: u32) -> Option<Arc<SourceFile>> {
+        let index = self
+            .files
+            .partition_point(|file| file.span().low() <= pos)
+            .checked_sub(1)?;
+        let file = &self.files[index];
+
+        (pos <= file.span().high()).then(|| file.clone())
+    }
+
     pub fn add_file(
         &mut self,
         content: impl Into<String>,
@@ -42,4 +67,32 @@ mod tests {
         assert_eq!(file.name(), "name");
         assert_eq!(file.path(), None);
     }
+
+    #[test]
+    fn test_source_map_lookup_file() {
+        let mut source_map = SourceMap::new();
+        let a = source_map.add_file("abc", "a", None);
+        let empty = source_map.add_file("", "empty", None);
+        let b = source_map.add_file("de", "b", None);
+        let name = |pos| {
+            source_map
+                .lookup_file(pos)
+                .map(|file| file.name().to_owned())
+        };
+
+        assert_eq!(name(0).as_deref(), Some("a"));
+        assert_eq!(name(2).as_deref(), Some("a"));
+        assert_eq!(name(3).as_deref(), Some("b"));
+        assert_eq!(name(4).as_deref(), Some("b"));
+        assert_eq!(name(5).as_deref(), Some("b"));
+        assert_eq!(name(6), None);
+        assert!(empty.span().is_empty());
+        assert!(Arc::ptr_eq(&source_map.lookup_file(1).unwrap(), &a));
+
+        let mut continued = SourceMap::from_file(b.clone());
+        let c = continued.add_file("f", "c", None);
+        assert_eq!(c.span().low(), b.span().high());
+        assert!(continued.lookup_file(0).is_none());
+        assert_eq!(continued.lookup_file(5).unwrap().name(), "c");
+    }
 }
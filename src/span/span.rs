@@ -1,7 +1,10 @@
+use super::SyntaxContext;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Span {
     low: u32,
     high: u32,
+    ctxt: SyntaxContext,
 }
 
 impl Span {
@@ -9,7 +12,11 @@ impl Span {
 
     pub const fn new(low: u32, high: u32) -> Self {
         debug_assert!(low <= high);
-        Self { low, high }
+        Self {
+            low,
+            high,
+            ctxt: SyntaxContext::ROOT,
+        }
     }
 
     pub const fn empty(pos: u32) -> Self {
@@ -24,6 +31,19 @@ impl Span {
         self.high
     }
 
+    /// The `comptime` expansion the span has been produced by; see [`SyntaxContext`].
+    pub const fn ctxt(self) -> SyntaxContext {
+        self.ctxt
+    }
+
+    pub const fn with_ctxt(self, ctxt: SyntaxContext) -> Self {
+        Self {
+            low: self.low,
+            high: self.high,
+            ctxt,
+        }
+    }
+
     pub const fn len(self) -> u32 {
         self.high - self.low
     }
@@ -48,13 +68,24 @@ impl Span {
         Self {
             low: self.low,
             high: high.max(self.high),
+            ctxt: self.ctxt,
         }
     }
 
+    /// Moves the span by `delta` bytes, e.g. to follow an edit made before it.
+    pub fn shift(self, delta: i64) -> Self {
+        Self::new(
+            (self.low as i64 + delta) as u32,
+            (self.high as i64 + delta) as u32,
+        )
+        .with_ctxt(self.ctxt)
+    }
+
     pub fn merge(lhs: Self, rhs: Self) -> Self {
         Self {
             low: lhs.low.min(rhs.low),
             high: rhs.high.max(rhs.high),
+            ctxt: lhs.ctxt,
         }
     }
 }
@@ -0,0 +1,181 @@
+use super::Span;
+use crate::symbol::Symbol;
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use rustc_hash::FxHashMap;
+use std::fmt::Display;
+
+lazy_static! {
+    // contexts are never freed, since spans may outlive any compilation; the table only grows with
+    // expansions never seen before, but long-running processes such as the language server see new
+    // ones after every edit moving a `comptime` item, hence the cap
+    static ref EXPANSIONS: RwLock<ExpansionTable> = RwLock::new(ExpansionTable::default());
+}
+
+/// The maximum number of expansions kept; later expansions get the root context.
+const MAX_EXPANSIONS: usize = 1 << 16;
+
+/// Identifies the chain of `comptime` expansions a span has been produced by, e.g. the iteration
+/// of a `comptime loop` its item has been copied for. Spans written by the user have the root
+/// context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SyntaxContext(u32);
+
+impl SyntaxContext {
+    pub const ROOT: Self = Self(0);
+
+    /// Returns the context of the expansion; the same expansion always yields the same context,
+    /// so that the expansions of a pack for different flags can still be compared.
+    ///
+    /// Once 65536 expansions are known, new ones get the root context, so that their spans are
+    /// reported without the backtrace of their expansion.
+    pub fn new(expansion: Expansion) -> Self {
+        if let Some(ctxt) = EXPANSIONS.read().contexts.get(&expansion) {
+            return *ctxt;
+        }
+
+        EXPANSIONS.write().intern(expansion, MAX_EXPANSIONS)
+    }
+
+    pub fn is_root(self) -> bool {
+        self == Self::ROOT
+    }
+
+    pub fn expansion(self) -> Option<Expansion> {
+        match self.0 {
+            0 => None,
+            index => Some(EXPANSIONS.read().expansions[index as usize - 1].clone()),
+        }
+    }
+
+    /// Returns the expansions of the context, innermost first.
+    pub fn backtrace(self) -> Vec<Expansion> {
+        let mut expansions = Vec::new();
+        let mut ctxt = self;
+
+        while let Some(expansion) = ctxt.expansion() {
+            ctxt = expansion.parent;
+            expansions.push(expansion);
+        }
+
+        expansions
+    }
+}
+
+/// A step of `comptime` expansion.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Expansion {
+    /// The context the expansion happened in.
+    pub parent: SyntaxContext,
+    pub kind: ExpansionKind,
+    /// The span of the `comptime loop` or of the `!ident` call.
+    pub call_site: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ExpansionKind {
+    /// An iteration of a `comptime loop`.
+    Loop { var: Symbol, value: i64 },
+    /// An identifier composed by `!ident`.
+    ComposedIdent { name: Symbol },
+}
+
+impl Display for ExpansionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpansionKind::Loop { var, value } => write!(
+                f,
+                "in expansion of `comptime loop` ({} = {})",
+                var.to_str(),
+                value
+            ),
+            ExpansionKind::ComposedIdent { name } => {
+                write!(f, "in expansion of `!ident` ({})", name)
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct ExpansionTable {
+    expansions: Vec<Expansion>,
+    contexts: FxHashMap<Expansion, SyntaxContext>,
+}
+
+impl ExpansionTable {
+    fn intern(&mut self, expansion: Expansion, max_expansions: usize) -> SyntaxContext {
+        if let Some(ctxt) = self.contexts.get(&expansion) {
+            return *ctxt;
+        }
+
+        if max_expansions <= self.expansions.len() {
+            return SyntaxContext::ROOT;
+        }
+
+        self.expansions.push(expansion.clone());
+        let ctxt = SyntaxContext(self.expansions.len() as u32);
+        self.contexts.insert(expansion, ctxt);
+        ctxt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syntax_context_backtrace() {
+        let outer = SyntaxContext::new(Expansion {
+            parent: SyntaxContext::ROOT,
+            kind: ExpansionKind::Loop {
+                var: Symbol::from_str("n"),
+                value: 3,
+            },
+            call_site: Span::new(0, 40),
+        });
+        let inner = SyntaxContext::new(Expansion {
+            parent: outer,
+            kind: ExpansionKind::ComposedIdent {
+                name: Symbol::from_str("uv_3"),
+            },
+            call_site: Span::new(20, 35),
+        });
+
+        assert!(SyntaxContext::ROOT.backtrace().is_empty());
+        assert!(!outer.is_root());
+
+        let backtrace = inner.backtrace();
+        assert_eq!(backtrace.len(), 2);
+        assert_eq!(
+            backtrace[0].kind.to_string(),
+            "in expansion of `!ident` (`uv_3`)"
+        );
+        assert_eq!(backtrace[1].call_site, Span::new(0, 40));
+        assert_eq!(
+            backtrace[1].kind.to_string(),
+            "in expansion of `comptime loop` (n = 3)"
+        );
+
+        let same = SyntaxContext::new(backtrace[1].clone());
+        assert_eq!(same, outer);
+    }
+
+    #[test]
+    fn test_expansion_table_cap() {
+        let expansion = |value| Expansion {
+            parent: SyntaxContext::ROOT,
+            kind: ExpansionKind::Loop {
+                var: Symbol::from_str("n"),
+                value,
+            },
+            call_site: Span::new(0, 40),
+        };
+        let mut table = ExpansionTable::default();
+
+        assert_eq!(table.intern(expansion(0), 2), SyntaxContext(1));
+        assert_eq!(table.intern(expansion(1), 2), SyntaxContext(2));
+        assert_eq!(table.intern(expansion(2), 2), SyntaxContext::ROOT);
+        assert_eq!(table.intern(expansion(0), 2), SyntaxContext(1));
+        assert_eq!(table.expansions.len(), 2);
+    }
+}
@@ -6,20 +6,35 @@ pub use symbol::*;
 
 use self::interner::Interner;
 use lazy_static::lazy_static;
-use parking_lot::Mutex;
+use parking_lot::{RwLock, RwLockUpgradableReadGuard};
 use std::fmt::{Debug, Display};
 
 lazy_static! {
-    static ref STR_INTERNER: Mutex<Interner> = Mutex::new(Interner::new());
+    // most lookups hit already interned strings, so they only take the shared lock; this keeps
+    // threads compiling different variants of the same pack from serializing on the interner
+    static ref STR_INTERNER: RwLock<Interner> = RwLock::new(Interner::new());
 }
 
 impl Symbol {
     pub fn from_str(str: impl AsRef<str>) -> Self {
-        STR_INTERNER.lock().intern(str)
+        let str = str.as_ref();
+
+        if let Some(symbol) = STR_INTERNER.read().get(str) {
+            return symbol;
+        }
+
+        // only one upgradable guard exists at a time, so no other thread can intern the same
+        // string between the lookup and the upgrade
+        let interner = STR_INTERNER.upgradable_read();
+
+        match interner.get(str) {
+            Some(symbol) => symbol,
+            None => RwLockUpgradableReadGuard::upgrade(interner).intern(str),
+        }
     }
 
     pub fn to_str(self) -> &'static str {
-        STR_INTERNER.lock().str(self)
+        STR_INTERNER.read().str(self)
     }
 }
 
@@ -93,6 +108,24 @@ mod tests {
         assert_eq!(symbol.index(), symbol2.index());
     }
 
+    #[test]
+    fn test_symbol_from_str_concurrently() {
+        let strs = Vec::from_iter((0..64).map(|_| random_str(16)));
+        let handles = Vec::from_iter((0..8).map(|_| {
+            let strs = strs.clone();
+            std::thread::spawn(move || Vec::from_iter(strs.iter().map(Symbol::from_str)))
+        }));
+        let results = Vec::from_iter(handles.into_iter().map(|handle| handle.join().unwrap()));
+
+        for symbols in &results {
+            assert_eq!(symbols, &results[0]);
+        }
+
+        for (symbol, str) in results[0].iter().zip(&strs) {
+            assert_eq!(symbol.to_str(), str);
+        }
+    }
+
     #[test]
     #[should_panic]
     fn test_symbol_to_str_invalid_index() {
@@ -40,3 +40,6 @@ impl Chunk {
 
 // NOTE: it is safe to implement `Send` for the `Chunk`, because the `ptr` is pointing memory location allocated by the `data`
 unsafe impl Send for Chunk {}
+
+// NOTE: it is safe to implement `Sync` for the `Chunk`, because it cannot be mutated through a shared reference
+unsafe impl Sync for Chunk {}
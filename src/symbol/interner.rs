@@ -22,6 +22,10 @@ impl Interner {
         self.strs[u32::from(symbol) as usize]
     }
 
+    pub fn get(&self, str: &str) -> Option<Symbol> {
+        self.reversed.get(str).copied()
+    }
+
     pub fn intern(&mut self, str: impl AsRef<str>) -> Symbol {
         let str = str.as_ref();
 
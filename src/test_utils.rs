@@ -0,0 +1,550 @@
+use crate::{
+    parse::{
+        ast::{
+            AstAttribute, AstAttributeItem, AstBinaryExpr, AstBinaryExprOp, AstBinaryExprOpKind,
+            AstCallExpr, AstCallExprArg, AstCompTime, AstCompTimeBlock, AstCompTimeElsePart,
+            AstCompTimeIf, AstCompTimeIfPart, AstCompTimeIfPredicateExpr,
+            AstCompTimeIfPredicateExprAnd, AstCompTimeIfPredicateExprFlag,
+            AstCompTimeIfPredicateExprKind, AstCompTimeIfPredicateExprNot,
+            AstCompTimeIfPredicateExprSingle, AstCompTimeIfPredicateExprSingleKind,
+            AstCompTimeKind, AstCompTimeLoop, AstComposedIdentifier, AstComposedIdentifierArg,
+            AstExpr, AstExprKind, AstFnDef, AstFnDefParam, AstFnDefReturnType, AstIdentifier,
+            AstIdentifierKind, AstImport, AstInput, AstKeyword, AstLiteral, AstLiteralKind,
+            AstMemberExpr, AstObjectExpr, AstObjectExprField, AstPass, AstPassLevel,
+            AstPassLevelKind, AstPathQualifier, AstPunc, AstPuncKind, AstShaderPack, AstStage,
+            AstStatement, AstStatementKind, AstStatementReturn, AstStatementVarDecl,
+            AstStatementVarDeclAssignment, AstStatementVarDeclTypeName, AstStringLiteral,
+            AstTopLevel, AstTopLevelKind, AstTypeName, NodeId,
+        },
+        lexer::TokenNumberLiteralKind,
+        low_lexer::is_id_continue,
+    },
+    span::Span,
+    symbol::Symbol,
+};
+
+pub fn node_id() -> NodeId {
+    NodeId::new(1)
+}
+
+pub fn punc(kind: AstPuncKind) -> AstPunc {
+    AstPunc {
+        span: Span::ZERO,
+        kind,
+    }
+}
+
+pub fn keyword(str: &str) -> AstKeyword {
+    AstKeyword {
+        span: Span::ZERO,
+        symbol: Symbol::from_str(str),
+    }
+}
+
+pub fn string_literal(str: &str) -> AstStringLiteral {
+    AstStringLiteral {
+        node_id: node_id(),
+        span: Span::ZERO,
+        content: Symbol::from_str(format!("\"{}\"", str)),
+        unquoted_content: Symbol::from_str(str),
+        terminated: true,
+    }
+}
+
+pub fn ident(str: &str) -> AstIdentifier {
+    AstIdentifier {
+        node_id: node_id(),
+        span: Span::ZERO,
+        kind: AstIdentifierKind::Symbol(Symbol::from_str(str)),
+    }
+}
+
+/// Creates an identifier spanning the `nth` occurrence of `name` as a whole word in `source`.
+pub fn ident_at(source: &str, name: &str, nth: usize) -> AstIdentifier {
+    let is_boundary = |char: Option<char>| !char.is_some_and(is_id_continue);
+    let low = source
+        .match_indices(name)
+        .map(|(low, _)| low)
+        .filter(|low| {
+            is_boundary(source[..*low].chars().next_back())
+                && is_boundary(source[low + name.len()..].chars().next())
+        })
+        .nth(nth)
+        .unwrap();
+
+    AstIdentifier {
+        span: Span::new(low as u32, (low + name.len()) as u32),
+        ..ident(name)
+    }
+}
+
+pub fn type_name(str: &str) -> AstTypeName {
+    AstTypeName {
+        node_id: node_id(),
+        span: Span::ZERO,
+        ident: ident(str),
+    }
+}
+
+/// Creates `!ident("<rule>", <args>)`.
+pub fn composed_ident(rule: &str, args: Vec<AstExpr>) -> AstIdentifier {
+    AstIdentifier {
+        node_id: node_id(),
+        span: Span::ZERO,
+        kind: AstIdentifierKind::Composed(AstComposedIdentifier {
+            punc_bang: punc(AstPuncKind::LogNot),
+            keyword_ident: keyword("ident"),
+            punc_open_paren: punc(AstPuncKind::OpenParen),
+            rule_str: string_literal(rule),
+            punc_comma: Some(punc(AstPuncKind::Comma)),
+            args: Vec::from_iter(args.into_iter().map(|expr| AstComposedIdentifierArg {
+                node_id: node_id(),
+                span: Span::ZERO,
+                expr,
+                punc_comma: None,
+            })),
+            punc_close_paren: punc(AstPuncKind::CloseParen),
+        }),
+    }
+}
+
+pub fn expr(kind: AstExprKind) -> AstExpr {
+    AstExpr {
+        node_id: node_id(),
+        span: Span::ZERO,
+        kind,
+    }
+}
+
+pub fn expr_ident(str: &str) -> AstExpr {
+    expr(AstExprKind::Identifier(ident(str)))
+}
+
+pub fn expr_int(str: &str) -> AstExpr {
+    expr(AstExprKind::Literal(AstLiteral {
+        node_id: node_id(),
+        span: Span::ZERO,
+        kind: AstLiteralKind::Number {
+            kind: TokenNumberLiteralKind::IntegerDecimal,
+            content: Symbol::from_str(str),
+            suffix: None,
+        },
+    }))
+}
+
+pub fn expr_float(str: &str) -> AstExpr {
+    expr(AstExprKind::Literal(AstLiteral {
+        node_id: node_id(),
+        span: Span::ZERO,
+        kind: AstLiteralKind::Number {
+            kind: TokenNumberLiteralKind::Float,
+            content: Symbol::from_str(str),
+            suffix: None,
+        },
+    }))
+}
+
+pub fn expr_call(callee: AstIdentifier, args: Vec<AstExpr>) -> AstExpr {
+    expr(AstExprKind::Call(AstCallExpr {
+        node_id: node_id(),
+        span: Span::ZERO,
+        qualifier: None,
+        callee,
+        punc_open_paren: punc(AstPuncKind::OpenParen),
+        args: Vec::from_iter(args.into_iter().map(|expr| AstCallExprArg {
+            node_id: node_id(),
+            span: Span::ZERO,
+            expr,
+            punc_comma: None,
+        })),
+        punc_close_paren: punc(AstPuncKind::CloseParen),
+    }))
+}
+
+pub fn expr_binary(kind: AstBinaryExprOpKind, lhs: AstExpr, rhs: AstExpr) -> AstExpr {
+    expr(AstExprKind::Binary(AstBinaryExpr {
+        node_id: node_id(),
+        span: Span::ZERO,
+        op: AstBinaryExprOp {
+            span: Span::ZERO,
+            kind,
+        },
+        lhs: Box::new(lhs),
+        rhs: Box::new(rhs),
+    }))
+}
+
+/// Creates `<lhs>.<member>`.
+pub fn expr_member(lhs: AstExpr, member: &str) -> AstExpr {
+    expr(AstExprKind::Member(AstMemberExpr {
+        node_id: node_id(),
+        span: Span::ZERO,
+        lhs: Box::new(lhs),
+        punc_dot: punc(AstPuncKind::Dot),
+        member: ident(member),
+    }))
+}
+
+/// Creates `{ <name>: <expr>, ... }`.
+pub fn expr_object(fields: Vec<(&str, AstExpr)>) -> AstExpr {
+    expr(AstExprKind::Object(AstObjectExpr {
+        node_id: node_id(),
+        span: Span::ZERO,
+        punc_open_brace: punc(AstPuncKind::OpenBrace),
+        fields: Vec::from_iter(fields.into_iter().map(|(name, expr)| AstObjectExprField {
+            node_id: node_id(),
+            span: Span::ZERO,
+            ident: ident(name),
+            punc_colon: punc(AstPuncKind::Colon),
+            expr,
+            punc_comma: None,
+        })),
+        punc_close_brace: punc(AstPuncKind::CloseBrace),
+    }))
+}
+
+pub fn flag(name: &str) -> AstCompTimeIfPredicateExpr {
+    single(AstCompTimeIfPredicateExprSingleKind::Flag(
+        AstCompTimeIfPredicateExprFlag {
+            node_id: node_id(),
+            span: Span::ZERO,
+            flag: string_literal(name),
+        },
+    ))
+}
+
+pub fn not(expr: AstCompTimeIfPredicateExpr) -> AstCompTimeIfPredicateExpr {
+    single(AstCompTimeIfPredicateExprSingleKind::Not(
+        AstCompTimeIfPredicateExprNot {
+            node_id: node_id(),
+            span: Span::ZERO,
+            keyword_not: keyword("not"),
+            expr: Box::new(expr),
+        },
+    ))
+}
+
+pub fn and(
+    lhs: AstCompTimeIfPredicateExpr,
+    rhs: AstCompTimeIfPredicateExpr,
+) -> AstCompTimeIfPredicateExpr {
+    predicate(AstCompTimeIfPredicateExprKind::And(
+        AstCompTimeIfPredicateExprAnd {
+            node_id: node_id(),
+            span: Span::ZERO,
+            lhs: Box::new(lhs),
+            keyword_and: keyword("and"),
+            rhs: Box::new(rhs),
+        },
+    ))
+}
+
+fn single(kind: AstCompTimeIfPredicateExprSingleKind) -> AstCompTimeIfPredicateExpr {
+    predicate(AstCompTimeIfPredicateExprKind::Single(
+        AstCompTimeIfPredicateExprSingle {
+            node_id: node_id(),
+            span: Span::ZERO,
+            kind,
+        },
+    ))
+}
+
+fn predicate(kind: AstCompTimeIfPredicateExprKind) -> AstCompTimeIfPredicateExpr {
+    AstCompTimeIfPredicateExpr {
+        node_id: node_id(),
+        span: Span::ZERO,
+        kind,
+    }
+}
+
+fn block(items: Vec<AstTopLevelKind>) -> AstCompTimeBlock<AstTopLevel> {
+    AstCompTimeBlock {
+        node_id: node_id(),
+        span: Span::ZERO,
+        punc_open_brace: punc(AstPuncKind::OpenBrace),
+        items: Vec::from_iter(items.into_iter().map(top_level)),
+        punc_close_brace: punc(AstPuncKind::CloseBrace),
+    }
+}
+
+fn comptime(kind: AstCompTimeKind<AstTopLevel>) -> AstTopLevelKind {
+    AstTopLevelKind::CompTime(AstCompTime {
+        node_id: node_id(),
+        span: Span::ZERO,
+        keyword_comptime: keyword("comptime"),
+        kind,
+    })
+}
+
+/// Creates `comptime if <predicate> { <items> } [else { <else_items> }]`.
+pub fn comptime_if(
+    predicate: AstCompTimeIfPredicateExpr,
+    items: Vec<AstTopLevelKind>,
+    else_items: Option<Vec<AstTopLevelKind>>,
+) -> AstTopLevelKind {
+    comptime(AstCompTimeKind::If(AstCompTimeIf {
+        node_id: node_id(),
+        span: Span::ZERO,
+        if_part: AstCompTimeIfPart {
+            node_id: node_id(),
+            span: Span::ZERO,
+            keyword_if: keyword("if"),
+            predicate,
+            block: block(items),
+        },
+        else_if_parts: vec![],
+        else_part: else_items.map(|items| AstCompTimeElsePart {
+            node_id: node_id(),
+            span: Span::ZERO,
+            keyword_else: keyword("else"),
+            block: block(items),
+        }),
+    }))
+}
+
+/// Creates `comptime loop <var> times <count> { <items> }`.
+pub fn comptime_loop(var: &str, count: AstExpr, items: Vec<AstTopLevelKind>) -> AstTopLevelKind {
+    comptime(AstCompTimeKind::Loop(AstCompTimeLoop {
+        node_id: node_id(),
+        span: Span::ZERO,
+        keyword_loop: keyword("loop"),
+        loop_var_ident: ident(var),
+        keyword_times: keyword("times"),
+        expr: count,
+        block: block(items),
+    }))
+}
+
+/// Creates `import "<path>";`.
+pub fn import(path: &str) -> AstTopLevelKind {
+    AstTopLevelKind::Import(AstImport {
+        node_id: node_id(),
+        span: Span::ZERO,
+        keyword_import: keyword("import"),
+        path: string_literal(path),
+        alias: None,
+        punc_semicolon: punc(AstPuncKind::Semicolon),
+    })
+}
+
+/// Creates `@<name>="<value>"`.
+pub fn attribute(name: &str, value: &str) -> AstAttribute {
+    AstAttribute {
+        node_id: node_id(),
+        span: Span::ZERO,
+        items: vec![AstAttributeItem {
+            node_id: node_id(),
+            span: Span::ZERO,
+            punc_at: punc(AstPuncKind::At),
+            ident: ident(name),
+            punc_assign: punc(AstPuncKind::Assign),
+            expr: string_literal(value),
+        }],
+    }
+}
+
+pub fn input(ident: AstIdentifier, ty: &str) -> AstTopLevelKind {
+    AstTopLevelKind::Input(ast_input(ident, ty))
+}
+
+/// Creates an `in` item of a pass.
+pub fn pass_input(ident: AstIdentifier, ty: &str) -> AstPassLevelKind {
+    AstPassLevelKind::Input(ast_input(ident, ty))
+}
+
+pub fn ast_input(ident: AstIdentifier, ty: &str) -> AstInput {
+    AstInput {
+        node_id: node_id(),
+        span: Span::ZERO,
+        doc: None,
+        attributes: vec![],
+        keyword_in: keyword("in"),
+        ident,
+        punc_colon: punc(AstPuncKind::Colon),
+        type_name: type_name(ty),
+        punc_semicolon: punc(AstPuncKind::Semicolon),
+    }
+}
+
+pub fn statement(kind: AstStatementKind) -> AstStatement {
+    AstStatement {
+        node_id: node_id(),
+        span: Span::ZERO,
+        kind,
+    }
+}
+
+/// Creates `let <ident> = <rhs>;`.
+pub fn statement_let(ident: AstIdentifier, rhs: AstExpr) -> AstStatement {
+    statement_let_typed(ident, None, rhs)
+}
+
+/// Creates `let <ident>[: <ty>] = <rhs>;`.
+pub fn statement_let_typed(ident: AstIdentifier, ty: Option<&str>, rhs: AstExpr) -> AstStatement {
+    statement(AstStatementKind::VarDecl(AstStatementVarDecl {
+        keyword_let: keyword("let"),
+        ident,
+        type_name: ty.map(|ty| AstStatementVarDeclTypeName {
+            node_id: node_id(),
+            span: Span::ZERO,
+            punc_colon: punc(AstPuncKind::Colon),
+            type_name: type_name(ty),
+        }),
+        assignment: Some(AstStatementVarDeclAssignment {
+            node_id: node_id(),
+            span: Span::ZERO,
+            punc_assignment: punc(AstPuncKind::Assign),
+            rhs,
+        }),
+        punc_semicolon: punc(AstPuncKind::Semicolon),
+    }))
+}
+
+pub fn statement_return(expr: AstExpr) -> AstStatement {
+    statement(AstStatementKind::Return(AstStatementReturn {
+        keyword_return: keyword("return"),
+        expr: Some(expr),
+        punc_semicolon: punc(AstPuncKind::Semicolon),
+    }))
+}
+
+/// Creates `fn <ident>(<params>) [-> <return_type>] { <statements> }`.
+pub fn fn_def(
+    ident: AstIdentifier,
+    params: Vec<(AstIdentifier, &str)>,
+    return_type: Option<&str>,
+    statements: Vec<AstStatement>,
+) -> AstTopLevelKind {
+    AstTopLevelKind::FnDef(AstFnDef {
+        node_id: node_id(),
+        span: Span::ZERO,
+        doc: None,
+        attributes: vec![],
+        keyword_pub: None,
+        keyword_fn: keyword("fn"),
+        ident,
+        punc_open_paren: punc(AstPuncKind::OpenParen),
+        params: Vec::from_iter(params.into_iter().map(|(ident, ty)| AstFnDefParam {
+            node_id: node_id(),
+            span: Span::ZERO,
+            attributes: vec![],
+            ident,
+            punc_colon: punc(AstPuncKind::Colon),
+            type_name: type_name(ty),
+            punc_comma: None,
+        })),
+        punc_close_paren: punc(AstPuncKind::CloseParen),
+        return_type: return_type.map(|ty| AstFnDefReturnType {
+            node_id: node_id(),
+            span: Span::ZERO,
+            punc_arrow: punc(AstPuncKind::Arrow),
+            type_name: type_name(ty),
+        }),
+        punc_open_brace: punc(AstPuncKind::OpenBrace),
+        statements,
+        punc_close_brace: punc(AstPuncKind::CloseBrace),
+    })
+}
+
+/// Creates `[pub] fn <name>() { <calls>(); }`, where calls may be qualified, e.g. `a::f`.
+pub fn fn_def_calling(name: &str, is_pub: bool, calls: &[&str]) -> AstTopLevelKind {
+    let statements = Vec::from_iter(calls.iter().map(|call| {
+        let (qualifier, callee) = match call.split_once("::") {
+            Some((module, callee)) => (Some(module), callee),
+            None => (None, *call),
+        };
+        let mut expr = expr_call(ident(callee), vec![]);
+
+        if let AstExprKind::Call(call) = &mut expr.kind {
+            call.qualifier = qualifier.map(|module| {
+                Box::new(AstPathQualifier {
+                    node_id: node_id(),
+                    span: Span::ZERO,
+                    module: ident(module),
+                    punc_path_sep: punc(AstPuncKind::PathSep),
+                })
+            });
+        }
+
+        statement(AstStatementKind::Expr(expr))
+    }));
+    let mut kind = fn_def(ident(name), vec![], None, statements);
+
+    if let AstTopLevelKind::FnDef(fn_def) = &mut kind {
+        fn_def.keyword_pub = is_pub.then(|| keyword("pub"));
+    }
+
+    kind
+}
+
+/// Creates `<stage> { <statements> }`.
+pub fn stage(stage: AstIdentifier, statements: Vec<AstStatement>) -> AstPassLevelKind {
+    AstPassLevelKind::Stage(AstStage {
+        node_id: node_id(),
+        span: Span::ZERO,
+        attributes: vec![],
+        stage,
+        punc_open_brace: punc(AstPuncKind::OpenBrace),
+        statements,
+        punc_close_brace: punc(AstPuncKind::CloseBrace),
+    })
+}
+
+/// Creates `pass <ident> { <pass_levels> }`.
+pub fn pass_with(ident: AstIdentifier, pass_levels: Vec<AstPassLevelKind>) -> AstTopLevelKind {
+    AstTopLevelKind::Pass(ast_pass(ident, pass_levels))
+}
+
+pub fn ast_pass(ident: AstIdentifier, pass_levels: Vec<AstPassLevelKind>) -> AstPass {
+    AstPass {
+        node_id: node_id(),
+        span: Span::ZERO,
+        doc: None,
+        attributes: vec![],
+        keyword_pass: keyword("pass"),
+        ident,
+        punc_open_brace: punc(AstPuncKind::OpenBrace),
+        pass_levels: Vec::from_iter(pass_levels.into_iter().map(|kind| AstPassLevel {
+            node_id: node_id(),
+            span: Span::ZERO,
+            kind,
+        })),
+        punc_close_brace: punc(AstPuncKind::CloseBrace),
+    }
+}
+
+pub fn pass(name: &str) -> AstTopLevelKind {
+    pass_with(ident(name), vec![])
+}
+
+fn top_level(kind: AstTopLevelKind) -> AstTopLevel {
+    AstTopLevel {
+        node_id: node_id(),
+        span: Span::ZERO,
+        kind,
+    }
+}
+
+pub fn shader_pack(top_levels: Vec<AstTopLevelKind>) -> AstShaderPack {
+    AstShaderPack {
+        node_id: node_id(),
+        span: Span::ZERO,
+        top_levels: Vec::from_iter(top_levels.into_iter().map(top_level)),
+    }
+}
+
+/// Returns the names of the inputs and passes of an expanded pack, in order.
+pub fn top_level_names(pack: &AstShaderPack) -> Vec<String> {
+    Vec::from_iter(pack.top_levels.iter().map(|top_level| {
+        let ident = match &top_level.kind {
+            AstTopLevelKind::Input(input) => &input.ident,
+            AstTopLevelKind::Pass(pass) => &pass.ident,
+            _ => unreachable!(),
+        };
+
+        match &ident.kind {
+            AstIdentifierKind::Symbol(symbol) => symbol.to_str().to_owned(),
+            _ => unreachable!(),
+        }
+    }))
+}